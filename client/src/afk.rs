@@ -0,0 +1,62 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// When each player last moved or did a task during a game. See `Client::afk_players`
+#[derive(Debug, Default)]
+pub(crate) struct ActivityTracker {
+    last_active: HashMap<i32, Instant>,
+    /// Players already announced as AFK, until they do something
+    announced: HashSet<i32>,
+}
+
+impl ActivityTracker {
+    /// Starts tracking `client_ids` from now, forgetting anyone else
+    pub(crate) fn start(&mut self, client_ids: impl Iterator<Item = i32>, now: Instant) {
+        self.last_active = client_ids.map(|client_id| (client_id, now)).collect();
+        self.announced.clear();
+    }
+
+    /// Counts everyone as active now, e.g. after a meeting where nobody can move
+    pub(crate) fn restart(&mut self, now: Instant) {
+        for last_active in self.last_active.values_mut() {
+            *last_active = now;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.last_active.clear();
+        self.announced.clear();
+    }
+
+    pub(crate) fn remove(&mut self, client_id: i32) {
+        self.last_active.remove(&client_id);
+        self.announced.remove(&client_id);
+    }
+
+    /// Ignored for players that weren't there at the start
+    pub(crate) fn active(&mut self, client_id: i32, now: Instant) {
+        if let Some(last_active) = self.last_active.get_mut(&client_id) {
+            *last_active = now;
+            self.announced.remove(&client_id);
+        }
+    }
+
+    /// Everyone who's done nothing for at least `threshold`, sorted by client id
+    pub(crate) fn idle(&self, threshold: Duration, now: Instant) -> Vec<i32> {
+        let mut idle = self
+            .last_active
+            .iter()
+            .filter(|(_, last_active)| now.duration_since(**last_active) >= threshold)
+            .map(|(client_id, _)| *client_id)
+            .collect::<Vec<_>>();
+        idle.sort_unstable();
+        idle
+    }
+
+    /// Marks a player as announced, returning false if they already were
+    pub(crate) fn announce(&mut self, client_id: i32) -> bool {
+        self.announced.insert(client_id)
+    }
+}
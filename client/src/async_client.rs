@@ -0,0 +1,448 @@
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use common::data::{DisconnectReason, GameId, GameOverReason, SystemType, Vector2};
+use futures_core::Stream;
+use log::{error, warn};
+use tokio::{
+    net::{lookup_host, UdpSocket},
+    sync::{mpsc, oneshot},
+    time::{Interval, MissedTickBehavior},
+};
+
+use crate::{
+    movement::MOVEMENT_INTERVAL,
+    networking::{first_ipv4, NetClient, BUFFER_SIZE},
+    Client, ClientError, ClientSettings, EventHandler, GameState, LobbyInfo, ServerTarget,
+    DISCONNECT_TIMEOUT, TICK_INTERVAL,
+};
+
+type Command = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// What an `AsyncClient` has to say, the same things an `EventHandler` is told
+#[derive(Debug, Clone)]
+pub enum ClientEvent {
+    JoinedGame,
+    Disconnected(DisconnectReason),
+    PlayerJoined(i32),
+    PlayerLeft {
+        client_id: i32,
+        reason: Option<DisconnectReason>,
+    },
+    LobbyChanged(LobbyInfo),
+    VisibilityChanged(bool),
+    ChatMessage {
+        player_id: i32,
+        name: String,
+        message: String,
+    },
+    ImpostorsAssigned(Vec<u8>),
+    GameStarted,
+    PlayerMoved {
+        client_id: i32,
+        position: Vector2,
+    },
+    PlayerDied {
+        player_id: u8,
+        killer_id: i32,
+    },
+    TaskUpdated {
+        client_id: i32,
+        task_index: u32,
+    },
+    SabotageTriggered(SystemType),
+    MeetingStarted {
+        caller_id: i32,
+        reported_player_id: Option<u8>,
+    },
+    VotingComplete {
+        exiled_player_id: Option<u8>,
+        tie: bool,
+    },
+    GameEnded(GameOverReason),
+    /// Something broke inside the client. See `EventHandler::client_error`
+    Error(ClientError),
+}
+
+/// A `Client` run as a tokio task rather than on its own threads, so many can share a runtime
+///
+/// Cheap to clone. Every command is run on the client between packets. The task stops once the
+/// client disconnects or every handle is dropped, after which commands return `NotConnected`
+#[derive(Clone)]
+pub struct AsyncClient {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+/// The events from an `AsyncClient`, ending once it stops
+pub struct ClientEvents {
+    receiver: mpsc::UnboundedReceiver<ClientEvent>,
+}
+
+impl AsyncClient {
+    /// Starts joining a game by code, like `Client::run_game_code`. Must be called from within a
+    /// tokio runtime
+    pub async fn connect<S: Into<ServerTarget>>(
+        server: S,
+        game_code: &str,
+        mut settings: ClientSettings,
+    ) -> io::Result<(Self, ClientEvents)> {
+        let server = server.into();
+        let addr = match &server {
+            ServerTarget::Main(main) => main.to_addr(),
+            ServerTarget::Custom { host, port } => {
+                first_ipv4(host, lookup_host((host.as_str(), *port)).await?)?
+            }
+        };
+        if server.is_custom() {
+            settings = settings.for_custom_server();
+        }
+        let socket = settings.transport.bind()?;
+        socket.set_nonblocking(true)?;
+        let socket = UdpSocket::from_std(socket)?;
+        let game_id = GameId::from_chars(game_code);
+        let net_client = NetClient::manual_to(addr, settings.clock.clone());
+        let mut client = Client::new(net_client);
+        client.apply_settings(&mut settings);
+        client.start_session(&settings.connect_username, Some(game_id));
+
+        let (command_send, command_recv) = mpsc::unbounded_channel();
+        let (event_send, event_recv) = mpsc::unbounded_channel();
+        let handler = ChannelHandler { events: event_send };
+        tokio::spawn(run(
+            client,
+            handler,
+            settings,
+            game_id,
+            socket,
+            command_recv,
+        ));
+        Ok((
+            Self {
+                commands: command_send,
+            },
+            ClientEvents {
+                receiver: event_recv,
+            },
+        ))
+    }
+
+    /// Runs `f` on the client and returns what it returns
+    pub async fn call<T, F>(&self, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> T + Send + 'static,
+    {
+        self.dispatch(f)?.await.map_err(|_| stopped())
+    }
+
+    /// Queues `f` without waiting for it to run, so a `Swarm` can reach every member at once
+    pub(crate) fn dispatch<T, F>(&self, f: F) -> io::Result<oneshot::Receiver<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> T + Send + 'static,
+    {
+        let (result_send, result_recv) = oneshot::channel();
+        self.commands
+            .send(Box::new(move |client| {
+                let _ = result_send.send(f(client));
+            }))
+            .map_err(|_| stopped())?;
+        Ok(result_recv)
+    }
+
+    pub async fn send_chat(&self, message: &str) -> io::Result<()> {
+        let message = message.to_string();
+        self.call(move |client| client.send_chat(&message)).await
+    }
+
+    pub async fn set_name(&self, name: &str) -> io::Result<()> {
+        let name = name.to_string();
+        self.call(move |client| client.set_name(&name)).await
+    }
+
+    pub async fn set_color(&self, color_index: u8) -> io::Result<()> {
+        self.call(move |client| client.set_color(color_index)).await
+    }
+
+    /// See `Client::walk_to`
+    pub async fn walk_to(&self, target: Vector2) -> io::Result<()> {
+        self.call(move |client| client.walk_to(target)).await
+    }
+
+    /// The lobby currently joined. See `Client::lobby`
+    pub async fn lobby(&self) -> io::Result<Option<LobbyInfo>> {
+        self.call(|client| client.lobby().cloned()).await
+    }
+
+    /// See `Client::state`
+    pub async fn state(&self) -> io::Result<GameState> {
+        self.call(|client| client.state()).await
+    }
+
+    /// Leaves and stops the task
+    pub async fn disconnect(&self) -> io::Result<()> {
+        self.call(|client| client.disconnect()).await
+    }
+
+    /// See `Client::disconnect_with_reason`
+    pub async fn disconnect_with_reason(&self, reason: DisconnectReason) -> io::Result<()> {
+        self.call(move |client| client.disconnect_with_reason(reason))
+            .await
+    }
+}
+
+pub(crate) fn stopped() -> io::Error {
+    io::Error::new(ErrorKind::NotConnected, "Client has stopped")
+}
+
+impl ClientEvents {
+    /// The next event, or `None` once the client has stopped
+    pub async fn recv(&mut self) -> Option<ClientEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for ClientEvents {
+    type Item = ClientEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Does what `Client::run_game_inner` does, with the socket and timers driven by tokio
+async fn run(
+    mut client: Client,
+    mut handler: ChannelHandler,
+    settings: ClientSettings,
+    game_id: GameId,
+    socket: UdpSocket,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    // Timers run on their own schedule, so resends and reordering timeouts still happen under
+    // steady traffic
+    let mut timers = timer_interval(TICK_INTERVAL);
+    loop {
+        if let Err(error) = flush(&mut client, &socket).await {
+            error!("Send failed: {}", error);
+            return;
+        }
+        if client.should_disconnect {
+            // Like `Client::disconnect_with_reason`, but waiting for acknowledgements here
+            // rather than blocking the executor
+            let reason = client.leave_reason.take();
+            if reason.is_some() && !drain(&mut client, &socket, &mut buffer).await {
+                warn!("Disconnecting with packets still unacknowledged");
+            }
+            client.client.send_disconnect_reason(reason.as_ref());
+            let _ = flush(&mut client, &socket).await;
+            return;
+        }
+        // Tick more often while walking so the snapshots are evenly spaced
+        let period = if client.movement.is_some() {
+            MOVEMENT_INTERVAL
+        } else {
+            TICK_INTERVAL
+        };
+        if timers.period() != period {
+            timers = timer_interval(period);
+        }
+        tokio::select! {
+            received = socket.recv_from(&mut buffer) => match received {
+                // Redirects change the address, anything from elsewhere is ignored
+                Ok((length, from)) if from == client.client.addr() => {
+                    client.client.feed_datagram(&buffer[..length])
+                }
+                Ok((_, from)) => warn!("Ignoring datagram from {}", from),
+                Err(error) => {
+                    error!("Receive failed: {}", error);
+                    return;
+                }
+            },
+            command = commands.recv() => match command {
+                Some(command) => command(&mut client),
+                // Nobody left to give commands or read events
+                None => client.disconnect(),
+            },
+            _ = timers.tick() => client.client.poll_timers(),
+        }
+        client.tick();
+        while let Some(packet) = client.client.try_read_packet() {
+            match client.handle_hazel_packet(&mut handler, &settings, Some(game_id), packet) {
+                Ok(true) => (),
+                Ok(false) => {
+                    let _ = flush(&mut client, &socket).await;
+                    return;
+                }
+                Err(error) => {
+                    handler.client_error(&mut client, error);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Ticks every `period`. After a late tick the next is a full period later, rather than a burst
+/// catching up
+fn timer_interval(period: Duration) -> Interval {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Sends everything the client has queued, including resends that are due
+async fn flush(client: &mut Client, socket: &UdpSocket) -> io::Result<()> {
+    while let Some(datagram) = client.client.poll_outbound() {
+        socket.send_to(&datagram, client.client.addr()).await?;
+    }
+    Ok(())
+}
+
+/// Keeps sending and reading acknowledgements until everything sent has been acknowledged, for
+/// up to `DISCONNECT_TIMEOUT`. Returns whether it all was
+async fn drain(client: &mut Client, socket: &UdpSocket, buffer: &mut [u8]) -> bool {
+    let deadline = tokio::time::sleep(DISCONNECT_TIMEOUT);
+    tokio::pin!(deadline);
+    let mut timers = timer_interval(TICK_INTERVAL);
+    loop {
+        if flush(client, socket).await.is_err() {
+            return false;
+        }
+        if client.client.is_flushed() {
+            return true;
+        }
+        tokio::select! {
+            received = socket.recv_from(buffer) => match received {
+                Ok((length, from)) if from == client.client.addr() => {
+                    client.client.feed_datagram(&buffer[..length])
+                }
+                Ok(_) => (),
+                Err(_) => return false,
+            },
+            _ = timers.tick() => client.client.poll_timers(),
+            _ = &mut deadline => return false,
+        }
+    }
+}
+
+/// Turns callbacks into `ClientEvent`s. Sends fail once `ClientEvents` is dropped, which is fine
+struct ChannelHandler {
+    events: mpsc::UnboundedSender<ClientEvent>,
+}
+
+impl ChannelHandler {
+    fn send(&self, event: ClientEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl EventHandler for ChannelHandler {
+    fn disconnect_reason(&mut self, _client: &mut Client, reason: DisconnectReason) {
+        self.send(ClientEvent::Disconnected(reason));
+    }
+
+    fn joined_game(&mut self, _client: &mut Client) {
+        self.send(ClientEvent::JoinedGame);
+    }
+
+    fn player_joined(&mut self, _client: &mut Client, client_id: i32) {
+        self.send(ClientEvent::PlayerJoined(client_id));
+    }
+
+    fn player_left(
+        &mut self,
+        _client: &mut Client,
+        client_id: i32,
+        reason: Option<DisconnectReason>,
+    ) {
+        self.send(ClientEvent::PlayerLeft { client_id, reason });
+    }
+
+    fn lobby_changed(&mut self, _client: &mut Client, lobby: &LobbyInfo) {
+        self.send(ClientEvent::LobbyChanged(lobby.clone()));
+    }
+
+    fn visibility_changed(&mut self, _client: &mut Client, is_public: bool) {
+        self.send(ClientEvent::VisibilityChanged(is_public));
+    }
+
+    fn chat_message(
+        &mut self,
+        _client: &mut Client,
+        player_id: i32,
+        name: String,
+        message: String,
+    ) {
+        self.send(ClientEvent::ChatMessage {
+            player_id,
+            name,
+            message,
+        });
+    }
+
+    fn impostors_assigned(&mut self, _client: &mut Client, impostors: Vec<u8>) {
+        self.send(ClientEvent::ImpostorsAssigned(impostors));
+    }
+
+    fn game_started(&mut self, _client: &mut Client) {
+        self.send(ClientEvent::GameStarted);
+    }
+
+    fn player_moved(&mut self, _client: &mut Client, client_id: i32, position: Vector2) {
+        self.send(ClientEvent::PlayerMoved {
+            client_id,
+            position,
+        });
+    }
+
+    fn player_died(&mut self, _client: &mut Client, player_id: u8, killer_id: i32) {
+        self.send(ClientEvent::PlayerDied {
+            player_id,
+            killer_id,
+        });
+    }
+
+    fn task_updated(&mut self, _client: &mut Client, client_id: i32, task_index: u32) {
+        self.send(ClientEvent::TaskUpdated {
+            client_id,
+            task_index,
+        });
+    }
+
+    fn sabotage_triggered(&mut self, _client: &mut Client, system: SystemType) {
+        self.send(ClientEvent::SabotageTriggered(system));
+    }
+
+    fn meeting_started(
+        &mut self,
+        _client: &mut Client,
+        caller_id: i32,
+        reported_player_id: Option<u8>,
+    ) {
+        self.send(ClientEvent::MeetingStarted {
+            caller_id,
+            reported_player_id,
+        });
+    }
+
+    fn voting_complete(&mut self, _client: &mut Client, exiled_player_id: Option<u8>, tie: bool) {
+        self.send(ClientEvent::VotingComplete {
+            exiled_player_id,
+            tie,
+        });
+    }
+
+    fn game_ended(&mut self, _client: &mut Client, reason: GameOverReason) {
+        self.send(ClientEvent::GameEnded(reason));
+    }
+
+    fn client_error(&mut self, _client: &mut Client, error: ClientError) {
+        self.send(ClientEvent::Error(error));
+    }
+}
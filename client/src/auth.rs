@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{
+    data::{GameVersion, HazelPacket},
+    reader::{IntoReader, PacketWriter, Serialize},
+};
+
+use crate::{
+    networking::{NetClient, TransportConfig},
+    secure::SecureTransport,
+    SystemClock,
+};
+
+/// Official auth servers listen this many ports above the server they're for
+const AUTH_PORT_OFFSET: u16 = 2;
+
+/// The message an auth server answers with
+const AUTH_RESPONSE_TAG: u8 = 1;
+
+/// How long to wait for an auth server to give a nonce
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a nonce is used for before asking again, so warm-ups and rejoins don't each wait
+/// for a handshake
+const NONCE_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Gets whatever a server wants before it accepts a hello, like a token from a matchmaker. See
+/// `ClientSettings::authenticator`
+pub trait Authenticator: Send {
+    /// Called before every hello to `addr`, including after changing server or reconnecting.
+    /// Returns bytes to add to the end of the hello, or `None` to send it as is
+    ///
+    /// On error the hello is sent without a token and it's up to the server what happens
+    fn authenticate(&mut self, addr: SocketAddr, username: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Called before every hello to `addr` too. Returns the nonce to send as the last one
+    /// received, for versions whose hello has it. See `GameVersion::hello_has_chat_mode`
+    #[allow(unused_variables)]
+    fn nonce(&mut self, addr: SocketAddr, username: &str) -> io::Result<Option<u32>> {
+        Ok(None)
+    }
+}
+
+/// For vanilla servers, which don't want anything
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoAuth;
+
+impl Authenticator for NoAuth {
+    fn authenticate(&mut self, _addr: SocketAddr, _username: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
+
+/// Sends the same token to every server, as a string
+#[derive(Debug, Clone)]
+pub struct StaticToken(pub String);
+
+impl Authenticator for StaticToken {
+    fn authenticate(&mut self, _addr: SocketAddr, _username: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut w = PacketWriter::new();
+        w.write_string(&self.0);
+        Ok(Some(w.finish()))
+    }
+}
+
+/// Gets a nonce from the auth server next to each server over a `SecureTransport`, as official
+/// servers have wanted since 2021. `version` has to be new enough for the hello to carry it
+///
+/// Each nonce is reused for `NONCE_LIFETIME`, as getting one means a handshake
+pub struct NonceAuth {
+    transport: Arc<dyn SecureTransport>,
+    version: GameVersion,
+    /// Which platform we say we're on. 2 for Steam
+    pub platform: u8,
+    /// The last nonce for each auth server and username, and when it was given
+    nonces: HashMap<(SocketAddr, String), (u32, Instant)>,
+}
+
+impl NonceAuth {
+    pub fn new(transport: Arc<dyn SecureTransport>, version: GameVersion) -> Self {
+        Self {
+            transport,
+            version,
+            platform: 2,
+            nonces: HashMap::new(),
+        }
+    }
+
+    /// Asks the auth server at `auth_addr` for a new nonce
+    fn request_nonce(&self, auth_addr: SocketAddr, username: &str) -> io::Result<u32> {
+        let mut client = NetClient::connect_secure(
+            auth_addr,
+            Arc::new(SystemClock),
+            TransportConfig::default(),
+            Some(&*self.transport),
+        )?;
+        client.send_hello(Box::new(AuthHello {
+            version: self.version,
+            platform: self.platform,
+            username: username.to_string(),
+        }));
+        let started = Instant::now();
+        while let Some(remaining) = AUTH_TIMEOUT.checked_sub(started.elapsed()) {
+            let data = match client.read_packet_timeout(remaining) {
+                Ok(HazelPacket::Unreliable { data }) | Ok(HazelPacket::Reliable { data, .. }) => {
+                    data
+                }
+                Ok(HazelPacket::Disconnect { .. }) => {
+                    return Err(io::Error::new(
+                        ErrorKind::ConnectionRefused,
+                        "Auth server disconnected",
+                    ))
+                }
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            let mut r = data.into_reader();
+            while let Ok((tag, mut message)) = r.read_message() {
+                if tag == AUTH_RESPONSE_TAG {
+                    return message.read_u32();
+                }
+            }
+        }
+        Err(io::Error::new(
+            ErrorKind::TimedOut,
+            "No nonce from auth server",
+        ))
+    }
+}
+
+impl Authenticator for NonceAuth {
+    fn authenticate(&mut self, _addr: SocketAddr, _username: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    fn nonce(&mut self, addr: SocketAddr, username: &str) -> io::Result<Option<u32>> {
+        let key = (auth_addr(addr)?, username.to_string());
+        if let Some((nonce, given)) = self.nonces.get(&key) {
+            if given.elapsed() < NONCE_LIFETIME {
+                return Ok(Some(*nonce));
+            }
+        }
+        let nonce = self.request_nonce(key.0, username)?;
+        self.nonces.insert(key, (nonce, Instant::now()));
+        Ok(Some(nonce))
+    }
+}
+
+/// The auth server for the server at `addr`
+fn auth_addr(addr: SocketAddr) -> io::Result<SocketAddr> {
+    let port = addr.port().checked_add(AUTH_PORT_OFFSET).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("No auth port above {}", addr.port()),
+        )
+    })?;
+    Ok(SocketAddr::new(addr.ip(), port))
+}
+
+/// The hello an auth server expects
+#[derive(Debug)]
+struct AuthHello {
+    version: GameVersion,
+    platform: u8,
+    username: String,
+}
+
+impl Serialize for AuthHello {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_u8(0); // Reserved byte?
+        w.write_u32(self.version.to_u32());
+        w.write_u8(self.platform);
+        w.write_string(&self.username);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        net::{Ipv4Addr, UdpSocket},
+        thread,
+    };
+
+    use common::{
+        data::{HazelPacketOut, HazelType},
+        reader::Data,
+    };
+
+    use crate::secure::SecureSession;
+
+    /// Plain UDP, standing in for DTLS
+    struct Plain;
+
+    impl SecureTransport for Plain {
+        fn connect(&self, socket: UdpSocket) -> io::Result<Box<dyn SecureSession>> {
+            socket.set_read_timeout(Some(Duration::from_millis(10)))?;
+            Ok(Box::new(PlainSession(socket)))
+        }
+    }
+
+    struct PlainSession(UdpSocket);
+
+    impl SecureSession for PlainSession {
+        fn send(&self, datagram: &[u8]) -> io::Result<()> {
+            self.0.send(datagram).map(|_| ())
+        }
+
+        fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.0.recv(buffer)
+        }
+    }
+
+    #[test]
+    fn test_auth_hello() {
+        let hello = AuthHello {
+            version: GameVersion::new(2021, 4, 25, 0),
+            platform: 2,
+            username: "otter".to_string(),
+        };
+        let mut expected = vec![0];
+        expected.extend_from_slice(&hello.version.to_u32().to_le_bytes());
+        expected.push(2);
+        expected.push(5);
+        expected.extend_from_slice(b"otter");
+        assert_eq!(hello.serialize_bytes(), expected);
+    }
+
+    #[test]
+    fn test_nonce_reused() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        let port = server.local_addr().unwrap().port();
+        // Answers every hello with a nonce, until nothing's been sent for a while
+        let hellos = thread::spawn(move || {
+            let mut w = PacketWriter::new();
+            w.start_message(AUTH_RESPONSE_TAG);
+            w.write_u32(0x1234_5678);
+            w.end_message();
+            let answer = HazelPacketOut::Unreliable {
+                data: Box::new(Data::Bytes(w.finish())),
+            }
+            .serialize_bytes();
+            let mut buffer = [0; 1024];
+            let mut hellos = 0;
+            while let Ok((_, from)) = server.recv_from(&mut buffer) {
+                if buffer[0] == HazelType::Hello as u8 {
+                    hellos += 1;
+                    server.send_to(&answer, from).unwrap();
+                }
+            }
+            hellos
+        });
+
+        let mut auth = NonceAuth::new(Arc::new(Plain), GameVersion::HELLO_CHAT_MODE);
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port - AUTH_PORT_OFFSET);
+        assert_eq!(auth.nonce(addr, "otter").unwrap(), Some(0x1234_5678));
+        assert_eq!(auth.nonce(addr, "otter").unwrap(), Some(0x1234_5678));
+        assert_eq!(hellos.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_auth_port_overflow() {
+        let mut auth = NonceAuth::new(Arc::new(Plain), GameVersion::HELLO_CHAT_MODE);
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), u16::MAX);
+        let error = auth.nonce(addr, "otter").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{io, net::SocketAddr, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use client::{Config, Identity, MainServer, ServerTarget};
+use common::data::GameVersion;
+
+use crate::notify::Watches;
+
+/// Among Us client and tools. Opens the GUI when run without a command
+#[derive(Debug, Parser)]
+#[command(name = "client")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Server to use: eu, na, asia or a private one like impostor.example.com:22023. Dummies are
+    /// spread over every region given, other commands use the first
+    #[arg(long, global = true, value_name = "REGION")]
+    region: Vec<ServerTarget>,
+
+    /// Config file to use instead of the one in the user's config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Connect with the product user id and friend code in the config file, so sessions look like
+    /// the same account. One is made the first time. Used by wizard, annoy and moderate
+    #[arg(long, global = true)]
+    identity: bool,
+}
+
+impl Cli {
+    /// Every region given, or just Europe
+    pub fn regions(&self) -> Vec<ServerTarget> {
+        if self.region.is_empty() {
+            vec![MainServer::Europe.into()]
+        } else {
+            self.region.clone()
+        }
+    }
+
+    /// The config file given, or the default one
+    pub fn config(&self) -> io::Result<Config> {
+        match self.config.clone().or_else(Config::default_path) {
+            Some(path) => Config::open(path),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No config directory, use --config",
+            )),
+        }
+    }
+
+    /// The identity from the config file, if `--identity` was given and it could be read
+    pub fn identity(&self) -> Option<Identity> {
+        if !self.identity {
+            return None;
+        }
+        let identity = self
+            .config()
+            .and_then(|mut config| Identity::from_config(&mut config, &mut rand::thread_rng()));
+        match identity {
+            Ok(identity) => Some(identity),
+            Err(error) => {
+                println!("Couldn't read identity: {}", error);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Lists every public game
+    Scan {
+        /// Host names to flag
+        blacklist: Vec<String>,
+        /// Stop once this many responses in a row bring no new games
+        #[arg(long, value_name = "RESPONSES")]
+        stop_after: Option<u32>,
+    },
+    /// Terminal game browser
+    Tui,
+    /// Joins a game with bots that stand around
+    Dummy {
+        game_code: String,
+        /// How many bots to join
+        #[arg(default_value_t = 1)]
+        count: u32,
+        /// Name bots from a template like "Bot {n}" instead of random words
+        #[arg(long, value_name = "TEMPLATE")]
+        name_template: Option<String>,
+    },
+    /// Joins a game and keeps changing everyone's name and color
+    Wizard { game_code: String },
+    /// Joins a game, renames everyone and leaves
+    Annoy { game_code: String },
+    /// Watches games and writes their stats as JSON lines
+    Observe {
+        stats_file: PathBuf,
+        #[arg(required = true)]
+        game_codes: Vec<String>,
+    },
+    /// Joins a game and enforces chat rules from a file. See `Moderator` for the format
+    Moderate {
+        game_code: String,
+        rules_file: PathBuf,
+    },
+    /// Desktop notifications for hosts and lobbies
+    Notify(Watches),
+    /// Measures latency to every region and any extra servers
+    Regions { addresses: Vec<SocketAddr> },
+    /// Serves the game list over HTTP
+    Mirror {
+        #[arg(default_value = "127.0.0.1:8080")]
+        bind_address: SocketAddr,
+    },
+    /// Finds a protocol version the server accepts, e.g. after a game update
+    ///
+    /// Versions are encoded from build dates, so every day between the two is tried, newest first
+    ProbeVersion {
+        /// First build date to try, like 2020.9.7. Defaults to the newest known version
+        #[arg(long, value_name = "DATE")]
+        from: Option<GameVersion>,
+        /// Last build date to try. Defaults to a year after the first
+        #[arg(long, value_name = "DATE")]
+        to: Option<GameVersion>,
+        /// Revisions to try for each day
+        #[arg(long, default_value_t = 1)]
+        revisions: u32,
+        /// Binary search for the oldest accepted version instead, for servers that accept anything
+        /// at least as new as the version they need
+        #[arg(long)]
+        bisect: bool,
+    },
+    /// Prints a shell completion script
+    Completions { shell: Shell },
+}
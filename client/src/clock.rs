@@ -0,0 +1,87 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Where resends, timeouts and other timers get the time from. See `ClientSettings::clock`
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real time. The default
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests. Clones share the same time
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    /// Starts at the current real time
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networking::NetClient;
+    use common::reader::Data;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), start);
+
+        shared.advance(Duration::from_secs(3));
+        assert_eq!(clock.now(), start + Duration::from_secs(3));
+        assert_eq!(shared.now(), clock.now());
+    }
+
+    #[test]
+    fn test_manual_clock_drives_resends() {
+        let clock = ManualClock::new();
+        let mut client = NetClient::manual(Arc::new(clock.clone()));
+        client.send_reliable(Box::new(Data::Bytes(vec![1, 2, 3])));
+        let sent = client.poll_outbound().unwrap();
+        assert_eq!(client.poll_outbound(), None);
+
+        // Nothing's resent until the clock says it's time, however long it really takes
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(client.poll_outbound(), None);
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(client.poll_outbound(), Some(sent));
+        assert_eq!(client.poll_outbound(), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Settings kept between runs, shared by the GUI and the command line tools
+///
+/// The file has one `key value` line per setting, with keys grouped by a prefix such as
+/// `identity.` or `gui.`. Nothing is written until something is set and saved
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    path: PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Reads the config in `path`. A missing file is an empty config
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut config = Self {
+            path: path.as_ref().to_path_buf(),
+            values: BTreeMap::new(),
+        };
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(config),
+            Err(error) => return Err(error),
+        };
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(' ') {
+                Some((key, value)) => (key, value.trim()),
+                None => (line, ""),
+            };
+            config.values.insert(key.to_string(), value.to_string());
+        }
+        Ok(config)
+    }
+
+    /// `client.cfg` in the user's config directory, e.g. `~/.config/among-us/client.cfg`. `None`
+    /// if the environment doesn't say where that is
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("client.cfg"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn set<V: ToString>(&mut self, key: &str, value: V) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Writes every setting back to the file, creating it and its directory if needed
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut data = String::new();
+        for (key, value) in &self.values {
+            data.push_str(&format!("{} {}\n", key, value));
+        }
+        fs::write(&self.path, data)
+    }
+}
+
+/// `among-us` in the platform's per-user config directory
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        PathBuf::from(dir)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".config")
+    };
+    Some(base.join("among-us"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let dir = env::temp_dir().join("among-us-test-config");
+        let path = dir.join("client.cfg");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut config = Config::open(&path).unwrap();
+        assert_eq!(config.get("gui.username"), None);
+        assert!(!path.exists());
+
+        config.set("gui.username", "oregano");
+        config.set("gui.hide_full", true);
+        config.set("identity.friend_code", "otter#0042");
+        config.save().unwrap();
+        let read = Config::open(&path).unwrap();
+        let data = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read, config);
+        assert_eq!(read.get("gui.hide_full"), Some("true"));
+        assert_eq!(
+            data,
+            "gui.hide_full true\ngui.username oregano\nidentity.friend_code otter#0042\n"
+        );
+    }
+}
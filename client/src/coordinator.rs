@@ -0,0 +1,145 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use common::data::{DisconnectReason, GameOverReason};
+use log::{error, info, warn};
+
+use crate::{
+    error::ClientError, Client, ClientSettings, EventHandler, MainServer, ServerTarget, StatsSink,
+};
+
+/// How long an observer waits before trying a lobby again after failing to reach it
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Settings for a `Coordinator`
+pub struct CoordinatorSettings {
+    /// The server the lobbies are on
+    pub server: ServerTarget,
+
+    /// How many lobbies to observe at once
+    pub pool_size: usize,
+
+    /// Username to connect with
+    pub connect_username: String,
+
+    /// In game username for the observers
+    pub game_username: String,
+
+    /// Whether to put a lobby code back on the end of the queue once its game has finished, so
+    /// the next game in that lobby is observed too
+    pub requeue: bool,
+}
+
+impl Default for CoordinatorSettings {
+    fn default() -> Self {
+        Self {
+            server: MainServer::Europe.into(),
+            pool_size: 4,
+            connect_username: "observer".to_string(),
+            game_username: "observer".to_string(),
+            requeue: false,
+        }
+    }
+}
+
+/// Observes games across many lobbies at once
+///
+/// A pool of clients takes lobby codes from a shared queue, joins, stays until the game ends,
+/// records the game to the stats sink and then moves on to the next code
+pub struct Coordinator<S: StatsSink + 'static> {
+    settings: CoordinatorSettings,
+    queue: Arc<Mutex<VecDeque<String>>>,
+    sink: Arc<Mutex<S>>,
+}
+
+impl<S: StatsSink + 'static> Coordinator<S> {
+    pub fn new(settings: CoordinatorSettings, sink: S) -> Self {
+        Self {
+            settings,
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Adds a lobby code to the end of the queue
+    pub fn add_code(&self, code: &str) {
+        self.queue.lock().unwrap().push_back(code.to_string());
+    }
+
+    /// Runs the observers until the queue is empty. Lobbies that couldn't be reached go back on
+    /// the end of the queue
+    pub fn run(self) {
+        let handles: Vec<JoinHandle<()>> = (0..self.settings.pool_size)
+            .map(|worker| {
+                let queue = self.queue.clone();
+                let sink = self.sink.clone();
+                let server = self.settings.server.clone();
+                let requeue = self.settings.requeue;
+                let connect_username = self.settings.connect_username.clone();
+                let game_username = self.settings.game_username.clone();
+                thread::Builder::new()
+                    .name(format!("observer-{}", worker))
+                    .spawn(move || loop {
+                        let code = match queue.lock().unwrap().pop_front() {
+                            Some(code) => code,
+                            None => return,
+                        };
+                        info!("Observer {} joining {}", worker, code);
+
+                        let settings = ClientSettings {
+                            connect_username: connect_username.clone(),
+                            game_username: game_username.clone(),
+                            stats_sink: Some(Box::new(sink.clone())),
+                            ..ClientSettings::default()
+                        };
+                        let handler = ObserverHandler { finished: false };
+                        let handler =
+                            match Client::run_game_code(handler, server.clone(), &code, settings) {
+                                Ok(handler) => handler,
+                                Err(error) => {
+                                    warn!(
+                                        "Observer {} couldn't observe {}: {}",
+                                        worker, code, error
+                                    );
+                                    queue.lock().unwrap().push_back(code);
+                                    thread::sleep(RETRY_DELAY);
+                                    continue;
+                                }
+                            };
+
+                        if handler.finished && requeue {
+                            queue.lock().unwrap().push_back(code);
+                        }
+                    })
+                    .expect("Couldn't spawn thread")
+            })
+            .collect();
+        for (worker, handle) in handles.into_iter().enumerate() {
+            if let Err(payload) = handle.join() {
+                let thread = format!("observer-{}", worker);
+                error!("{}", ClientError::from_panic(&thread, payload));
+            }
+        }
+    }
+}
+
+/// Leaves as soon as the game ends or the join fails
+struct ObserverHandler {
+    finished: bool,
+}
+
+impl EventHandler for ObserverHandler {
+    fn disconnect_reason(&mut self, client: &mut Client, reason: DisconnectReason) {
+        warn!("Observer disconnected: {:?}", reason);
+        client.disconnect();
+    }
+
+    fn game_ended(&mut self, client: &mut Client, _reason: GameOverReason) {
+        self.finished = true;
+        client.disconnect();
+    }
+}
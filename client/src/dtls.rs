@@ -0,0 +1,151 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::UdpSocket,
+    sync::Mutex,
+    time::Duration,
+};
+
+use log::warn;
+use openssl::{
+    ssl::{self, SslConnector, SslMethod, SslStream, SslVerifyMode},
+    x509::X509,
+};
+
+use crate::secure::{SecureSession, SecureTransport};
+
+/// How long the DTLS handshake can take before giving up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a `DtlsSession::recv` waits before letting a send through. Sends and receives share
+/// the session, so this is the most a send can be held up
+const DTLS_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// DTLS with OpenSSL
+///
+/// Official servers use a self-signed certificate, so by default it isn't checked at all and
+/// anyone in the middle can read and change everything. Give it with `with_certificate` to make
+/// sure it's really them
+#[derive(Debug, Clone, Default)]
+pub struct Dtls {
+    certificate: Option<X509>,
+}
+
+impl Dtls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only talks to servers with `pem` as their certificate
+    pub fn with_certificate(pem: &[u8]) -> io::Result<Self> {
+        let certificate = X509::from_pem(pem).map_err(other)?;
+        Ok(Self {
+            certificate: Some(certificate),
+        })
+    }
+}
+
+impl SecureTransport for Dtls {
+    fn connect(&self, socket: UdpSocket) -> io::Result<Box<dyn SecureSession>> {
+        let mut builder = SslConnector::builder(SslMethod::dtls()).map_err(other)?;
+        match &self.certificate {
+            Some(certificate) => {
+                builder
+                    .cert_store_mut()
+                    .add_cert(certificate.clone())
+                    .map_err(other)?;
+                builder.set_verify(SslVerifyMode::PEER);
+            }
+            None => {
+                warn!(
+                    "Not checking the DTLS certificate of {}, give one with Dtls::with_certificate",
+                    socket
+                        .peer_addr()
+                        .map_or_else(|_| "the server".to_string(), |addr| addr.to_string())
+                );
+                builder.set_verify(SslVerifyMode::NONE)
+            }
+        }
+        let config = builder
+            .build()
+            .configure()
+            .map_err(other)?
+            .verify_hostname(false)
+            .use_server_name_indication(false);
+
+        socket.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let stream = config
+            .connect("", DatagramStream(socket))
+            .map_err(|error| {
+                io::Error::new(
+                    ErrorKind::ConnectionRefused,
+                    format!("DTLS handshake failed: {}", error),
+                )
+            })?;
+        stream
+            .get_ref()
+            .0
+            .set_read_timeout(Some(DTLS_POLL_INTERVAL))?;
+        Ok(Box::new(DtlsSession {
+            stream: Mutex::new(stream),
+        }))
+    }
+}
+
+struct DtlsSession {
+    stream: Mutex<SslStream<DatagramStream>>,
+}
+
+impl SecureSession for DtlsSession {
+    fn send(&self, datagram: &[u8]) -> io::Result<()> {
+        self.stream
+            .lock()
+            .unwrap()
+            .ssl_write(datagram)
+            .map_err(ssl_io)?;
+        Ok(())
+    }
+
+    fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.stream.lock().unwrap().ssl_read(buffer).map_err(ssl_io)
+    }
+}
+
+/// A connected socket read and written a datagram at a time, for OpenSSL
+#[derive(Debug)]
+struct DatagramStream(UdpSocket);
+
+impl Read for DatagramStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.recv(buf)
+    }
+}
+
+impl Write for DatagramStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps timeouts as they were so the receive thread can tell them apart
+fn ssl_io(error: ssl::Error) -> io::Error {
+    error.into_io_error().unwrap_or_else(other)
+}
+
+fn other<E: std::error::Error + Send + Sync + 'static>(error: E) -> io::Error {
+    io::Error::other(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bad_certificate() {
+        let error = Dtls::with_certificate(b"not a certificate").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Other);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    any::Any,
+    fmt, io,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use log::error;
+
+/// Something that stopped the client, rather than the server disconnecting it. Panics are
+/// passed to `EventHandler::client_error` too
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// A background thread panicked, taking down the connection or scan it was running
+    InternalThreadPanic { thread: String, message: String },
+    /// The server couldn't be looked up or connected to
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InternalThreadPanic { thread, message } => {
+                write!(f, "Thread {} panicked: {}", thread, message)
+            }
+            ClientError::Io { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        ClientError::Io {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl ClientError {
+    /// From what `JoinHandle::join` or `catch_unwind` gives back
+    pub(crate) fn from_panic(thread: &str, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "Unknown panic".to_string(),
+            },
+        };
+        ClientError::InternalThreadPanic {
+            thread: thread.to_string(),
+            message,
+        }
+    }
+}
+
+/// Where a background thread's panic ends up, for whoever owns the thread to pick up. Only the
+/// first is kept, as any after it are usually knock-on effects
+pub(crate) type PanicSlot = Arc<Mutex<Option<ClientError>>>;
+
+/// Spawns a thread called `name` that puts its panic in `slot` instead of losing it
+pub(crate) fn spawn_named<F>(name: &str, slot: PanicSlot, f: F) -> JoinHandle<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let thread_name = name.to_string();
+    thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                let error = ClientError::from_panic(&thread_name, payload);
+                error!("{}", error);
+                slot.lock().unwrap().get_or_insert(error);
+            }
+        })
+        .expect("Couldn't spawn thread")
+}
@@ -1,10 +1,17 @@
 use std::{
-    sync::mpsc::{self, Receiver, Sender, TryRecvError},
-    time::Instant,
+    any::Any,
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use client::{Client, ClientSettings, EventHandler, MainServer, ScanSettings};
-use common::data::GameListing;
+use client::{
+    BandwidthStats, Client, ClientError, ClientSettings, Config, EventHandler, MainServer,
+    ScanSettings, MAX_NAME_LENGTH,
+};
+use common::data::{Color, DisconnectReason, GameListing, Hat, Maps, Pet, Skin, Vector2};
 use glium::{
     glutin::{
         self, dpi::LogicalSize, event::Event, event::WindowEvent, event_loop::ControlFlow,
@@ -16,113 +23,295 @@ use imgui::*;
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 
-pub fn run() {
+pub fn run(mut file_config: Config) {
     // Game scanning
     let (ask_scan_send, ask_scan_recv) = mpsc::channel();
     let (scan_results_send, scan_results_recv) = mpsc::channel();
-    let _scan_thread = std::thread::spawn(move || {
-        let settings = ScanSettings {
-            connect_username: "scan".to_string(),
-            max_requests: 1,
-            cache_size: 1,
-            ..ScanSettings::default()
-        };
+    let _scan_thread = std::thread::Builder::new()
+        .name("gui-scan".to_string())
+        .spawn(move || {
+            let settings = ScanSettings {
+                connect_username: "scan".to_string(),
+                max_requests: 1,
+                cache_size: 1,
+                ..ScanSettings::default()
+            };
 
-        let callback = |listings: Vec<GameListing>| {
-            scan_results_send.send(listings).unwrap();
-            if ask_scan_recv.recv().is_err() {
-                return false;
-            }
-            true
-        };
+            let callback = |listings: Vec<GameListing>| {
+                scan_results_send.send(listings).unwrap();
+                if ask_scan_recv.recv().is_err() {
+                    return false;
+                }
+                true
+            };
 
-        Client::server_scan(settings, callback);
-    });
+            if let Err(error) = Client::server_scan(settings, callback) {
+                log::error!("Scan failed: {}", error);
+            }
+        })
+        .unwrap();
 
     // Main client
+    #[derive(Clone)]
     enum JoinGameInfo {
         Listing(GameListing),
         Code(String),
     }
+    /// Sent from the GUI to the client thread
+    enum ClientRequest {
+        /// Leaves any current game and joins another with the given username
+        Join(JoinGameInfo, String),
+        /// Passed on to the client in the current game
+        Command(ClientCommand),
+    }
+    /// Sent from the client thread to the running client
+    enum ClientCommand {
+        Stop,
+        Teleport(Vector2),
+        WalkTo(Vector2),
+        SetColor(Color),
+        SetHat(Hat),
+        SetSkin(Skin),
+        SetPet(Pet),
+    }
     #[derive(Debug, Clone)]
     enum InfoOut {
         ChatMessage {
             player_name: String,
             message: String,
         },
+        /// Shown as a toast
+        Error(String),
+        /// A client, game or receive thread panicked. Shown until reconnecting
+        Crashed(String),
+        /// The connection's traffic, shown in the status bar
+        Connection(BandwidthStats),
     }
-    let (join_game_send, join_game_recv) = mpsc::channel();
     let (info_out_send, info_out_recv) = mpsc::channel();
-    std::thread::spawn(move || {
-        // Wait for initial connection request
-        let mut game_info = match join_game_recv.recv() {
-            Ok(info) => info,
-            Err(_) => return,
+    let mut supervisor = Supervisor::spawn(info_out_send);
+
+    /// Starts the client thread and restarts it if it dies, so a panic doesn't leave the GUI
+    /// with nothing to send joins to
+    struct Supervisor {
+        request_send: Sender<ClientRequest>,
+        thread: Option<JoinHandle<()>>,
+        info_out_send: Sender<InfoOut>,
+        /// The last join asked for, made again by `reconnect`
+        last_join: Option<(JoinGameInfo, String)>,
+    }
+
+    impl Supervisor {
+        fn spawn(info_out_send: Sender<InfoOut>) -> Self {
+            let (request_send, thread) = Self::start(info_out_send.clone());
+            Self {
+                request_send,
+                thread: Some(thread),
+                info_out_send,
+                last_join: None,
+            }
+        }
+
+        fn start(info_out_send: Sender<InfoOut>) -> (Sender<ClientRequest>, JoinHandle<()>) {
+            let (request_send, request_recv) = mpsc::channel();
+            let thread = std::thread::Builder::new()
+                .name("gui-client".to_string())
+                .spawn(move || client_thread(request_recv, info_out_send))
+                .unwrap();
+            (request_send, thread)
+        }
+
+        /// Reports the client thread dying as a crash. Call every frame
+        fn check(&mut self) {
+            if !self.thread.as_ref().is_some_and(JoinHandle::is_finished) {
+                return;
+            }
+            let message = match self.thread.take().unwrap().join() {
+                Ok(()) => "Client thread stopped".to_string(),
+                Err(payload) => panic_message(payload),
+            };
+            let _ = self.info_out_send.send(InfoOut::Crashed(message));
+        }
+
+        /// Restarts the client thread if it died
+        fn revive(&mut self) {
+            if self.thread.is_none() {
+                let (request_send, thread) = Self::start(self.info_out_send.clone());
+                self.request_send = request_send;
+                self.thread = Some(thread);
+            }
+        }
+
+        fn join(&mut self, game_info: JoinGameInfo, username: String) {
+            self.last_join = Some((game_info.clone(), username.clone()));
+            self.revive();
+            let _ = self
+                .request_send
+                .send(ClientRequest::Join(game_info, username));
+        }
+
+        /// Makes the last join again, restarting the client thread if it died
+        fn reconnect(&mut self) {
+            self.revive();
+            if let Some((game_info, username)) = self.last_join.clone() {
+                let _ = self
+                    .request_send
+                    .send(ClientRequest::Join(game_info, username));
+            }
+        }
+
+        /// Returns false if the client thread has stopped
+        fn command(&mut self, command: ClientCommand) -> bool {
+            self.request_send
+                .send(ClientRequest::Command(command))
+                .is_ok()
+        }
+    }
+
+    /// Runs a game thread per join, passing commands on to the latest
+    fn client_thread(request_recv: Receiver<ClientRequest>, info_out_send: Sender<InfoOut>) {
+        // Wait for initial connection request. Nothing to move before then
+        let (mut game_info, mut username) = loop {
+            match request_recv.recv() {
+                Ok(ClientRequest::Join(game_info, username)) => break (game_info, username),
+                Ok(ClientRequest::Command(_)) => (),
+                Err(_) => return,
+            }
         };
         loop {
             // Client settings
             let settings = ClientSettings {
-                connect_username: "oregano".to_string(),
-                game_username: "oregano".to_string(),
+                connect_username: username.clone(),
+                game_username: username,
                 ..ClientSettings::default()
             };
 
             // Handler
-            let (stop_send, stop_recv) = mpsc::channel();
+            let (command_send, command_recv) = mpsc::channel();
             let handler = ClientHandler {
-                stop_recv,
+                command_recv,
                 info_out_send: info_out_send.clone(),
+                last_status: None,
             };
 
             // Run
-            std::thread::spawn(move || match game_info {
-                JoinGameInfo::Listing(listing) => Client::run_game(handler, listing, settings),
-                JoinGameInfo::Code(code) => {
-                    Client::run_game_code(handler, MainServer::Europe, &code, settings)
-                }
-            });
-
-            // Wait for connection request
-            game_info = match join_game_recv.recv() {
-                Ok(info) => info,
-                Err(_) => {
-                    // Send stop request and exit
-                    let _ = stop_send.send(());
-                    return;
+            let mut game = Some(
+                std::thread::Builder::new()
+                    .name("gui-game".to_string())
+                    .spawn(move || match game_info {
+                        JoinGameInfo::Listing(listing) => {
+                            Client::run_game(handler, listing, settings)
+                        }
+                        JoinGameInfo::Code(code) => {
+                            Client::run_game_code(handler, MainServer::Europe, &code, settings)
+                        }
+                    })
+                    .unwrap(),
+            );
+
+            // Pass on requests until the next connection request, checking on the game in
+            // between
+            let request = loop {
+                match request_recv.recv_timeout(SUPERVISE_INTERVAL) {
+                    Ok(ClientRequest::Join(game_info, username)) => break (game_info, username),
+                    Ok(ClientRequest::Command(command)) => {
+                        let _ = command_send.send(command);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if !game.as_ref().is_some_and(JoinHandle::is_finished) {
+                            continue;
+                        }
+                        // A game that ends itself has already reported why, through
+                        // `disconnect_reason` or, if its receive thread panicked, `client_error`
+                        if let Err(payload) = game.take().unwrap().join() {
+                            let message = panic_message(payload);
+                            let _ = info_out_send.send(InfoOut::Crashed(message));
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        // Send stop request and exit
+                        let _ = command_send.send(ClientCommand::Stop);
+                        return;
+                    }
                 }
             };
 
+            game_info = request.0;
+            username = request.1;
+
             // Disconnect old thread
-            let _ = stop_send.send(());
+            let _ = command_send.send(ClientCommand::Stop);
         }
+    }
 
-        struct ClientHandler {
-            stop_recv: Receiver<()>,
-            info_out_send: Sender<InfoOut>,
+    struct ClientHandler {
+        command_recv: Receiver<ClientCommand>,
+        info_out_send: Sender<InfoOut>,
+        /// When the status bar was last sent connection stats
+        last_status: Option<Instant>,
+    }
+
+    impl EventHandler for ClientHandler {
+        fn disconnect_reason(&mut self, client: &mut Client, reason: DisconnectReason) {
+            let message = if client.client_id.is_some() {
+                format!("Disconnected: {:?}", reason)
+            } else {
+                format!("Join failed: {:?}", reason)
+            };
+            let _ = self.info_out_send.send(InfoOut::Error(message));
+            client.disconnect();
         }
 
-        impl EventHandler for ClientHandler {
-            fn packet_received(&mut self, client: &mut Client) {
-                if self.stop_recv.try_recv().is_ok() {
-                    client.disconnect();
+        fn packet_received(&mut self, client: &mut Client) {
+            if !matches!(self.last_status, Some(sent) if sent.elapsed() < STATUS_INTERVAL) {
+                self.last_status = Some(Instant::now());
+                let _ = self
+                    .info_out_send
+                    .send(InfoOut::Connection(client.bandwidth()));
+            }
+            while let Ok(command) = self.command_recv.try_recv() {
+                match command {
+                    ClientCommand::Stop => {
+                        client.disconnect();
+                        return;
+                    }
+                    // Nothing to change until we're in a game
+                    _ if client.client_id.is_none() => (),
+                    ClientCommand::Teleport(position) => client.set_position(position),
+                    ClientCommand::WalkTo(position) => client.walk_to(position),
+                    ClientCommand::SetColor(color) => client.set_color(color as u8),
+                    ClientCommand::SetHat(hat) => client.set_hat(hat as u32),
+                    ClientCommand::SetSkin(skin) => client.set_skin(skin as u32),
+                    ClientCommand::SetPet(pet) => client.set_pet(pet as u32),
                 }
             }
+        }
 
-            fn chat_message(&mut self, client: &mut Client, player_id: i32, message: String) {
-                let player_name = match client.net_objects.get_player_control(player_id) {
-                    Some(control) => control.name.as_ref().unwrap().clone(),
-                    None => "???".to_string(),
-                };
-                let _ = self.info_out_send.send(InfoOut::ChatMessage {
-                    player_name,
-                    message,
-                });
-            }
+        fn chat_message(
+            &mut self,
+            _client: &mut Client,
+            _player_id: i32,
+            player_name: String,
+            message: String,
+        ) {
+            let _ = self.info_out_send.send(InfoOut::ChatMessage {
+                player_name,
+                message,
+            });
         }
-    });
 
-    // Initialize imgui
-    let mut system = System::init("Among Us Client", 1024., 768.);
+        fn client_error(&mut self, _client: &mut Client, error: ClientError) {
+            let _ = self.info_out_send.send(InfoOut::Crashed(error.to_string()));
+        }
+    }
+
+    // Initialize imgui. The dock layout is only built when there's none saved
+    let layout_file = file_config.path().with_file_name(LAYOUT_FILE);
+    let has_layout = layout_file.exists();
+    if let Some(dir) = layout_file.parent() {
+        // imgui won't make it when saving the layout
+        let _ = fs::create_dir_all(dir);
+    }
+    let mut system = System::init("Among Us Client", 1024., 768., &layout_file);
     system.imgui.io_mut().config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
 
     // Style
@@ -139,27 +328,65 @@ pub fn run() {
     // State init
     struct State {
         game_code_input: ImString,
+        username_input: ImString,
+        config: GuiConfig,
         scan_results: Vec<GameListing>,
+        scan_stopped: bool,
         messages: Vec<(String, String)>,
+        position_input: [f32; 2],
+        /// Whether the room buttons walk rather than teleport
+        walk: bool,
+        /// What's picked in each list in `cosmetic_names`
+        cosmetics: [usize; 4],
+        /// The latest stats from the client. `None` until connected and after disconnecting
+        connection: Option<BandwidthStats>,
+        /// Errors from the background threads and when they arrived
+        toasts: Vec<(String, Instant)>,
+        /// Why the client last crashed, until reconnecting or joining another game
+        crashed: Option<String>,
     }
 
-    impl Default for State {
-        fn default() -> Self {
+    impl State {
+        fn new(config: GuiConfig) -> Self {
+            let mut game_code_input = ImString::with_capacity(6);
+            game_code_input.push_str(&config.game_code);
+            let mut username_input = ImString::with_capacity(MAX_NAME_LENGTH);
+            username_input.push_str(&config.username);
             Self {
-                game_code_input: ImString::with_capacity(6),
+                game_code_input,
+                username_input,
+                config,
                 scan_results: Vec::new(),
+                scan_stopped: false,
                 messages: Vec::new(),
+                position_input: [0., 0.],
+                walk: false,
+                cosmetics: [0; 4],
+                connection: None,
+                toasts: Vec::new(),
+                crashed: None,
             }
         }
     }
 
+    // Names for the cosmetics window, as imgui wants them
+    let cosmetic_names = [
+        ("color", cosmetic_list(Color::ALL, Color::name)),
+        ("hat", cosmetic_list(Hat::ALL, Hat::name)),
+        ("skin", cosmetic_list(Skin::ALL, Skin::name)),
+        ("pet", cosmetic_list(Pet::ALL, Pet::name)),
+    ];
+
     // Gui loop
     let start = Instant::now();
-    let mut last_width = 0;
-    let mut last_height = 0;
-    let mut state = State::default();
+    let mut docked = has_layout;
+    let mut saved_config = GuiConfig::load(&file_config);
+    let mut state = State::new(saved_config.clone());
+    let mut last_config = saved_config.clone();
+    let mut last_edit = Instant::now();
     system.main_loop(move |_run, ui, width, height| {
         // Read messages from threads
+        supervisor.check();
         match scan_results_recv.try_recv() {
             Ok(results) => {
                 if !results.is_empty() {
@@ -167,9 +394,13 @@ pub fn run() {
                 }
             }
             Err(TryRecvError::Empty) => (),
-            Err(value) => {
-                eprintln!("{}", value);
-                return;
+            Err(TryRecvError::Disconnected) => {
+                if !state.scan_stopped {
+                    state.scan_stopped = true;
+                    state
+                        .toasts
+                        .push(("Scanning stopped".to_string(), Instant::now()));
+                }
             }
         }
         loop {
@@ -179,12 +410,22 @@ pub fn run() {
                         player_name,
                         message,
                     } => state.messages.push((player_name, message)),
+                    InfoOut::Error(message) => {
+                        state.connection = None;
+                        state.toasts.push((message, Instant::now()));
+                    }
+                    InfoOut::Crashed(message) => {
+                        state.connection = None;
+                        state
+                            .toasts
+                            .push((format!("Client crashed: {}", message), Instant::now()));
+                        state.crashed = Some(message);
+                    }
+                    InfoOut::Connection(stats) => state.connection = Some(stats),
                 },
                 Err(TryRecvError::Empty) => break,
-                Err(value) => {
-                    eprintln!("{}", value);
-                    return;
-                }
+                // The supervisor keeps a sender, so this can't happen
+                Err(TryRecvError::Disconnected) => break,
             }
         }
 
@@ -212,36 +453,236 @@ pub fn run() {
             .resizable(false)
             .movable(false)
             .build(ui, || {
-                if ui.button(im_str!("Scan"), [ui.window_content_region_width(), 20.]) {
-                    ask_scan_send.send(true).unwrap();
+                if ui.button(im_str!("Scan"), [ui.window_content_region_width(), 20.])
+                    && ask_scan_send.send(true).is_err()
+                {
+                    state
+                        .toasts
+                        .push(("Scanning stopped".to_string(), Instant::now()));
                 }
-                for listing in state.scan_results.iter() {
+                let config = &mut state.config;
+                ui.checkbox(im_str!("Skeld"), &mut config.show_skeld);
+                ui.same_line(0.);
+                ui.checkbox(im_str!("Mira"), &mut config.show_mira_hq);
+                ui.same_line(0.);
+                ui.checkbox(im_str!("Polus"), &mut config.show_porus);
+                ui.checkbox(im_str!("Hide full"), &mut config.hide_full);
+                ui.separator();
+                let config = &state.config;
+                for listing in state
+                    .scan_results
+                    .iter()
+                    .filter(|listing| config.shows(listing))
+                {
+                    let (tag, color) = map_tag(listing.map_id);
+                    ui.text_colored(color, tag);
+                    ui.same_line(0.);
                     ui.text(format!("{} {}", listing.id, listing.host_username));
+                    if ui.is_item_hovered() {
+                        // No language, listings don't carry one. See `GameListing`
+                        ui.tooltip_text(format!(
+                            "Listed for {}\n{}",
+                            format_age(listing.age),
+                            listing.address
+                        ));
+                    }
                     ui.same_line(0.);
                     if ui.small_button(im_str!("Join")) {
                         // Join game
-                        if let Err(_error) =
-                            join_game_send.send(JoinGameInfo::Listing(listing.to_owned()))
-                        {
-                            todo!()
-                        }
+                        state.crashed = None;
+                        supervisor.join(
+                            JoinGameInfo::Listing(listing.to_owned()),
+                            state.config.username.clone(),
+                        );
+                    }
+                    ui.text_colored(
+                        fill_color(listing),
+                        format!("{:>2}/{:<2}", listing.player_count, listing.max_players),
+                    );
+                    ui.same_line(0.);
+                    // One mark per impostor
+                    ui.text_colored(IMPOSTOR_COLOR, "!".repeat(listing.num_imposters as usize));
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(format!("{} impostors", listing.num_imposters));
                     }
-                    ui.text(format!(
-                        "{:>2}/{:<2} {:?}",
-                        listing.player_count, listing.max_players, listing.map_id
-                    ));
                     ui.separator();
                 }
                 ui.separator();
+                ui.input_text(im_str!("Username"), &mut state.username_input)
+                    .build();
                 ui.input_text(im_str!("Game code"), &mut state.game_code_input)
                     .chars_noblank(true)
                     .chars_uppercase(true)
                     .build();
+                state.config.username = state.username_input.to_str().to_string();
+                state.config.game_code = state.game_code_input.to_str().to_string();
                 if ui.button(im_str!("Join"), [ui.window_content_region_width(), 20.]) {
-                    if let Err(_error) = join_game_send.send(JoinGameInfo::Code(
-                        state.game_code_input.to_str().to_string(),
-                    )) {
-                        todo!()
+                    state.crashed = None;
+                    supervisor.join(
+                        JoinGameInfo::Code(state.config.game_code.clone()),
+                        state.config.username.clone(),
+                    );
+                }
+            });
+
+        // Status bar
+        let mut reconnect = false;
+        Window::new(im_str!("Status"))
+            .position([10., height as f32 - 10.], Condition::Always)
+            .position_pivot([0., 1.])
+            .title_bar(false)
+            .resizable(false)
+            .movable(false)
+            .always_auto_resize(true)
+            .build(ui, || match (&state.crashed, &state.connection) {
+                (Some(message), _) => {
+                    ui.text_colored(ERROR_COLOR, format!("Crashed: {}", message));
+                    ui.same_line(0.);
+                    if ui.small_button(im_str!("Reconnect")) {
+                        reconnect = true;
+                    }
+                }
+                (None, Some(stats)) => {
+                    ui.text(format!(
+                        "Sent {} KB, received {} KB",
+                        stats.bytes_sent / 1024,
+                        stats.bytes_received / 1024
+                    ));
+                    ui.same_line(0.);
+                    let backlog = format!("{} unacked", stats.unacknowledged);
+                    match stats.oldest_unacknowledged {
+                        Some(oldest) if oldest >= SLOW_ACK => {
+                            ui.text_colored(
+                                ERROR_COLOR,
+                                format!("{}, oldest {:.1}s", backlog, oldest.as_secs_f32()),
+                            );
+                        }
+                        _ => ui.text(backlog),
+                    }
+                }
+                (None, None) => ui.text("Not connected"),
+            });
+        if reconnect {
+            state.crashed = None;
+            supervisor.reconnect();
+        }
+
+        // Toasts, newest at the bottom
+        state
+            .toasts
+            .retain(|(_, shown)| shown.elapsed() < TOAST_DURATION);
+        if !state.toasts.is_empty() {
+            let mut dismissed = None;
+            Window::new(im_str!("Notifications"))
+                .position([width as f32 - 10., height as f32 - 10.], Condition::Always)
+                .position_pivot([1., 1.])
+                .title_bar(false)
+                .resizable(false)
+                .movable(false)
+                .always_auto_resize(true)
+                .build(ui, || {
+                    for (index, (message, _)) in state.toasts.iter().enumerate() {
+                        ui.text_colored(ERROR_COLOR, message);
+                        ui.same_line(0.);
+                        if ui.small_button(&im_str!("x##toast{}", index)) {
+                            dismissed = Some(index);
+                        }
+                    }
+                });
+            if let Some(index) = dismissed {
+                state.toasts.remove(index);
+            }
+        }
+
+        // Remember inputs once they've stopped changing, rather than on every keystroke
+        if state.config != last_config {
+            last_config = state.config.clone();
+            last_edit = Instant::now();
+        }
+        if state.config != saved_config && last_edit.elapsed() >= SAVE_DELAY {
+            saved_config = state.config.clone();
+            saved_config.save(&mut file_config);
+        }
+
+        // Position window
+        Window::new(im_str!("Position"))
+            .resizable(false)
+            .movable(false)
+            .build(ui, || {
+                ui.input_float2(im_str!("X/Y"), &mut state.position_input)
+                    .build();
+                let mut target = None;
+                let half_width = (ui.window_content_region_width() - 8.) / 2.;
+                if ui.button(im_str!("Teleport"), [half_width, 20.]) {
+                    target = Some((state.position_input, false));
+                }
+                ui.same_line(0.);
+                if ui.button(im_str!("Walk"), [half_width, 20.]) {
+                    target = Some((state.position_input, true));
+                }
+                ui.separator();
+                ui.text("Skeld");
+                ui.same_line(0.);
+                ui.checkbox(im_str!("Walk there"), &mut state.walk);
+                for (index, (room, x, y)) in SKELD_ROOMS.iter().enumerate() {
+                    if index % 3 != 0 {
+                        ui.same_line(0.);
+                    }
+                    if ui.small_button(&ImString::new(*room)) {
+                        state.position_input = [*x, *y];
+                        target = Some((state.position_input, state.walk));
+                    }
+                }
+                if let Some(([x, y], walk)) = target {
+                    let command = if walk {
+                        ClientCommand::WalkTo(Vector2::new(x, y))
+                    } else {
+                        ClientCommand::Teleport(Vector2::new(x, y))
+                    };
+                    if !supervisor.command(command) {
+                        state
+                            .toasts
+                            .push(("Client thread stopped".to_string(), Instant::now()));
+                    }
+                }
+            });
+
+        // Cosmetics window
+        Window::new(im_str!("Cosmetics"))
+            .resizable(false)
+            .movable(false)
+            .build(ui, || {
+                let mut command = None;
+                for (kind, (label, names)) in cosmetic_names.iter().enumerate() {
+                    let names = names.iter().collect::<Vec<_>>();
+                    let selected = &mut state.cosmetics[kind];
+                    ComboBox::new(&im_str!("##{}", label))
+                        .build_simple_string(ui, selected, &names);
+                    ui.same_line(0.);
+                    if ui.button(&im_str!("Set {}", label), [80., 0.]) {
+                        command = Some(match kind {
+                            0 => ClientCommand::SetColor(Color::ALL[*selected]),
+                            1 => ClientCommand::SetHat(Hat::ALL[*selected]),
+                            2 => ClientCommand::SetSkin(Skin::ALL[*selected]),
+                            _ => ClientCommand::SetPet(Pet::ALL[*selected]),
+                        });
+                    }
+                    // Preview the color, as there are no sprites to show for the rest
+                    if kind == 0 {
+                        let [r, g, b] = Color::ALL[*selected].rgb();
+                        ui.same_line(0.);
+                        ColorButton::new(
+                            im_str!("##preview"),
+                            [r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.],
+                        )
+                        .build(ui);
+                    }
+                }
+                if let Some(command) = command {
+                    if !supervisor.command(command) {
+                        state
+                            .toasts
+                            .push(("Client thread stopped".to_string(), Instant::now()));
                     }
                 }
             });
@@ -256,10 +697,9 @@ pub fn run() {
                 }
             });
 
-        // Dock windows if resized or first run
-        if width != last_width || height != last_height {
-            last_height = height;
-            last_width = width;
+        // Dock windows on the first run. After that imgui keeps the layout in `LAYOUT_FILE`
+        if !docked {
+            docked = true;
             Dock::new().build(|root| {
                 root.position([0., 0.])
                     .size([width as f32 / 2., height as f32 / 2.])
@@ -274,7 +714,17 @@ pub fn run() {
                                 Direction::Right,
                                 0.25,
                                 |right| {
-                                    right.dock_window(im_str!("Chat"));
+                                    right.split(
+                                        Direction::Down,
+                                        0.4,
+                                        |down| {
+                                            down.dock_window(im_str!("Position"));
+                                            down.dock_window(im_str!("Cosmetics"));
+                                        },
+                                        |up| {
+                                            up.dock_window(im_str!("Chat"));
+                                        },
+                                    );
                                 },
                                 |left| {
                                     left.dock_window(im_str!("Hello world"));
@@ -287,6 +737,164 @@ pub fn run() {
     })
 }
 
+/// Rough middle of each Skeld room, for the room buttons
+const SKELD_ROOMS: &[(&str, f32, f32)] = &[
+    ("Cafeteria", -1., 3.),
+    ("Weapons", 9., 1.),
+    ("O2", 6.5, -3.5),
+    ("Navigation", 16.5, -4.8),
+    ("Shields", 9.3, -12.3),
+    ("Comms", 4., -15.5),
+    ("Storage", -1.5, -15.5),
+    ("Admin", 4.5, -7.5),
+    ("Electrical", -7.5, -8.5),
+    ("Lower Engine", -17., -13.5),
+    ("Security", -13.5, -5.5),
+    ("Reactor", -20.5, -5.5),
+    ("Upper Engine", -17., -1.),
+    ("MedBay", -9., -4.),
+];
+
+fn cosmetic_list<T: Copy>(all: &[T], name: fn(T) -> &'static str) -> Vec<ImString> {
+    all.iter().map(|&item| ImString::new(name(item))).collect()
+}
+
+/// Where imgui keeps window positions and the dock layout, next to the config file
+const LAYOUT_FILE: &str = "imgui.ini";
+
+/// How long inputs have to stay the same before they're saved
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Inputs and filters remembered between runs, kept in the config file under `gui.`
+#[derive(Debug, Clone, PartialEq)]
+struct GuiConfig {
+    game_code: String,
+    username: String,
+    show_skeld: bool,
+    show_mira_hq: bool,
+    show_porus: bool,
+    /// Hide games with no space left
+    hide_full: bool,
+}
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        Self {
+            game_code: String::new(),
+            username: "oregano".to_string(),
+            show_skeld: true,
+            show_mira_hq: true,
+            show_porus: true,
+            hide_full: false,
+        }
+    }
+}
+
+impl GuiConfig {
+    /// Reads the `gui.` settings, using defaults for anything missing
+    fn load(file_config: &Config) -> Self {
+        let mut config = Self::default();
+        let flag = |key: &str, default: bool| {
+            file_config
+                .get(key)
+                .map_or(default, |value| value == "true")
+        };
+        if let Some(game_code) = file_config.get("gui.game_code") {
+            config.game_code = game_code.to_string();
+        }
+        if let Some(username) = file_config.get("gui.username") {
+            config.username = username.to_string();
+        }
+        config.show_skeld = flag("gui.show_skeld", config.show_skeld);
+        config.show_mira_hq = flag("gui.show_mira_hq", config.show_mira_hq);
+        config.show_porus = flag("gui.show_porus", config.show_porus);
+        config.hide_full = flag("gui.hide_full", config.hide_full);
+        config
+    }
+
+    fn save(&self, file_config: &mut Config) {
+        file_config.set("gui.game_code", &self.game_code);
+        file_config.set("gui.username", &self.username);
+        file_config.set("gui.show_skeld", self.show_skeld);
+        file_config.set("gui.show_mira_hq", self.show_mira_hq);
+        file_config.set("gui.show_porus", self.show_porus);
+        file_config.set("gui.hide_full", self.hide_full);
+        if let Err(error) = file_config.save() {
+            eprintln!("Couldn't save config: {}", error);
+        }
+    }
+
+    /// Whether a listing passes the filters
+    fn shows(&self, listing: &GameListing) -> bool {
+        let map_shown = if listing.map_id == Maps::PORUS {
+            self.show_porus
+        } else if listing.map_id == Maps::MIRA_HQ {
+            self.show_mira_hq
+        } else {
+            self.show_skeld
+        };
+        map_shown && !(self.hide_full && listing.player_count >= listing.max_players)
+    }
+}
+
+const IMPOSTOR_COLOR: [f32; 4] = [0.85, 0.2, 0.2, 1.];
+
+const ERROR_COLOR: [f32; 4] = [1., 0.45, 0.4, 1.];
+
+/// How often the client thread sends connection stats for the status bar
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Unacknowledged packets older than this are shown as a problem. Resends happen every second so
+/// a healthy connection stays well under it
+const SLOW_ACK: Duration = Duration::from_secs(3);
+
+/// How often the client thread checks whether the game thread has panicked
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long toasts stay up if not dismissed
+const TOAST_DURATION: Duration = Duration::from_secs(10);
+
+/// What a thread panicked with, if it was a string
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Unknown panic".to_string(),
+        },
+    }
+}
+
+/// A short colored tag for each map, so rows can be told apart at a glance
+fn map_tag(map: Maps) -> (&'static str, [f32; 4]) {
+    if map == Maps::PORUS {
+        ("POL", [0.45, 0.6, 0.95, 1.])
+    } else if map == Maps::MIRA_HQ {
+        ("MIR", [0.3, 0.8, 0.7, 1.])
+    } else {
+        ("SKL", [0.7, 0.7, 0.7, 1.])
+    }
+}
+
+/// Green with room to spare, yellow with one space left and red when full
+fn fill_color(listing: &GameListing) -> [f32; 4] {
+    if listing.player_count >= listing.max_players {
+        [0.9, 0.25, 0.25, 1.]
+    } else if listing.player_count + 1 == listing.max_players {
+        [0.9, 0.8, 0.2, 1.]
+    } else {
+        [0.3, 0.85, 0.3, 1.]
+    }
+}
+
+fn format_age(age: u32) -> String {
+    if age < 60 {
+        format!("{}s", age)
+    } else {
+        format!("{}m {}s", age / 60, age % 60)
+    }
+}
+
 /// Handles window backend stuff
 struct System {
     event_loop: EventLoop<()>,
@@ -298,7 +906,7 @@ struct System {
 }
 
 impl System {
-    fn init(title: &str, width: f64, height: f64) -> System {
+    fn init(title: &str, width: f64, height: f64, layout_file: &Path) -> System {
         let builder = WindowBuilder::new()
             .with_title(title)
             .with_inner_size(LogicalSize::new(width, height));
@@ -308,7 +916,7 @@ impl System {
             Display::new(builder, context, &event_loop).expect("Failed to create display");
 
         let mut imgui = Context::create();
-        imgui.set_ini_filename(None);
+        imgui.set_ini_filename(Some(ImString::new(layout_file.to_string_lossy())));
 
         let mut platform = WinitPlatform::init(&mut imgui);
         {
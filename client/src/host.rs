@@ -0,0 +1,599 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use common::data::{
+    map_tasks, GameOptions, GameOverReason, PlayerData, TaskLength, VoteState, World,
+};
+use rand::{seq::SliceRandom, Rng};
+
+/// A player joining a game we're hosting
+///
+/// The join packet only says who joined. Their name comes later and goes through
+/// `EventHandler::check_name`, and the servers never pass on versions, platforms or addresses
+#[derive(Debug, Clone)]
+pub struct JoinRequest {
+    pub client_id: i32,
+}
+
+/// What to do with a player joining a game we're hosting
+#[derive(Debug, Clone)]
+pub enum Admission {
+    Accept,
+
+    /// Remove the player from the game. They can join again
+    ///
+    /// The official servers don't let the host say why, so there's no reason to give
+    Kick,
+
+    /// Remove the player from the game and have the server keep them out of it
+    Ban,
+
+    /// Let the player stay but never spawn a character for them
+    Spectate,
+}
+
+/// Bans by name, kept in a file so they survive restarts. Players' addresses never reach the
+/// host, so names are all there is to go on
+///
+/// The file has one ban per line, `name <name>`. Names are compared ignoring case
+#[derive(Debug)]
+pub struct BanList {
+    path: PathBuf,
+    names: HashSet<String>,
+}
+
+impl BanList {
+    /// Reads the bans in `path`. A missing file is treated as empty and created on the first ban
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut bans = Self {
+            path: path.as_ref().to_path_buf(),
+            names: HashSet::new(),
+        };
+        let file = match File::open(&bans.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(bans),
+            Err(error) => return Err(error),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(' ') {
+                Some(("name", name)) => {
+                    bans.names.insert(name.trim().to_lowercase());
+                }
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "Invalid ban line")),
+            }
+        }
+        Ok(bans)
+    }
+
+    pub fn is_name_banned(&self, name: &str) -> bool {
+        self.names.contains(&name.to_lowercase())
+    }
+
+    pub fn ban_name(&mut self, name: &str) -> io::Result<()> {
+        if self.names.insert(name.to_lowercase()) {
+            self.append(&format!("name {}", name))?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, line: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+/// Picks impostors given the player ids and the number to pick
+pub type ImpostorCallback = Box<dyn FnMut(&[u8], usize) -> Vec<u8> + Send>;
+
+/// How the host picks impostors when a game starts
+#[derive(Default)]
+pub enum ImpostorSelection {
+    /// Every player is equally likely
+    #[default]
+    Random,
+
+    /// Players are more likely to be picked the higher their weight. Players without a weight
+    /// have a weight of 1, and a weight of 0 means never picked unless there's no one else
+    Weighted(HashMap<u8, u32>),
+
+    Custom(ImpostorCallback),
+}
+
+impl ImpostorSelection {
+    pub fn select<R: Rng>(&mut self, player_ids: &[u8], count: usize, rng: &mut R) -> Vec<u8> {
+        match self {
+            ImpostorSelection::Random => player_ids.choose_multiple(rng, count).copied().collect(),
+            ImpostorSelection::Weighted(weights) => {
+                let mut remaining = player_ids
+                    .iter()
+                    .map(|id| (*id, weights.get(id).copied().unwrap_or(1)))
+                    .collect::<Vec<_>>();
+                let mut selected = Vec::new();
+                while selected.len() < count && !remaining.is_empty() {
+                    let total: u32 = remaining.iter().map(|(_, weight)| weight).sum();
+                    let index = if total == 0 {
+                        rng.gen_range(0, remaining.len())
+                    } else {
+                        let mut target = rng.gen_range(0, total);
+                        remaining
+                            .iter()
+                            .position(|(_, weight)| {
+                                if target < *weight {
+                                    true
+                                } else {
+                                    target -= weight;
+                                    false
+                                }
+                            })
+                            .unwrap()
+                    };
+                    selected.push(remaining.remove(index).0);
+                }
+                selected
+            }
+            ImpostorSelection::Custom(callback) => callback(player_ids, count),
+        }
+    }
+}
+
+/// The most impostors the official game allows for a number of players
+pub fn max_impostors(player_count: usize) -> usize {
+    GameOptions::max_imposters(player_count.min(u8::MAX as usize) as u8) as usize
+}
+
+/// Task type ids the host can hand out, split by kind
+#[derive(Debug, Clone, Default)]
+pub struct TaskPool {
+    pub common: Vec<u8>,
+    pub short: Vec<u8>,
+    pub long: Vec<u8>,
+}
+
+impl TaskPool {
+    /// Every task on a map, by `GameOptions::map_id`
+    pub fn for_map(map_id: u8) -> Option<Self> {
+        let mut pool = Self::default();
+        for task in map_tasks(map_id)? {
+            match task.length {
+                TaskLength::Common => pool.common.push(task.id),
+                TaskLength::Short => pool.short.push(task.id),
+                TaskLength::Long => pool.long.push(task.id),
+            }
+        }
+        Some(pool)
+    }
+
+    /// Picks tasks for each player using the task counts in `options`
+    ///
+    /// Everyone gets the same common tasks. Short and long tasks are picked separately for each
+    /// player
+    pub fn assign<R: Rng>(
+        &self,
+        options: &GameOptions,
+        player_ids: &[u8],
+        rng: &mut R,
+    ) -> HashMap<u8, Vec<u8>> {
+        let common = self
+            .common
+            .choose_multiple(rng, options.num_common_tasks as usize)
+            .copied()
+            .collect::<Vec<_>>();
+        player_ids
+            .iter()
+            .map(|&player_id| {
+                let mut tasks = common.clone();
+                tasks.extend(
+                    self.long
+                        .choose_multiple(rng, options.num_long_tasks as usize),
+                );
+                tasks.extend(
+                    self.short
+                        .choose_multiple(rng, options.num_short_tasks as usize),
+                );
+                (player_id, tasks)
+            })
+            .collect()
+    }
+}
+
+/// A meeting being run by us as host
+#[derive(Debug)]
+pub(crate) struct HostMeeting {
+    /// Net id of the spawned `MeetingHud`
+    pub(crate) net_id: u32,
+    started: Instant,
+    /// Discussion plus voting time. `None` if voting never times out
+    duration: Option<Duration>,
+    /// When the result was sent, if it has been
+    pub(crate) completed: Option<Instant>,
+}
+
+impl HostMeeting {
+    pub(crate) fn new(net_id: u32, options: Option<&GameOptions>, now: Instant) -> Self {
+        let duration = options.and_then(|options| {
+            if options.voting_time <= 0 {
+                None
+            } else {
+                let seconds = options.discussion_time.max(0) + options.voting_time;
+                Some(Duration::from_secs(seconds as u64))
+            }
+        });
+        Self {
+            net_id,
+            started: now,
+            duration,
+            completed: None,
+        }
+    }
+
+    pub(crate) fn timed_out(&self, now: Instant) -> bool {
+        self.duration
+            .is_some_and(|duration| now.duration_since(self.started) >= duration)
+    }
+}
+
+/// How long to wait for everyone to load the map after starting a game before going on without them
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A game we've started as host, waiting for everyone to load the map
+#[derive(Debug)]
+pub(crate) struct GameStart {
+    started: Instant,
+    /// Client ids of everyone who's sent `ClientReady`, including us
+    pub(crate) ready: HashSet<i32>,
+}
+
+impl GameStart {
+    pub(crate) fn new(host_id: i32, now: Instant) -> Self {
+        Self {
+            started: now,
+            ready: std::iter::once(host_id).collect(),
+        }
+    }
+
+    pub(crate) fn timed_out(&self, now: Instant) -> bool {
+        now.duration_since(self.started) >= READY_TIMEOUT
+    }
+}
+
+/// Works out who gets voted out. Returns the exiled player, if any, and whether there was a tie
+///
+/// Skips count as a candidate, so if skipping gets the most votes no one is exiled
+pub fn tally_votes(states: &[VoteState]) -> (Option<u8>, bool) {
+    let mut counts: HashMap<Option<u8>, u32> = HashMap::new();
+    for state in states
+        .iter()
+        .filter(|state| state.did_vote && !state.is_dead)
+    {
+        *counts.entry(state.voted_for).or_default() += 1;
+    }
+    let max = match counts.values().max() {
+        Some(&max) => max,
+        None => return (None, false),
+    };
+    let mut top = counts.iter().filter(|(_, &count)| count == max);
+    let (&exiled, _) = top.next().unwrap();
+    if top.next().is_some() {
+        (None, true)
+    } else {
+        (exiled, false)
+    }
+}
+
+/// What happened to prompt a win check. Decides which `GameOverReason` is reported
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WinCheckCause {
+    Kill,
+    Exile,
+    Disconnect,
+    TaskComplete,
+}
+
+/// The value reactor and life support countdowns sit at when not sabotaged
+const INACTIVE_COUNTDOWN: f32 = 10000.;
+
+/// Works out whether the game is over, and why
+///
+/// Returns `None` before impostors have been assigned
+pub fn check_win(
+    players: &HashMap<u8, PlayerData>,
+    cause: WinCheckCause,
+) -> Option<GameOverReason> {
+    if !players.values().any(|data| data.is_imposter) {
+        return None;
+    }
+    let mut impostors_alive = 0;
+    let mut crew_alive = 0;
+    let mut total_tasks = 0;
+    let mut completed_tasks = 0;
+    for data in players.values().filter(|data| !data.disconnected) {
+        if data.is_imposter {
+            if !data.is_dead {
+                impostors_alive += 1;
+            }
+            continue;
+        }
+        if !data.is_dead {
+            crew_alive += 1;
+        }
+        // Dead crewmates still do their tasks as ghosts
+        total_tasks += data.tasks.len();
+        completed_tasks += data.tasks.iter().filter(|task| task.is_complete()).count();
+    }
+
+    if impostors_alive == 0 {
+        return Some(match cause {
+            WinCheckCause::Disconnect => GameOverReason::ImpostorDisconnect,
+            _ => GameOverReason::HumansByVote,
+        });
+    }
+    if crew_alive <= impostors_alive {
+        return Some(match cause {
+            WinCheckCause::Exile => GameOverReason::ImpostorByVote,
+            WinCheckCause::Disconnect => GameOverReason::HumansDisconnect,
+            _ => GameOverReason::ImpostorByKill,
+        });
+    }
+    if total_tasks > 0 && completed_tasks == total_tasks {
+        return Some(GameOverReason::HumansByTask);
+    }
+    None
+}
+
+/// How long until a reactor or oxygen sabotage kills everyone, if one is active
+pub(crate) fn critical_countdown(world: &World) -> Option<Duration> {
+    [world.reactor_countdown, world.life_supp_countdown]
+        .iter()
+        .filter(|&&countdown| countdown < INACTIVE_COUNTDOWN)
+        .map(|&countdown| Duration::from_secs_f32(countdown.max(0.)))
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::data::TaskInfo;
+    use rand::{rngs::SmallRng, SeedableRng};
+    use std::fs;
+
+    #[test]
+    fn test_ban_list() {
+        let path = std::env::temp_dir().join("among-us-test-bans.txt");
+        let _ = fs::remove_file(&path);
+        let mut bans = BanList::open(&path).unwrap();
+        assert!(!bans.is_name_banned("Griefer"));
+        bans.ban_name("Griefer").unwrap();
+        bans.ban_name("griefer").unwrap();
+        assert!(bans.is_name_banned("GRIEFER"));
+
+        // Saved once, and read back after a restart
+        assert_eq!(fs::read_to_string(&path).unwrap(), "name Griefer\n");
+        assert!(BanList::open(&path).unwrap().is_name_banned("griefer"));
+
+        fs::write(&path, "ip 10.0.0.1\n").unwrap();
+        assert_eq!(
+            BanList::open(&path).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    fn vote(player_id: u8, voted_for: Option<u8>) -> VoteState {
+        VoteState {
+            player_id,
+            voted_for,
+            is_dead: false,
+            did_vote: true,
+            did_report: false,
+        }
+    }
+
+    #[test]
+    fn test_tally_votes() {
+        assert_eq!(tally_votes(&[]), (None, false));
+        assert_eq!(
+            tally_votes(&[vote(0, Some(2)), vote(1, Some(2)), vote(2, Some(0))]),
+            (Some(2), false)
+        );
+
+        // Not voting and being dead don't count
+        let mut not_voted = vote(3, None);
+        not_voted.did_vote = false;
+        let mut dead = vote(4, Some(0));
+        dead.is_dead = true;
+        assert_eq!(
+            tally_votes(&[
+                vote(0, Some(2)),
+                vote(1, Some(0)),
+                vote(2, Some(0)),
+                not_voted,
+                dead
+            ]),
+            (Some(0), false)
+        );
+    }
+
+    #[test]
+    fn test_tally_votes_tie() {
+        assert_eq!(
+            tally_votes(&[vote(0, Some(1)), vote(1, Some(0))]),
+            (None, true)
+        );
+        // Skipping ties like any other candidate
+        assert_eq!(
+            tally_votes(&[
+                vote(0, Some(1)),
+                vote(1, None),
+                vote(2, None),
+                vote(3, Some(1))
+            ]),
+            (None, true)
+        );
+    }
+
+    #[test]
+    fn test_tally_votes_skip() {
+        assert_eq!(
+            tally_votes(&[vote(0, None), vote(1, None), vote(2, Some(0))]),
+            (None, false)
+        );
+    }
+
+    /// Players by id, impostors first, each crewmate with one task
+    fn players(impostors: u8, crew: u8) -> HashMap<u8, PlayerData> {
+        (0..impostors + crew)
+            .map(|player_id| {
+                let is_imposter = player_id < impostors;
+                let data = PlayerData {
+                    is_imposter,
+                    tasks: if is_imposter {
+                        Vec::new()
+                    } else {
+                        vec![TaskInfo::new(0)]
+                    },
+                    ..PlayerData::default()
+                };
+                (player_id, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_win_parity() {
+        // Impostors win once they're as many as the crew
+        let mut game = players(2, 3);
+        assert_eq!(check_win(&game, WinCheckCause::Kill), None);
+        game.get_mut(&2).unwrap().is_dead = true;
+        assert_eq!(
+            check_win(&game, WinCheckCause::Kill),
+            Some(GameOverReason::ImpostorByKill)
+        );
+        assert_eq!(
+            check_win(&game, WinCheckCause::Exile),
+            Some(GameOverReason::ImpostorByVote)
+        );
+        assert_eq!(
+            check_win(&game, WinCheckCause::Disconnect),
+            Some(GameOverReason::HumansDisconnect)
+        );
+
+        // Crew win once the impostors are gone
+        let mut game = players(2, 3);
+        game.get_mut(&0).unwrap().is_dead = true;
+        assert_eq!(check_win(&game, WinCheckCause::Exile), None);
+        game.get_mut(&1).unwrap().is_dead = true;
+        assert_eq!(
+            check_win(&game, WinCheckCause::Exile),
+            Some(GameOverReason::HumansByVote)
+        );
+        game.get_mut(&1).unwrap().is_dead = false;
+        game.get_mut(&1).unwrap().disconnected = true;
+        assert_eq!(
+            check_win(&game, WinCheckCause::Disconnect),
+            Some(GameOverReason::ImpostorDisconnect)
+        );
+    }
+
+    #[test]
+    fn test_check_win_tasks() {
+        let mut game = players(1, 3);
+        for player_id in 1..4 {
+            game.get_mut(&player_id).unwrap().tasks[0].set_complete();
+        }
+        // Dead crewmates' tasks still count
+        game.get_mut(&3).unwrap().is_dead = true;
+        assert_eq!(
+            check_win(&game, WinCheckCause::TaskComplete),
+            Some(GameOverReason::HumansByTask)
+        );
+        game.get_mut(&1).unwrap().tasks.push(TaskInfo::new(1));
+        assert_eq!(check_win(&game, WinCheckCause::TaskComplete), None);
+    }
+
+    #[test]
+    fn test_check_win_before_impostors() {
+        assert_eq!(check_win(&players(0, 4), WinCheckCause::Kill), None);
+    }
+
+    #[test]
+    fn test_weighted_selection() {
+        let player_ids = [0, 1, 2, 3];
+        let weights = vec![(0, 0), (1, 0), (2, 1000)].into_iter().collect();
+        let mut selection = ImpostorSelection::Weighted(weights);
+        let select = |selection: &mut ImpostorSelection, count, seed| {
+            selection.select(&player_ids, count, &mut SmallRng::seed_from_u64(seed))
+        };
+
+        // The same seed picks the same players
+        assert_eq!(select(&mut selection, 2, 7), select(&mut selection, 2, 7));
+        let heavy = (0..100)
+            .filter(|&seed| select(&mut selection, 1, seed) == vec![2])
+            .count();
+        assert!(heavy > 95, "{}", heavy);
+
+        for seed in 0..20 {
+            // Weight 0 is only picked once everyone else has been
+            let mut picked = select(&mut selection, 2, seed);
+            picked.sort_unstable();
+            assert_eq!(picked, vec![2, 3]);
+            let picked = select(&mut selection, 4, seed);
+            assert!(picked[2] < 2 && picked[3] < 2, "{:?}", picked);
+        }
+        // Asking for more than there are picks everyone
+        assert_eq!(select(&mut selection, 10, 0).len(), player_ids.len());
+    }
+
+    #[test]
+    fn test_assign_tasks() {
+        let pool = TaskPool::for_map(0).unwrap();
+        let options = GameOptions {
+            num_common_tasks: 1,
+            num_long_tasks: 1,
+            num_short_tasks: 2,
+            ..GameOptions::default()
+        };
+        let assigned = pool.assign(&options, &[0, 1], &mut SmallRng::seed_from_u64(1));
+        assert_eq!(assigned.len(), 2);
+        // Everyone gets the same common task
+        assert_eq!(assigned[&0][0], assigned[&1][0]);
+        for tasks in assigned.values() {
+            assert_eq!(tasks.len(), 4);
+            assert!(pool.common.contains(&tasks[0]));
+            assert!(pool.long.contains(&tasks[1]));
+            assert!(pool.short.contains(&tasks[2]) && pool.short.contains(&tasks[3]));
+            assert_ne!(tasks[2], tasks[3]);
+        }
+    }
+
+    #[test]
+    fn test_assign_tasks_exhausted() {
+        let pool = TaskPool {
+            common: vec![1],
+            short: vec![2, 3],
+            long: Vec::new(),
+        };
+        let options = GameOptions {
+            num_common_tasks: 3,
+            num_long_tasks: 2,
+            num_short_tasks: 5,
+            ..GameOptions::default()
+        };
+        // Everything in the pool is handed out once, never repeated
+        let assigned = pool.assign(&options, &[4], &mut SmallRng::seed_from_u64(1));
+        let mut tasks = assigned[&4].clone();
+        tasks.sort_unstable();
+        assert_eq!(tasks, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::io::{self, ErrorKind};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::config::Config;
+
+const FRIEND_CODE_WORDS: &[&str] = &[
+    "otter", "moth", "crab", "llama", "toad", "owl", "yak", "wren", "bean", "pickle", "panda",
+    "noodle", "badger", "ferret", "goose", "squid",
+];
+
+/// Account details newer servers expect in the hello. See `ClientSettings::identity`
+///
+/// Kept in the config file as `identity.product_user_id` and `identity.friend_code`, so every
+/// session looks like the same account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// 32 lower-case hex digits
+    pub product_user_id: String,
+    /// A word, `#` and four digits, e.g. `otter#1234`
+    pub friend_code: String,
+}
+
+impl Identity {
+    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+        let product_user_id = (0..32)
+            .map(|_| std::char::from_digit(rng.gen_range(0, 16), 16).unwrap())
+            .collect();
+        let word = FRIEND_CODE_WORDS.choose(rng).unwrap();
+        Self {
+            product_user_id,
+            friend_code: format!("{}#{:04}", word, rng.gen_range(0, 10000)),
+        }
+    }
+
+    /// The identity kept in `config`. If there isn't one yet a new one is made and saved there
+    pub fn from_config<R: Rng>(config: &mut Config, rng: &mut R) -> io::Result<Self> {
+        match (
+            config.get("identity.product_user_id"),
+            config.get("identity.friend_code"),
+        ) {
+            (Some(product_user_id), Some(friend_code)) => Ok(Self {
+                product_user_id: product_user_id.to_string(),
+                friend_code: friend_code.to_string(),
+            }),
+            (None, None) => {
+                let identity = Self::generate(rng);
+                config.set("identity.product_user_id", &identity.product_user_id);
+                config.set("identity.friend_code", &identity.friend_code);
+                config.save()?;
+                Ok(identity)
+            }
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Incomplete identity",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_identity_from_config() {
+        let path = std::env::temp_dir().join("among-us-test-identity.cfg");
+        let _ = fs::remove_file(&path);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let mut config = Config::open(&path).unwrap();
+        let identity = Identity::from_config(&mut config, &mut rng).unwrap();
+        assert_eq!(identity.product_user_id.len(), 32);
+        assert!(identity
+            .product_user_id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        let (word, digits) = identity.friend_code.split_once('#').unwrap();
+        assert!(FRIEND_CODE_WORDS.contains(&word));
+        assert_eq!(digits.len(), 4);
+
+        // Saved, so the next run gets the same one back
+        let mut reopened = Config::open(&path).unwrap();
+        let again = Identity::from_config(&mut reopened, &mut rng).unwrap();
+        assert_eq!(again, identity);
+
+        fs::remove_file(&path).unwrap();
+
+        let mut partial = Config::open(&path).unwrap();
+        partial.set("identity.friend_code", "otter#1234");
+        let error = Identity::from_config(&mut partial, &mut rng).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::ClientSettings;
+
+/// One of the things set once our player spawns
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InitialInfoStep {
+    Name,
+    Color,
+    Skin,
+    Hat,
+    Pet,
+}
+
+/// How to send our name, color, skin, hat and pet once our player spawns. See
+/// `ClientSettings::initial_info`
+///
+/// Sending everything at once looks like a bot and can race the host setting things up, so each
+/// step can wait a while first
+#[derive(Debug, Clone)]
+pub struct InitialInfo {
+    /// The order to send things in. Steps left out aren't sent
+    pub order: Vec<InitialInfoStep>,
+    /// How long to wait before each step
+    pub delay: Duration,
+    /// Up to this much extra is added to each wait at random
+    pub jitter: Duration,
+}
+
+impl Default for InitialInfo {
+    /// Everything straight away, in the order the official client sends it
+    fn default() -> Self {
+        Self {
+            order: vec![
+                InitialInfoStep::Name,
+                InitialInfoStep::Color,
+                InitialInfoStep::Skin,
+                InitialInfoStep::Hat,
+                InitialInfoStep::Pet,
+            ],
+            delay: Duration::from_secs(0),
+            jitter: Duration::from_secs(0),
+        }
+    }
+}
+
+/// Something to set, with its value
+#[derive(Debug, Clone)]
+pub(crate) enum InfoValue {
+    Name(String),
+    Color(u8),
+    Skin(u32),
+    Hat(u32),
+    Pet(u32),
+}
+
+/// The steps of an `InitialInfo` still to be sent
+#[derive(Debug)]
+pub(crate) struct PendingInfo {
+    steps: VecDeque<InfoValue>,
+    next_at: Instant,
+    delay: Duration,
+    jitter: Duration,
+}
+
+impl PendingInfo {
+    pub(crate) fn new<R: Rng>(settings: &ClientSettings, now: Instant, rng: &mut R) -> Self {
+        let info = &settings.initial_info;
+        let steps = info
+            .order
+            .iter()
+            .map(|step| match step {
+                InitialInfoStep::Name => InfoValue::Name(settings.game_username.clone()),
+                InitialInfoStep::Color => InfoValue::Color(settings.initial_color),
+                InitialInfoStep::Skin => InfoValue::Skin(settings.initial_skin),
+                InitialInfoStep::Hat => InfoValue::Hat(settings.initial_hat),
+                InitialInfoStep::Pet => InfoValue::Pet(settings.initial_pet),
+            })
+            .collect();
+        let mut pending = Self {
+            steps,
+            next_at: now,
+            delay: info.delay,
+            jitter: info.jitter,
+        };
+        pending.schedule(now, rng);
+        pending
+    }
+
+    /// Picks when the next step is due
+    fn schedule<R: Rng>(&mut self, now: Instant, rng: &mut R) {
+        let jitter = self.jitter.as_secs_f64() * rng.gen::<f64>();
+        self.next_at = now + self.delay + Duration::from_secs_f64(jitter);
+    }
+
+    /// Takes the next step if it's due
+    pub(crate) fn next_due<R: Rng>(&mut self, now: Instant, rng: &mut R) -> Option<InfoValue> {
+        if now < self.next_at {
+            return None;
+        }
+        let value = self.steps.pop_front()?;
+        self.schedule(now, rng);
+        Some(value)
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
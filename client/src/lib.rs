@@ -1,30 +1,158 @@
 use std::{
-    collections::HashSet, sync::mpsc::channel, sync::mpsc::RecvTimeoutError, sync::Arc,
-    sync::RwLock, time::Duration,
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::mpsc::channel,
+    sync::mpsc::RecvTimeoutError,
+    sync::Arc,
+    sync::{Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
 };
 
 use common::{
     data::GenericMessage,
     data::RPCCallback,
     data::Vector2,
-    reader::{Data, IntoReader, PacketWriter, Serialize},
+    reader::{Data, IntoReader, PacketWriter, ParseAnomaly, Serialize},
 };
 use common::{
     data::{
-        DisconnectReason, GameData, GameId, GameInfo, GameListing, HazelPacket, JoinGamePacket,
-        Languages, Lobby, NetObject, Packet, PacketType, PlayerControl, PlayerPhysics,
-        PlayerTransform, Prefab, RequestGameListPacket, ServerListPacket, VoteBanSystem, World,
+        opcode_name, AlterGameTag, ChatMode, DisconnectReason, GameData, GameId, GameInfo,
+        GameInfoBatch, GameListing, GameOptions, GameOverReason, GameVersion, HazelExtension,
+        HazelPacket, HelloPacket, HostGamePacket, JoinGamePacket, Languages, Lobby, MeetingHud,
+        NetObject, OpcodeKind, Packet, PacketType, PlayerControl, PlayerData, PlayerPhysics,
+        PlayerTransform, Prefab, RequestGameListPacket, ServerListPacket, SystemType, TaskInfo,
+        VoteBanSystem, VoteState, World,
     },
     reader::GetReader,
+    utils::Sanitizer,
 };
 use log::{debug, error, info, warn};
-
-pub use crate::networking::MainServer;
-use crate::networking::NetClient;
-
+use rand::{rngs::SmallRng, SeedableRng};
+
+pub use common::data::ObjectKind;
+
+use crate::afk::ActivityTracker;
+pub use crate::async_client::{AsyncClient, ClientEvent, ClientEvents};
+pub use crate::auth::{Authenticator, NoAuth, NonceAuth, StaticToken};
+pub use crate::clock::{Clock, ManualClock, SystemClock};
+pub use crate::config::Config;
+pub use crate::coordinator::{Coordinator, CoordinatorSettings};
+#[cfg(feature = "dtls")]
+pub use crate::dtls::Dtls;
+pub use crate::error::ClientError;
+pub use crate::host::{
+    check_win, max_impostors, tally_votes, Admission, BanList, ImpostorCallback, ImpostorSelection,
+    JoinRequest, TaskPool, WinCheckCause,
+};
+use crate::host::{critical_countdown, GameStart, HostMeeting};
+pub use crate::identity::Identity;
+use crate::initial_info::{InfoValue, PendingInfo};
+pub use crate::initial_info::{InitialInfo, InitialInfoStep};
+pub use crate::listing::{ListingChecker, ScanProgress};
+pub use crate::lobby::LobbyInfo;
+pub use crate::moderation::{ModAction, ModerationRule, Moderator};
+use crate::movement::{Movement, MOVEMENT_INTERVAL};
+pub use crate::names::{fit_name, NameGenerator, TemplateNames, WordNames, MAX_NAME_LENGTH};
+pub use crate::navigation::{map_nav, NavGraph, MIRA_HQ_NAV, POLUS_NAV, SKELD_NAV};
+pub use crate::networking::{
+    BandwidthStats, InboundLimit, MainServer, Overflow, ServerTarget, TransportConfig,
+};
+use crate::networking::{MtuProbeResult, NetClient};
+pub use crate::recorder::Recorder;
+pub use crate::redirects::RedirectCache;
+pub use crate::render::ObserverRender;
+pub use crate::secure::{SecureSession, SecureTransport};
+pub use crate::simulation::{ReplayTiming, Simulation};
+pub use crate::spectator::{SpectatorEvent, SpectatorFeed, SpectatorPlayer};
+pub use crate::state::{GameState, PlayerState};
+use crate::stats::StatsCollector;
+pub use crate::stats::{GameStats, JsonlStatsSink, PlayerStats, StatsSink};
+pub use crate::swarm::{Swarm, SwarmEvent};
+use crate::timeline::TimelineRecorder;
+pub use crate::timeline::{Timeline, TimelineEvent};
+
+mod afk;
+mod async_client;
+mod auth;
+mod clock;
+mod config;
+mod coordinator;
+#[cfg(feature = "dtls")]
+mod dtls;
+mod error;
+mod host;
+mod identity;
+mod initial_info;
+mod listing;
+mod lobby;
+mod moderation;
+mod movement;
+mod names;
+mod navigation;
 mod networking;
-
-const AMONG_US_VERSION: u32 = 50_51_65_50;
+mod recorder;
+mod redirects;
+mod render;
+mod secure;
+mod simulation;
+mod spectator;
+mod state;
+mod stats;
+mod swarm;
+mod timeline;
+
+/// Protocol versions the client is known to work with. Not sorted: the first is the version sent
+/// by default, and the rest are the fallbacks, newest first, tried in order when
+/// `ClientSettings::version_fallback` is set and the server rejects it
+pub const KNOWN_VERSIONS: &[GameVersion] = &[
+    GameVersion::new(2020, 9, 7, 0),
+    GameVersion::new(2020, 10, 22, 0),
+    GameVersion::new(2020, 9, 22, 0),
+    GameVersion::new(2020, 9, 9, 0),
+    GameVersion::new(2020, 6, 9, 0),
+];
+
+/// The number of player colors. See `ClientSettings::initial_color`
+const NUM_COLORS: u8 = 12;
+
+/// The longest to wait for a packet before checking timers
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long doors shut by an impostor stay shut
+const DOOR_CLOSE_TIME: Duration = Duration::from_secs(10);
+
+/// How long after voting ends to remove the meeting screen, giving time for the exile cutscene
+const MEETING_CLOSE_DELAY: Duration = Duration::from_secs(8);
+
+/// What `server_scan` names the thread that talks to the server
+const SCAN_THREAD: &str = "server-scan";
+
+/// How long `disconnect_with_reason` waits for sent packets to be acknowledged
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait for answers when probing the path MTU
+const MTU_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Units a second players walk at before the speed game option
+const BASE_WALK_SPEED: f32 = 2.5;
+
+/// The owner id used for objects spawned by the host that no player owns
+const HOST_OWNED: i32 = -2;
+
+/// How long a sent game message is looked out for coming back, as the server echoes them
+/// straight away if at all
+const ECHO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most sent game messages remembered for spotting echoes. Movement goes out often enough to fill
+/// this well within `ECHO_TIMEOUT`, and the oldest are forgotten first
+const MAX_SENT_ECHOES: usize = 256;
+
+/// How many unknown RPCs to keep per net object, dropping the oldest. See
+/// `NetObjectHandler::unknown_rpcs`
+const UNKNOWN_RPC_LIMIT: usize = 32;
 
 /// Misc options for the client
 ///
@@ -94,6 +222,135 @@ pub struct ClientSettings {
 
     /// Whether to send username, skin, pet etc. when joining a game
     pub send_initial_info: bool,
+
+    /// The order and timing of the initial info, if it's sent
+    pub initial_info: InitialInfo,
+
+    /// Where to record a summary of each game once it ends
+    pub stats_sink: Option<Box<dyn StatsSink>>,
+
+    /// Where to write a `Timeline` of each game once it ends, as `<game id>-<start time>.json`.
+    /// Works without hosting, e.g. for observer bots
+    pub timeline_dir: Option<PathBuf>,
+
+    /// How to clean up player names and chat before they reach the `EventHandler`
+    ///
+    /// `None` passes them through untouched. The raw values are still available through
+    /// `Client::raw_names` and `EventHandler::raw_chat_message`
+    pub sanitizer: Option<Sanitizer>,
+
+    /// Bans to enforce and add to when hosting
+    pub ban_list: Option<BanList>,
+
+    /// How to pick impostors when hosting
+    pub impostor_selection: ImpostorSelection,
+
+    /// The tasks to hand out when hosting. `None` uses every task on the map being played
+    pub task_pool: Option<TaskPool>,
+
+    /// Rules to check incoming chat against, after `sanitizer`. See `EventHandler::chat_flagged`
+    pub moderator: Option<Moderator>,
+
+    /// Where to stream game state for spectator overlays when hosting
+    pub spectator_feed: Option<SpectatorFeed>,
+
+    /// The protocol version to say hello with. Also decides the hello layout and which
+    /// `GameOptions` fields are sent
+    pub version: GameVersion,
+
+    /// Whether to reconnect with the other `KNOWN_VERSIONS` when the server disconnects with
+    /// `DisconnectReason::IncorrectVersion`, e.g. right after a game update
+    ///
+    /// `EventHandler::version_fallback` is called with the version that worked
+    pub version_fallback: bool,
+
+    /// Whether to send movement snapshots unreliably like the game does. Lost snapshots are
+    /// replaced by the next one so resending them only wastes bandwidth
+    pub unreliable_movement: bool,
+
+    /// Caps outgoing traffic in bytes per second, so many clients on one connection don't
+    /// saturate it or trip server rate limits. `None` for no cap
+    pub max_upload_rate: Option<u32>,
+
+    /// Socket buffer sizes and marking. Raise the buffers when running many clients, as bursts
+    /// overflow the OS defaults
+    pub transport: TransportConfig,
+
+    /// Wraps every connection, e.g. in `Dtls` for current official servers. `None` for plain UDP.
+    /// Not used by `AsyncClient`, which does its own networking
+    pub secure_transport: Option<Arc<dyn SecureTransport>>,
+
+    /// Product user id and friend code to say hello with, for servers that track accounts. `None`
+    /// sends the older hello without them
+    pub identity: Option<Identity>,
+
+    /// The first byte of the hello, which the game always sends as 0. Newer Hazel revisions
+    /// expect their version there
+    pub hazel_version: u8,
+
+    /// The language to say hello with, for versions whose hello has one
+    pub hello_language: Languages,
+
+    /// Whether we can type in chat or only use quick chat, for versions whose hello has it. With
+    /// `QuickChatOnly` the server won't pass on anything we type, so `send_chat` doesn't try
+    pub chat_mode: ChatMode,
+
+    /// Fetches a token or nonce to send in the hello, for servers that want one. `NoAuth` for
+    /// vanilla servers and `NonceAuth` for current official ones. Shared with the client, so
+    /// anything it keeps between hellos is still there for the next run
+    pub authenticator: Arc<Mutex<dyn Authenticator>>,
+
+    /// Adds fields to the Hazel hello and disconnect, for servers on newer revisions that expect
+    /// them. `None` sends them as vanilla does
+    pub hazel_extension: Option<Arc<dyn HazelExtension>>,
+
+    /// Where to record every datagram sent and received, across redirects and reconnects.
+    /// `None` records nothing
+    pub recorder: Option<Recorder>,
+
+    /// Say in chat when a player hasn't moved or done a task for this long during a game. `None`
+    /// never announces. See `Client::afk_players`
+    pub afk_announce: Option<Duration>,
+
+    /// Whether to probe the path MTU when connecting, so large reliable payloads are fragmented
+    /// to fit the path rather than relying on IP fragmentation. The probes go out once the hello
+    /// is acknowledged and packets sent before the answers are in use the default size
+    pub probe_mtu: bool,
+
+    /// Whether to handle reliable packets in the order the server sent them, so e.g. a name is
+    /// never set before the player it's for spawns. A lost packet delays later ones until it's
+    /// resent
+    pub ordered_reliable: bool,
+
+    /// How many received packets can wait while the handler is busy, and what happens to
+    /// unreliable ones past that. Reliable packets are never dropped
+    pub inbound_limit: InboundLimit,
+
+    /// Whether to skip game messages that can't be parsed rather than dropping the whole packet
+    /// they came in. Skipped messages go to `EventHandler::parse_anomaly`
+    pub lenient_parsing: bool,
+
+    /// Whether to ignore echoes of game messages we sent, which the server sometimes sends back.
+    /// Either way `Client::origin` says where the message being handled came from
+    pub ignore_own_echoes: bool,
+
+    /// Where resends, timeouts and host timers get the time from. Swap for a `ManualClock` to
+    /// fast-forward time in tests
+    pub clock: Arc<dyn Clock>,
+
+    /// Seeds the client's random choices, like impostors, tasks and initial info jitter, so they're
+    /// the same every run. `None` seeds from the OS
+    pub seed: Option<u64>,
+
+    /// Game servers to connect and say hello to while the main server works out which one the
+    /// game is on, e.g. from `ScanProgress::node_addresses`. When redirected to one of them the
+    /// join is sent straight away instead of after another handshake
+    pub warm_addresses: Vec<SocketAddr>,
+
+    /// Remembers which game server the main server sent each game to. `Client::run_game_code`
+    /// goes straight there for a game it knows, falling back to the main server if the game isn't
+    /// found. Shared so later runs with the same settings benefit too
+    pub redirect_cache: Option<Arc<Mutex<RedirectCache>>>,
 }
 
 impl Default for ClientSettings {
@@ -108,13 +365,73 @@ impl Default for ClientSettings {
             game_scene: "OnlineGame".to_string(),
             send_scene: true,
             send_initial_info: true,
+            initial_info: InitialInfo::default(),
+            stats_sink: None,
+            timeline_dir: None,
+            sanitizer: Some(Sanitizer::default()),
+            ban_list: None,
+            impostor_selection: ImpostorSelection::default(),
+            task_pool: None,
+            moderator: None,
+            spectator_feed: None,
+            version: KNOWN_VERSIONS[0],
+            version_fallback: false,
+            unreliable_movement: true,
+            max_upload_rate: None,
+            transport: TransportConfig::default(),
+            secure_transport: None,
+            afk_announce: None,
+            identity: None,
+            hazel_version: 0,
+            hello_language: Languages::ENGLISH,
+            chat_mode: ChatMode::FreeChat,
+            authenticator: Arc::new(Mutex::new(NoAuth)),
+            hazel_extension: None,
+            recorder: None,
+            probe_mtu: false,
+            ordered_reliable: false,
+            inbound_limit: InboundLimit::default(),
+            lenient_parsing: false,
+            ignore_own_echoes: false,
+            clock: Arc::new(SystemClock),
+            seed: None,
+            warm_addresses: Vec::new(),
+            redirect_cache: None,
         }
     }
 }
 
+impl ClientSettings {
+    /// Turns off what only makes sense for official servers, for private ones like Impostor.
+    /// Game codes there have nothing to do with official ones so the redirect cache is dropped,
+    /// warm addresses would be official game servers, and they take plain UDP
+    pub fn for_custom_server(mut self) -> Self {
+        self.redirect_cache = None;
+        self.warm_addresses.clear();
+        self.secure_transport = None;
+        self
+    }
+}
+
+/// Looks up `server`, adjusting `settings` if it's a private one
+fn resolve_target(
+    server: ServerTarget,
+    settings: ClientSettings,
+) -> Result<(SocketAddr, ClientSettings), ClientError> {
+    let addr = server.resolve().map_err(|error| ClientError::Io {
+        kind: error.kind(),
+        message: format!("Couldn't look up {}: {}", server, error),
+    })?;
+    if server.is_custom() {
+        Ok((addr, settings.for_custom_server()))
+    } else {
+        Ok((addr, settings))
+    }
+}
+
 pub struct ScanSettings {
-    /// The main server to query for games
-    pub server: MainServer,
+    /// The server to query for games. A `ServerTarget::Custom` is looked up when the scan starts
+    pub server: ServerTarget,
 
     /// Username to use when connecting to the server
     pub connect_username: String,
@@ -138,31 +455,155 @@ pub struct ScanSettings {
     pub max_requests: u32,
 
     pub cache_size: u32,
+
+    /// Ends the scan once this many responses in a row brought no new games. `None` to scan until
+    /// the callback says to stop
+    pub stop_when_exhausted: Option<u32>,
 }
 
 impl Default for ScanSettings {
     fn default() -> Self {
         Self {
-            server: MainServer::Europe,
+            server: MainServer::Europe.into(),
             connect_username: "client".to_string(),
             maps: 7,
             language: Languages::ALL,
             num_imposters: 0,
             max_requests: 10,
             cache_size: 200,
+            stop_when_exhausted: None,
+        }
+    }
+}
+
+/// Who a game message came from. Ours if it matches one we sent recently, otherwise worked out
+/// from who owns the object it's about
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Us, so an echo of something we sent
+    Us,
+    /// Another client, by client id
+    Client(i32),
+}
+
+/// What a sent game message was about, to tell when the server echoes it back
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Echo {
+    UpdateData(u32),
+    Rpc { net_id: u32, call_id: u8 },
+    Destroy(u32),
+}
+
+impl Echo {
+    fn of(info: &GameInfo) -> Option<Self> {
+        match *info {
+            GameInfo::UpdateData { net_id, .. } => Some(Echo::UpdateData(net_id)),
+            GameInfo::RPC {
+                net_id, call_id, ..
+            } => Some(Echo::Rpc { net_id, call_id }),
+            GameInfo::Destroy { net_id } => Some(Echo::Destroy(net_id)),
+            _ => None,
         }
     }
 }
 
+/// Connection settings from `ClientSettings`, kept to set up every new connection the same way
+#[derive(Clone, Default)]
+struct ConnectionOptions {
+    max_upload_rate: Option<u32>,
+    ordered_reliable: bool,
+    inbound_limit: InboundLimit,
+    hazel_extension: Option<Arc<dyn HazelExtension>>,
+    recorder: Option<Recorder>,
+}
+
+impl ConnectionOptions {
+    /// Sets everything but the recorder, which only goes on the connection in use. See
+    /// `Client::use_connection`
+    fn configure_connection(&self, client: &mut NetClient) {
+        client.set_max_upload_rate(self.max_upload_rate);
+        client.set_ordered(self.ordered_reliable);
+        client.set_inbound_limit(self.inbound_limit);
+        client.set_extension(self.hazel_extension.clone());
+    }
+}
+
 pub struct Client {
     client: NetClient,
     should_disconnect: bool,
+    /// Why a manual client is leaving, sent by its driver once everything else is acknowledged
+    leave_reason: Option<DisconnectReason>,
     game_id: Option<GameId>,
     pub client_id: Option<i32>,
     pub host_id: Option<i32>,
     pub player_ids: HashSet<i32>,
     pub net_objects: NetObjectHandler,
+    /// The last game options sent by the host
+    pub game_options: Option<GameOptions>,
     is_public: bool,
+    lobby: Option<LobbyInfo>,
+    /// The listing the game was joined from, if any
+    listing: Option<GameListing>,
+    /// Options to create a game with, for `Client::create_game`
+    host_options: Option<GameOptions>,
+    stats: Option<StatsCollector>,
+    timeline: Option<TimelineRecorder>,
+    /// The original names of players whose names were changed by the sanitizer, by player id
+    pub raw_names: HashMap<u8, String>,
+    /// Players let in as spectators by `EventHandler::admit_player`
+    pub spectators: HashSet<i32>,
+    /// Checked against names as players ask for them, and added to by `kick_player` when banning
+    pub ban_list: Option<BanList>,
+    impostor_selection: ImpostorSelection,
+    task_pool: Option<TaskPool>,
+    moderator: Option<Moderator>,
+    meeting: Option<HostMeeting>,
+    /// Players still loading the map after `start_game`
+    starting: Option<GameStart>,
+    /// Whether a game is being played, between `GameStarted` and `GameEnded`
+    in_game: bool,
+    /// When the current reactor or oxygen sabotage runs out, as host
+    sabotage_deadline: Option<Instant>,
+    /// Rooms whose doors we've shut as host, and when to open them again
+    closed_doors: Vec<(SystemType, Instant)>,
+    spectator_feed: Option<SpectatorFeed>,
+    /// The protocol version sent in the hello packet
+    version: GameVersion,
+    /// Versions left to try if the server rejects `version`
+    fallback_versions: Vec<GameVersion>,
+    unreliable_movement: bool,
+    /// Set on every new connection
+    connection: ConnectionOptions,
+    transport: TransportConfig,
+    secure_transport: Option<Arc<dyn SecureTransport>>,
+    afk_announce: Option<Duration>,
+    activity: ActivityTracker,
+    /// Where we're walking to, if anywhere. See `move_towards`
+    movement: Option<Movement>,
+    identity: Option<Identity>,
+    hazel_version: u8,
+    hello_language: Languages,
+    chat_mode: ChatMode,
+    /// Other players' chat modes, for servers that send them
+    chat_modes: HashMap<i32, ChatMode>,
+    authenticator: Arc<Mutex<dyn Authenticator>>,
+    probe_mtu: bool,
+    /// Where the game message being handled came from
+    origin: Option<Origin>,
+    /// Game messages sent to everyone lately and when, oldest first. See `Client::origin_of`
+    sent_echoes: VecDeque<(Echo, Instant)>,
+    /// Initial info still to be sent
+    initial_info: Option<PendingInfo>,
+    /// From `ClientSettings::connect_username`, for saying hello again when changing server
+    connect_username: String,
+    /// Connections that have said hello, for `ClientSettings::warm_addresses`
+    warm: HashMap<SocketAddr, NetClient>,
+    redirect_cache: Option<Arc<Mutex<RedirectCache>>>,
+    /// The main server to fall back to if we went straight to a game server from
+    /// `redirect_cache` and the game wasn't there
+    main_server: Option<SocketAddr>,
+    /// For everything the client picks at random. See `ClientSettings::seed`
+    rng: SmallRng,
 }
 
 impl Client {
@@ -170,13 +611,295 @@ impl Client {
         Self {
             client,
             should_disconnect: false,
+            leave_reason: None,
             game_id: None,
             client_id: None,
             host_id: None,
             player_ids: HashSet::new(),
             net_objects: NetObjectHandler::new(),
+            game_options: None,
             is_public: false,
+            lobby: None,
+            listing: None,
+            host_options: None,
+            stats: None,
+            timeline: None,
+            raw_names: HashMap::new(),
+            spectators: HashSet::new(),
+            ban_list: None,
+            impostor_selection: ImpostorSelection::default(),
+            task_pool: None,
+            moderator: None,
+            meeting: None,
+            starting: None,
+            in_game: false,
+            sabotage_deadline: None,
+            closed_doors: Vec::new(),
+            spectator_feed: None,
+            version: KNOWN_VERSIONS[0],
+            fallback_versions: Vec::new(),
+            unreliable_movement: true,
+            connection: ConnectionOptions::default(),
+            transport: TransportConfig::default(),
+            secure_transport: None,
+            afk_announce: None,
+            activity: ActivityTracker::default(),
+            movement: None,
+            identity: None,
+            hazel_version: 0,
+            hello_language: Languages::ENGLISH,
+            chat_mode: ChatMode::FreeChat,
+            chat_modes: HashMap::new(),
+            authenticator: Arc::new(Mutex::new(NoAuth)),
+            probe_mtu: false,
+            origin: None,
+            sent_echoes: VecDeque::new(),
+            initial_info: None,
+            connect_username: String::new(),
+            warm: HashMap::new(),
+            redirect_cache: None,
+            main_server: None,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Reliable packets bigger than this are sent as fragments. See `ClientSettings::probe_mtu`
+    pub fn max_packet_size(&self) -> usize {
+        self.client.max_packet_size()
+    }
+
+    /// Traffic on the current connection since it was made
+    pub fn bandwidth(&self) -> BandwidthStats {
+        self.client.bandwidth()
+    }
+
+    /// Unreliable packets dropped on the current connection because the handler fell behind. See
+    /// `ClientSettings::inbound_limit`
+    pub fn inbound_dropped(&self) -> u64 {
+        self.client.inbound_dropped()
+    }
+
+    /// Replaces the connection, keeping the clock, socket options, upload cap, ordering, inbound
+    /// limit and recorder. Manual clients get a fresh manual connection to `addr`
+    fn reconnect(&mut self, addr: SocketAddr) -> io::Result<()> {
+        let clock = self.client.clock();
+        let mut client = if self.client.is_manual() {
+            NetClient::manual_to(addr, clock)
+        } else {
+            NetClient::connect_secure(
+                addr,
+                clock,
+                self.transport,
+                self.secure_transport.as_deref(),
+            )?
+        };
+        self.connection.configure_connection(&mut client);
+        self.use_connection(client);
+        Ok(())
+    }
+
+    /// Switches to `client`, attaching the recorder. Warm connections don't get it until now so
+    /// captures only hold the session's own traffic
+    fn use_connection(&mut self, client: NetClient) {
+        client.set_recorder(self.connection.recorder.clone());
+        self.client = client;
+    }
+
+    /// Connects and says hello to each address ahead of a possible redirect. See
+    /// `ClientSettings::warm_addresses`
+    fn warm_up(&mut self, connect_username: &str, addresses: &[SocketAddr]) {
+        if self.client.is_manual() {
+            return;
+        }
+        for &addr in addresses {
+            if addr == self.client.addr() || self.warm.contains_key(&addr) {
+                continue;
+            }
+            let connection = NetClient::connect_secure(
+                addr,
+                self.client.clock(),
+                self.transport,
+                self.secure_transport.as_deref(),
+            );
+            let mut client = match connection {
+                Ok(client) => client,
+                Err(error) => {
+                    warn!("Couldn't warm up {}: {}", addr, error);
+                    continue;
+                }
+            };
+            self.connection.configure_connection(&mut client);
+            let hello = self.hello_data(addr, connect_username);
+            client.send_hello(Box::new(hello));
+            self.warm.insert(addr, client);
+        }
+        if !self.warm.is_empty() {
+            debug!("Warmed up {} connections", self.warm.len());
+        }
+    }
+
+    /// The current time according to `ClientSettings::clock`
+    pub fn now(&self) -> Instant {
+        self.client.now()
+    }
+
+    /// Where the game message currently being handled came from, for use in `EventHandler`
+    /// callbacks. `None` outside of game messages or when the sender can't be told, like for
+    /// objects that haven't spawned yet
+    pub fn origin(&self) -> Option<Origin> {
+        self.origin
+    }
+
+    /// Works out who sent a game message. One matching a message we sent to everyone is taken to
+    /// be its echo. Otherwise it's put down to the object's owner, with host owned objects and
+    /// our own objects counting as the host's, as only the host can send for others
+    fn origin_of(&mut self, info: &GameInfo) -> Option<Origin> {
+        let now = self.now();
+        while self
+            .sent_echoes
+            .front()
+            .is_some_and(|(_, sent)| now.duration_since(*sent) >= ECHO_TIMEOUT)
+        {
+            self.sent_echoes.pop_front();
+        }
+        if let Some(echo) = Echo::of(info) {
+            if let Some(index) = self.sent_echoes.iter().position(|(sent, _)| *sent == echo) {
+                self.sent_echoes.remove(index);
+                return Some(Origin::Us);
+            }
+        }
+
+        let sender = match info {
+            GameInfo::ChangeScene { client_id, .. } | GameInfo::ClientReady { client_id } => {
+                return Some(if Some(*client_id) == self.client_id {
+                    Origin::Us
+                } else {
+                    Origin::Client(*client_id)
+                });
+            }
+            GameInfo::UpdateData { net_id, .. }
+            | GameInfo::RPC { net_id, .. }
+            | GameInfo::Destroy { net_id } => {
+                let owner_id = self.net_objects.get(*net_id)?.owner_id();
+                if owner_id == HOST_OWNED || Some(owner_id) == self.client_id {
+                    self.host_id?
+                } else {
+                    owner_id
+                }
+            }
+            GameInfo::CreateFromPrefab { .. } | GameInfo::Unknown => return None,
+        };
+        // Ours, but not something we remember sending, so there's no telling
+        if Some(sender) == self.client_id {
+            return None;
+        }
+        Some(Origin::Client(sender))
+    }
+
+    /// Remembers what's in a batch about to go to everyone, so `origin_of` knows its echoes
+    fn remember_sent(&mut self, batch: &GameInfoBatch) {
+        if batch.target().is_some() {
+            return;
+        }
+        let now = self.now();
+        for echo in batch.infos().iter().filter_map(Echo::of) {
+            self.sent_echoes.push_back((echo, now));
+        }
+        while self.sent_echoes.len() > MAX_SENT_ECHOES {
+            self.sent_echoes.pop_front();
+        }
+    }
+
+    /// The protocol version sent to the server
+    pub fn version(&self) -> GameVersion {
+        self.version
+    }
+
+    /// Whether the lobby is listed publicly, as last set by the host
+    pub fn is_public(&self) -> bool {
+        self.is_public
+    }
+
+    /// The lobby currently joined, kept up to date as players come and go and settings change
+    pub fn lobby(&self) -> Option<&LobbyInfo> {
+        self.lobby.as_ref()
+    }
+
+    /// A snapshot of the players, their positions and any sabotage, so handlers don't have to
+    /// match up the net objects themselves
+    pub fn state(&self) -> GameState {
+        let mut players = match self.net_objects.game_datas.first() {
+            Some(game_data) => game_data
+                .players
+                .iter()
+                .map(|(&player_id, data)| {
+                    let client_id = self
+                        .net_objects
+                        .player_controls
+                        .iter()
+                        .find(|control| control.player_id == player_id)
+                        .map(|control| control.owner_id());
+                    let position = client_id.and_then(|client_id| {
+                        self.net_objects
+                            .player_transforms
+                            .iter()
+                            .find(|transform| transform.owner_id() == client_id)
+                            .map(|transform| transform.target_position)
+                    });
+                    PlayerState {
+                        player_id,
+                        client_id,
+                        name: data.name.clone(),
+                        color: data.color,
+                        position,
+                        is_dead: data.is_dead,
+                        is_imposter: data.is_imposter,
+                        disconnected: data.disconnected,
+                    }
+                })
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        players.sort_by_key(|player| player.player_id);
+        let sabotaged = match self.net_objects.worlds.first() {
+            Some(world) if self.in_game => world.sabotaged_systems(),
+            _ => Vec::new(),
+        };
+        GameState {
+            game_id: self.game_id,
+            client_id: self.client_id,
+            host_id: self.host_id,
+            is_public: self.is_public,
+            map_id: self.game_options.as_ref().map(|options| options.map_id),
+            in_game: self.in_game,
+            players,
+            sabotaged,
+        }
+    }
+
+    /// Rebuilds `lobby` from the latest state, returning it if anything changed
+    fn refresh_lobby(&mut self) -> Option<LobbyInfo> {
+        let (game_id, host_id) = match (self.game_id, self.host_id, self.client_id) {
+            (Some(game_id), Some(host_id), Some(_)) => (game_id, host_id),
+            _ => return None,
+        };
+        let mut lobby = match &self.lobby {
+            Some(lobby) => lobby.clone(),
+            None => LobbyInfo::new(game_id, host_id, self.listing.as_ref(), self.now()),
+        };
+        lobby.host_id = host_id;
+        lobby.player_count = self.player_ids.len() + 1;
+        if let Some(options) = &self.game_options {
+            lobby.apply_options(options);
+        }
+        if self.lobby.is_some() || self.is_public {
+            lobby.is_public = self.is_public;
         }
+        if self.lobby.as_ref() == Some(&lobby) {
+            return None;
+        }
+        self.lobby = Some(lobby.clone());
+        Some(lobby)
     }
 
     /// Returns true if in-game and host, false otherwise
@@ -187,10 +910,114 @@ impl Client {
         self.host_id.unwrap() == self.client_id.unwrap()
     }
 
-    /// Scan the server for game listings until the callback returns false
-    pub fn server_scan<F>(settings: ScanSettings, mut callback: F)
+    /// Whether we said hello as quick chat only, so can't type in chat
+    pub fn is_quick_chat_only(&self) -> bool {
+        self.version.hello_has_chat_mode() && self.chat_mode == ChatMode::QuickChatOnly
+    }
+
+    /// A player's chat mode, if the server told us it
+    pub fn chat_mode_of(&self, player_id: i32) -> Option<ChatMode> {
+        self.chat_modes.get(&player_id).copied()
+    }
+
+    /// Connects to a server and measures how long it takes for the hello packet to be acknowledged
+    ///
+    /// Fails with `ErrorKind::TimedOut` if there's no acknowledgement within `timeout`
+    pub fn measure_latency(
+        addr: SocketAddr,
+        connect_username: &str,
+        timeout: Duration,
+    ) -> io::Result<Duration> {
+        let mut client = Client::new(NetClient::connect_direct(addr)?);
+        let started = Instant::now();
+        let hello_ack_id = client.send_hello(connect_username);
+        while let Some(remaining) = timeout.checked_sub(started.elapsed()) {
+            match client.client.read_packet_timeout(remaining) {
+                Ok(HazelPacket::Acknowledge { ack_id }) if ack_id == hello_ack_id => {
+                    return Ok(started.elapsed());
+                }
+                Ok(_) => (),
+                Err(_) => break,
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "No response to hello",
+        ))
+    }
+
+    /// Whether the server at `addr` accepts protocol `version`. Says hello then asks for the game
+    /// list, which gets either listings or an `IncorrectVersion` disconnect
+    ///
+    /// Errors if the server disconnects for another reason or doesn't answer within `timeout`
+    pub fn probe_version(
+        addr: SocketAddr,
+        version: GameVersion,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        // Servers reject a version either in a game message or in the Hazel disconnect itself
+        fn rejected(reason: Option<DisconnectReason>) -> io::Result<bool> {
+            match reason {
+                Some(DisconnectReason::IncorrectVersion) => Ok(false),
+                Some(reason) => Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("Disconnected: {:?}", reason),
+                )),
+                None => Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    "Disconnected",
+                )),
+            }
+        }
+
+        let mut client = Client::new(NetClient::connect_direct(addr)?);
+        client.version = version;
+        let started = Instant::now();
+        client.send_hello("probe");
+        client.request_game_list(Languages::ALL, 7, 0);
+        while let Some(remaining) = timeout.checked_sub(started.elapsed()) {
+            let data = match client.client.read_packet_timeout(remaining) {
+                Ok(HazelPacket::Unreliable { data }) | Ok(HazelPacket::Reliable { data, .. }) => {
+                    data
+                }
+                Ok(HazelPacket::Disconnect { reason, .. }) => return rejected(reason),
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            let packets = match data.into_reader().read_all::<Packet>() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            for packet in packets {
+                match packet {
+                    Packet::GameList(_) => return Ok(true),
+                    Packet::Disconnected(reason) => return rejected(Some(reason)),
+                    _ => (),
+                }
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "No answer to game list request",
+        ))
+    }
+
+    /// Scan the server for game listings until the callback returns false or the server
+    /// disconnects. Fails if the server can't be looked up or the thread talking to it panicked
+    pub fn server_scan<F>(settings: ScanSettings, mut callback: F) -> Result<(), ClientError>
     where
         F: FnMut(Vec<GameListing>) -> bool,
+    {
+        Client::server_scan_progress(settings, |listings, _| callback(listings))
+    }
+
+    /// Like `server_scan`, also passing how far the scan has got
+    pub fn server_scan_progress<F>(
+        settings: ScanSettings,
+        mut callback: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(Vec<GameListing>, &ScanProgress) -> bool,
     {
         #[derive(PartialEq)]
         enum ScanState {
@@ -200,279 +1027,656 @@ impl Client {
         let (req_send, req_recv) = channel::<bool>();
 
         let game_listings = Arc::new(RwLock::new(Vec::new()));
+        let scan_progress = Arc::new(RwLock::new(ScanProgress::default()));
+        let stale_limit = settings.stop_when_exhausted;
+
+        let client = NetClient::connect(&settings.server)?;
 
         let listings = game_listings.clone();
+        let progress = scan_progress.clone();
         // client thread so client stays connected while game listings are being parsed
-        let client_thread = std::thread::spawn(move || {
-            let client = NetClient::connect(settings.server).unwrap();
-            let mut client = Client::new(client);
-
-            // Hello packet
-            client.send_hello(&settings.connect_username);
+        let client_thread = thread::Builder::new()
+            .name(SCAN_THREAD.to_string())
+            .spawn(move || {
+                let mut client = Client::new(client);
 
-            let mut state = ScanState::Connecting;
-            let mut reqs_sent = 0;
+                // Hello packet
+                client.send_hello(&settings.connect_username);
 
-            // Main loop
-            loop {
-                // Get next packet
-                let packet = client.client.read_packet();
+                let mut state = ScanState::Connecting;
 
-                // Connected once any packet received
-                if state == ScanState::Connecting {
-                    state = ScanState::Sending;
-                }
+                // Main loop
+                loop {
+                    // Get next packet
+                    let packet = client.client.read_packet();
 
-                // Send requests if needed
-                if reqs_sent < settings.max_requests {
-                    let num_requested = reqs_sent * 10;
-                    let num_cache = listings.read().unwrap().len();
-                    let num_to_req: i32 =
-                        settings.cache_size as i32 - (num_requested + num_cache as u32) as i32;
-                    // Divide rounding up
-                    let reqs_to_make = (num_to_req + 9) as u32 / 10;
-                    for _ in 0..reqs_to_make {
-                        client.request_game_list(
-                            settings.language,
-                            settings.maps,
-                            settings.num_imposters,
-                        );
+                    // Connected once any packet received
+                    if state == ScanState::Connecting {
+                        state = ScanState::Sending;
                     }
-                    reqs_sent += reqs_to_make;
-                }
 
-                match packet {
-                    // This works? Love rust
-                    HazelPacket::Unreliable { data } | HazelPacket::Reliable { data, .. } => {
-                        let mut r = data.into_reader();
-
-                        // Read packets
-                        let packets = r.read_all::<Packet>();
-                        if let Err(packet_error) = packets {
-                            error!("Error reading packets {}", packet_error);
-                            continue;
+                    // Send requests if needed
+                    let (in_flight, exhausted) = {
+                        let progress = progress.read().unwrap();
+                        let exhausted =
+                            matches!(stale_limit, Some(limit) if progress.is_exhausted(limit));
+                        (progress.in_flight, exhausted)
+                    };
+                    if in_flight < settings.max_requests && !exhausted {
+                        let num_requested = in_flight * 10;
+                        let num_cache = listings.read().unwrap().len();
+                        let num_to_req: i32 =
+                            settings.cache_size as i32 - (num_requested + num_cache as u32) as i32;
+                        // Divide rounding up
+                        let reqs_to_make = (num_to_req + 9) as u32 / 10;
+                        for _ in 0..reqs_to_make {
+                            client.request_game_list(
+                                settings.language,
+                                settings.maps,
+                                settings.num_imposters,
+                            );
+                            progress.write().unwrap().request_sent();
                         }
-                        let packets = packets.unwrap();
-
-                        // Handle packets
-                        for packet in packets {
-                            match packet {
-                                Packet::Disconnected(reason) => {
-                                    warn!("Disconnect: {:?}", reason);
-                                    return;
-                                }
-                                Packet::ServerList(_) => (),
-                                Packet::GameList(listing_packet) => {
-                                    if let Some(value) = reqs_sent.checked_sub(1) {
-                                        reqs_sent = value;
+                    }
+
+                    match packet {
+                        // This works? Love rust
+                        HazelPacket::Unreliable { data } | HazelPacket::Reliable { data, .. } => {
+                            let mut r = data.into_reader();
+
+                            // Read packets
+                            let packets = r.read_all::<Packet>();
+                            if let Err(packet_error) = packets {
+                                error!("Error reading packets {}", packet_error);
+                                continue;
+                            }
+                            let packets = packets.unwrap();
+
+                            // Handle packets
+                            for packet in packets {
+                                match packet {
+                                    Packet::Disconnected(reason) => {
+                                        warn!("Disconnect: {:?}", reason);
+                                        return;
+                                    }
+                                    Packet::ServerList(_) => (),
+                                    Packet::GameList(listing_packet) => {
+                                        // Held until the listings are added, so they're never
+                                        // counted without being there to take
+                                        let mut progress = progress.write().unwrap();
+                                        progress.response(&listing_packet.games);
+                                        if !listing_packet.unparsed.is_empty() {
+                                            warn!(
+                                                "Skipped {} unparseable listings",
+                                                listing_packet.unparsed.len()
+                                            );
+                                        }
+                                        if let Some(counts) = listing_packet.counts {
+                                            info!(
+                                                "Public games: {} Skeld, {} Mira HQ, {} Porus",
+                                                counts.skeld, counts.mira_hq, counts.porus
+                                            );
+                                        }
+                                        listings.write().unwrap().extend(listing_packet.games);
                                     }
-                                    listings.write().unwrap().extend(listing_packet.games);
+                                    _ => warn!("Unhandled packet {:?} in server_scan", packet),
                                 }
-                                _ => warn!("Unhandled packet {:?} in server_scan", packet),
                             }
                         }
+                        HazelPacket::Disconnect { reason, .. } => {
+                            warn!("Hazel disconnect: {:?}", reason);
+                            return;
+                        }
+                        HazelPacket::Acknowledge { .. }
+                        | HazelPacket::KeepAlive { .. }
+                        | HazelPacket::Hello { .. }
+                        | HazelPacket::Fragment { .. } => (),
                     }
-                    HazelPacket::Disconnect => {
-                        warn!("Hazel disconnect");
-                        return;
-                    }
-                    HazelPacket::Acknowledge { .. }
-                    | HazelPacket::KeepAlive { .. }
-                    | HazelPacket::Hello { .. } => (),
-                }
 
-                // Check if should exit
-                match req_recv.recv_timeout(Duration::from_millis(200)) {
-                    Ok(should_continue) => {
-                        if !should_continue {
-                            break;
+                    // Check if should exit
+                    match req_recv.recv_timeout(Duration::from_millis(200)) {
+                        Ok(should_continue) => {
+                            if !should_continue {
+                                break;
+                            }
                         }
+                        Err(RecvTimeoutError::Timeout) => (),
+                        _ => panic!(),
                     }
-                    Err(RecvTimeoutError::Timeout) => (),
-                    _ => panic!(),
                 }
-            }
-        });
+            })
+            .expect("Couldn't spawn thread");
 
         loop {
+            // Only stops by itself on a disconnect or panic
+            if client_thread.is_finished() && game_listings.read().unwrap().is_empty() {
+                return client_thread
+                    .join()
+                    .map_err(|payload| ClientError::from_panic(SCAN_THREAD, payload));
+            }
+            // Progress first so every listing it counts has been added
+            let progress = scan_progress.read().unwrap().clone();
+            let exhausted = matches!(stale_limit, Some(limit) if progress.is_exhausted(limit));
             let values = {
                 let mut listings = game_listings.write().unwrap();
                 std::mem::replace(&mut *listings, Vec::new())
             };
-            if values.is_empty() {
+            if values.is_empty() && !exhausted {
                 continue;
             }
-            let should_continue = callback(values);
-            req_send.send(should_continue).unwrap();
+            let should_continue = callback(values, &progress) && !exhausted;
+            // Fails if the thread has stopped, which is picked up next time round
+            let _ = req_send.send(should_continue);
             if !should_continue {
-                client_thread.join().unwrap();
-                return;
+                return client_thread
+                    .join()
+                    .map_err(|payload| ClientError::from_panic(SCAN_THREAD, payload));
             }
         }
     }
 
-    pub fn run_game_code<H: EventHandler>(
+    /// Joins a game by code, returning the handler once disconnected, or what broke if the
+    /// client stopped itself. Private servers get `ClientSettings::for_custom_server`
+    pub fn run_game_code<H: EventHandler, S: Into<ServerTarget>>(
         handler: H,
-        server: MainServer,
+        server: S,
         game_code: &str,
         settings: ClientSettings,
-    ) {
-        let client = NetClient::connect(server).unwrap();
-        Client::run_game_inner(handler, client, GameId::from_chars(game_code), settings);
+    ) -> Result<H, ClientError> {
+        let (server, settings) = resolve_target(server.into(), settings)?;
+        let game_id = GameId::from_chars(game_code);
+        let cached = settings
+            .redirect_cache
+            .as_ref()
+            .and_then(|cache| cache.lock().unwrap().get(game_id));
+        let addr = match cached {
+            Some(addr) => {
+                info!("Going straight to {} for {}", addr, game_id);
+                addr
+            }
+            None => server,
+        };
+        let main_server = cached.map(|_| server);
+        Client::run_game_inner(
+            handler,
+            addr,
+            main_server,
+            Some(game_id),
+            None,
+            None,
+            settings,
+        )
+    }
+
+    /// Creates a game and joins it as host, returning the handler once disconnected, or what
+    /// broke if the client stopped itself. `EventHandler::game_created` is called with its id.
+    /// Private servers get `ClientSettings::for_custom_server`
+    pub fn create_game<H: EventHandler, S: Into<ServerTarget>>(
+        handler: H,
+        server: S,
+        options: GameOptions,
+        settings: ClientSettings,
+    ) -> Result<H, ClientError> {
+        let (server, settings) = resolve_target(server.into(), settings)?;
+        Client::run_game_inner(handler, server, None, None, None, Some(options), settings)
     }
 
-    pub fn run_game<H: EventHandler>(handler: H, listing: GameListing, settings: ClientSettings) {
-        let client = NetClient::connect_direct(listing.address.to_sock_add()).unwrap();
-        Client::run_game_inner(handler, client, listing.id, settings);
+    /// Joins a game from a scan listing, returning the handler once disconnected, or what broke
+    /// if the client stopped itself
+    pub fn run_game<H: EventHandler>(
+        handler: H,
+        listing: GameListing,
+        settings: ClientSettings,
+    ) -> Result<H, ClientError> {
+        let addr = listing.address.to_sock_add();
+        let game_id = listing.id;
+        Client::run_game_inner(
+            handler,
+            addr,
+            None,
+            Some(game_id),
+            Some(listing),
+            None,
+            settings,
+        )
     }
 
+    /// Joins `game_id`, or creates a game with `host_options` if `None`. `main_server` is where to
+    /// go if `addr` is a game server from the redirect cache that doesn't have the game
     fn run_game_inner<H: EventHandler>(
         mut handler: H,
-        client: NetClient,
-        game_id: GameId,
-        settings: ClientSettings,
-    ) {
+        addr: SocketAddr,
+        main_server: Option<SocketAddr>,
+        game_id: Option<GameId>,
+        listing: Option<GameListing>,
+        host_options: Option<GameOptions>,
+        mut settings: ClientSettings,
+    ) -> Result<H, ClientError> {
+        let client = NetClient::connect_secure(
+            addr,
+            settings.clock.clone(),
+            settings.transport,
+            settings.secure_transport.as_deref(),
+        )?;
         let mut client = Client::new(client);
-
-        // Send hello packet
-        client.send_hello(&settings.connect_username);
-
-        // Join game
-        client.join_game_id(game_id);
+        client.listing = listing;
+        client.host_options = host_options;
+        client.main_server = main_server;
+        client.apply_settings(&mut settings);
+        client.start_session(&settings.connect_username, game_id);
+        client.warm_up(&settings.connect_username, &settings.warm_addresses);
 
         // Parsing loop
         loop {
             if client.should_disconnect {
                 break;
             }
-            let hazel_packet = client.client.read_packet();
-            handler.packet_received(&mut client);
-
-            match hazel_packet {
-                HazelPacket::Unreliable { data } | HazelPacket::Reliable { data, .. } => {
-                    let mut r = data.into_reader();
-
-                    // Read packets
-                    let packets = r.read_all::<Packet>();
-                    if let Err(packet_error) = packets {
-                        error!("Error reading packets {}", packet_error);
-                        continue;
+            client.tick();
+            // Tick more often while walking so the snapshots are evenly spaced
+            let timeout = if client.movement.is_some() {
+                MOVEMENT_INTERVAL
+            } else {
+                TICK_INTERVAL
+            };
+            let hazel_packet = match client.client.read_packet_timeout(timeout) {
+                Ok(packet) => packet,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    match client.client.thread_panic() {
+                        Some(error) => {
+                            handler.client_error(&mut client, error.clone());
+                            return Err(error);
+                        }
+                        None => error!("Receive thread stopped"),
                     }
-                    let packets = packets.unwrap();
+                    break;
+                }
+            };
+            match client.handle_hazel_packet(&mut handler, &settings, game_id, hazel_packet) {
+                Ok(true) => (),
+                Ok(false) => return Ok(handler),
+                Err(error) => {
+                    handler.client_error(&mut client, error.clone());
+                    return Err(error);
+                }
+            }
+        }
+        Ok(handler)
+    }
 
-                    // Handle packets
-                    for packet in packets {
-                        match packet {
-                            Packet::Disconnected(reason) => {
-                                handler.disconnect_reason(&mut client, reason)
-                            }
-                            Packet::ServerList(packet) => handler.server_info(&mut client, packet),
-                            Packet::GameList(_listings) => warn!("Unexpected game list packet"),
-                            Packet::ChangeServer { address } => {
-                                client.client =
-                                    NetClient::connect_direct(address.to_sock_add()).unwrap();
-                                client.send_hello(&settings.connect_username);
-                                client.join_game_id(game_id);
-                            }
-                            Packet::ClientJoinedGame(data) => {
-                                client.client_id = Some(data.client_id);
-                                client.host_id = Some(data.host_id);
-                                client.player_ids.extend(data.player_ids.into_iter());
-                                if settings.send_scene {
-                                    client.change_scene(&settings.game_scene);
+    /// Copies what's needed from the settings, taking anything that can't be cloned
+    fn apply_settings(&mut self, settings: &mut ClientSettings) {
+        self.stats = settings.stats_sink.take().map(StatsCollector::new);
+        self.timeline = settings.timeline_dir.clone().map(TimelineRecorder::new);
+        self.ban_list = settings.ban_list.take();
+        self.impostor_selection = std::mem::take(&mut settings.impostor_selection);
+        self.task_pool = settings.task_pool.clone();
+        self.moderator = settings.moderator.clone();
+        self.spectator_feed = settings.spectator_feed.take();
+        self.unreliable_movement = settings.unreliable_movement;
+        self.transport = settings.transport;
+        self.secure_transport = settings.secure_transport.clone();
+        self.afk_announce = settings.afk_announce;
+        self.identity = settings.identity.clone();
+        self.hazel_version = settings.hazel_version;
+        self.hello_language = settings.hello_language;
+        self.chat_mode = settings.chat_mode;
+        self.authenticator = settings.authenticator.clone();
+        self.redirect_cache = settings.redirect_cache.clone();
+        self.connect_username = settings.connect_username.clone();
+        if let Some(seed) = settings.seed {
+            self.rng = SmallRng::seed_from_u64(seed);
+        }
+        self.probe_mtu = settings.probe_mtu;
+        self.connection = ConnectionOptions {
+            max_upload_rate: settings.max_upload_rate,
+            ordered_reliable: settings.ordered_reliable,
+            inbound_limit: settings.inbound_limit,
+            hazel_extension: settings.hazel_extension.clone(),
+            recorder: settings.recorder.clone(),
+        };
+        self.connection.configure_connection(&mut self.client);
+        self.client.set_recorder(self.connection.recorder.clone());
+        self.version = settings.version;
+        self.fallback_versions = if settings.version_fallback {
+            KNOWN_VERSIONS
+                .iter()
+                .copied()
+                .filter(|&version| version != settings.version)
+                .collect()
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Handles a packet from the server. Returns `false` once disconnected for good, or an error
+    /// if a redirect or retry couldn't connect
+    fn handle_hazel_packet<H: EventHandler>(
+        &mut self,
+        handler: &mut H,
+        settings: &ClientSettings,
+        game_id: Option<GameId>,
+        hazel_packet: HazelPacket,
+    ) -> Result<bool, ClientError> {
+        handler.packet_received(self);
+
+        match hazel_packet {
+            HazelPacket::Unreliable { data } | HazelPacket::Reliable { data, .. } => {
+                let mut r = data.into_reader();
+
+                // Read packets
+                let mut anomalies = Vec::new();
+                let packets = if settings.lenient_parsing {
+                    Packet::read_all_lenient(&mut r, &mut anomalies)
+                } else {
+                    r.read_all::<Packet>()
+                };
+                for anomaly in anomalies {
+                    warn!(
+                        "Skipped game message {}: {}",
+                        opcode_name(OpcodeKind::GameInfo, anomaly.tag),
+                        anomaly.error
+                    );
+                    handler.parse_anomaly(self, anomaly);
+                }
+                if let Err(packet_error) = packets {
+                    error!("Error reading packets {}", packet_error);
+                    return Ok(true);
+                }
+                let packets = packets.unwrap();
+
+                // Handle packets
+                for packet in packets {
+                    match packet {
+                        Packet::Disconnected(DisconnectReason::IncorrectVersion)
+                            if !self.fallback_versions.is_empty() =>
+                        {
+                            self.fall_back_version(settings, game_id)?;
+                            // Anything else was from the old connection
+                            break;
+                        }
+                        Packet::Disconnected(DisconnectReason::GameNotFound)
+                            if self.main_server.is_some() && self.client_id.is_none() =>
+                        {
+                            let main_server = self.main_server.take().unwrap();
+                            info!("Game not on cached server, asking {}", main_server);
+                            if let (Some(cache), Some(game_id)) = (&self.redirect_cache, game_id) {
+                                if let Err(error) = cache.lock().unwrap().remove(game_id) {
+                                    warn!("Couldn't update redirect cache: {}", error);
                                 }
                             }
-                            Packet::PlayerJoined {
-                                game_id,
-                                player_id,
-                                host_id,
-                            } => {
-                                if game_id != client.game_id.unwrap() {
-                                    continue;
+                            self.reconnect(main_server)?;
+                            self.start_session(&settings.connect_username, game_id);
+                            // Anything else was from the old connection
+                            break;
+                        }
+                        Packet::Disconnected(reason) => handler.disconnect_reason(self, reason),
+                        Packet::HostingGame { game_id } => {
+                            info!("Created game {}", game_id);
+                            self.game_options = self
+                                .host_options
+                                .clone()
+                                .map(|options| self.versioned_options(options));
+                            self.join_game_id(game_id);
+                            handler.game_created(self, game_id);
+                        }
+                        Packet::ServerList(packet) => handler.server_info(self, packet),
+                        Packet::GameList(_listings) => warn!("Unexpected game list packet"),
+                        Packet::ChangeServer { address } => {
+                            let addr = address.to_sock_add();
+                            if let (Some(cache), Some(game_id)) = (&self.redirect_cache, game_id) {
+                                if let Err(error) = cache.lock().unwrap().insert(game_id, addr) {
+                                    warn!("Couldn't update redirect cache: {}", error);
                                 }
-                                client.player_ids.insert(player_id);
-                                client.host_id = Some(host_id);
                             }
-                            Packet::PlayerLeft {
-                                game_id,
-                                player_id,
-                                host_id,
-                                ..
-                            } => {
-                                if game_id != client.game_id.unwrap() {
-                                    continue;
+                            match self.warm.remove(&addr) {
+                                Some(client) => {
+                                    debug!("Using warm connection to {}", addr);
+                                    self.use_connection(client);
+                                    self.resume_session(game_id);
                                 }
-                                client.player_ids.remove(&player_id);
-                                client.host_id = Some(host_id);
-                            }
-                            Packet::GameStarted => {
-                                if !client.is_host() {
-                                    client.send_ready();
+                                None => {
+                                    self.reconnect(addr)?;
+                                    self.start_session(&settings.connect_username, game_id);
                                 }
                             }
-                            Packet::GameInfo { game_id, data } => {
-                                if client.game_id.is_none() || game_id != client.game_id.unwrap() {
-                                    info!("Got game info for wrong game {}. Ignoring", game_id);
-                                    continue;
+                            // Only one redirect is expected, the rest disconnect when dropped
+                            self.warm.clear();
+                        }
+                        Packet::ClientJoinedGame(data) => {
+                            self.client_id = Some(data.client_id);
+                            self.host_id = Some(data.host_id);
+                            self.main_server = None;
+                            self.player_ids.extend(data.player_ids);
+                            self.chat_modes.extend(data.chat_modes);
+                            if self.version != settings.version {
+                                info!("Joined using fallback version {}", self.version);
+                                let version = self.version;
+                                handler.version_fallback(self, version);
+                            }
+                            if settings.send_scene {
+                                self.change_scene(&settings.game_scene);
+                                // The server doesn't send our own scene change back
+                                if self.is_host() {
+                                    let spawns = self.host_spawn_player(data.client_id);
+                                    Client::handle_game_info(self, handler, settings, spawns);
                                 }
-                                Client::handle_game_info(
-                                    &mut client,
-                                    &mut handler,
-                                    &settings,
-                                    data,
-                                );
                             }
-                            Packet::GameInfoTo {
-                                game_id,
-                                client_id,
-                                data,
-                            } => {
-                                // Check client_id is the right one
-                                if let Some(my_id) = client.client_id {
-                                    if my_id != client_id {
-                                        info!("Got GameInfo meant for {}. Ignoring", client_id);
+                        }
+                        Packet::PlayerJoined {
+                            game_id,
+                            player_id,
+                            host_id,
+                            chat_mode,
+                        } => {
+                            if Some(game_id) != self.game_id {
+                                continue;
+                            }
+                            self.player_ids.insert(player_id);
+                            if let Some(chat_mode) = chat_mode {
+                                self.chat_modes.insert(player_id, chat_mode);
+                            }
+                            self.host_id = Some(host_id);
+                            if self.is_host() {
+                                let request = JoinRequest {
+                                    client_id: player_id,
+                                };
+                                match handler.admit_player(self, request) {
+                                    Admission::Accept => (),
+                                    Admission::Kick => {
+                                        info!("Kicking {}", player_id);
+                                        self.kick_player(player_id, false);
                                         continue;
                                     }
+                                    Admission::Ban => {
+                                        info!("Banning {}", player_id);
+                                        self.kick_player(player_id, true);
+                                        continue;
+                                    }
+                                    Admission::Spectate => {
+                                        self.spectators.insert(player_id);
+                                    }
+                                }
+                            }
+                            handler.player_joined(self, player_id);
+                        }
+                        Packet::PlayerLeft {
+                            game_id,
+                            player_id,
+                            host_id,
+                            reason,
+                        } => {
+                            if Some(game_id) != self.game_id {
+                                continue;
+                            }
+                            self.player_ids.remove(&player_id);
+                            self.chat_modes.remove(&player_id);
+                            self.spectators.remove(&player_id);
+                            self.activity.remove(player_id);
+                            self.host_id = Some(host_id);
+                            if self.is_host() {
+                                self.host_player_left(player_id);
+                            }
+                            handler.player_left(self, player_id, reason);
+                        }
+                        Packet::GameStarted { game_id } => {
+                            if Some(game_id) != self.game_id {
+                                continue;
+                            }
+                            self.in_game = true;
+                            let now = self.now();
+                            if let Some(timeline) = &mut self.timeline {
+                                let map_id =
+                                    self.game_options.as_ref().map(|options| options.map_id);
+                                timeline.game_started(game_id, map_id, now);
+                            }
+                            self.spectate(SpectatorEvent::GameStarted);
+                            let others = self
+                                .player_ids
+                                .iter()
+                                .copied()
+                                .filter(|&player_id| Some(player_id) != self.client_id)
+                                .filter(|player_id| !self.spectators.contains(player_id))
+                                .collect::<Vec<_>>();
+                            self.activity.start(others.into_iter(), now);
+                            if let Some(stats) = &mut self.stats {
+                                stats.game_started(game_id, self.client.bandwidth(), now);
+                            }
+                            if !self.is_host() {
+                                self.send_ready();
+                            }
+                            handler.game_started(self);
+                        }
+                        Packet::GameEnded {
+                            game_id, reason, ..
+                        } => {
+                            if Some(game_id) != self.game_id {
+                                continue;
+                            }
+                            self.in_game = false;
+                            self.meeting = None;
+                            self.sabotage_deadline = None;
+                            self.closed_doors.clear();
+                            self.activity.clear();
+                            self.movement = None;
+                            self.spectate(SpectatorEvent::GameEnded {
+                                reason: format!("{:?}", reason),
+                            });
+                            let now = self.now();
+                            if let Some(stats) = &mut self.stats {
+                                let map_id =
+                                    self.game_options.as_ref().map(|options| options.map_id);
+                                if let Err(error) = stats.game_ended(
+                                    reason,
+                                    self.net_objects.game_datas.first(),
+                                    map_id,
+                                    &self.client.bandwidth(),
+                                    now,
+                                ) {
+                                    error!("Error recording game stats {}", error);
                                 }
-                                if client.game_id.is_none() || game_id != client.game_id.unwrap() {
-                                    info!("Got game info for wrong game {}. Ignoring", game_id);
-                                    continue;
+                            }
+                            let players = self.spectator_players();
+                            let map_id = self.game_options.as_ref().map(|options| options.map_id);
+                            if let Some(timeline) = &mut self.timeline {
+                                match timeline.game_ended(players, map_id) {
+                                    Ok(Some(path)) => info!("Wrote timeline to {:?}", path),
+                                    Ok(None) => (),
+                                    Err(error) => error!("Error writing timeline {}", error),
                                 }
-                                Client::handle_game_info(
-                                    &mut client,
-                                    &mut handler,
-                                    &settings,
-                                    data,
-                                );
                             }
-                            Packet::GameAltered { game_id, is_public } => {
-                                if game_id != client.game_id.unwrap() {
-                                    info!(
-                                        "Got game altered info for wrong game {:?}. Ignoring",
-                                        packet
-                                    );
+                            handler.game_ended(self, reason);
+                        }
+                        Packet::GameInfo { game_id, data } => {
+                            if Some(game_id) != self.game_id {
+                                info!("Got game info for wrong game {}. Ignoring", game_id);
+                                continue;
+                            }
+                            Client::handle_game_info(self, handler, settings, data);
+                        }
+                        Packet::GameInfoTo {
+                            game_id,
+                            client_id,
+                            data,
+                        } => {
+                            // Check client_id is the right one. Nothing can be meant for us before
+                            // we've joined
+                            if self.client_id != Some(client_id) {
+                                info!("Got GameInfo meant for {}. Ignoring", client_id);
+                                continue;
+                            }
+                            if Some(game_id) != self.game_id {
+                                info!("Got game info for wrong game {}. Ignoring", game_id);
+                                continue;
+                            }
+                            Client::handle_game_info(self, handler, settings, data);
+                        }
+                        Packet::GameAltered {
+                            game_id,
+                            alteration,
+                        } => {
+                            if Some(game_id) != self.game_id {
+                                info!("Got game altered info for wrong game {}. Ignoring", game_id);
+                                continue;
+                            }
+                            match alteration {
+                                AlterGameTag::ChangePrivacy { is_public } => {
+                                    self.is_public = is_public;
+                                    handler.visibility_changed(self, is_public);
+                                }
+                                AlterGameTag::Unknown { tag, .. } => {
+                                    warn!("Unknown game alteration {}", tag)
                                 }
-                                client.is_public = is_public;
                             }
-                            _ => warn!("Unhandled packet type {:?}", packet),
+                            handler.game_altered(self, alteration);
                         }
+                        _ => warn!("Unhandled packet type {:?}", packet),
                     }
                 }
-                HazelPacket::Disconnect => {
-                    if client.should_disconnect {
-                        info!("Disconnected");
-                        return;
-                    } else {
-                        info!("Disconnected. Rejoining");
-                        client.send_hello(&settings.connect_username);
-                        client.join_game_id(game_id);
-                    }
+            }
+            // Some servers reject the hello itself rather than answering it
+            HazelPacket::Disconnect {
+                reason: Some(DisconnectReason::IncorrectVersion),
+                ..
+            } if !self.fallback_versions.is_empty() => self.fall_back_version(settings, game_id)?,
+            HazelPacket::Disconnect { reason, .. } => {
+                if let Some(reason) = reason {
+                    handler.disconnect_reason(self, reason);
+                }
+                if self.should_disconnect {
+                    info!("Disconnected");
+                    return Ok(false);
+                } else {
+                    info!("Disconnected. Rejoining");
+                    self.start_session(&settings.connect_username, game_id);
                 }
-                HazelPacket::Acknowledge { .. }
-                | HazelPacket::KeepAlive { .. }
-                | HazelPacket::Hello { .. } => (),
             }
+            HazelPacket::Acknowledge { .. }
+            | HazelPacket::KeepAlive { .. }
+            | HazelPacket::Hello { .. }
+            | HazelPacket::Fragment { .. } => (),
         }
+        if let Some(lobby) = self.refresh_lobby() {
+            handler.lobby_changed(self, &lobby);
+        }
+        Ok(true)
+    }
+
+    /// Reconnects with the next of `fallback_versions` after the server rejected ours
+    fn fall_back_version(
+        &mut self,
+        settings: &ClientSettings,
+        game_id: Option<GameId>,
+    ) -> io::Result<()> {
+        let version = self.fallback_versions.remove(0);
+        info!(
+            "Version {} rejected, retrying with {}",
+            self.version, version
+        );
+        self.version = version;
+        self.reconnect(self.client.addr())?;
+        self.start_session(&settings.connect_username, game_id);
+        Ok(())
     }
 
     fn handle_game_info<H: EventHandler>(
@@ -482,13 +1686,28 @@ impl Client {
         data: Vec<GameInfo>,
     ) {
         for info in data {
+            let origin = client.origin_of(&info);
+            if settings.ignore_own_echoes && origin == Some(Origin::Us) {
+                debug!("Ignoring echo of our own {:?}", info);
+                continue;
+            }
+            client.origin = origin;
             match info {
-                GameInfo::Destroy { net_id } => {
-                    if !client.net_objects.remove(net_id) {
-                        info!("Destroy called for unknown net object {}", net_id);
+                GameInfo::Destroy { net_id } => match client.net_objects.kind(net_id) {
+                    Some(kind) => {
+                        client.net_objects.remove(net_id);
+                        if kind == ObjectKind::MeetingHud {
+                            let now = client.now();
+                            client.activity.restart(now);
+                        }
+                        handler.object_destroyed(client, net_id, kind);
                     }
-                }
+                    None => info!("Destroy called for unknown net object {}", net_id),
+                },
                 GameInfo::UpdateData { net_id, data } => {
+                    let before = client.transform_position(net_id);
+                    let doors_before = client.door_states(net_id);
+                    let sabotaged_before = client.sabotaged_systems(net_id);
                     if let Some(obj) = client.net_objects.get(net_id) {
                         match data {
                             Data::Bytes(data) => {
@@ -503,6 +1722,37 @@ impl Client {
                     } else {
                         info!("Update Data called for unknown net object {}", net_id);
                     }
+                    client.sanitize_new_names(settings);
+                    if let (Some((owner_id, before)), Some((_, after))) =
+                        (before, client.transform_position(net_id))
+                    {
+                        if before.x() != after.x() || before.y() != after.y() {
+                            let now = client.now();
+                            client.activity.active(owner_id, now);
+                            handler.player_moved(client, owner_id, after);
+                        }
+                    }
+                    if let (Some(before), Some(after)) = (doors_before, client.door_states(net_id))
+                    {
+                        // A room's doors move together, so report each room once
+                        let mut changed = Vec::new();
+                        for (door, (was_open, open)) in before.into_iter().zip(after).enumerate() {
+                            match World::door_room(door) {
+                                Some(room) if was_open != open && !changed.contains(&room) => {
+                                    changed.push(room);
+                                    handler.door_state_changed(client, room, open);
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    if let (Some(before), Some(after)) =
+                        (sabotaged_before, client.sabotaged_systems(net_id))
+                    {
+                        for system in after.into_iter().filter(|system| !before.contains(system)) {
+                            handler.sabotage_triggered(client, system);
+                        }
+                    }
                 }
                 GameInfo::RPC {
                     net_id,
@@ -516,51 +1766,260 @@ impl Client {
                             Data::Bytes(data) => data,
                             Data::Object(_) => panic!("Can't unpack a serialized object"),
                         };
+                        let owner_id = obj.owner_id();
                         let rpc_data = obj.handle_rpc(call_id, &mut (&data[..]).get_reader());
+                        client.sanitize_new_names(settings);
 
                         // React to any callback
                         match rpc_data {
                             Ok(rpc_data) => match rpc_data {
                                 RPCCallback::ChatMessage { message } => {
-                                    let owner_id = obj.owner_id();
-                                    handler.chat_message(client, owner_id, message);
+                                    if let Some(stats) = &mut client.stats {
+                                        stats.chat_message();
+                                    }
+                                    handler.raw_chat_message(client, owner_id, &message);
+                                    let message = match &settings.sanitizer {
+                                        Some(sanitizer) => sanitizer.sanitize(&message),
+                                        None => message,
+                                    };
+                                    let name = client.player_name(owner_id);
+                                    if let Some(control) =
+                                        client.net_objects.get_player_control(owner_id)
+                                    {
+                                        let player_id = control.player_id;
+                                        client.spectate(SpectatorEvent::Chat {
+                                            player_id,
+                                            name: name.clone(),
+                                            message: message.clone(),
+                                        });
+                                    }
+                                    client.moderate(handler, owner_id, &name, &message);
+                                    handler.chat_message(client, owner_id, name, message);
                                 }
-                                RPCCallback::None => (),
-                                // callback => warn!("Unhandled RPC callback {:?}", callback),
-                            },
-                            Err(error) => error!("Error handling net object rpc {}", error),
-                        }
-                    } else {
-                        info!("Handle RPC called for unknown net object {}", net_id);
-                    }
-                }
-                GameInfo::CreateFromPrefab { prefab, .. } => {
-                    let is_self = if let Prefab::Player(control, _, _) = &prefab {
-                        control.owner_id() == client.client_id.unwrap()
-                    } else {
-                        false
+                                RPCCallback::GameOptions(options) => {
+                                    client.game_options = Some(options);
+                                }
+                                RPCCallback::SetInfected { impostors } => {
+                                    if let Some(game_data) =
+                                        client.net_objects.game_datas.first_mut()
+                                    {
+                                        for (player_id, data) in game_data.players.iter_mut() {
+                                            data.is_imposter = impostors.contains(player_id);
+                                        }
+                                    }
+                                    handler.impostors_assigned(client, impostors);
+                                }
+                                RPCCallback::MurderPlayer { target_net_id } => {
+                                    if let Some(stats) = &mut client.stats {
+                                        stats.kill();
+                                    }
+                                    if client.is_host() {
+                                        client.host_murder_player(target_net_id);
+                                    }
+                                    let victim = client
+                                        .net_objects
+                                        .player_controls
+                                        .iter()
+                                        .find(|control| control.net_id() == target_net_id)
+                                        .map(|control| control.player_id);
+                                    if let Some(victim) = victim {
+                                        if !client.is_host() {
+                                            client.spectate(SpectatorEvent::Kill {
+                                                victim_player_id: victim,
+                                            });
+                                        }
+                                        handler.player_died(client, victim, owner_id);
+                                    }
+                                }
+                                RPCCallback::CompleteTask { task_index } => {
+                                    let now = client.now();
+                                    client.activity.active(owner_id, now);
+                                    if client.is_host() {
+                                        client.host_complete_task(owner_id, task_index);
+                                    } else {
+                                        client.mark_task_complete(owner_id, task_index);
+                                    }
+                                    handler.task_updated(client, owner_id, task_index);
+                                }
+                                RPCCallback::MeetingCalled { reported_player_id } => {
+                                    if let Some(stats) = &mut client.stats {
+                                        stats.meeting();
+                                    }
+                                    let reporter = client
+                                        .net_objects
+                                        .get_player_control(owner_id)
+                                        .map(|control| control.player_id);
+                                    if let (Some(reporter), false) = (reporter, client.is_host()) {
+                                        client.spectate(SpectatorEvent::MeetingStarted {
+                                            reporter_player_id: reporter,
+                                            body_player_id: reported_player_id,
+                                        });
+                                    }
+                                    handler.meeting_started(client, owner_id, reported_player_id);
+                                }
+                                RPCCallback::ReportBody { target_player_id } => {
+                                    if client.is_host() && client.meeting.is_none() {
+                                        client.host_start_meeting(owner_id, target_player_id);
+                                        if client.meeting.is_some() {
+                                            handler.meeting_started(
+                                                client,
+                                                owner_id,
+                                                target_player_id,
+                                            );
+                                        }
+                                    }
+                                }
+                                RPCCallback::CastVote {
+                                    voter_player_id,
+                                    suspect_player_id,
+                                } => {
+                                    if client.is_host() {
+                                        client.host_cast_vote(voter_player_id, suspect_player_id);
+                                    }
+                                }
+                                RPCCallback::VotingComplete {
+                                    exiled_player_id,
+                                    tie,
+                                } => {
+                                    if !client.is_host() {
+                                        client.spectate(SpectatorEvent::VotingComplete {
+                                            exiled_player_id,
+                                            tie,
+                                        });
+                                    }
+                                    handler.voting_complete(client, exiled_player_id, tie);
+                                }
+                                RPCCallback::ClearVote => debug!("Vote cleared by host"),
+                                RPCCallback::MeetingClosed => debug!("Meeting closed"),
+                                RPCCallback::CheckName { name } => {
+                                    let banned = match &client.ban_list {
+                                        Some(ban_list) => ban_list.is_name_banned(&name),
+                                        None => false,
+                                    };
+                                    if client.is_host() && banned {
+                                        info!("Kicking {} for banned name {}", owner_id, name);
+                                        client.kick_player(owner_id, true);
+                                    } else if client.is_host() {
+                                        if let Some(name) =
+                                            handler.check_name(client, owner_id, name)
+                                        {
+                                            client.host_set_name(owner_id, &name);
+                                        }
+                                    }
+                                }
+                                RPCCallback::CheckColor { color } => {
+                                    if client.is_host() {
+                                        if let Some(color) =
+                                            handler.check_color(client, owner_id, color)
+                                        {
+                                            client.host_set_color(owner_id, color);
+                                        }
+                                    }
+                                }
+                                RPCCallback::CloseDoors { room } => {
+                                    if client.is_host() {
+                                        client.host_close_doors(room);
+                                    } else {
+                                        debug!("Doors closed in {:?}", room);
+                                    }
+                                }
+                                RPCCallback::Unknown { call_id } => {
+                                    let kind = client.net_objects.kind(net_id).unwrap();
+                                    warn!("Unknown {:?} RPC call {}", kind, call_id);
+                                    let rpc = UnknownRpc {
+                                        kind,
+                                        call_id,
+                                        data,
+                                        received: client.now(),
+                                    };
+                                    client.net_objects.record_unknown_rpc(net_id, rpc);
+                                }
+                                RPCCallback::None => (),
+                                // callback => warn!("Unhandled RPC callback {:?}", callback),
+                            },
+                            Err(error) => error!("Error handling net object rpc {}", error),
+                        }
+                    } else {
+                        info!("Handle RPC called for unknown net object {}", net_id);
+                    }
+                }
+                GameInfo::CreateFromPrefab { mut prefab, .. } => {
+                    if let Prefab::MeetingHud(meeting_hud) = &mut prefab {
+                        let player_ids = client
+                            .net_objects
+                            .game_datas
+                            .first()
+                            .map(GameData::player_ids)
+                            .unwrap_or_default();
+                        meeting_hud.set_player_ids(&player_ids);
+                    }
+                    let is_self = if let Prefab::Player(control, _, _) = &prefab {
+                        control.owner_id() == client.client_id.unwrap()
+                    } else {
+                        false
                     };
                     debug!("Created net obj {:?}", prefab);
-                    client.net_objects.add(prefab);
+                    let objects = client.net_objects.add(prefab);
+                    client.sanitize_new_names(settings);
+                    for object in objects {
+                        handler.object_spawned(client, object);
+                    }
                     if is_self {
                         if settings.send_initial_info {
-                            client.set_name(&settings.game_username);
-                            client.set_color(settings.initial_color);
-                            client.set_skin(settings.initial_skin);
-                            client.set_hat(settings.initial_hat);
-                            client.set_pet(settings.initial_pet);
+                            let now = client.now();
+                            client.initial_info =
+                                Some(PendingInfo::new(settings, now, &mut client.rng));
+                            client.send_initial_info();
                         }
                         handler.joined_game(client);
                     }
                 }
-                GameInfo::ChangeScene { .. } => {
+                GameInfo::ChangeScene { client_id, .. } => {
                     if client.is_host() {
-                        todo!()
+                        if client.spectators.contains(&client_id) {
+                            debug!("Not spawning spectator {}", client_id);
+                        } else {
+                            let spawns = client.host_spawn_player(client_id);
+                            Client::handle_game_info(client, handler, settings, spawns);
+                        }
+                    }
+                }
+                GameInfo::ClientReady { client_id } => {
+                    if let Some(starting) = &mut client.starting {
+                        starting.ready.insert(client_id);
+                        client.host_check_ready();
                     }
                 }
                 _ => warn!("Unhandled game info {:?}", info),
             }
         }
+        client.origin = None;
+    }
+
+    /// Replaces any player names the sanitizer would change, keeping the originals in `raw_names`.
+    /// Called as soon as objects change, so handlers never see a raw name
+    fn sanitize_new_names(&mut self, settings: &ClientSettings) {
+        let sanitizer = match &settings.sanitizer {
+            Some(sanitizer) => sanitizer,
+            None => return,
+        };
+        let raw_names = &mut self.raw_names;
+        let mut sanitize = |player_id: u8, name: &mut String| {
+            let sanitized = sanitizer.sanitize(name);
+            if sanitized != *name {
+                raw_names.insert(player_id, std::mem::replace(name, sanitized));
+            }
+        };
+        for control in self.net_objects.player_controls.iter_mut() {
+            if let Some(name) = &mut control.name {
+                sanitize(control.player_id, name);
+            }
+        }
+        for game_data in self.net_objects.game_datas.iter_mut() {
+            for (&player_id, data) in game_data.players.iter_mut() {
+                sanitize(player_id, &mut data.name);
+            }
+        }
     }
 
     pub fn request_game_list(&mut self, language: Languages, maps: u8, num_imposters: i8) {
@@ -572,11 +2031,157 @@ impl Client {
         self.should_disconnect = true;
     }
 
-    pub fn send_hello(&mut self, connect_username: &str) {
-        self.client.send_hello(Box::new(HelloData {
-            version: AMONG_US_VERSION,
-            username: connect_username.to_string(),
-        }));
+    /// Leaves saying why. Everything already sent gets up to `DISCONNECT_TIMEOUT` to be
+    /// acknowledged first, so e.g. a last chat message isn't overtaken by the disconnect
+    ///
+    /// Manual clients can't wait here without blocking whatever drives them, so the reason is
+    /// kept for the driver to send once it has flushed, as `AsyncClient` and `Simulation` do
+    pub fn disconnect_with_reason(&mut self, reason: DisconnectReason) {
+        if self.client.is_manual() {
+            self.leave_reason = Some(reason);
+            self.should_disconnect = true;
+            return;
+        }
+        let flushed = self.client.flush(DISCONNECT_TIMEOUT);
+        if !flushed {
+            warn!("Disconnecting with packets still unacknowledged");
+        }
+        self.client.send_disconnect_reason(Some(&reason));
+        // Make sure it's gone before the socket closes, unless the server's stopped answering
+        if flushed {
+            self.client.flush(DISCONNECT_TIMEOUT);
+        }
+        self.should_disconnect = true;
+    }
+
+    /// Leaves the game but stays connected to the server, ready to `join_game_code` or
+    /// `request_game_list`. There's no message for leaving a game so this is done the way the
+    /// game does it, by disconnecting, then reconnecting and saying hello again. Fails if the
+    /// reconnect does
+    pub fn leave_game(&mut self) -> Result<(), ClientError> {
+        let game_id = match self.game_id {
+            Some(value) => value,
+            None => {
+                warn!("Not in a game");
+                return Ok(());
+            }
+        };
+        info!("Leaving {}", game_id);
+        self.leave_to(self.client.addr())
+    }
+
+    /// Disconnects from the game, then connects to `addr` and says hello with the
+    /// `ClientSettings::connect_username`
+    fn leave_to(&mut self, addr: SocketAddr) -> Result<(), ClientError> {
+        self.reconnect(addr)?;
+        self.warm.clear();
+        self.reset_game();
+        let connect_username = self.connect_username.clone();
+        self.send_hello(&connect_username);
+        Ok(())
+    }
+
+    /// Forgets everything about the game we were in
+    fn reset_game(&mut self) {
+        self.game_id = None;
+        self.client_id = None;
+        self.host_id = None;
+        self.player_ids.clear();
+        self.chat_modes.clear();
+        self.net_objects = NetObjectHandler::new();
+        self.game_options = None;
+        self.is_public = false;
+        self.lobby = None;
+        self.listing = None;
+        self.host_options = None;
+        self.raw_names.clear();
+        self.spectators.clear();
+        self.meeting = None;
+        self.starting = None;
+        self.in_game = false;
+        self.sabotage_deadline = None;
+        self.closed_doors.clear();
+        self.activity = ActivityTracker::default();
+        self.movement = None;
+        self.origin = None;
+        self.initial_info = None;
+    }
+
+    /// Says hello with whatever token and nonce `ClientSettings::authenticator` gives
+    pub fn send_hello(&mut self, connect_username: &str) -> u16 {
+        let hello = self.hello_data(self.client.addr(), connect_username);
+        self.client.send_hello(Box::new(hello))
+    }
+
+    fn hello_data(&mut self, addr: SocketAddr, connect_username: &str) -> HelloPacket {
+        let mut authenticator = self.authenticator.lock().unwrap();
+        let token = authenticator
+            .authenticate(addr, connect_username)
+            .unwrap_or_else(|error| {
+                warn!(
+                    "Couldn't authenticate, saying hello without a token: {}",
+                    error
+                );
+                None
+            });
+        let last_nonce = authenticator
+            .nonce(addr, connect_username)
+            .unwrap_or_else(|error| {
+                warn!("Couldn't get a nonce, saying hello without one: {}", error);
+                None
+            });
+        if last_nonce.is_some() && !self.version.hello_has_chat_mode() {
+            warn!("Version {} can't send a nonce in its hello", self.version);
+        }
+        // The identity and token go after everything the game sends
+        let mut w = PacketWriter::new();
+        if let Some(identity) = &self.identity {
+            w.write_string(&identity.product_user_id);
+            w.write_string(&identity.friend_code);
+        }
+        if let Some(token) = &token {
+            w.write_bytes_raw(token);
+        }
+        HelloPacket {
+            hazel_version: self.hazel_version,
+            last_nonce,
+            language: Some(self.hello_language),
+            chat_mode: Some(self.chat_mode),
+            extra: w.finish(),
+            ..HelloPacket::new(self.version, connect_username)
+        }
+    }
+
+    /// Says hello, starts probing the MTU if enabled and rejoins the game we're in, otherwise `game_id`.
+    /// With neither, creates a game with `host_options`
+    fn start_session(&mut self, connect_username: &str, game_id: Option<GameId>) {
+        self.send_hello(connect_username);
+        self.resume_session(game_id);
+    }
+
+    /// `start_session` on a connection that's already said hello
+    fn resume_session(&mut self, game_id: Option<GameId>) {
+        if self.probe_mtu {
+            self.client.start_mtu_probe(MTU_PROBE_TIMEOUT);
+        }
+        // `game_id` is out of date if we've left and joined another
+        match (self.game_id.or(game_id), self.host_options.clone()) {
+            (Some(game_id), _) => self.join_game_id(game_id),
+            (None, Some(game_options)) => {
+                let game_options = self.versioned_options(game_options);
+                let packet = HostGamePacket { game_options };
+                self.send_reliable(PacketType::HostingGame, Box::new(packet));
+            }
+            (None, None) => error!("No game to join or options to create one with"),
+        }
+    }
+
+    /// `options` laid out for the version we're speaking
+    fn versioned_options(&self, options: GameOptions) -> GameOptions {
+        GameOptions {
+            game_settings_version: self.version.game_options_version(),
+            ..options
+        }
     }
 
     pub fn send_reliable(&mut self, packet_type: PacketType, data: Box<dyn Serialize>) {
@@ -596,22 +2201,28 @@ impl Client {
     }
 
     pub fn send_ready(&mut self) {
-        let packet = Packet::GameInfo {
-            game_id: self.game_id.unwrap(),
-            data: vec![GameInfo::ClientReady {
-                client_id: self.client_id.unwrap(),
-            }],
+        let info = GameInfo::ClientReady {
+            client_id: self.client_id.unwrap(),
         };
-        self.send_reliable(PacketType::GameInfo, Box::new(packet));
+        self.send_game_info(info);
     }
 
-    pub fn join_game_code(&mut self, code: &str) {
-        if self.game_id.is_some() {
-            unimplemented!();
-        }
-
+    /// Joins a game by code, leaving the one we're in first if any, the same way `leave_game`
+    /// does. We reconnect to the same server unless `ClientSettings::redirect_cache` knows the
+    /// new game is on another one. Fails if leaving does
+    pub fn join_game_code(&mut self, code: &str) -> Result<(), ClientError> {
         let game_id = GameId::from_chars(code);
+        if let Some(current) = self.game_id {
+            info!("Leaving {} for {}", current, game_id);
+            let addr = self
+                .redirect_cache
+                .as_ref()
+                .and_then(|cache| cache.lock().unwrap().get(game_id))
+                .unwrap_or_else(|| self.client.addr());
+            self.leave_to(addr)?;
+        }
         self.join_game_id(game_id);
+        Ok(())
     }
 
     fn join_game_id(&mut self, game_id: GameId) {
@@ -624,199 +2235,1236 @@ impl Client {
         self.send_reliable(PacketType::GameJoinDisconnect, Box::new(join_game_packet));
     }
 
-    pub fn change_scene(&mut self, scene_name: &str) {
-        let packet = Packet::GameInfo {
-            game_id: self.game_id.unwrap(),
-            data: vec![GameInfo::ChangeScene {
-                client_id: self.client_id.unwrap(),
-                scene: scene_name.to_string(),
-            }],
-        };
-        self.send_reliable(PacketType::GameInfo, Box::new(packet));
+    pub fn change_scene(&mut self, scene_name: &str) {
+        let info = GameInfo::ChangeScene {
+            client_id: self.client_id.unwrap(),
+            scene: scene_name.to_string(),
+        };
+        self.send_game_info(info);
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.set_player_name(self.client_id.unwrap(), name);
+    }
+
+    /// Asks the host to rename a player. As host the name is set as given, without checking
+    /// whether someone else has it
+    pub fn set_player_name(&mut self, player_id: i32, name: &str) {
+        if self.is_host() {
+            self.host_set_name(player_id, name);
+        } else {
+            let control = match self.net_objects.get_player_control(player_id) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_check_name(name);
+            self.send_to_host(info);
+        }
+    }
+
+    /// Returns `name`, or if another player already has it, `name` followed by the lowest number
+    /// that makes it unique. This is what the official host does
+    pub fn dedupe_name(&mut self, player_id: i32, name: &str) -> String {
+        let taken = self.taken(player_id, |data| data.name.clone());
+        if !taken.contains(&name.to_string()) {
+            return name.to_string();
+        }
+        (1..)
+            .map(|i| format!("{} {}", name, i))
+            .find(|candidate| !taken.contains(candidate))
+            .unwrap()
+    }
+
+    /// Returns `color`, or if another player already has it, the next color no one has. This is
+    /// what the official host does
+    pub fn next_free_color(&mut self, player_id: i32, color: u8) -> u8 {
+        let taken = self.taken(player_id, |data| data.color);
+        let mut color = color % NUM_COLORS;
+        for _ in 0..NUM_COLORS {
+            if !taken.contains(&color) {
+                break;
+            }
+            color = (color + 1) % NUM_COLORS;
+        }
+        color
+    }
+
+    /// The best name there is for a player by client id. Names arrive a little after players
+    /// spawn, so this falls back from their `PlayerControl` to their `GameData` entry to
+    /// "Player {client_id}"
+    pub fn player_name(&mut self, client_id: i32) -> String {
+        let control = self.net_objects.get_player_control(client_id);
+        if let Some(name) = control.as_ref().and_then(|control| control.name.clone()) {
+            return name;
+        }
+        let player_id = control.map(|control| control.player_id);
+        self.net_objects
+            .game_datas
+            .first()
+            .zip(player_id)
+            .and_then(|(game_data, player_id)| game_data.players.get(&player_id))
+            .map(|data| data.name.clone())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("Player {}", client_id))
+    }
+
+    /// Collects a value from every player except `player_id`
+    fn taken<T, F: Fn(&PlayerData) -> T>(&mut self, player_id: i32, f: F) -> Vec<T> {
+        let own_id = self
+            .net_objects
+            .get_player_control(player_id)
+            .map(|control| control.player_id);
+        self.net_objects
+            .game_datas
+            .first()
+            .map(|game_data| {
+                game_data
+                    .players
+                    .iter()
+                    .filter(|(&id, _)| Some(id) != own_id)
+                    .map(|(_, data)| f(data))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Tells everyone a player's name, as host
+    fn host_set_name(&mut self, player_id: i32, name: &str) {
+        let control = match self.net_objects.get_player_control(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        control.name = Some(name.to_string());
+        let id = control.player_id;
+        let info = control.rpc_set_name(name);
+        if let Some(data) = self.player_data(id) {
+            data.name = name.to_string();
+        }
+        self.send_game_info(info);
+    }
+
+    /// Tells everyone a player's color, as host
+    fn host_set_color(&mut self, player_id: i32, color_index: u8) {
+        let control = match self.net_objects.get_player_control(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let id = control.player_id;
+        let info = control.rpc_set_color(color_index);
+        if let Some(data) = self.player_data(id) {
+            data.color = color_index;
+        }
+        self.send_game_info(info);
+    }
+
+    fn player_data(&mut self, player_id: u8) -> Option<&mut PlayerData> {
+        self.net_objects
+            .game_datas
+            .first_mut()?
+            .players
+            .get_mut(&player_id)
+    }
+
+    /// A `GameInfoBatch` for the game we're in
+    fn batch(&self) -> GameInfoBatch {
+        GameInfoBatch::new(self.game_id.unwrap())
+    }
+
+    /// Sends a batch reliably, unless it's empty
+    fn send_batch(&mut self, batch: GameInfoBatch) {
+        if batch.is_empty() {
+            return;
+        }
+        self.remember_sent(&batch);
+        let packet_type = batch.packet_type();
+        self.send_reliable(packet_type, Box::new(batch.into_packet()));
+    }
+
+    /// Sends a `GameInfo` to everyone in the game
+    fn send_game_info(&mut self, info: GameInfo) {
+        self.send_batch(self.batch().push(info));
+    }
+
+    /// Sends a `GameInfo` to just the host
+    fn send_to_host(&mut self, info: GameInfo) {
+        self.send_batch(self.batch().to(self.host_id.unwrap()).push(info));
+    }
+
+    pub fn send_chat(&mut self, message: &str) {
+        self.send_chat_player(self.client_id.unwrap(), message);
+    }
+
+    /// Chat goes to everyone whether or not we're host. Does nothing if we're quick chat only
+    pub fn send_chat_player(&mut self, player_id: i32, message: &str) {
+        if self.is_quick_chat_only() {
+            warn!("Can't send chat with quick chat only: {}", message);
+            return;
+        }
+        let control = match self.net_objects.get_player_control(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = control.rpc_chat_message(message);
+        self.send_game_info(info);
+    }
+
+    pub fn set_color(&mut self, color_index: u8) {
+        self.set_player_color(self.client_id.unwrap(), color_index);
+    }
+
+    /// Asks the host to change a player's color. As host the color is set as given, without
+    /// checking whether someone else has it
+    pub fn set_player_color(&mut self, player_id: i32, color_index: u8) {
+        if self.is_host() {
+            self.host_set_color(player_id, color_index);
+        } else {
+            let control = match self.net_objects.get_player_control(player_id) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_check_color(color_index);
+            self.send_to_host(info);
+        }
+    }
+
+    pub fn set_skin(&mut self, skin_index: u32) {
+        if self.is_host() {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let id = control.player_id;
+            let info = control.rpc_set_skin(skin_index);
+            if let Some(data) = self.player_data(id) {
+                data.skin_id = skin_index;
+            }
+            self.send_game_info(info);
+        } else {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_set_skin(skin_index);
+            self.send_to_host(info);
+        }
+    }
+
+    pub fn set_hat(&mut self, hat_index: u32) {
+        if self.is_host() {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let id = control.player_id;
+            let info = control.rpc_set_hat(hat_index);
+            if let Some(data) = self.player_data(id) {
+                data.hat_id = hat_index;
+            }
+            self.send_game_info(info);
+        } else {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_set_hat(hat_index);
+            self.send_to_host(info);
+        }
+    }
+
+    pub fn set_pet(&mut self, pet_index: u32) {
+        if self.is_host() {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let id = control.player_id;
+            let info = control.rpc_set_pet(pet_index);
+            if let Some(data) = self.player_data(id) {
+                data.pet_id = pet_index;
+            }
+            self.send_game_info(info);
+        } else {
+            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_set_pet(pet_index);
+            self.send_to_host(info);
+        }
+    }
+
+    /// Teleports us, stopping any walk started with `move_towards`
+    pub fn set_position(&mut self, new_pos: Vector2) {
+        self.movement = None;
+        self.set_player_position(self.client_id.unwrap(), new_pos);
+    }
+
+    pub fn set_player_position(&mut self, player_id: i32, new_pos: Vector2) {
+        if self.is_host() {
+            let transform = match self.net_objects.get_player_transform(player_id) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = transform.rpc_snap_to(new_pos);
+            self.send_game_info(info);
+        } else {
+            let transform = match self.net_objects.get_player_transform(player_id) {
+                Some(value) => value,
+                None => return,
+            };
+            let info = transform.rpc_snap_to(new_pos);
+            self.send_to_host(info);
+        }
+    }
+
+    /// Walks us in a straight line to `target` at `speed` units a second, sending movement
+    /// snapshots as we go so others see us walk. The game's normal speed is `walk_speed`.
+    /// Replaces any walk in progress
+    pub fn move_towards(&mut self, target: Vector2, speed: f32) {
+        let from = match self
+            .client_id
+            .and_then(|id| self.net_objects.get_player_transform(id))
+        {
+            Some(transform) => transform.target_position,
+            None => return,
+        };
+        self.movement = Some(Movement::new(from, vec![target], speed, self.now()));
+    }
+
+    /// Walks us to `target` along the map's corridors at the game's speed. Goes straight there if
+    /// the map isn't known. See `NavGraph`
+    pub fn walk_to(&mut self, target: Vector2) {
+        let from = match self
+            .client_id
+            .and_then(|id| self.net_objects.get_player_transform(id))
+        {
+            Some(transform) => transform.target_position,
+            None => return,
+        };
+        let map_id = self
+            .game_options
+            .as_ref()
+            .map_or(0, |options| options.map_id);
+        let waypoints = match map_nav(map_id) {
+            Some(graph) => graph.path(from, target),
+            None => vec![target],
+        };
+        let speed = self.walk_speed();
+        self.movement = Some(Movement::new(from, waypoints, speed, self.now()));
+    }
+
+    /// How fast players walk in units a second, going by the game options
+    pub fn walk_speed(&self) -> f32 {
+        let modifier = self
+            .game_options
+            .as_ref()
+            .map_or(1., |options| options.player_speed);
+        BASE_WALK_SPEED * modifier
+    }
+
+    /// Whether we're still walking somewhere. See `move_towards`
+    pub fn is_moving(&self) -> bool {
+        self.movement.is_some()
+    }
+
+    /// Stops walking where we are
+    pub fn stop_moving(&mut self) {
+        if let Some(mut movement) = self.movement.take() {
+            let (position, _) = movement.step(self.now());
+            self.send_movement(position, Vector2::ZERO);
+        }
+    }
+
+    /// Sends the next snapshot of the walk in progress, if it's time
+    fn step_movement(&mut self) {
+        let now = self.now();
+        let movement = match &mut self.movement {
+            Some(movement) if movement.is_due(now) => movement,
+            _ => return,
+        };
+        let (position, velocity) = movement.step(now);
+        if movement.arrived() {
+            self.movement = None;
+        }
+        self.send_movement(position, velocity);
+    }
+
+    /// Sends a movement snapshot, for walking rather than teleporting like `set_position`
+    pub fn send_movement(&mut self, position: Vector2, velocity: Vector2) {
+        self.send_player_movement(self.client_id.unwrap(), position, velocity);
+    }
+
+    /// Sends a movement snapshot for a player. Unreliable unless
+    /// `ClientSettings::unreliable_movement` is turned off
+    pub fn send_player_movement(&mut self, player_id: i32, position: Vector2, velocity: Vector2) {
+        let transform = match self.net_objects.get_player_transform(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = transform.data_movement(position, velocity);
+        let batch = self.batch().push(info);
+        if self.unreliable_movement {
+            self.remember_sent(&batch);
+            self.send_unreliable(batch.packet_type(), Box::new(batch.into_packet()));
+        } else {
+            self.send_batch(batch);
+        }
+    }
+
+    pub fn enter_vent(&mut self, vent_id: u32) {
+        self.player_enter_vent(self.client_id.unwrap(), vent_id);
+    }
+
+    pub fn player_enter_vent(&mut self, player_id: i32, vent_id: u32) {
+        let physics = match self.net_objects.get_player_physics(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = physics.rpc_enter_vent(vent_id);
+        self.send_game_info(info);
+    }
+
+    /// Our tasks, once the host has handed them out. Empty before the game starts
+    pub fn tasks(&self) -> &[TaskInfo] {
+        let player_id = match self
+            .net_objects
+            .player_controls
+            .iter()
+            .find(|control| Some(control.owner_id()) == self.client_id)
+        {
+            Some(control) => control.player_id,
+            None => return &[],
+        };
+        self.net_objects
+            .game_datas
+            .first()
+            .and_then(|game_data| game_data.players.get(&player_id))
+            .map_or(&[], |data| &data.tasks)
+    }
+
+    /// Tells everyone one of our tasks is done. `task_index` is an index into `Client::tasks`
+    pub fn complete_task(&mut self, task_index: u32) {
+        let client_id = self.client_id.unwrap();
+        let control = match self.net_objects.get_player_control(client_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = control.rpc_complete_task(task_index);
+        self.send_game_info(info);
+        if self.is_host() {
+            self.host_complete_task(client_id, task_index);
+        } else {
+            self.mark_task_complete(client_id, task_index);
+        }
+    }
+
+    /// Shuts every door of `room` for a while. Impostor only
+    pub fn close_doors(&mut self, room: SystemType) {
+        if self.is_host() {
+            self.host_close_doors(room);
+            return;
+        }
+        let world = match self.net_objects.worlds.first() {
+            Some(value) => value,
+            None => return,
+        };
+        let info = world.rpc_close_doors(room);
+        self.send_to_host(info);
+    }
+
+    /// Reports a dead player's body, starting a meeting
+    pub fn report_body(&mut self, dead_player_id: u8) {
+        self.request_meeting(Some(dead_player_id));
+    }
+
+    /// Presses the emergency button
+    pub fn call_meeting(&mut self) {
+        self.request_meeting(None);
+    }
+
+    /// Asks the host for a meeting, or starts one if we're host
+    fn request_meeting(&mut self, target_player_id: Option<u8>) {
+        let client_id = self.client_id.unwrap();
+        if self.is_host() {
+            self.host_start_meeting(client_id, target_player_id);
+            return;
+        }
+        let control = match self.net_objects.get_player_control(client_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = control.rpc_report_body(target_player_id);
+        self.send_to_host(info);
+    }
+
+    /// Votes in the current meeting. `None` to skip
+    pub fn cast_vote(&mut self, suspect_player_id: Option<u8>) {
+        let voter_player_id = match self
+            .client_id
+            .and_then(|client_id| self.net_objects.get_player_control(client_id))
+        {
+            Some(control) => control.player_id,
+            None => return,
+        };
+        if self.is_host() {
+            self.host_cast_vote(voter_player_id, suspect_player_id);
+            return;
+        }
+        let info = match self.net_objects.meeting_huds.first() {
+            Some(meeting_hud) => meeting_hud.rpc_cast_vote(voter_player_id, suspect_player_id),
+            None => return,
+        };
+        self.send_to_host(info);
+    }
+
+    /// Host only. Lists the lobby publicly or takes it off the list. The server echoes the change
+    /// back, which is when `is_public` changes and `EventHandler::visibility_changed` is called
+    pub fn set_public(&mut self, is_public: bool) {
+        if !self.is_host() {
+            warn!("Only the host can change visibility");
+            return;
+        }
+        let packet = Packet::GameAltered {
+            game_id: self.game_id.unwrap(),
+            alteration: AlterGameTag::ChangePrivacy { is_public },
+        };
+        self.send_reliable(PacketType::AlterGameInfo, Box::new(packet));
+    }
+
+    /// Host only. Changes the lobby settings for everyone, and for anyone joining later. Options
+    /// the game would reject are ignored, see `GameOptions::validate`
+    pub fn set_game_options(&mut self, options: GameOptions) {
+        if !self.is_host() {
+            warn!("Only the host can change game options");
+            return;
+        }
+        if let Err(invalid) = options.validate() {
+            for reason in invalid {
+                warn!("Invalid game options: {}", reason);
+            }
+            return;
+        }
+        let options = self.versioned_options(options);
+        let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+            Some(value) => value,
+            None => return,
+        };
+        let info = control.rpc_set_game_options(&options);
+        self.game_options = Some(options);
+        self.send_game_info(info);
+    }
+
+    /// Host only. Starts the game for everyone in the lobby. Once everyone has loaded the map, or
+    /// after 10 seconds, the map is spawned and roles are handed out with `assign_roles`
+    ///
+    /// Only the Skeld can be spawned, other maps get roles but no map
+    pub fn start_game(&mut self) {
+        if !self.is_host() {
+            warn!("Only the host can start the game");
+            return;
+        }
+        if self.in_game || self.starting.is_some() {
+            warn!("Game already started");
+            return;
+        }
+        if self.game_options.is_none() {
+            warn!("Can't start a game without game options");
+            return;
+        }
+        let game_id = self.game_id.unwrap();
+        self.send_reliable(
+            PacketType::GameStarted,
+            Box::new(Packet::GameStarted { game_id }),
+        );
+        self.starting = Some(GameStart::new(self.client_id.unwrap(), self.now()));
+    }
+
+    /// Spawns the map and hands out roles once everyone playing has loaded the map, or once
+    /// they've had long enough
+    fn host_check_ready(&mut self) {
+        let starting = match &self.starting {
+            Some(value) => value,
+            None => return,
+        };
+        let now = self.now();
+        let waiting = self
+            .player_ids
+            .iter()
+            .filter(|player_id| !self.spectators.contains(player_id))
+            .filter(|player_id| !starting.ready.contains(player_id))
+            .copied()
+            .collect::<Vec<_>>();
+        if !waiting.is_empty() {
+            if !starting.timed_out(now) {
+                return;
+            }
+            warn!("Starting without {:?}, who didn't load in time", waiting);
+        }
+        self.starting = None;
+        self.host_spawn_world();
+        self.assign_roles();
+    }
+
+    /// Spawns the ship for the map being played
+    fn host_spawn_world(&mut self) {
+        let map_id = self
+            .game_options
+            .as_ref()
+            .map_or(0, |options| options.map_id);
+        if map_id != 0 {
+            warn!("Can't spawn map {} as host, only the Skeld", map_id);
+            return;
+        }
+        let world = World::new(self.net_objects.next_net_id(), HOST_OWNED);
+        self.send_game_info(GameInfo::CreateFromPrefab {
+            spawn_flags: 0,
+            prefab: Prefab::World(world.clone()),
+        });
+        self.net_objects.add(Prefab::World(world));
+    }
+
+    /// Spawns a player who's loaded the lobby, first sending them everything already spawned. The
+    /// lobby and game data are spawned along with the first player
+    ///
+    /// Returns the spawns to handle ourselves, as the server doesn't send them back
+    fn host_spawn_player(&mut self, client_id: i32) -> Vec<GameInfo> {
+        if self.net_objects.get_player_control(client_id).is_some() {
+            debug!("Player {} already spawned", client_id);
+            return Vec::new();
+        }
+        let mut net_id = self.net_objects.next_net_id();
+        let mut prefabs = Vec::new();
+        let new_lobby = self.net_objects.game_datas.is_empty();
+        let player_id = match self.net_objects.game_datas.first_mut() {
+            Some(game_data) => {
+                let player_id = match (0..u8::MAX).find(|id| !game_data.players.contains_key(id)) {
+                    Some(value) => value,
+                    None => {
+                        warn!("No player ids left for {}", client_id);
+                        return Vec::new();
+                    }
+                };
+                let data = PlayerData {
+                    dirty: true,
+                    ..PlayerData::default()
+                };
+                game_data.add_player(player_id, data);
+                player_id
+            }
+            None => {
+                let mut game_data = GameData::new(net_id + 1, HOST_OWNED);
+                game_data.add_player(0, PlayerData::default());
+                let vote_ban = VoteBanSystem::new(net_id + 2, HOST_OWNED);
+                prefabs.push(Prefab::Lobby(Lobby::new(net_id, HOST_OWNED)));
+                prefabs.push(Prefab::GameData(game_data, vote_ban));
+                net_id += 3;
+                0
+            }
+        };
+
+        if Some(client_id) != self.client_id {
+            let mut batch = self.batch().to(client_id);
+            for prefab in self.net_objects.prefabs() {
+                batch = batch.spawn(0, prefab);
+            }
+            // Joiners only learn the lobby settings from the host
+            let control = self.net_objects.get_player_control(self.client_id.unwrap());
+            if let (Some(control), Some(options)) = (control, &self.game_options) {
+                batch = batch.push(control.rpc_set_game_options(options));
+            }
+            self.send_batch(batch);
+        }
+
+        prefabs.push(Prefab::Player(
+            PlayerControl::new(net_id, client_id, player_id),
+            PlayerPhysics::new(net_id + 1, client_id),
+            PlayerTransform::new(net_id + 2, client_id, Vector2::ZERO),
+        ));
+        let spawn = |prefab: Prefab| GameInfo::CreateFromPrefab {
+            // Marks the player as someone's character
+            spawn_flags: if let Prefab::Player(..) = prefab {
+                1
+            } else {
+                0
+            },
+            prefab,
+        };
+        let batch = self.batch().extend(prefabs.iter().cloned().map(spawn));
+        self.send_batch(batch);
+        if !new_lobby {
+            self.update_game_data();
+        }
+        prefabs.into_iter().map(spawn).collect()
+    }
+
+    /// Host only. Picks impostors and hands out tasks, telling everyone. Done as the game starts
+    pub fn assign_roles(&mut self) {
+        if !self.is_host() {
+            warn!("Only the host can assign roles");
+            return;
+        }
+        let options = match &self.game_options {
+            Some(options) => options.clone(),
+            None => {
+                warn!("Can't assign roles without game options");
+                return;
+            }
+        };
+        let mut player_ids = match self.net_objects.game_datas.first() {
+            Some(game_data) => game_data
+                .players
+                .iter()
+                .filter(|(_, data)| !data.disconnected)
+                .map(|(&player_id, _)| player_id)
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+        player_ids.sort_unstable();
+
+        let count = (options.num_imposters.max(1) as usize).min(max_impostors(player_ids.len()));
+        let impostors = self
+            .impostor_selection
+            .select(&player_ids, count, &mut self.rng);
+        let task_pool = match &self.task_pool {
+            Some(task_pool) => task_pool.clone(),
+            None => match TaskPool::for_map(options.map_id) {
+                Some(task_pool) => task_pool,
+                None => {
+                    warn!("No tasks for map {}", options.map_id);
+                    TaskPool::default()
+                }
+            },
+        };
+        let tasks = task_pool.assign(&options, &player_ids, &mut self.rng);
+
+        let set_infected = match self.net_objects.get_player_control(self.client_id.unwrap()) {
+            Some(control) => control.rpc_set_infected(&impostors),
+            None => return,
+        };
+        let mut batch = self.batch();
+        let game_data = self.net_objects.game_datas.first_mut().unwrap();
+        for player_id in player_ids.iter() {
+            let task_ids = &tasks[player_id];
+            batch = batch.push(game_data.rpc_set_tasks(*player_id, task_ids));
+            let data = game_data.players.get_mut(player_id).unwrap();
+            data.is_imposter = impostors.contains(player_id);
+            data.tasks = task_ids
+                .iter()
+                .map(|&task_id| TaskInfo::new(task_id as u32))
+                .collect();
+        }
+        self.send_batch(batch.push(set_infected));
+    }
+
+    /// Sends whatever initial info is due
+    fn send_initial_info(&mut self) {
+        let now = self.now();
+        loop {
+            let value = match &mut self.initial_info {
+                Some(pending) => pending.next_due(now, &mut self.rng),
+                None => None,
+            };
+            match value {
+                None => break,
+                Some(InfoValue::Name(name)) => self.set_name(&name),
+                Some(InfoValue::Color(color)) => self.set_color(color),
+                Some(InfoValue::Skin(skin)) => self.set_skin(skin),
+                Some(InfoValue::Hat(hat)) => self.set_hat(hat),
+                Some(InfoValue::Pet(pet)) => self.set_pet(pet),
+            }
+        }
+        if self.initial_info.as_ref().is_some_and(PendingInfo::is_done) {
+            self.initial_info = None;
+        }
+    }
+
+    /// The owner and position of a `PlayerTransform`
+    fn transform_position(&self, net_id: u32) -> Option<(i32, Vector2)> {
+        self.net_objects
+            .player_transforms
+            .iter()
+            .find(|transform| transform.net_id() == net_id)
+            .map(|transform| (transform.owner_id(), transform.target_position))
+    }
+
+    /// What's sabotaged, if `net_id` is a `World`
+    fn sabotaged_systems(&self, net_id: u32) -> Option<Vec<SystemType>> {
+        self.net_objects
+            .worlds
+            .iter()
+            .find(|world| world.net_id() == net_id)
+            .map(World::sabotaged_systems)
+    }
+
+    /// Which doors are open, if `net_id` is a `World`
+    fn door_states(&self, net_id: u32) -> Option<Vec<bool>> {
+        self.net_objects
+            .worlds
+            .iter()
+            .find(|world| world.net_id() == net_id)
+            .map(|world| world.door_open.clone())
+    }
+
+    /// Players in the game who haven't moved or done a task for at least `threshold`, by client
+    /// id. Meetings don't count. Empty outside of games and during meetings
+    pub fn afk_players(&self, threshold: Duration) -> Vec<i32> {
+        if !self.in_game || !self.net_objects.meeting_huds.is_empty() {
+            return Vec::new();
+        }
+        self.activity.idle(threshold, self.now())
+    }
+
+    /// Says in chat who's newly gone AFK. See `ClientSettings::afk_announce`
+    fn announce_afk(&mut self) {
+        let threshold = match self.afk_announce {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        for client_id in self.afk_players(threshold) {
+            if !self.activity.announce(client_id) {
+                continue;
+            }
+            let message = format!(
+                "{} has been AFK for {}s",
+                self.player_name(client_id),
+                threshold.as_secs()
+            );
+            let control = match self
+                .client_id
+                .and_then(|id| self.net_objects.get_player_control(id))
+            {
+                Some(value) => value,
+                None => return,
+            };
+            let info = control.rpc_chat_message(&message);
+            self.send_game_info(info);
+        }
+    }
+
+    /// Runs anything waiting on a timer. Called at least every `TICK_INTERVAL`
+    fn tick(&mut self) {
+        match self.client.poll_mtu_probe() {
+            Some(MtuProbeResult::Probed(size)) => info!("Path MTU probed as {} bytes", size),
+            Some(MtuProbeResult::Unanswered) => {
+                warn!("No MTU probes answered, keeping {}", self.max_packet_size())
+            }
+            None => (),
+        }
+        self.send_initial_info();
+        self.announce_afk();
+        self.step_movement();
+        let now = self.now();
+        if self
+            .timeline
+            .as_mut()
+            .is_some_and(|timeline| timeline.snapshot_due(now))
+        {
+            let players = self.spectator_players();
+            if let Some(timeline) = &mut self.timeline {
+                timeline.record(SpectatorEvent::Players { players }, now);
+            }
+        }
+        if !self.is_host() {
+            return;
+        }
+        if self
+            .spectator_feed
+            .as_mut()
+            .is_some_and(|feed| feed.players_due(now))
+        {
+            let players = self.spectator_players();
+            self.spectate(SpectatorEvent::Players { players });
+        }
+        if self.in_game {
+            let countdown = self.net_objects.worlds.first().and_then(critical_countdown);
+            match (countdown, self.sabotage_deadline) {
+                (Some(countdown), None) => self.sabotage_deadline = Some(now + countdown),
+                (Some(_), Some(deadline)) if now >= deadline => {
+                    info!("Sabotage timer ran out");
+                    self.end_game(GameOverReason::ImpostorBySabotage);
+                }
+                (None, _) => self.sabotage_deadline = None,
+                _ => (),
+            }
+        }
+        let (reopened, closed) = self
+            .closed_doors
+            .drain(..)
+            .partition::<Vec<_>, _>(|&(_, reopen)| now >= reopen);
+        self.closed_doors = closed;
+        for (room, _) in reopened {
+            self.host_set_doors(&World::doors_of(room), true);
+        }
+        self.host_check_ready();
+        if let Some(meeting) = &self.meeting {
+            match meeting.completed {
+                None if meeting.timed_out(now) => self.host_complete_voting(),
+                Some(completed) if now.duration_since(completed) >= MEETING_CLOSE_DELAY => {
+                    self.host_close_meeting()
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Host only. Shuts every door of `room` and sends the new door states. They open again after
+    /// `DOOR_CLOSE_TIME`
+    fn host_close_doors(&mut self, room: SystemType) {
+        if !self.in_game || self.closed_doors.iter().any(|&(closed, _)| closed == room) {
+            return;
+        }
+        let doors = World::doors_of(room);
+        if doors.is_empty() {
+            debug!("No doors in {:?}", room);
+            return;
+        }
+        let reopen = self.now() + DOOR_CLOSE_TIME;
+        self.closed_doors.push((room, reopen));
+        self.host_set_doors(&doors, false);
     }
 
-    pub fn set_name(&mut self, name: &str) {
-        self.set_player_name(self.client_id.unwrap(), name);
+    /// Host only. Opens or closes `doors`, indexes into `World::door_open`, for everyone
+    fn host_set_doors(&mut self, doors: &[usize], open: bool) {
+        let world = match self.net_objects.worlds.first_mut() {
+            Some(value) => value,
+            None => return,
+        };
+        let mut dirty = 0;
+        for &door in doors {
+            world.door_open[door] = open;
+            dirty |= 1 << door;
+        }
+        let info = world.doors_update_info(dirty);
+        self.send_game_info(info);
     }
 
-    pub fn set_player_name(&mut self, player_id: i32, name: &str) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(player_id) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_check_name(name);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
+    /// Host only. Calls a meeting on behalf of `player_id` and spawns the voting screen.
+    /// `target_player_id` is the body reported, or `None` for the emergency button
+    pub fn host_start_meeting(&mut self, player_id: i32, target_player_id: Option<u8>) {
+        if self.meeting.is_some() {
+            debug!("Meeting already in progress");
+            return;
         }
+        let control = match self.net_objects.get_player_control(player_id) {
+            Some(value) => value,
+            None => return,
+        };
+        let reporter_id = control.player_id;
+        let meeting_called = control.rpc_meeting_called(target_player_id);
+
+        let states = match self.net_objects.game_datas.first() {
+            Some(game_data) => game_data
+                .player_ids()
+                .into_iter()
+                .map(|player_id| {
+                    let data = &game_data.players[&player_id];
+                    VoteState {
+                        player_id,
+                        voted_for: None,
+                        is_dead: data.is_dead || data.disconnected,
+                        did_vote: false,
+                        did_report: player_id == reporter_id,
+                    }
+                })
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+
+        let net_id = self.net_objects.next_net_id();
+        let meeting_hud = MeetingHud::new(net_id, HOST_OWNED, states.clone());
+        let batch = self
+            .batch()
+            .push(meeting_called)
+            .spawn(0, Prefab::MeetingHud(meeting_hud));
+        self.send_batch(batch);
+
+        self.net_objects
+            .meeting_huds
+            .push(MeetingHud::new(net_id, HOST_OWNED, states));
+        self.meeting = Some(HostMeeting::new(
+            net_id,
+            self.game_options.as_ref(),
+            self.now(),
+        ));
+        self.spectate(SpectatorEvent::MeetingStarted {
+            reporter_player_id: reporter_id,
+            body_player_id: target_player_id,
+        });
     }
 
-    pub fn send_chat(&mut self, message: &str) {
-        self.send_chat_player(self.client_id.unwrap(), message);
+    /// Records a vote, finishing voting early once everyone alive has voted
+    fn host_cast_vote(&mut self, voter_player_id: u8, suspect_player_id: Option<u8>) {
+        let net_id = match &self.meeting {
+            Some(meeting) if meeting.completed.is_none() => meeting.net_id,
+            _ => return,
+        };
+        let meeting_hud = match self
+            .net_objects
+            .meeting_huds
+            .iter_mut()
+            .find(|meeting_hud| meeting_hud.net_id() == net_id)
+        {
+            Some(value) => value,
+            None => return,
+        };
+        let index = match meeting_hud
+            .states
+            .iter()
+            .position(|state| state.player_id == voter_player_id)
+        {
+            Some(value) => value,
+            None => return,
+        };
+        let state = &mut meeting_hud.states[index];
+        if state.is_dead || state.did_vote {
+            return;
+        }
+        state.did_vote = true;
+        state.voted_for = suspect_player_id;
+        let all_voted = meeting_hud
+            .states
+            .iter()
+            .all(|state| state.is_dead || state.did_vote);
+
+        let info = meeting_hud.update_data_info(1 << index);
+        self.send_game_info(info);
+        if all_voted {
+            self.host_complete_voting();
+        }
     }
 
-    pub fn send_chat_player(&mut self, player_id: i32, message: &str) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(player_id) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_chat_message(message);
-            let packet = Packet::GameInfo {
-                game_id: self.game_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfo, Box::new(packet));
+    /// Works out who's exiled and tells everyone
+    fn host_complete_voting(&mut self) {
+        let now = self.now();
+        let net_id = match &mut self.meeting {
+            Some(meeting) => {
+                meeting.completed = Some(now);
+                meeting.net_id
+            }
+            None => return,
+        };
+        let meeting_hud = match self
+            .net_objects
+            .meeting_huds
+            .iter()
+            .find(|meeting_hud| meeting_hud.net_id() == net_id)
+        {
+            Some(value) => value,
+            None => return,
+        };
+        let (exiled_player_id, tie) = tally_votes(&meeting_hud.states);
+        info!("Voting complete. Exiled {:?}", exiled_player_id);
+        let info = meeting_hud.rpc_voting_complete(exiled_player_id, tie);
+        self.send_game_info(info);
+        self.spectate(SpectatorEvent::VotingComplete {
+            exiled_player_id,
+            tie,
+        });
+
+        if let Some(data) = exiled_player_id.and_then(|id| self.player_data(id)) {
+            data.is_dead = true;
+            data.dirty = true;
+            self.update_game_data();
+            self.host_check_win(WinCheckCause::Exile);
         }
     }
 
-    pub fn set_color(&mut self, color_index: u8) {
-        self.set_player_color(self.client_id.unwrap(), color_index);
+    /// Removes the voting screen once the exile cutscene has had time to play
+    fn host_close_meeting(&mut self) {
+        let meeting = match self.meeting.take() {
+            Some(value) => value,
+            None => return,
+        };
+        self.net_objects.remove(meeting.net_id);
+        self.delete_net_object(meeting.net_id);
     }
 
-    pub fn set_player_color(&mut self, player_id: i32, color_index: u8) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(player_id) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_check_color(color_index);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
+    /// Sends an event to spectators, if hosting with a feed, and adds it to the timeline
+    fn spectate(&mut self, event: SpectatorEvent) {
+        if let Some(feed) = &self.spectator_feed {
+            if self.is_host() {
+                feed.broadcast(&event);
+            }
+        }
+        // Snapshots go in the timeline at their own rate, see `tick`
+        if matches!(event, SpectatorEvent::Players { .. }) {
+            return;
+        }
+        let now = self.now();
+        if let Some(timeline) = &mut self.timeline {
+            timeline.record(event, now);
         }
     }
 
-    pub fn set_skin(&mut self, skin_index: u32) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_set_skin(skin_index);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
-        }
+    fn spectator_players(&self) -> Vec<SpectatorPlayer> {
+        self.state()
+            .players
+            .into_iter()
+            .map(|player| SpectatorPlayer {
+                player_id: player.player_id,
+                name: player.name,
+                color: player.color,
+                is_imposter: player.is_imposter,
+                is_dead: player.is_dead,
+                disconnected: player.disconnected,
+                position: player.position.map(|position| (position.x(), position.y())),
+            })
+            .collect()
     }
 
-    pub fn set_hat(&mut self, hat_index: u32) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_set_hat(hat_index);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
+    /// Marks the victim of a kill as dead
+    fn host_murder_player(&mut self, target_net_id: u32) {
+        let player_id = match self
+            .net_objects
+            .player_controls
+            .iter()
+            .find(|control| control.net_id() == target_net_id)
+        {
+            Some(control) => control.player_id,
+            None => return,
+        };
+        if let Some(data) = self.player_data(player_id) {
+            data.is_dead = true;
+            data.dirty = true;
+            self.update_game_data();
+            self.spectate(SpectatorEvent::Kill {
+                victim_player_id: player_id,
+            });
+            self.host_check_win(WinCheckCause::Kill);
         }
     }
 
-    pub fn set_pet(&mut self, pet_index: u32) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let control = match self.net_objects.get_player_control(self.client_id.unwrap()) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = control.rpc_set_pet(pet_index);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
+    fn host_complete_task(&mut self, owner_id: i32, task_index: u32) {
+        let player_id = match self.mark_task_complete(owner_id, task_index) {
+            Some(value) => value,
+            None => return,
+        };
+        if let Some(data) = self.player_data(player_id) {
+            data.dirty = true;
         }
+        self.update_game_data();
+        self.host_check_win(WinCheckCause::TaskComplete);
     }
 
-    pub fn set_position(&mut self, new_pos: Vector2) {
-        self.set_player_position(self.client_id.unwrap(), new_pos);
+    /// Marks a player's task as complete in `GameData`, returning their player id if they had it
+    fn mark_task_complete(&mut self, owner_id: i32, task_index: u32) -> Option<u8> {
+        let player_id = self.net_objects.get_player_control(owner_id)?.player_id;
+        let data = self.player_data(player_id)?;
+        match data.tasks.get_mut(task_index as usize) {
+            Some(task) => task.set_complete(),
+            None => {
+                warn!("{} completed unknown task {}", owner_id, task_index);
+                return None;
+            }
+        }
+        Some(player_id)
     }
 
-    pub fn set_player_position(&mut self, player_id: i32, new_pos: Vector2) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let transform = match self.net_objects.get_player_transform(player_id) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = transform.rpc_snap_to(new_pos);
-            let packet = Packet::GameInfoTo {
-                game_id: self.game_id.unwrap(),
-                client_id: self.host_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfoTo, Box::new(packet));
+    fn host_player_left(&mut self, owner_id: i32) {
+        let player_id = match self.net_objects.get_player_control(owner_id) {
+            Some(control) => control.player_id,
+            None => return,
+        };
+        if let Some(data) = self.player_data(player_id) {
+            data.disconnected = true;
+            data.dirty = true;
+            self.update_game_data();
+            self.host_check_win(WinCheckCause::Disconnect);
         }
     }
 
-    pub fn enter_vent(&mut self, vent_id: u32) {
-        self.player_enter_vent(self.client_id.unwrap(), vent_id);
+    /// Ends the game if someone has won
+    fn host_check_win(&mut self, cause: WinCheckCause) {
+        if !self.in_game {
+            return;
+        }
+        let reason = match self.net_objects.game_datas.first() {
+            Some(game_data) => check_win(&game_data.players, cause),
+            None => return,
+        };
+        if let Some(reason) = reason {
+            self.end_game(reason);
+        }
     }
 
-    pub fn player_enter_vent(&mut self, player_id: i32, vent_id: u32) {
-        if self.is_host() {
-            todo!()
-        } else {
-            let physics = match self.net_objects.get_player_physics(player_id) {
-                Some(value) => value,
-                None => return,
-            };
-            let info = physics.rpc_enter_vent(vent_id);
-            let packet = Packet::GameInfo {
-                game_id: self.game_id.unwrap(),
-                data: vec![info],
-            };
-            self.send_reliable(PacketType::GameInfo, Box::new(packet));
+    /// Host only. Ends the game for everyone
+    pub fn end_game(&mut self, reason: GameOverReason) {
+        if !self.is_host() {
+            warn!("Only the host can end the game");
+            return;
         }
+        info!("Ending game: {:?}", reason);
+        self.in_game = false;
+        self.sabotage_deadline = None;
+        self.closed_doors.clear();
+        self.send_reliable(
+            PacketType::EndGame,
+            Box::new(Packet::GameEnded {
+                game_id: self.game_id.unwrap(),
+                reason,
+                show_ad: false,
+            }),
+        );
     }
 
-    pub fn kick_player(&mut self, _player_id: i32, _ban: bool) {
-        panic!("Will get you banned from official servers")
-        // self.send_reliable(
-        //     PacketType::KickPlayer,
-        //     Box::new(Packet::KickPlayer {
-        //         game_id: self.game_id.unwrap(),
-        //         player_id,
-        //         ban,
-        //     }),
-        // );
+    /// Runs a chat message past `ClientSettings::moderator`, kicking or banning the sender if a
+    /// rule says to and we're host
+    fn moderate<H: EventHandler>(
+        &mut self,
+        handler: &mut H,
+        client_id: i32,
+        name: &str,
+        message: &str,
+    ) {
+        let actions = match &self.moderator {
+            Some(moderator) => moderator.check(message),
+            None => return,
+        };
+        if actions.is_empty() {
+            return;
+        }
+        if actions.contains(&ModAction::Log) {
+            warn!("Flagged chat from {}: {}", name, message);
+        }
+        let ban = actions.contains(&ModAction::Ban);
+        if ban || actions.contains(&ModAction::Kick) {
+            if !self.is_host() {
+                warn!("Can't remove {} without being host", name);
+            } else if Some(client_id) != self.client_id {
+                self.kick_player(client_id, ban);
+            }
+        }
+        handler.chat_flagged(self, client_id, message, &actions);
     }
 
-    pub fn delete_net_object(&mut self, net_id: u32) {
+    /// Host only. Banning also adds the player's name to the ban list if there is one
+    pub fn kick_player(&mut self, player_id: i32, ban: bool) {
+        if !self.is_host() {
+            panic!("Will get you banned from official servers")
+        }
+        if ban {
+            let name = self
+                .net_objects
+                .get_player_control(player_id)
+                .and_then(|control| control.name.clone());
+            if let (Some(ban_list), Some(name)) = (&mut self.ban_list, name) {
+                if let Err(error) = ban_list.ban_name(&name) {
+                    error!("Error saving ban {}", error);
+                }
+            }
+        }
         self.send_reliable(
-            PacketType::GameInfo,
-            Box::new(Packet::GameInfo {
+            PacketType::KickPlayer,
+            Box::new(Packet::KickPlayer {
                 game_id: self.game_id.unwrap(),
-                data: vec![GameInfo::Destroy { net_id }],
+                player_id,
+                ban,
             }),
-        )
+        );
+    }
+
+    pub fn delete_net_object(&mut self, net_id: u32) {
+        self.send_batch(self.batch().destroy(net_id));
     }
 
     pub fn update_game_data(&mut self) {
@@ -826,13 +3474,7 @@ impl Client {
             .get_mut(0)
             .unwrap()
             .rpc_update_player_info();
-        self.send_reliable(
-            PacketType::GameInfo,
-            Box::new(Packet::GameInfo {
-                game_id: self.game_id.unwrap(),
-                data: vec![info],
-            }),
-        );
+        self.send_game_info(info);
     }
 }
 
@@ -842,11 +3484,154 @@ pub trait EventHandler {
 
     fn joined_game(&mut self, client: &mut Client) {}
 
+    /// Called when another client joins our game, after `admit_player` if we're host. Not called
+    /// for players the host turns away
+    fn player_joined(&mut self, client: &mut Client, client_id: i32) {}
+
+    /// Called when the server creates the game asked for by `Client::create_game`, before
+    /// joining it
+    fn game_created(&mut self, client: &mut Client, game_id: GameId) {}
+
+    /// Called when another client leaves our game. `reason` tells quitting apart from being kicked
+    /// or banned, if the server sent one
+    fn player_left(
+        &mut self,
+        client: &mut Client,
+        client_id: i32,
+        reason: Option<DisconnectReason>,
+    ) {
+    }
+
+    /// Called when anything in `Client::lobby` changes, including when first joining
+    fn lobby_changed(&mut self, client: &mut Client, lobby: &LobbyInfo) {}
+
+    /// Called when the host lists the lobby publicly or takes it off the list. See
+    /// `Client::set_public`
+    fn visibility_changed(&mut self, client: &mut Client, is_public: bool) {}
+
+    /// Called for every change the host makes to the game, after any more specific event like
+    /// `visibility_changed`. Includes alterations we don't understand
+    fn game_altered(&mut self, client: &mut Client, alteration: AlterGameTag) {}
+
+    /// Called on joining a game after falling back to another of the `KNOWN_VERSIONS`. See
+    /// `ClientSettings::version_fallback`
+    fn version_fallback(&mut self, client: &mut Client, version: GameVersion) {}
+
     fn packet_received(&mut self, client: &mut Client) {}
 
     fn server_info(&mut self, client: &mut Client, data: ServerListPacket) {}
 
-    fn chat_message(&mut self, client: &mut Client, player_id: i32, message: String) {}
+    /// `name` is the sender's name from `Client::player_name`
+    fn chat_message(&mut self, client: &mut Client, player_id: i32, name: String, message: String) {
+    }
+
+    /// Called before `chat_message` when a message matches any `ClientSettings::moderator` rules.
+    /// `actions` are every matching rule's. Kicks and bans have already been done if we're host
+    fn chat_flagged(
+        &mut self,
+        client: &mut Client,
+        client_id: i32,
+        message: &str,
+        actions: &[ModAction],
+    ) {
+    }
+
+    /// Host only. Called when a player asks for a name. Returns the name to give them, or `None`
+    /// to ignore the request
+    ///
+    /// Defaults to `Client::dedupe_name`
+    fn check_name(&mut self, client: &mut Client, player_id: i32, name: String) -> Option<String> {
+        Some(client.dedupe_name(player_id, &name))
+    }
+
+    /// Host only. Called when a player asks for a color. Returns the color to give them, or
+    /// `None` to ignore the request
+    ///
+    /// Defaults to `Client::next_free_color`
+    fn check_color(&mut self, client: &mut Client, player_id: i32, color: u8) -> Option<u8> {
+        Some(client.next_free_color(player_id, color))
+    }
+
+    /// Host only. Called when a player joins the game to decide whether to let them in
+    fn admit_player(&mut self, client: &mut Client, request: JoinRequest) -> Admission {
+        Admission::Accept
+    }
+
+    /// Called with the chat message exactly as received, before `chat_message`
+    fn raw_chat_message(&mut self, client: &mut Client, player_id: i32, message: &str) {}
+
+    /// Called when the host says who the impostors are, by player id, as the game starts. They're
+    /// also marked in `PlayerData::is_imposter`. Not called when we're host
+    fn impostors_assigned(&mut self, client: &mut Client, impostors: Vec<u8>) {}
+
+    /// Called when the host starts the game, before the map has loaded
+    fn game_started(&mut self, client: &mut Client) {}
+
+    fn game_ended(&mut self, client: &mut Client, reason: GameOverReason) {}
+
+    /// Called when a player's position changes, by client id. Only positions sent as data
+    /// updates, which is how the game sends movement
+    fn player_moved(&mut self, client: &mut Client, client_id: i32, position: Vector2) {}
+
+    /// Called when a player is killed. `killer_id` is the impostor's client id
+    fn player_died(&mut self, client: &mut Client, player_id: u8, killer_id: i32) {}
+
+    /// Called when a player completes a task. `task_index` is into their `PlayerData::tasks`,
+    /// which is already marked complete
+    fn task_updated(&mut self, client: &mut Client, client_id: i32, task_index: u32) {}
+
+    /// Called when a system is sabotaged, i.e. reactor, life support, electrical or comms.
+    /// Not called again until it's fixed and sabotaged again
+    fn sabotage_triggered(&mut self, client: &mut Client, system: SystemType) {}
+
+    /// Called when the doors of `room` open or close
+    fn door_state_changed(&mut self, client: &mut Client, room: SystemType, open: bool) {}
+
+    /// Called when a meeting starts. `caller_id` is the client who called it and
+    /// `reported_player_id` the body they found, or `None` for the emergency button
+    fn meeting_started(
+        &mut self,
+        client: &mut Client,
+        caller_id: i32,
+        reported_player_id: Option<u8>,
+    ) {
+    }
+
+    /// Called when a meeting's votes are in. `exiled_player_id` is `None` if no one was voted
+    /// out. The votes are in the `MeetingHud`
+    fn voting_complete(&mut self, client: &mut Client, exiled_player_id: Option<u8>, tie: bool) {}
+
+    /// Called for each game message skipped by `ClientSettings::lenient_parsing`
+    fn parse_anomaly(&mut self, client: &mut Client, anomaly: ParseAnomaly) {}
+
+    /// Called for each net object in a spawned prefab, once they've all been added
+    fn object_spawned(&mut self, client: &mut Client, object: ObjectRef) {}
+
+    /// Called after a net object is removed
+    fn object_destroyed(&mut self, client: &mut Client, net_id: u32, kind: ObjectKind) {}
+
+    /// Called when the client stops because something broke inside it, like a background thread
+    /// panicking. The client disconnects after this
+    fn client_error(&mut self, client: &mut Client, error: ClientError) {}
+}
+
+/// A net object by id, with enough to find it in `NetObjectHandler`. See
+/// `EventHandler::object_spawned`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ObjectRef {
+    pub net_id: u32,
+    pub owner_id: i32,
+    pub kind: ObjectKind,
+}
+
+impl ObjectRef {
+    fn of<T: NetObject>(object: &T, kind: ObjectKind) -> Self {
+        Self {
+            net_id: object.net_id(),
+            owner_id: object.owner_id(),
+            kind,
+        }
+    }
 }
 
 pub struct NetObjectHandler {
@@ -857,6 +3642,19 @@ pub struct NetObjectHandler {
     pub lobbies: Vec<Lobby>,
     pub game_datas: Vec<GameData>,
     pub vote_bans: Vec<VoteBanSystem>,
+    pub meeting_huds: Vec<MeetingHud>,
+    /// The last few unknown RPCs each object got, by net id
+    unknown_rpcs: HashMap<u32, VecDeque<UnknownRpc>>,
+}
+
+/// An RPC with a call id the object didn't know, kept as received. See
+/// `NetObjectHandler::unknown_rpcs`
+#[derive(Debug, Clone)]
+pub struct UnknownRpc {
+    pub kind: ObjectKind,
+    pub call_id: u8,
+    pub data: Vec<u8>,
+    pub received: Instant,
 }
 
 impl Default for NetObjectHandler {
@@ -869,6 +3667,8 @@ impl Default for NetObjectHandler {
             lobbies: Vec::new(),
             game_datas: Vec::new(),
             vote_bans: Vec::new(),
+            meeting_huds: Vec::new(),
+            unknown_rpcs: HashMap::new(),
         }
     }
 }
@@ -878,6 +3678,44 @@ impl NetObjectHandler {
         Self::default()
     }
 
+    /// Everything spawned, as the prefabs they were spawned from. What the host sends to someone
+    /// joining
+    pub fn prefabs(&self) -> Vec<Prefab> {
+        let mut prefabs = self
+            .lobbies
+            .iter()
+            .cloned()
+            .map(Prefab::Lobby)
+            .collect::<Vec<_>>();
+        let game_datas = self.game_datas.iter().cloned();
+        prefabs.extend(
+            game_datas
+                .zip(self.vote_bans.iter().cloned())
+                .map(|(game_data, vote_ban)| Prefab::GameData(game_data, vote_ban)),
+        );
+        for control in self.player_controls.iter() {
+            let owner_id = control.owner_id();
+            let physics = self
+                .player_physics
+                .iter()
+                .find(|o| o.owner_id() == owner_id);
+            let transform = self
+                .player_transforms
+                .iter()
+                .find(|o| o.owner_id() == owner_id);
+            if let (Some(physics), Some(transform)) = (physics, transform) {
+                prefabs.push(Prefab::Player(
+                    control.clone(),
+                    physics.clone(),
+                    transform.clone(),
+                ));
+            }
+        }
+        prefabs.extend(self.worlds.iter().cloned().map(Prefab::World));
+        prefabs.extend(self.meeting_huds.iter().cloned().map(Prefab::MeetingHud));
+        prefabs
+    }
+
     pub fn get_player_control(&mut self, owner_id: i32) -> Option<&mut PlayerControl> {
         self.player_controls
             .iter_mut()
@@ -896,25 +3734,101 @@ impl NetObjectHandler {
             .find(|obj| obj.owner_id() == owner_id)
     }
 
-    pub fn add(&mut self, prefab: Prefab) {
+    /// Adds a spawned prefab's objects, returning references to them
+    pub fn add(&mut self, prefab: Prefab) -> Vec<ObjectRef> {
         match prefab {
             Prefab::Player(control, physics, transform) => {
+                let refs = vec![
+                    ObjectRef::of(&control, ObjectKind::PlayerControl),
+                    ObjectRef::of(&physics, ObjectKind::PlayerPhysics),
+                    ObjectRef::of(&transform, ObjectKind::PlayerTransform),
+                ];
                 self.player_controls.push(control);
                 self.player_physics.push(physics);
                 self.player_transforms.push(transform);
+                refs
+            }
+            Prefab::World(world) => {
+                let refs = vec![ObjectRef::of(&world, ObjectKind::World)];
+                self.worlds.push(world);
+                refs
+            }
+            Prefab::Lobby(lobby) => {
+                let refs = vec![ObjectRef::of(&lobby, ObjectKind::Lobby)];
+                self.lobbies.push(lobby);
+                refs
             }
-            Prefab::World(world) => self.worlds.push(world),
-            Prefab::Lobby(lobby) => self.lobbies.push(lobby),
             Prefab::GameData(game_data, vote_ban) => {
+                let refs = vec![
+                    ObjectRef::of(&game_data, ObjectKind::GameData),
+                    ObjectRef::of(&vote_ban, ObjectKind::VoteBanSystem),
+                ];
                 self.game_datas.push(game_data);
                 self.vote_bans.push(vote_ban);
+                refs
             }
-            Prefab::Unknown => warn!("Tried to add unknown prefab to handler"),
+            Prefab::MeetingHud(meeting_hud) => {
+                let refs = vec![ObjectRef::of(&meeting_hud, ObjectKind::MeetingHud)];
+                self.meeting_huds.push(meeting_hud);
+                refs
+            }
+            Prefab::Unknown => {
+                warn!("Tried to add unknown prefab to handler");
+                Vec::new()
+            }
+        }
+    }
+
+    /// The type of the object with the given net id, if there is one
+    pub fn kind(&self, net_id: u32) -> Option<ObjectKind> {
+        fn has<T: NetObject>(objects: &[T], net_id: u32) -> bool {
+            objects.iter().any(|obj| obj.net_id() == net_id)
+        }
+        if has(&self.player_controls, net_id) {
+            Some(ObjectKind::PlayerControl)
+        } else if has(&self.player_physics, net_id) {
+            Some(ObjectKind::PlayerPhysics)
+        } else if has(&self.player_transforms, net_id) {
+            Some(ObjectKind::PlayerTransform)
+        } else if has(&self.worlds, net_id) {
+            Some(ObjectKind::World)
+        } else if has(&self.lobbies, net_id) {
+            Some(ObjectKind::Lobby)
+        } else if has(&self.game_datas, net_id) {
+            Some(ObjectKind::GameData)
+        } else if has(&self.vote_bans, net_id) {
+            Some(ObjectKind::VoteBanSystem)
+        } else if has(&self.meeting_huds, net_id) {
+            Some(ObjectKind::MeetingHud)
+        } else {
+            None
+        }
+    }
+
+    /// Unknown RPCs sent to an object, oldest first. Up to the last 32 are kept, until the object
+    /// is removed
+    pub fn unknown_rpcs(&self, net_id: u32) -> impl Iterator<Item = &UnknownRpc> {
+        self.unknown_rpcs.get(&net_id).into_iter().flatten()
+    }
+
+    /// Every object's unknown RPCs, by net id
+    pub fn all_unknown_rpcs(&self) -> impl Iterator<Item = (u32, &UnknownRpc)> {
+        self.unknown_rpcs
+            .iter()
+            .flat_map(|(&net_id, rpcs)| rpcs.iter().map(move |rpc| (net_id, rpc)))
+    }
+
+    fn record_unknown_rpc(&mut self, net_id: u32, rpc: UnknownRpc) {
+        let rpcs = self.unknown_rpcs.entry(net_id).or_default();
+        if rpcs.len() == UNKNOWN_RPC_LIMIT {
+            rpcs.pop_front();
         }
+        rpcs.push_back(rpc);
     }
 
     /// Remove an object, returning true if object exists
     pub fn remove(&mut self, net_id: u32) -> bool {
+        self.unknown_rpcs.remove(&net_id);
         if let Some(index) = self
             .player_controls
             .iter()
@@ -959,9 +3873,33 @@ impl NetObjectHandler {
             self.vote_bans.remove(index);
             return true;
         }
+        if let Some(index) = self
+            .meeting_huds
+            .iter()
+            .position(|obj| obj.net_id() == net_id)
+        {
+            self.meeting_huds.remove(index);
+            return true;
+        }
         false
     }
 
+    /// A net id no object is using yet, for the host to spawn objects with
+    pub fn next_net_id(&self) -> u32 {
+        let net_ids = self
+            .player_controls
+            .iter()
+            .map(NetObject::net_id)
+            .chain(self.player_physics.iter().map(NetObject::net_id))
+            .chain(self.player_transforms.iter().map(NetObject::net_id))
+            .chain(self.worlds.iter().map(NetObject::net_id))
+            .chain(self.lobbies.iter().map(NetObject::net_id))
+            .chain(self.game_datas.iter().map(NetObject::net_id))
+            .chain(self.vote_bans.iter().map(NetObject::net_id))
+            .chain(self.meeting_huds.iter().map(NetObject::net_id));
+        net_ids.max().map_or(1, |net_id| net_id + 1)
+    }
+
     pub fn get(&mut self, net_id: u32) -> Option<&mut dyn NetObject> {
         if let Some(obj) = self
             .player_controls
@@ -1000,20 +3938,13 @@ impl NetObjectHandler {
         if let Some(obj) = self.vote_bans.iter_mut().find(|obj| obj.net_id() == net_id) {
             return Some(obj);
         }
+        if let Some(obj) = self
+            .meeting_huds
+            .iter_mut()
+            .find(|obj| obj.net_id() == net_id)
+        {
+            return Some(obj);
+        }
         None
     }
 }
-
-#[derive(Debug)]
-struct HelloData {
-    version: u32,
-    username: String,
-}
-
-impl Serialize for HelloData {
-    fn serialize(&self, w: &mut PacketWriter) {
-        w.write_u8(0); // Reserved byte?
-        w.write_u32(self.version);
-        w.write_string(&self.username);
-    }
-}
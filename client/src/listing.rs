@@ -0,0 +1,116 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+};
+
+use common::data::{Address, GameListing, ListingFlags};
+
+/// Flags suspicious game listings across a whole scan
+///
+/// On top of `GameListing::flags` this remembers which address each code was seen on, to catch
+/// codes listed more than once, and checks host names against a blacklist
+#[derive(Debug, Default)]
+pub struct ListingChecker {
+    /// Lower-case host name fragments. A host name containing any of these is blacklisted
+    blacklist: Vec<String>,
+    addresses: HashMap<i32, Address>,
+}
+
+impl ListingChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flags any listing whose host name contains `pattern`, ignoring case
+    pub fn blacklist(&mut self, pattern: &str) {
+        self.blacklist.push(pattern.to_lowercase());
+    }
+
+    pub fn check(&mut self, listing: &GameListing) -> ListingFlags {
+        let mut flags = listing.flags();
+
+        let address = self
+            .addresses
+            .entry(listing.id.id)
+            .or_insert_with(|| listing.address.clone());
+        if *address != listing.address {
+            flags |= ListingFlags::DUPLICATE_CODE;
+        }
+
+        let host_username = listing.host_username.to_lowercase();
+        if self
+            .blacklist
+            .iter()
+            .any(|pattern| host_username.contains(pattern))
+        {
+            flags |= ListingFlags::BLACKLISTED_HOST;
+        }
+
+        flags
+    }
+}
+
+/// How far a scan has got. See `Client::server_scan_progress`
+///
+/// The server hands out a few random games per request, so there's no way to know when every game
+/// has been seen. Once several responses in a row bring nothing new it probably has
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+    /// Requests sent that haven't been answered yet
+    pub in_flight: u32,
+    pub requests_sent: u32,
+    pub responses: u32,
+    /// Listings in every response so far, repeats included
+    pub listings_received: usize,
+    /// Responses in a row that had no games not already seen
+    pub stale_responses: u32,
+    seen: HashSet<i32>,
+    nodes: HashSet<SocketAddr>,
+}
+
+impl ScanProgress {
+    pub(crate) fn request_sent(&mut self) {
+        self.in_flight += 1;
+        self.requests_sent += 1;
+    }
+
+    pub(crate) fn response(&mut self, games: &[GameListing]) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        self.responses += 1;
+        self.listings_received += games.len();
+        let mut any_new = false;
+        for game in games {
+            any_new |= self.seen.insert(game.id.id);
+            self.nodes.insert(game.address.to_sock_add());
+        }
+        if any_new {
+            self.stale_responses = 0;
+        } else {
+            self.stale_responses += 1;
+        }
+    }
+
+    /// How many different games have been seen
+    pub fn unique_games(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// The average number of listings per response
+    pub fn listings_per_response(&self) -> f32 {
+        if self.responses == 0 {
+            0.0
+        } else {
+            self.listings_received as f32 / self.responses as f32
+        }
+    }
+
+    /// The game servers games were listed on, for `ClientSettings::warm_addresses`
+    pub fn node_addresses(&self) -> Vec<SocketAddr> {
+        self.nodes.iter().copied().collect()
+    }
+
+    /// Whether the last `stale_limit` responses brought no new games
+    pub fn is_exhausted(&self, stale_limit: u32) -> bool {
+        self.stale_responses >= stale_limit
+    }
+}
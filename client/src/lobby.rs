@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use common::data::{GameId, GameListing, GameOptions};
+
+/// What's known about the lobby currently joined. See `Client::lobby`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LobbyInfo {
+    pub game_id: GameId,
+    pub host_id: i32,
+    pub is_public: bool,
+    /// Including us
+    pub player_count: usize,
+    /// From the host's game options, or the listing until they arrive
+    pub max_players: Option<u8>,
+    pub map_id: Option<u8>,
+    pub num_imposters: Option<i8>,
+    /// How old the lobby was when joined. Only known when joined from a listing
+    age_at_join: Option<Duration>,
+    joined_at: Instant,
+}
+
+impl LobbyInfo {
+    pub(crate) fn new(
+        game_id: GameId,
+        host_id: i32,
+        listing: Option<&GameListing>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            game_id,
+            host_id,
+            // Only public games are listed
+            is_public: listing.is_some(),
+            player_count: 1,
+            max_players: listing.map(|listing| listing.max_players),
+            map_id: listing.map(|listing| listing.map_id.bits()),
+            num_imposters: listing.map(|listing| listing.num_imposters as i8),
+            age_at_join: listing.map(|listing| Duration::from_secs(listing.age as u64)),
+            joined_at: now,
+        }
+    }
+
+    pub(crate) fn apply_options(&mut self, options: &GameOptions) {
+        self.max_players = Some(options.max_players);
+        self.map_id = Some(options.map_id);
+        self.num_imposters = Some(options.num_imposters);
+    }
+
+    /// How long the lobby has existed, if joined from a listing. `now` is usually `Client::now`
+    pub fn age(&self, now: Instant) -> Option<Duration> {
+        self.age_at_join
+            .map(|age| age + now.duration_since(self.joined_at))
+    }
+
+    /// How long since the lobby was joined. `now` is usually `Client::now`
+    pub fn time_joined(&self, now: Instant) -> Duration {
+        now.duration_since(self.joined_at)
+    }
+}
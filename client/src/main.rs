@@ -1,14 +1,29 @@
 use std::{
+    io,
+    net::SocketAddr,
+    path::Path,
     thread::JoinHandle,
     time::{Duration, Instant},
 };
 
-use client::{Client, ClientSettings, EventHandler, MainServer, ScanSettings};
-use common::data::{DisconnectReason, GameListing};
+use client::{
+    fit_name, Client, ClientEvent, ClientSettings, Config, Coordinator, CoordinatorSettings,
+    EventHandler, Identity, JsonlStatsSink, ListingChecker, MainServer, ModAction, Moderator,
+    NameGenerator, ScanProgress, ScanSettings, ServerTarget, Swarm, SwarmEvent, TemplateNames,
+    WordNames, KNOWN_VERSIONS,
+};
+use common::data::{DisconnectReason, GameListing, GameVersion};
 
+use clap::{CommandFactory, Parser};
 use rand::{prelude::SmallRng, Rng, SeedableRng};
 
+use crate::cli::{Cli, Command};
+
+mod cli;
 mod gui;
+mod mirror;
+mod notify;
+mod tui;
 
 fn main() {
     // Init logging
@@ -17,40 +32,87 @@ fn main() {
         .unwrap();
 
     // Read command
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        gui();
-        return;
-    }
-
-    let mode = args.get(1).unwrap();
+    let mut cli = Cli::parse();
+    let servers = cli.regions();
+    let server = servers[0].clone();
+    let command = match cli.command.take() {
+        Some(command) => command,
+        None => {
+            match cli.config() {
+                Ok(config) => gui(config),
+                Err(error) => println!("Couldn't read config: {}", error),
+            }
+            return;
+        }
+    };
 
-    match mode.to_ascii_lowercase().as_str() {
-        "scan" => scan(),
-        "dummy" => dummy(args),
-        "wizard" => wizard(args),
-        "annoy" => annoy(args),
-        other => println!("Unknown command {}", other),
+    match command {
+        Command::Scan {
+            blacklist,
+            stop_after,
+        } => scan(server, &blacklist, stop_after),
+        Command::Tui => tui::run(server),
+        Command::Dummy {
+            game_code,
+            count,
+            name_template,
+        } => {
+            let names: Box<dyn NameGenerator> = match name_template {
+                Some(template) => Box::new(TemplateNames::new(&template)),
+                None => Box::new(WordNames::with_default_words(SmallRng::from_entropy())),
+            };
+            dummy(&servers, &game_code, count, names)
+        }
+        Command::Wizard { game_code } => wizard(server, &game_code, cli.identity()),
+        Command::Annoy { game_code } => annoy(server, &game_code, cli.identity()),
+        Command::Observe {
+            stats_file,
+            game_codes,
+        } => observe(server, &stats_file, &game_codes),
+        Command::Moderate {
+            game_code,
+            rules_file,
+        } => moderate(server, &game_code, &rules_file, cli.identity()),
+        Command::Notify(watches) => notify::run(server, watches),
+        Command::Regions { addresses } => regions(&addresses),
+        Command::Mirror { bind_address } => mirror::run(server, bind_address),
+        Command::ProbeVersion {
+            from,
+            to,
+            revisions,
+            bisect,
+        } => probe_version(server, from, to, revisions, bisect),
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "client", &mut io::stdout())
+        }
     }
 }
 
-fn gui() {
-    gui::run();
+fn gui(config: Config) {
+    gui::run(config);
 }
 
-fn scan() {
+fn scan(server: ServerTarget, blacklist: &[String], stop_after: Option<u32>) {
     let mut total = 0;
     let started = Instant::now();
 
+    let mut checker = ListingChecker::new();
+    for pattern in blacklist {
+        checker.blacklist(pattern);
+    }
+
     let settings = ScanSettings {
+        server,
         connect_username: "bobby".to_string(),
+        stop_when_exhausted: stop_after,
         ..ScanSettings::default()
     };
 
-    let callback = |listings: Vec<GameListing>| {
+    let callback = |listings: Vec<GameListing>, progress: &ScanProgress| {
         for listing in listings.iter() {
+            let flags = checker.check(listing);
             println!(
-                "{:<6} {:<21} {:>2}/{:<2} {:<8} {:<2} {:<6} {}",
+                "{:<6} {:<21} {:>2}/{:<2} {:<8} {:<2} {:<6} {}{}",
                 listing.id,
                 listing.address,
                 listing.player_count,
@@ -59,24 +121,32 @@ fn scan() {
                 listing.num_imposters,
                 listing.age,
                 listing.host_username,
+                if flags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {:?}", flags)
+                },
             );
         }
         total += listings.len();
-        eprint!("{} games found. {:?} elapsed\r", total, started.elapsed());
+        eprint!(
+            "{} games found, {} unique. {} requests in flight, {:.1} games per response. {:?} elapsed\r",
+            total,
+            progress.unique_games(),
+            progress.in_flight,
+            progress.listings_per_response(),
+            started.elapsed()
+        );
 
         true
     };
 
-    Client::server_scan(settings, callback);
-}
-
-fn wizard(args: Vec<String>) {
-    if args.len() < 3 {
-        println!("Usage: ./client wizard <game_code>");
-        return;
+    if let Err(error) = Client::server_scan_progress(settings, callback) {
+        log::error!("Scan failed: {}", error);
     }
+}
 
-    let game_code: String = args.get(2).unwrap().parse().unwrap();
+fn wizard(server: ServerTarget, game_code: &str, identity: Option<Identity>) {
     let handler = WizardHandler {
         last_change: Instant::now(),
         rng: rand::rngs::SmallRng::seed_from_u64(1337),
@@ -86,9 +156,12 @@ fn wizard(args: Vec<String>) {
         connect_username: "HackerMan".to_string(),
         game_username: "HackerMan".to_string(),
         initial_hat: 12,
+        identity,
         ..ClientSettings::default()
     };
-    Client::run_game_code(handler, MainServer::Europe, &game_code, settings);
+    if let Err(error) = Client::run_game_code(handler, server, game_code, settings) {
+        log::error!("{}", error);
+    }
 }
 
 #[derive(Debug)]
@@ -141,13 +214,7 @@ impl EventHandler for WizardHandler {
     }
 }
 
-fn annoy(args: Vec<String>) {
-    if args.len() < 3 {
-        println!("Usage: ./client annoy <game_code>");
-        return;
-    }
-
-    let game_code: String = args.get(2).unwrap().parse().unwrap();
+fn annoy(server: ServerTarget, game_code: &str, identity: Option<Identity>) {
     let handler = AnnoyHandler {
         has_joined: false,
         last_change: Instant::now(),
@@ -157,9 +224,12 @@ fn annoy(args: Vec<String>) {
         game_username: "zero cool".to_string(),
         send_initial_info: false,
         // game_scene: "Tutorial".to_string(),
+        identity,
         ..ClientSettings::default()
     };
-    Client::run_game_code(handler, MainServer::Europe, &game_code, settings);
+    if let Err(error) = Client::run_game_code(handler, server, game_code, settings) {
+        log::error!("{}", error);
+    }
 }
 
 #[derive(Debug)]
@@ -216,44 +286,211 @@ t3h PeNgU1N oF d00m");
     }
 }
 
-fn dummy(args: Vec<String>) {
-    if args.len() < 3 {
-        println!("Usage: ./client dummy <game_code> [dummy_count]");
-        return;
+fn observe(server: ServerTarget, stats_file: &Path, game_codes: &[String]) {
+    let sink = JsonlStatsSink::open(stats_file).unwrap();
+    let settings = CoordinatorSettings {
+        server,
+        requeue: true,
+        ..CoordinatorSettings::default()
+    };
+    let coordinator = Coordinator::new(settings, sink);
+    for code in game_codes {
+        coordinator.add_code(code);
     }
+    coordinator.run();
+}
 
-    let game_code: String = args.get(2).unwrap().parse().unwrap();
-    let dummy_count = if args.len() < 4 {
-        1
-    } else {
-        args.get(3).unwrap().parse().unwrap()
-    };
-    let handles: Vec<JoinHandle<()>> = (1..=dummy_count)
-        .map(|i| {
-            let game_code = game_code.clone();
-            std::thread::spawn(move || {
-                let handler = DummyHandler {};
-                let settings = ClientSettings {
-                    game_username: format!("Dummy {}", i),
-                    initial_color: 5,
-                    initial_hat: 11,
-                    ..ClientSettings::default()
-                };
-                Client::run_game_code(handler, MainServer::Europe, &game_code, settings);
-            })
+fn regions(addresses: &[SocketAddr]) {
+    let mut targets = MainServer::ALL
+        .iter()
+        .map(|server| (format!("{:?}", server), server.to_addr()))
+        .collect::<Vec<_>>();
+    for addr in addresses {
+        targets.push(("Custom".to_string(), *addr));
+    }
+
+    // Measure all at once so unreachable servers don't hold up the rest
+    let handles: Vec<JoinHandle<_>> = targets
+        .into_iter()
+        .map(|(name, addr)| {
+            std::thread::Builder::new()
+                .name(format!("ping-{}", name))
+                .spawn(move || {
+                    let result = Client::measure_latency(addr, "ping", Duration::from_secs(3));
+                    (name, addr, result)
+                })
+                .unwrap()
         })
         .collect();
-    for handle in handles {
-        handle.join().unwrap();
+    let mut results = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    results.sort_by_key(|(_, _, result)| match result {
+        Ok(rtt) => *rtt,
+        Err(_) => Duration::MAX,
+    });
+
+    for (name, addr, result) in results {
+        match result {
+            Ok(rtt) => println!("{:<12} {:<21} {:>5}ms", name, addr, rtt.as_millis()),
+            Err(error) => println!("{:<12} {:<21} unreachable ({})", name, addr, error),
+        }
+    }
+}
+
+fn probe_version(
+    server: ServerTarget,
+    from: Option<GameVersion>,
+    to: Option<GameVersion>,
+    revisions: u32,
+    bisect: bool,
+) {
+    let from = from.unwrap_or_else(|| {
+        let newest = *KNOWN_VERSIONS.iter().max().unwrap();
+        GameVersion {
+            revision: 0,
+            ..newest
+        }
+    });
+    let to = to.unwrap_or(GameVersion {
+        year: from.year + 1,
+        ..from
+    });
+
+    // Every day in between, oldest first. Days past the end of a month are harmless extras
+    let mut candidates = Vec::new();
+    let mut date = from;
+    while date <= to {
+        for revision in 0..revisions {
+            candidates.push(GameVersion { revision, ..date });
+        }
+        date = date.next_day();
+    }
+    println!("Trying {} versions", candidates.len());
+
+    let addr = match server.resolve() {
+        Ok(addr) => addr,
+        Err(error) => {
+            println!("Couldn't look up {}: {}", server, error);
+            return;
+        }
+    };
+    let probe = |version: GameVersion| {
+        let result = Client::probe_version(addr, version, Duration::from_secs(3));
+        match &result {
+            Ok(true) => println!("{} ({}) accepted", version, version.to_u32()),
+            Ok(false) => println!("{} ({}) rejected", version, version.to_u32()),
+            Err(error) => println!("{} ({}) failed: {}", version, version.to_u32(), error),
+        }
+        result
+    };
+
+    let accepted = if bisect {
+        // Oldest accepted, assuming everything newer is accepted too
+        let (mut low, mut high) = (0, candidates.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            match probe(candidates[middle]) {
+                Ok(true) => high = middle,
+                Ok(false) => low = middle + 1,
+                Err(_) => return,
+            }
+        }
+        candidates.get(low).copied()
+    } else {
+        candidates
+            .iter()
+            .rev()
+            .copied()
+            .find(|&version| matches!(probe(version), Ok(true)))
+    };
+
+    match accepted {
+        Some(version) => println!("Use {} ({})", version, version.to_u32()),
+        None => println!("No version accepted"),
+    }
+}
+
+/// Dummies take turns between `servers`
+fn dummy(
+    servers: &[ServerTarget],
+    game_code: &str,
+    dummy_count: u32,
+    mut names: Box<dyn NameGenerator>,
+) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut swarm = Swarm::new();
+        for i in 0..dummy_count as usize {
+            let settings = ClientSettings {
+                game_username: fit_name(&names.generate(i)),
+                initial_color: 5,
+                initial_hat: 11,
+                ..ClientSettings::default()
+            };
+            let server = servers[i % servers.len()].clone();
+            if let Err(error) = swarm.join(server, game_code, settings).await {
+                println!("Dummy {} couldn't start: {}", i, error);
+            }
+        }
+        while let Some(SwarmEvent { member, event }) = swarm.recv().await {
+            if let ClientEvent::Disconnected(reason) = event {
+                println!("Dummy {} disconnected: {:?}", member, reason);
+                // Otherwise the server hanging up makes it rejoin
+                if let Some(client) = swarm.member(member) {
+                    let _ = client.disconnect().await;
+                }
+            }
+        }
+    });
+}
+
+fn moderate(server: ServerTarget, game_code: &str, rules_file: &Path, identity: Option<Identity>) {
+    let moderator = match Moderator::open(rules_file) {
+        Ok(moderator) => moderator,
+        Err(error) => {
+            println!("Couldn't read rules: {}", error);
+            return;
+        }
+    };
+    let settings = ClientSettings {
+        connect_username: "moderator".to_string(),
+        game_username: "moderator".to_string(),
+        moderator: Some(moderator),
+        identity,
+        ..ClientSettings::default()
+    };
+    if let Err(error) = Client::run_game_code(ModerateHandler {}, server, game_code, settings) {
+        log::error!("{}", error);
     }
 }
 
 #[derive(Debug)]
-struct DummyHandler {}
+struct ModerateHandler {}
 
-impl EventHandler for DummyHandler {
+impl EventHandler for ModerateHandler {
     fn disconnect_reason(&mut self, client: &mut Client, reason: DisconnectReason) {
         println!("Disconnected: {:?}", reason);
         client.disconnect();
     }
+
+    fn chat_flagged(
+        &mut self,
+        client: &mut Client,
+        client_id: i32,
+        message: &str,
+        actions: &[ModAction],
+    ) {
+        let name = client.player_name(client_id);
+        if actions.contains(&ModAction::Report) {
+            println!("Reported {} (client {}): {}", name, client_id, message);
+        }
+        if actions.contains(&ModAction::Notify) {
+            notify::show("Chat flagged", &format!("{}: {}", name, message));
+        }
+    }
 }
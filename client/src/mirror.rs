@@ -0,0 +1,211 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use client::{Client, ScanSettings, ServerTarget};
+use common::data::GameListing;
+use serde::Serialize;
+
+/// How long a game stays in the list after it was last seen in a scan
+const LISTING_TTL: Duration = Duration::from_secs(30);
+
+/// How long to wait between game list requests
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a client gets to send its request or take the response before it's dropped
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+struct ListingJson {
+    id: String,
+    address: String,
+    host_username: String,
+    player_count: u8,
+    max_players: u8,
+    map: String,
+    num_imposters: u8,
+    age: u32,
+}
+
+impl From<&GameListing> for ListingJson {
+    fn from(listing: &GameListing) -> Self {
+        Self {
+            id: listing.id.to_string(),
+            address: listing.address.to_string(),
+            host_username: listing.host_username.clone(),
+            player_count: listing.player_count,
+            max_players: listing.max_players,
+            map: format!("{:?}", listing.map_id),
+            num_imposters: listing.num_imposters,
+            age: listing.age,
+        }
+    }
+}
+
+/// The current lobby list, kept pre-rendered so requests don't have to serialize anything
+#[derive(Default)]
+struct Mirror {
+    listings: Vec<(Instant, GameListing)>,
+    body: String,
+    etag: String,
+}
+
+impl Mirror {
+    fn update(&mut self, results: Vec<GameListing>) {
+        let now = Instant::now();
+        for listing in results {
+            match self
+                .listings
+                .iter_mut()
+                .find(|(_, other)| other.id == listing.id)
+            {
+                Some(existing) => *existing = (now, listing),
+                None => self.listings.push((now, listing)),
+            }
+        }
+        self.listings
+            .retain(|(last_seen, _)| last_seen.elapsed() < LISTING_TTL);
+
+        let json = self
+            .listings
+            .iter()
+            .map(|(_, listing)| ListingJson::from(listing))
+            .collect::<Vec<_>>();
+        let body = serde_json::to_string(&json).unwrap();
+        if body != self.body {
+            let mut hasher = DefaultHasher::new();
+            body.hash(&mut hasher);
+            self.etag = format!("\"{:016x}\"", hasher.finish());
+            self.body = body;
+        }
+    }
+}
+
+pub fn run(server: ServerTarget, bind_address: SocketAddr) {
+    let listener = match TcpListener::bind(bind_address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("Couldn't bind to {}: {}", bind_address, error);
+            return;
+        }
+    };
+    println!("Serving lobby list on http://{}/games", bind_address);
+
+    let mirror = Arc::new(RwLock::new(Mirror::default()));
+    mirror.write().unwrap().update(Vec::new());
+
+    // Game scanning
+    let _scan_thread = {
+        let mirror = mirror.clone();
+        std::thread::Builder::new()
+            .name("mirror-scan".to_string())
+            .spawn(move || {
+                let settings = ScanSettings {
+                    server,
+                    connect_username: "mirror".to_string(),
+                    max_requests: 1,
+                    cache_size: 1,
+                    ..ScanSettings::default()
+                };
+                let callback = |listings: Vec<GameListing>| {
+                    mirror.write().unwrap().update(listings);
+                    std::thread::sleep(REFRESH_INTERVAL);
+                    true
+                };
+                if let Err(error) = Client::server_scan(settings, callback) {
+                    log::error!("Scan failed: {}", error);
+                }
+            })
+            .unwrap()
+    };
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let mirror = mirror.clone();
+        std::thread::Builder::new()
+            .name("mirror-http".to_string())
+            .spawn(move || {
+                if let Err(error) = handle_connection(stream, &mirror) {
+                    eprintln!("HTTP error: {}", error);
+                }
+            })
+            .unwrap();
+    }
+}
+
+/// Serves a single request then closes the connection
+fn handle_connection(mut stream: TcpStream, mirror: &RwLock<Mirror>) -> io::Result<()> {
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    // Only If-None-Match matters
+    let mut if_none_match = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("if-none-match") {
+                if_none_match = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    if method != "GET" {
+        return write_response(&mut stream, "405 Method Not Allowed", &[], "");
+    }
+    if path != "/games" {
+        return write_response(&mut stream, "404 Not Found", &[], "");
+    }
+
+    // Copied out so a slow client doesn't hold up the scan thread's updates
+    let (body, etag) = {
+        let mirror = mirror.read().unwrap();
+        (mirror.body.clone(), mirror.etag.clone())
+    };
+    let headers = [
+        ("Content-Type", "application/json"),
+        ("ETag", etag.as_str()),
+        ("Cache-Control", "no-cache"),
+        ("Access-Control-Allow-Origin", "*"),
+    ];
+    if if_none_match.as_deref() == Some(etag.as_str()) {
+        return write_response(&mut stream, "304 Not Modified", &headers, "");
+    }
+    write_response(&mut stream, "200 OK", &headers, &body)
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {}\r\n", status)?;
+    for (name, value) in headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
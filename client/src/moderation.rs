@@ -0,0 +1,136 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, ErrorKind},
+    path::Path,
+};
+
+use regex::Regex;
+
+/// What to do when a chat message matches a `ModerationRule`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModAction {
+    /// Log the message
+    Log,
+    /// Left to `EventHandler::chat_flagged`, e.g. for a desktop notification
+    Notify,
+    /// Left to `EventHandler::chat_flagged`, e.g. to keep a record for the server operator
+    Report,
+    /// Kick the sender. Host only
+    Kick,
+    /// Ban the sender. Host only
+    Ban,
+}
+
+impl ModAction {
+    fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "log" => ModAction::Log,
+            "notify" => ModAction::Notify,
+            "report" => ModAction::Report,
+            "kick" => ModAction::Kick,
+            "ban" => ModAction::Ban,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Lower-case. Matches messages containing it, ignoring case
+    Keyword(String),
+    Regex(Regex),
+}
+
+/// A pattern to look for in chat and what to do when it's found
+#[derive(Debug, Clone)]
+pub struct ModerationRule {
+    matcher: Matcher,
+    pub actions: Vec<ModAction>,
+}
+
+impl ModerationRule {
+    /// Matches messages containing `keyword`, ignoring case
+    pub fn keyword(keyword: &str, actions: Vec<ModAction>) -> Self {
+        Self {
+            matcher: Matcher::Keyword(keyword.to_lowercase()),
+            actions,
+        }
+    }
+
+    pub fn regex(regex: Regex, actions: Vec<ModAction>) -> Self {
+        Self {
+            matcher: Matcher::Regex(regex),
+            actions,
+        }
+    }
+
+    pub fn is_match(&self, message: &str) -> bool {
+        match &self.matcher {
+            Matcher::Keyword(keyword) => message.to_lowercase().contains(keyword),
+            Matcher::Regex(regex) => regex.is_match(message),
+        }
+    }
+}
+
+/// Checks incoming chat against a set of rules. See `ClientSettings::moderator`
+///
+/// The rules file has one rule per line: comma separated actions then the pattern, e.g.
+/// `kick,notify free skins` or `log /discord\.gg/`. Patterns between slashes are regexes, anything
+/// else is a keyword. Actions are `log`, `notify`, `report`, `kick` and `ban`
+#[derive(Debug, Clone, Default)]
+pub struct Moderator {
+    pub rules: Vec<ModerationRule>,
+}
+
+impl Moderator {
+    pub fn new(rules: Vec<ModerationRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut rules = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} in rule \"{}\"", reason, line),
+                )
+            };
+            let (actions, pattern) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid("Missing pattern"))?;
+            let actions = actions
+                .split(',')
+                .map(|action| ModAction::parse(action).ok_or_else(|| invalid("Unknown action")))
+                .collect::<io::Result<Vec<_>>>()?;
+            let pattern = pattern.trim();
+            let rule = if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+                let regex = Regex::new(&pattern[1..pattern.len() - 1])
+                    .map_err(|error| invalid(&error.to_string()))?;
+                ModerationRule::regex(regex, actions)
+            } else {
+                ModerationRule::keyword(pattern, actions)
+            };
+            rules.push(rule);
+        }
+        Ok(Self { rules })
+    }
+
+    /// The actions of every rule `message` matches, without repeats
+    pub fn check(&self, message: &str) -> Vec<ModAction> {
+        let mut actions = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.is_match(message)) {
+            for action in &rule.actions {
+                if !actions.contains(action) {
+                    actions.push(*action);
+                }
+            }
+        }
+        actions
+    }
+}
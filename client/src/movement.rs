@@ -0,0 +1,68 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use common::data::Vector2;
+
+/// How often to send movement snapshots while walking. About what the game sends
+pub(crate) const MOVEMENT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A walk through waypoints in straight lines. See `Client::move_towards` and `Client::walk_to`
+#[derive(Debug)]
+pub(crate) struct Movement {
+    position: Vector2,
+    /// Points still to reach, the next first
+    waypoints: VecDeque<Vector2>,
+    /// Units per second
+    speed: f32,
+    last_step: Instant,
+}
+
+impl Movement {
+    pub(crate) fn new(from: Vector2, waypoints: Vec<Vector2>, speed: f32, now: Instant) -> Self {
+        Self {
+            position: from,
+            waypoints: waypoints.into(),
+            speed,
+            last_step: now,
+        }
+    }
+
+    /// Whether it's time to send another snapshot
+    pub(crate) fn is_due(&self, now: Instant) -> bool {
+        now.duration_since(self.last_step) >= MOVEMENT_INTERVAL
+    }
+
+    pub(crate) fn arrived(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    /// Moves on by however long it's been since the last step, returning the new position and
+    /// velocity. Waypoints reached on the way are passed through. The velocity is zero once the
+    /// last one is reached
+    pub(crate) fn step(&mut self, now: Instant) -> (Vector2, Vector2) {
+        let mut distance = self.speed * now.duration_since(self.last_step).as_secs_f32();
+        self.last_step = now;
+        while let Some(&next) = self.waypoints.front() {
+            let (dx, dy) = (next.x() - self.position.x(), next.y() - self.position.y());
+            let remaining = (dx * dx + dy * dy).sqrt();
+            if remaining <= distance {
+                distance -= remaining;
+                self.position = next;
+                self.waypoints.pop_front();
+                continue;
+            }
+            let (nx, ny) = (dx / remaining, dy / remaining);
+            self.position = Vector2::new(
+                self.position.x() + nx * distance,
+                self.position.y() + ny * distance,
+            );
+            return (
+                self.position,
+                Vector2::new(nx * self.speed, ny * self.speed),
+            );
+        }
+        (self.position, Vector2::ZERO)
+    }
+}
@@ -0,0 +1,122 @@
+use common::utils::Sanitizer;
+use rand::{seq::SliceRandom, Rng};
+
+/// The longest name the game shows, in characters
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// Picks names for bots. Closures taking the bot's index work too
+///
+/// Swarms all called "Dummy N" are easy to kick with one pattern, so names should vary
+pub trait NameGenerator {
+    /// A name for the `index`th bot, counting from 0. Pass it through `fit_name` before use
+    fn generate(&mut self, index: usize) -> String;
+}
+
+impl<F: FnMut(usize) -> String> NameGenerator for F {
+    fn generate(&mut self, index: usize) -> String {
+        self(index)
+    }
+}
+
+/// Cuts a name down to `MAX_NAME_LENGTH` characters without splitting any, dropping control
+/// characters and markup the game wouldn't show
+pub fn fit_name(name: &str) -> String {
+    let sanitizer = Sanitizer {
+        max_len: Some(MAX_NAME_LENGTH),
+        ..Sanitizer::default()
+    };
+    sanitizer.sanitize(name).trim().to_string()
+}
+
+/// Fills `{n}` in a template with the bot's number, counting from 1
+#[derive(Debug, Clone)]
+pub struct TemplateNames {
+    pub template: String,
+}
+
+impl TemplateNames {
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+        }
+    }
+}
+
+impl NameGenerator for TemplateNames {
+    fn generate(&mut self, index: usize) -> String {
+        self.template.replace("{n}", &(index + 1).to_string())
+    }
+}
+
+/// Joins a random word from each list, like "Shy Otter"
+#[derive(Debug, Clone)]
+pub struct WordNames<R: Rng> {
+    pub lists: Vec<Vec<String>>,
+    pub separator: String,
+    rng: R,
+}
+
+const ADJECTIVES: &[&str] = &[
+    "Shy", "Odd", "Red", "Sly", "Big", "Wee", "Calm", "Lazy", "Bold", "Keen", "Fuzzy", "Quiet",
+    "Lucky", "Grumpy", "Sunny", "Dizzy",
+];
+
+const NOUNS: &[&str] = &[
+    "Otter", "Moth", "Crab", "Llama", "Toad", "Owl", "Yak", "Wren", "Bean", "Pickle", "Panda",
+    "Noodle", "Badger", "Ferret", "Goose", "Squid",
+];
+
+impl<R: Rng> WordNames<R> {
+    pub fn new(lists: Vec<Vec<String>>, separator: &str, rng: R) -> Self {
+        Self {
+            lists,
+            separator: separator.to_string(),
+            rng,
+        }
+    }
+
+    /// An adjective and an animal-ish noun, which always fit in `MAX_NAME_LENGTH`
+    pub fn with_default_words(rng: R) -> Self {
+        let to_strings = |words: &[&str]| words.iter().map(|word| word.to_string()).collect();
+        Self::new(vec![to_strings(ADJECTIVES), to_strings(NOUNS)], "", rng)
+    }
+}
+
+impl<R: Rng> NameGenerator for WordNames<R> {
+    fn generate(&mut self, _index: usize) -> String {
+        let rng = &mut self.rng;
+        self.lists
+            .iter()
+            .filter_map(|list| list.choose(rng).cloned())
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_name_multi_byte() {
+        // Cut by characters, not bytes, so nothing gets split
+        let name = fit_name("Ünïcödé名前のボットです");
+        assert_eq!(name, "Ünïcödé名前のボッ");
+        assert_eq!(name.chars().count(), MAX_NAME_LENGTH);
+    }
+
+    #[test]
+    fn test_fit_name_exact_length() {
+        let name = "Twelve Chars";
+        assert_eq!(name.chars().count(), MAX_NAME_LENGTH);
+        assert_eq!(fit_name(name), name);
+        assert_eq!(fit_name("Thirteen Char"), "Thirteen Cha");
+    }
+
+    #[test]
+    fn test_fit_name_empty() {
+        assert_eq!(fit_name(""), "");
+        // Nothing left once control characters and markup are gone
+        assert_eq!(fit_name("\u{200b}<color=red></color> "), "");
+    }
+}
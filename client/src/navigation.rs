@@ -0,0 +1,303 @@
+use std::collections::BinaryHeap;
+
+use common::data::Vector2;
+
+/// Points along the corridors of a map and which can be walked between in a straight line. See
+/// `Client::walk_to`
+///
+/// Hand-placed and rough, good enough to keep bots out of walls on the usual routes
+#[derive(Debug, Copy, Clone)]
+pub struct NavGraph {
+    pub nodes: &'static [(f32, f32)],
+    pub edges: &'static [(usize, usize)],
+}
+
+pub const SKELD_NAV: NavGraph = NavGraph {
+    nodes: &[
+        (-1., 3.),     // 0 Cafeteria
+        (-9.5, -1.),   // 1 Upper hallway
+        (-17., -1.),   // 2 Upper Engine
+        (-9., -4.),    // 3 MedBay
+        (-17., -5.5),  // 4 Reactor hallway
+        (-20.5, -5.5), // 5 Reactor
+        (-13.5, -5.5), // 6 Security
+        (-17., -13.5), // 7 Lower Engine
+        (-9., -13.),   // 8 Lower hallway
+        (-7.5, -8.5),  // 9 Electrical
+        (-1.5, -15.5), // 10 Storage
+        (-1., -7.5),   // 11 Admin hallway
+        (4.5, -7.5),   // 12 Admin
+        (4., -15.5),   // 13 Communications
+        (9.3, -12.3),  // 14 Shields
+        (9., -5.),     // 15 East hallway
+        (6.5, -3.5),   // 16 O2
+        (16.5, -4.8),  // 17 Navigation
+        (9., 1.),      // 18 Weapons
+    ],
+    edges: &[
+        (0, 1),
+        (1, 2),
+        (1, 3),
+        (2, 4),
+        (4, 5),
+        (4, 6),
+        (4, 7),
+        (7, 8),
+        (8, 9),
+        (8, 10),
+        (0, 11),
+        (11, 10),
+        (11, 12),
+        (10, 13),
+        (13, 14),
+        (14, 15),
+        (15, 16),
+        (15, 17),
+        (15, 18),
+        (18, 0),
+    ],
+};
+
+pub const MIRA_HQ_NAV: NavGraph = NavGraph {
+    nodes: &[
+        (-4.4, 2.2),  // 0 Launchpad
+        (2., 0.),     // 1 Launchpad hallway
+        (9., 1.),     // 2 Locker Room
+        (6., 6.),     // 3 Decontamination
+        (6., 12.5),   // 4 Lab hallway
+        (2.5, 12.5),  // 5 Reactor
+        (9., 12.5),   // 6 Laboratory
+        (15.5, 0.),   // 7 MedBay
+        (15., 4.),    // 8 Communications
+        (17.5, 3.),   // 9 Crossroads
+        (19.5, 4.),   // 10 Storage
+        (25.5, 2.),   // 11 Cafeteria
+        (23., -2.),   // 12 Balcony
+        (17.5, 11.5), // 13 Upper hallway
+        (15., 19.5),  // 14 Office
+        (20., 19.5),  // 15 Admin
+        (17.5, 23.),  // 16 Greenhouse
+    ],
+    edges: &[
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 4),
+        (4, 5),
+        (4, 6),
+        (1, 7),
+        (7, 9),
+        (8, 9),
+        (9, 10),
+        (10, 11),
+        (11, 12),
+        (9, 13),
+        (13, 14),
+        (13, 15),
+        (14, 16),
+        (15, 16),
+    ],
+};
+
+pub const POLUS_NAV: NavGraph = NavGraph {
+    nodes: &[
+        (16.6, -2.4),  // 0 Dropship
+        (16., -8.),    // 1 Dropship path
+        (20.5, -11.5), // 2 Storage
+        (7.5, -11.),   // 3 Electrical
+        (3., -12.),    // 4 Security
+        (3., -21.),    // 5 O2
+        (11.5, -16.),  // 6 Communications
+        (12., -23.),   // 7 Weapons
+        (19.5, -17.5), // 8 Office
+        (23., -22.),   // 9 Admin
+        (27., -12.),   // 10 East path
+        (34.5, -7.),   // 11 Laboratory
+        (36.5, -21.),  // 12 Specimens
+        (25., -2.),    // 13 Decontamination
+    ],
+    edges: &[
+        (0, 1),
+        (1, 2),
+        (1, 3),
+        (3, 4),
+        (4, 5),
+        (3, 6),
+        (5, 7),
+        (6, 7),
+        (6, 8),
+        (2, 8),
+        (8, 9),
+        (2, 10),
+        (10, 11),
+        (10, 12),
+        (9, 12),
+        (0, 13),
+        (13, 11),
+    ],
+};
+
+/// The graph for a map by its id, like `GameOptions::map_id`
+pub fn map_nav(map_id: u8) -> Option<NavGraph> {
+    match map_id {
+        0 => Some(SKELD_NAV),
+        1 => Some(MIRA_HQ_NAV),
+        2 => Some(POLUS_NAV),
+        _ => None,
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// A node on the frontier, ordered so the closest comes out of the heap first
+#[derive(PartialEq)]
+struct Visit {
+    cost: f32,
+    node: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl NavGraph {
+    fn nearest(&self, point: (f32, f32)) -> usize {
+        (0..self.nodes.len())
+            .min_by(|&a, &b| {
+                distance(self.nodes[a], point).total_cmp(&distance(self.nodes[b], point))
+            })
+            .unwrap()
+    }
+
+    /// Waypoints from `from` to `to` through the nodes nearest each, ending at `to`. Just `to` if
+    /// the nodes aren't connected
+    pub fn path(&self, from: Vector2, to: Vector2) -> Vec<Vector2> {
+        let (start, end) = (
+            self.nearest((from.x(), from.y())),
+            self.nearest((to.x(), to.y())),
+        );
+
+        // Dijkstra
+        let mut costs = vec![f32::INFINITY; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        let mut frontier = BinaryHeap::new();
+        costs[start] = 0.;
+        frontier.push(Visit {
+            cost: 0.,
+            node: start,
+        });
+        while let Some(Visit { cost, node }) = frontier.pop() {
+            if node == end {
+                break;
+            }
+            if cost > costs[node] {
+                continue;
+            }
+            let neighbours = self.edges.iter().filter_map(|&(a, b)| {
+                if a == node {
+                    Some(b)
+                } else if b == node {
+                    Some(a)
+                } else {
+                    None
+                }
+            });
+            for next in neighbours {
+                let next_cost = cost + distance(self.nodes[node], self.nodes[next]);
+                if next_cost < costs[next] {
+                    costs[next] = next_cost;
+                    previous[next] = Some(node);
+                    frontier.push(Visit {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        if costs[end].is_infinite() {
+            return vec![to];
+        }
+
+        let mut nodes = vec![end];
+        while let Some(node) = previous[*nodes.last().unwrap()] {
+            nodes.push(node);
+        }
+        nodes
+            .into_iter()
+            .rev()
+            .map(|node| Vector2::new(self.nodes[node].0, self.nodes[node].1))
+            .chain(std::iter::once(to))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The waypoints from one node to another, as node ids
+    fn route(graph: &NavGraph, from: usize, to: usize) -> Vec<usize> {
+        let point = |node: usize| Vector2::new(graph.nodes[node].0, graph.nodes[node].1);
+        let path = graph.path(point(from), point(to));
+        // Without the destination itself on the end
+        path[..path.len() - 1]
+            .iter()
+            .map(|waypoint| graph.nearest((waypoint.x(), waypoint.y())))
+            .collect()
+    }
+
+    #[test]
+    fn test_shortest_routes() {
+        // Cafeteria to Navigation through Weapons rather than round by Storage
+        assert_eq!(route(&SKELD_NAV, 0, 17), vec![0, 18, 15, 17]);
+        // Launchpad to Laboratory through Decontamination
+        assert_eq!(route(&MIRA_HQ_NAV, 0, 6), vec![0, 1, 2, 3, 4, 6]);
+        // Dropship to Weapons through Electrical and Communications
+        assert_eq!(route(&POLUS_NAV, 0, 7), vec![0, 1, 3, 6, 7]);
+    }
+
+    #[test]
+    fn test_every_node_reachable() {
+        for map_id in 0..3 {
+            let graph = map_nav(map_id).unwrap();
+            for node in 0..graph.nodes.len() {
+                assert_eq!(route(&graph, 0, node).last(), Some(&node));
+            }
+        }
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let graph = NavGraph {
+            nodes: &[(0., 0.), (1., 0.), (10., 10.)],
+            edges: &[(0, 1)],
+        };
+        let to = Vector2::new(10., 11.);
+        let path = graph.path(Vector2::new(0., 0.), to);
+        let path = path
+            .iter()
+            .map(|waypoint| (waypoint.x(), waypoint.y()))
+            .collect::<Vec<_>>();
+        // Straight there instead
+        assert_eq!(path, vec![(to.x(), to.y())]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        assert_eq!(SKELD_NAV.nearest((-20., -6.)), 5);
+        // Doesn't panic on points that aren't numbers
+        SKELD_NAV.nearest((f32::NAN, 0.));
+    }
+}
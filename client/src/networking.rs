@@ -1,26 +1,171 @@
 use std::{
-    collections::HashMap,
-    io::Result,
-    net::{SocketAddr, UdpSocket},
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::{self, ErrorKind, Result},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    str::FromStr,
     sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{channel, Receiver, RecvTimeoutError, Sender},
-        Arc, RwLock,
+        Arc, Condvar, Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
 
 use common::{
-    data::{HazelPacket, HazelPacketOut},
-    reader::{IntoReader, Serialize},
+    capture::Direction,
+    data::{
+        fragment, write_disconnect_reason, DisconnectReason, HazelExtension, HazelPacket,
+        HazelPacketOut, Reassembler,
+    },
+    reader::{Data, IntoReader, PacketWriter, Serialize},
 };
 
-use log::{error, info};
+use log::{debug, error, info, warn};
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::{spawn_named, ClientError, PanicSlot},
+    recorder::Recorder,
+    secure::{SecureSession, SecureTransport},
+};
 
 pub const DEFAULT_PORT: u16 = 22023;
 pub const _ANNOUNCE_PORT: u16 = 22024;
-const BUFFER_SIZE: usize = 65_507;
+pub(crate) const BUFFER_SIZE: usize = 65_507;
+
+/// Reliable packets bigger than this are split into fragments, unless `NetClient::start_mtu_probe`
+/// finds something smaller
+const MAX_PACKET_SIZE: usize = BUFFER_SIZE;
+
+/// How long reliable packets wait for a missing earlier one before being delivered anyway, when
+/// ordering is on. A few resends' worth
+const ORDER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the receive thread wakes up without traffic to check `ORDER_TIMEOUT`
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Datagram sizes tried by `NetClient::start_mtu_probe`, largest first. 1472 fills a 1500 byte
+/// ethernet frame after IP and UDP headers and 548 fits the smallest MTU IPv4 guarantees
+const PROBE_SIZES: [usize; 6] = [1472, 1400, 1280, 1200, 1024, 548];
+
+/// How long a reliable packet goes unacknowledged before it's sent again
+const RESEND_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// How many times a reliable packet is sent again before it's given up on. Hazel keeps resending
+/// until it hears back, since a single resend is lost as easily as the original, but a server
+/// that hasn't answered in this many intervals isn't going to
+const MAX_RESENDS: u32 = 10;
+
+/// How often `NetClient::flush` checks whether everything's gone
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Most reliable packets kept waiting for an acknowledgement, half the ack id space. Past this
+/// the oldest is given up on
+const MAX_UNCONFIRMED: usize = 1 << 15;
+
+/// How long to wait for the rest of a fragmented payload before giving up on it
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OS socket options for a connection, for when the defaults drop packets under load. See
+/// `NetClient::connect_with_config`
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TransportConfig {
+    /// Receive buffer size in bytes (`SO_RCVBUF`). Datagrams arriving while it's full are
+    /// dropped, so bursts need it bigger. The OS may cap it, e.g. Linux at `net.core.rmem_max`.
+    /// `None` keeps the OS default
+    pub recv_buffer_size: Option<usize>,
+
+    /// Send buffer size in bytes (`SO_SNDBUF`). `None` keeps the OS default
+    pub send_buffer_size: Option<usize>,
+
+    /// The IP type of service byte, with the DSCP mark in its top six bits, e.g. `46 << 2` for
+    /// expedited forwarding. `None` leaves it unmarked
+    pub tos: Option<u32>,
+}
+
+impl TransportConfig {
+    /// Binds a socket to a random local port with these options
+    pub(crate) fn bind(&self) -> Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(tos) = self.tos {
+            socket.set_tos(tos)?;
+        }
+        let any_address = SocketAddr::from(([0, 0, 0, 0], 0));
+        socket.bind(&any_address.into())?;
+        Ok(socket.into())
+    }
+}
 
-/// The main servers Among Us connects to
+/// Where a connection's datagrams go, straight on the socket or through a `SecureSession`
+enum Wire {
+    Plain(UdpSocket),
+    Secure(Arc<dyn SecureSession>),
+}
+
+impl Wire {
+    /// Another handle to the same connection, for another thread
+    fn try_clone(&self) -> Result<Self> {
+        Ok(match self {
+            Wire::Plain(socket) => Wire::Plain(socket.try_clone()?),
+            Wire::Secure(session) => Wire::Secure(session.clone()),
+        })
+    }
+
+    fn send(&self, datagram: &[u8]) -> Result<()> {
+        match self {
+            Wire::Plain(socket) => socket.send(datagram).map(|_| ()),
+            Wire::Secure(session) => session.send(datagram),
+        }
+    }
+
+    fn recv(&self, buffer: &mut [u8]) -> Result<usize> {
+        match self {
+            Wire::Plain(socket) => socket.recv(buffer),
+            Wire::Secure(session) => session.recv(buffer),
+        }
+    }
+}
+
+/// How many received packets can wait for the client to read them. See
+/// `NetClient::set_inbound_limit`
+#[derive(Debug, Copy, Clone)]
+pub struct InboundLimit {
+    pub capacity: usize,
+    pub overflow: Overflow,
+}
+
+impl Default for InboundLimit {
+    fn default() -> Self {
+        Self {
+            capacity: 4096,
+            overflow: Overflow::DropOldest,
+        }
+    }
+}
+
+/// What happens to unreliable packets arriving once the inbound queue is full
+///
+/// Reliable packets are never dropped. The receive thread waits for room instead, and anything
+/// lost from the socket buffer meanwhile is resent by the server. Manual clients let reliable
+/// packets go over the limit, as nothing reads them while they're being fed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the packet that just arrived
+    DropNewest,
+    /// Drop the oldest waiting unreliable packet to make room, so the latest movement wins
+    DropOldest,
+}
+
+/// The main servers Among Us connects to. Any other server is a `ServerTarget::Custom`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MainServer {
     Europe,
     NorthAmerica,
@@ -28,6 +173,13 @@ pub enum MainServer {
 }
 
 impl MainServer {
+    /// Every official main server
+    pub const ALL: [MainServer; 3] = [
+        MainServer::Europe,
+        MainServer::NorthAmerica,
+        MainServer::Asia,
+    ];
+
     /// Get the address of a server
     pub fn to_addr(&self) -> SocketAddr {
         match self {
@@ -38,14 +190,143 @@ impl MainServer {
     }
 }
 
+impl FromStr for MainServer {
+    type Err = String;
+
+    /// `eu`, `na` or `asia`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "eu" | "europe" => MainServer::Europe,
+            "na" | "northamerica" => MainServer::NorthAmerica,
+            "asia" => MainServer::Asia,
+            _ => return Err(format!("Expected eu, na or asia, got {}", s)),
+        })
+    }
+}
+
+/// Where to connect, an official main server or a private one like Impostor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerTarget {
+    Main(MainServer),
+    /// A host name or IP address. Names are looked up again for each game or scan, so a server
+    /// on a dynamic address is followed between them
+    Custom {
+        host: String,
+        port: u16,
+    },
+}
+
+impl ServerTarget {
+    /// Looks up the address. Blocks on DNS for host names
+    pub fn resolve(&self) -> Result<SocketAddr> {
+        match self {
+            ServerTarget::Main(server) => Ok(server.to_addr()),
+            ServerTarget::Custom { host, port } => {
+                first_ipv4(host, (host.as_str(), *port).to_socket_addrs()?)
+            }
+        }
+    }
+
+    /// Whether this is a private server, which `ClientSettings::for_custom_server` is applied for
+    pub fn is_custom(&self) -> bool {
+        matches!(self, ServerTarget::Custom { .. })
+    }
+}
+
+/// Only IPv4 addresses are used, as that's all the client binds
+pub(crate) fn first_ipv4<I: Iterator<Item = SocketAddr>>(
+    host: &str,
+    mut addrs: I,
+) -> Result<SocketAddr> {
+    addrs
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("No IPv4 address for {}", host)))
+}
+
+impl From<MainServer> for ServerTarget {
+    fn from(server: MainServer) -> Self {
+        ServerTarget::Main(server)
+    }
+}
+
+impl From<SocketAddr> for ServerTarget {
+    fn from(addr: SocketAddr) -> Self {
+        ServerTarget::Custom {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+        }
+    }
+}
+
+impl fmt::Display for ServerTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerTarget::Main(server) => write!(f, "{:?}", server),
+            ServerTarget::Custom { host, port } => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+impl FromStr for ServerTarget {
+    type Err = String;
+
+    /// Anything `MainServer` takes, or a host name or IP address with an optional port like
+    /// `impostor.example.com:22023`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Ok(server) = s.parse::<MainServer>() {
+            return Ok(ServerTarget::Main(server));
+        }
+        let (host, port) = match s.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse().map_err(|_| format!("Invalid port in {}", s))?,
+            ),
+            None => (s, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("Expected a server, got {}", s));
+        }
+        Ok(ServerTarget::Custom {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
 /// UDP client that implements the Hazel protocol
 ///
 /// Sends a disconnect packet when dropped
 pub struct NetClient {
+    /// The server connected to
+    addr: SocketAddr,
     /// The `Sender` for the packet sending channel
     packet_out_send: Sender<HazelPacketOut>,
-    packet_in_recv: Receiver<HazelPacket>,
+    packets_in: Arc<PacketQueue>,
     ack_handler: Arc<RwLock<AckHandler>>,
+    bandwidth: Arc<RwLock<Bandwidth>>,
+    ordered: Arc<AtomicBool>,
+    max_packet_size: usize,
+    /// Set for clients made with `NetClient::manual`
+    manual: Option<Manual>,
+    clock: Arc<dyn Clock>,
+    extension: Option<Arc<dyn HazelExtension>>,
+    /// Set if the send or receive thread panicked
+    panicked: PanicSlot,
+    /// Packets passed to `send` that haven't gone out yet. See `NetClient::flush`
+    queued: Arc<AtomicUsize>,
+    /// Whether a disconnect has been sent, so dropping doesn't send another
+    disconnected: bool,
+    /// Shared with the send and receive threads
+    recorder: Arc<RwLock<Option<Recorder>>>,
+}
+
+/// What the send and receive threads own, for a client driven by hand instead
+struct Manual {
+    packet_out_recv: Receiver<HazelPacketOut>,
+    queue: SendQueue,
+    inbound: Inbound,
+    /// Datagrams waiting for `NetClient::poll_outbound`
+    outbound: VecDeque<Vec<u8>>,
 }
 
 // TODO: Track received packets for missed ones
@@ -53,221 +334,1365 @@ impl NetClient {
     /// Creates a client and binds it to a random local port, then connects to the
     /// given address and starts the send/receive loops
     pub fn connect_direct(addr: SocketAddr) -> Result<Self> {
+        Self::connect_with_clock(addr, Arc::new(SystemClock))
+    }
+
+    /// `connect_direct`, with timers run off `clock`
+    pub fn connect_with_clock(addr: SocketAddr, clock: Arc<dyn Clock>) -> Result<Self> {
+        Self::connect_with_config(addr, clock, TransportConfig::default())
+    }
+
+    /// `connect_with_clock`, with the socket set up as `config` says
+    pub fn connect_with_config(
+        addr: SocketAddr,
+        clock: Arc<dyn Clock>,
+        config: TransportConfig,
+    ) -> Result<Self> {
+        Self::connect_secure(addr, clock, config, None)
+    }
+
+    /// `connect_with_config`, with every datagram going through `secure` if given. Its handshake
+    /// is done before this returns
+    pub fn connect_secure(
+        addr: SocketAddr,
+        clock: Arc<dyn Clock>,
+        config: TransportConfig,
+        secure: Option<&dyn SecureTransport>,
+    ) -> Result<Self> {
         // Bind udp socket
-        let any_address = SocketAddr::from(([0, 0, 0, 0], 0));
-        let socket = UdpSocket::bind(any_address)?;
+        let socket = config.bind()?;
 
         // Connect to remote server
         socket.connect(addr)?;
-        info!("Connected to {}", addr);
+        let wire = match secure {
+            Some(secure) => {
+                let session = secure.connect(socket)?;
+                info!("Connected securely to {}", addr);
+                Wire::Secure(Arc::from(session))
+            }
+            None => {
+                // Secure sessions wait for themselves
+                socket.set_read_timeout(Some(RECV_POLL_INTERVAL))?;
+                info!("Connected to {}", addr);
+                Wire::Plain(socket)
+            }
+        };
 
         let (packet_out_send, packet_out_recv) = channel::<HazelPacketOut>();
-        let (packet_in_send, packet_in_recv) = channel::<HazelPacket>();
-        let ack_handler = AckHandler {
-            ack_index: 1,
-            unconfirmed: HashMap::new(),
-        };
-        let ack_handler = Arc::new(RwLock::new(ack_handler));
+        let packets_in = PacketQueue::new();
+        let ack_handler = Arc::new(RwLock::new(AckHandler::new()));
+        let bandwidth = Arc::new(RwLock::new(Bandwidth::new(clock.now())));
+        let ordered = Arc::new(AtomicBool::new(false));
+        let panicked = PanicSlot::default();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let recorder = Arc::new(RwLock::new(None));
 
         // Send thread
-        let send_socket = socket.try_clone().unwrap();
+        let send_wire = wire.try_clone()?;
         let _send_thread = {
             let ack_handler = ack_handler.clone();
-            std::thread::spawn(move || loop {
-                let packet = packet_out_recv.recv_timeout(Duration::from_millis(50));
-                match packet {
-                    Ok(packet) => {
-                        let packet_bytes = packet.serialize_bytes();
-
-                        // Send packet
-                        send_socket.send(&packet_bytes).unwrap();
-
-                        // Handle ack stuff
-                        {
-                            let mut ack_handler = ack_handler.write().unwrap();
-                            match packet {
-                                HazelPacketOut::Unreliable { .. } => (),
-                                HazelPacketOut::Reliable { ack_id, .. } => {
-                                    // Add to unconfirmed, checking not already inserted
-                                    assert!(ack_handler
-                                        .unconfirmed
-                                        .insert(ack_id, (Instant::now(), packet_bytes.clone()))
-                                        .is_none());
-                                }
-                                HazelPacketOut::Disconnect => (),
-                                HazelPacketOut::Hello { ack_id, .. } => {
-                                    // Add to unconfirmed, checking not already inserted
-                                    assert!(ack_handler
-                                        .unconfirmed
-                                        .insert(ack_id, (Instant::now(), packet_bytes.clone()))
-                                        .is_none());
-                                }
-                                HazelPacketOut::Acknowledge { .. } => (),
-                                HazelPacketOut::KeepAlive { ack_id } => {
-                                    // Add to unconfirmed, checking not already inserted
-                                    assert!(ack_handler
-                                        .unconfirmed
-                                        .insert(ack_id, (Instant::now(), packet_bytes.clone()))
-                                        .is_none());
-                                }
-                            }
-                        }
-                    }
+            let bandwidth = bandwidth.clone();
+            let clock = clock.clone();
+            let queued = queued.clone();
+            let recorder = recorder.clone();
+            let mut queue = SendQueue::default();
+            spawn_named("hazel-send", panicked.clone(), move || loop {
+                // Wait for a packet then take everything else waiting, so the most urgent can go
+                // first
+                match packet_out_recv.recv_timeout(Duration::from_millis(50)) {
+                    Ok(packet) => queue.push(packet),
                     Err(RecvTimeoutError::Timeout) => (),
                     Err(RecvTimeoutError::Disconnected) => break,
                 }
+                while let Ok(packet) = packet_out_recv.try_recv() {
+                    queue.push(packet);
+                }
 
-                // Resend unacknowledged packets
-                {
-                    // Lock ack handler
-                    let mut ack_handler = ack_handler.write().unwrap();
-
-                    // Temporarily take unconfirmed
-                    let unconfirmed =
-                        std::mem::replace(&mut ack_handler.unconfirmed, HashMap::new());
-
-                    // Partition by time since last send
-                    let (to_repeat_send, keep) = unconfirmed.into_iter().partition::<HashMap<
-                        u16,
-                        (Instant, Vec<u8>),
-                    >, _>(
-                        |(_, (instant, _))| instant.elapsed() >= Duration::from_millis(1000),
-                    );
+                while let Some(packet) = queue.pop() {
+                    let packet_bytes = ack_handler.read().unwrap().serialize(&packet);
 
-                    // Replace unconfirmed
-                    ack_handler.unconfirmed = keep;
+                    // Send packet
+                    send_limited(&send_wire, &bandwidth, &*clock, &recorder, &packet_bytes);
+                    ack_handler
+                        .write()
+                        .unwrap()
+                        .sent(&packet, &packet_bytes, clock.now());
+                    unqueue(&queued, &packet);
+                }
 
-                    // Repeat
-                    to_repeat_send.into_iter().for_each(|(_, (_, data))| {
-                        send_socket.send(&data).unwrap();
-                    });
+                // Resend unacknowledged packets
+                let resends = ack_handler.write().unwrap().due_resends(clock.now());
+                for data in resends {
+                    send_limited(&send_wire, &bandwidth, &*clock, &recorder, &data);
                 }
             })
         };
 
         // Receive thread
-        let recv_socket = socket.try_clone().unwrap();
+        let recv_wire = wire;
         let _recv_thread = {
             let packet_out_send = packet_out_send.clone();
             let ack_handler = ack_handler.clone();
-            std::thread::spawn(move || loop {
+            let bandwidth = bandwidth.clone();
+            let ordered = ordered.clone();
+            let clock = clock.clone();
+            let mut inbound = Inbound::new();
+            let packets_in = QueueWriter(packets_in.clone());
+            let send_panicked = panicked.clone();
+            let recorder = recorder.clone();
+            spawn_named("hazel-recv", panicked.clone(), move || loop {
+                // Nothing gets acknowledged without the send thread, so stop and let the client
+                // see the connection is gone
+                if send_panicked.lock().unwrap().is_some() {
+                    return;
+                }
+
                 // Receive packet
                 let mut buffer = vec![0; BUFFER_SIZE];
-                match recv_socket.recv(&mut buffer) {
-                    Ok(size) => buffer.resize(size, 0),
+                let ordered = ordered.load(Ordering::Relaxed);
+                let ready = match recv_wire.recv(&mut buffer) {
+                    Ok(size) => {
+                        buffer.resize(size, 0);
+                        bandwidth.write().unwrap().received(size);
+                        if let Some(recorder) = &*recorder.read().unwrap() {
+                            recorder.record(Direction::ToClient, &buffer);
+                        }
+                        inbound.receive(
+                            buffer,
+                            &ack_handler,
+                            &packet_out_send,
+                            ordered,
+                            clock.now(),
+                        )
+                    }
+                    Err(error)
+                        if error.kind() == ErrorKind::WouldBlock
+                            || error.kind() == ErrorKind::TimedOut =>
+                    {
+                        inbound.idle(ordered, clock.now())
+                    }
                     Err(error) => {
                         error!("{} {:?}", error, error.kind());
                         break;
                     }
-                }
+                };
 
-                // Read packet
-                let mut r = buffer.into_reader();
-                let packet = r.read::<HazelPacket>();
-                if let Err(packet_error) = packet {
-                    error!("Error reading hazel packet {}", packet_error);
-                    continue;
-                }
-                let packet = packet.unwrap();
-
-                // Handle packet
-                {
-                    let mut ack_handler = ack_handler.write().unwrap();
-                    match packet {
-                        HazelPacket::Unreliable { .. } => (),
-                        HazelPacket::Reliable { ack_id, .. } => {
-                            packet_out_send
-                                .send(HazelPacketOut::Acknowledge { ack_id })
-                                .unwrap();
-                        }
-                        HazelPacket::Disconnect => (),
-                        HazelPacket::Hello { ack_id, .. } => {
-                            packet_out_send
-                                .send(HazelPacketOut::Acknowledge { ack_id })
-                                .unwrap();
-                        }
-                        HazelPacket::Acknowledge { ack_id } => {
-                            ack_handler.unconfirmed.remove(&ack_id);
-                        }
-                        HazelPacket::KeepAlive { ack_id } => {
-                            packet_out_send
-                                .send(HazelPacketOut::Acknowledge { ack_id })
-                                .unwrap();
-                        }
+                // Send packets upwards, waiting for room if the client is behind
+                for packet in ready {
+                    if !packets_in.0.push(packet, true) {
+                        // Exit if the client has gone
+                        return;
                     }
                 }
-
-                // Send packet upwards
-                if packet_in_send.send(packet).is_err() {
-                    // Exit if channel closed
-                    return;
-                }
             })
         };
 
         // Return client
         let client = NetClient {
+            addr,
             packet_out_send,
-            packet_in_recv,
+            packets_in,
             ack_handler,
+            bandwidth,
+            ordered,
+            max_packet_size: MAX_PACKET_SIZE,
+            manual: None,
+            clock,
+            extension: None,
+            panicked,
+            queued,
+            disconnected: false,
+            recorder,
         };
         Ok(client)
     }
 
+    /// Creates a client with no socket and no threads, driven by hand with `feed_datagram`,
+    /// `poll_outbound` and `poll_timers`
+    ///
+    /// With a `ManualClock` the same inputs always give the same outputs. For tests and fuzzing
+    pub fn manual(clock: Arc<dyn Clock>) -> Self {
+        Self::manual_to(SocketAddr::from(([0, 0, 0, 0], 0)), clock)
+    }
+
+    /// `manual`, for the server at `addr`. The datagrams still have to be carried by hand
+    pub fn manual_to(addr: SocketAddr, clock: Arc<dyn Clock>) -> Self {
+        let (packet_out_send, packet_out_recv) = channel::<HazelPacketOut>();
+        NetClient {
+            addr,
+            packet_out_send,
+            packets_in: PacketQueue::new(),
+            ack_handler: Arc::new(RwLock::new(AckHandler::new())),
+            bandwidth: Arc::new(RwLock::new(Bandwidth::new(clock.now()))),
+            ordered: Arc::new(AtomicBool::new(false)),
+            max_packet_size: MAX_PACKET_SIZE,
+            manual: Some(Manual {
+                packet_out_recv,
+                queue: SendQueue::default(),
+                inbound: Inbound::new(),
+                outbound: VecDeque::new(),
+            }),
+            clock,
+            extension: None,
+            panicked: PanicSlot::default(),
+            queued: Arc::new(AtomicUsize::new(0)),
+            disconnected: false,
+            recorder: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Whether this client was made with `NetClient::manual`
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    /// The current time according to the client's clock
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Handles a datagram as if it was received from the server. Manual clients only
+    pub fn feed_datagram(&mut self, datagram: &[u8]) {
+        let manual = self.manual.as_mut().expect("Not a manual client");
+        self.bandwidth.write().unwrap().received(datagram.len());
+        if let Some(recorder) = &*self.recorder.read().unwrap() {
+            recorder.record(Direction::ToClient, datagram);
+        }
+        let ready = manual.inbound.receive(
+            datagram.to_vec(),
+            &self.ack_handler,
+            &self.packet_out_send,
+            self.ordered.load(Ordering::Relaxed),
+            self.clock.now(),
+        );
+        for packet in ready {
+            self.packets_in.push(packet, false);
+        }
+    }
+
+    /// The next datagram that would be sent to the server, including resends that are due.
+    /// Manual clients only
+    ///
+    /// The upload cap isn't applied, but traffic is still counted
+    pub fn poll_outbound(&mut self) -> Option<Vec<u8>> {
+        let manual = self.manual.as_mut().expect("Not a manual client");
+        while let Ok(packet) = manual.packet_out_recv.try_recv() {
+            manual.queue.push(packet);
+        }
+        {
+            let now = self.clock.now();
+            let mut ack_handler = self.ack_handler.write().unwrap();
+            while let Some(packet) = manual.queue.pop() {
+                let packet_bytes = ack_handler.serialize(&packet);
+                ack_handler.sent(&packet, &packet_bytes, now);
+                unqueue(&self.queued, &packet);
+                manual.outbound.push_back(packet_bytes);
+            }
+            manual.outbound.extend(ack_handler.due_resends(now));
+        }
+        let datagram = manual.outbound.pop_front()?;
+        self.bandwidth
+            .write()
+            .unwrap()
+            .sent(datagram.len(), Duration::from_secs(0));
+        if let Some(recorder) = &*self.recorder.read().unwrap() {
+            recorder.record(Direction::ToServer, &datagram);
+        }
+        Some(datagram)
+    }
+
+    /// Gives up on missing packets that have been waited on too long, as the receive thread does
+    /// when it's idle. Manual clients only, usually after their clock moves
+    pub fn poll_timers(&mut self) {
+        let manual = self.manual.as_mut().expect("Not a manual client");
+        let ready = manual
+            .inbound
+            .idle(self.ordered.load(Ordering::Relaxed), self.clock.now());
+        for packet in ready {
+            self.packets_in.push(packet, false);
+        }
+    }
+
     /// Creates a client and binds it to a random local port, then connects to the
-    /// given server and starts the send/receive loops
-    pub fn connect(server: MainServer) -> Result<Self> {
-        Self::connect_direct(server.to_addr())
+    /// given server and starts the send/receive loops. A `ServerTarget::Custom` is looked up first
+    pub fn connect(server: &ServerTarget) -> Result<Self> {
+        Self::connect_direct(server.resolve()?)
+    }
+
+    /// The server connected to
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Caps outgoing traffic, including resends, to `bytes_per_sec`. `None` removes the cap
+    ///
+    /// Packets over the cap wait in the send queue rather than being dropped
+    pub fn set_max_upload_rate(&self, bytes_per_sec: Option<u32>) {
+        self.bandwidth.write().unwrap().limit = bytes_per_sec;
+    }
+
+    /// Whether to deliver reliable packets in the order they were sent rather than the order
+    /// they arrive. Repeats of packets already delivered are dropped
+    ///
+    /// A packet that never arrives holds up the ones after it for `ORDER_TIMEOUT`
+    pub fn set_ordered(&self, ordered: bool) {
+        self.ordered.store(ordered, Ordering::Relaxed);
+    }
+
+    /// Records every datagram from now on to `recorder`, or stops recording with `None`
+    pub fn set_recorder(&self, recorder: Option<Recorder>) {
+        *self.recorder.write().unwrap() = recorder;
+    }
+
+    /// Adds fields to every hello and disconnect sent from now on. `None` sends them as is
+    pub fn set_extension(&mut self, extension: Option<Arc<dyn HazelExtension>>) {
+        self.extension = extension;
+    }
+
+    /// Reliable packets bigger than this are sent as fragments
+    pub fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
+    /// Starts finding the largest datagram that gets through to the server, so fragments fit the
+    /// path instead of relying on IP fragmentation. See `poll_mtu_probe` for the result
+    ///
+    /// Once the last hello has been acknowledged, a keep alive padded to each of `PROBE_SIZES` is
+    /// queued and the largest acknowledged within `timeout` becomes `max_packet_size`. Best
+    /// effort, as paths that fragment rather than drop big datagrams pass every probe
+    pub fn start_mtu_probe(&mut self, timeout: Duration) {
+        self.ack_handler.write().unwrap().probe = Some(MtuProbe {
+            timeout,
+            sizes: HashMap::new(),
+            acknowledged: Vec::new(),
+            deadline: None,
+        });
+    }
+
+    /// Moves an MTU probe started with `start_mtu_probe` along. Returns how it went once it's
+    /// over, `None` before then or if there isn't one
+    pub fn poll_mtu_probe(&mut self) -> Option<MtuProbeResult> {
+        let now = self.clock.now();
+        let mut ack_handler = self.ack_handler.write().unwrap();
+        let probe = ack_handler.probe.as_ref()?;
+        let deadline = match probe.deadline {
+            Some(deadline) => deadline,
+            None if ack_handler.hello_acknowledged => {
+                // Probes go through the queue like anything else, padded by the send thread
+                let ids = PROBE_SIZES
+                    .iter()
+                    .map(|&size| (ack_handler.get_next_index(), size))
+                    .collect::<Vec<_>>();
+                let probe = ack_handler.probe.as_mut().unwrap();
+                probe.sizes.extend(ids.iter().copied());
+                probe.deadline = Some(now + probe.timeout);
+                drop(ack_handler);
+                for (ack_id, _) in ids {
+                    self.send(HazelPacketOut::KeepAlive { ack_id });
+                }
+                return None;
+            }
+            None => return None,
+        };
+
+        // The largest probe is all that matters, so stop waiting once it's been answered
+        let largest = probe
+            .acknowledged
+            .iter()
+            .filter_map(|ack_id| probe.sizes.get(ack_id))
+            .max()
+            .copied();
+        if largest != Some(PROBE_SIZES[0]) && now < deadline {
+            return None;
+        }
+        ack_handler.probe = None;
+        drop(ack_handler);
+        Some(match largest {
+            Some(size) => {
+                self.max_packet_size = size;
+                MtuProbeResult::Probed(size)
+            }
+            None => MtuProbeResult::Unanswered,
+        })
+    }
+
+    /// Traffic counted since connecting, and what's still waiting to be acknowledged
+    pub fn bandwidth(&self) -> BandwidthStats {
+        let mut stats = self.bandwidth.read().unwrap().stats.clone();
+        let ack_handler = self.ack_handler.read().unwrap();
+        stats.unacknowledged = ack_handler.unconfirmed.len();
+        stats.oldest_unacknowledged = ack_handler.oldest_unconfirmed(self.clock.now());
+        stats
     }
 
     /// Sends a packet to the send thread
     fn send(&self, packet: HazelPacketOut) {
-        self.packet_out_send.send(packet).unwrap();
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        // Only fails once the send thread has gone, which `thread_panic` reports
+        if self.packet_out_send.send(packet).is_err() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            debug!("Send thread stopped, dropping packet");
+        }
+    }
+
+    /// Waits until everything sent so far has gone out and every reliable packet has been
+    /// acknowledged, or until `timeout`. Returns whether it all was
+    ///
+    /// Manual clients don't wait, as nothing moves until they're polled
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        loop {
+            let done = self.is_flushed();
+            if done || self.is_manual() || self.thread_panic().is_some() {
+                return done;
+            }
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(FLUSH_POLL_INTERVAL);
+        }
+    }
+
+    /// Whether everything sent so far has gone out and every reliable packet has been
+    /// acknowledged
+    pub fn is_flushed(&self) -> bool {
+        self.queued.load(Ordering::SeqCst) == 0
+            && self.ack_handler.read().unwrap().unconfirmed.is_empty()
+    }
+
+    /// Why the connection's threads stopped, if one of them panicked. Reading packets fails once
+    /// they have
+    pub fn thread_panic(&self) -> Option<ClientError> {
+        self.panicked.lock().unwrap().clone()
     }
 
     /// Read a packet
     pub fn read_packet(&self) -> HazelPacket {
-        self.packet_in_recv.recv().unwrap()
+        self.packets_in.pop(None).unwrap()
+    }
+
+    /// Read a packet if one is waiting
+    pub fn try_read_packet(&self) -> Option<HazelPacket> {
+        self.packets_in.pop(Some(Duration::from_secs(0))).ok()
+    }
+
+    /// Read a packet, giving up after `timeout` or if the receive thread has stopped
+    pub fn read_packet_timeout(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<HazelPacket, RecvTimeoutError> {
+        self.packets_in.pop(Some(timeout))
+    }
+
+    /// How many received packets can wait to be read, and what happens to unreliable ones past
+    /// that
+    pub fn set_inbound_limit(&self, limit: InboundLimit) {
+        self.packets_in.state.lock().unwrap().limit = limit;
+    }
+
+    /// Unreliable packets dropped because the client wasn't reading fast enough
+    pub fn inbound_dropped(&self) -> u64 {
+        self.packets_in.state.lock().unwrap().dropped
     }
 
     pub fn send_unreliable(&mut self, data: Box<dyn Serialize>) {
         self.send(HazelPacketOut::Unreliable { data });
     }
 
+    /// Sends data reliably, splitting it into fragments if it won't fit in one packet
     pub fn send_reliable(&mut self, data: Box<dyn Serialize>) {
-        let ack_id = self.ack_handler.write().unwrap().get_next_index();
-        self.send(HazelPacketOut::Reliable { ack_id, data });
+        let bytes = data.serialize_bytes();
+        // Type and ack id
+        if bytes.len() + 3 <= self.max_packet_size {
+            let ack_id = self.ack_handler.write().unwrap().get_next_index();
+            self.send(HazelPacketOut::Reliable { ack_id, data });
+            return;
+        }
+
+        let pieces = fragment(&bytes, self.max_packet_size);
+        let count = pieces.len() as u16;
+        let fragment_id = self.ack_handler.write().unwrap().get_next_fragment_id();
+        for (index, piece) in pieces.into_iter().enumerate() {
+            let ack_id = self.ack_handler.write().unwrap().get_next_index();
+            self.send(HazelPacketOut::Fragment {
+                ack_id,
+                fragment_id,
+                index: index as u16,
+                count,
+                data: piece.to_vec(),
+            });
+        }
     }
 
     /// Tells the server to initialize the connection
     /// Optionally send extra data unrelated to the Hazel protocol
-    pub fn send_hello(&mut self, data: Box<dyn Serialize>) {
+    ///
+    /// Returns the ack id the server will acknowledge the hello with
+    pub fn send_hello(&mut self, data: Box<dyn Serialize>) -> u16 {
         let ack_id = self.ack_handler.write().unwrap().get_next_index();
+        let data = match &self.extension {
+            Some(extension) => {
+                let mut w = PacketWriter::new();
+                data.serialize(&mut w);
+                extension.extend_hello(&mut w);
+                Box::new(Data::Bytes(w.finish()))
+            }
+            None => data,
+        };
+        {
+            let mut ack_handler = self.ack_handler.write().unwrap();
+            ack_handler.hello_id = Some(ack_id);
+            ack_handler.hello_acknowledged = false;
+        }
         self.send(HazelPacketOut::Hello { ack_id, data });
+        ack_id
+    }
+
+    /// Sends a disconnect packet, unless one's already been sent
+    pub(crate) fn send_disconnect(&mut self) {
+        self.send_disconnect_reason(None);
     }
 
-    /// Sends a disconnect packet
-    fn send_disconnect(&mut self) {
-        self.send(HazelPacketOut::Disconnect);
+    /// Sends a disconnect packet saying why, unless one's already been sent. Servers on older
+    /// Hazel revisions ignore the reason
+    pub(crate) fn send_disconnect_reason(&mut self, reason: Option<&DisconnectReason>) {
+        if self.disconnected {
+            return;
+        }
+        self.disconnected = true;
+        let mut w = PacketWriter::new();
+        if let Some(reason) = reason {
+            write_disconnect_reason(&mut w, reason);
+        }
+        if let Some(extension) = &self.extension {
+            extension.extend_disconnect(&mut w);
+        }
+        self.send(HazelPacketOut::Disconnect { data: w.finish() });
     }
 }
 
 impl Drop for NetClient {
     fn drop(&mut self) {
         self.send_disconnect();
+        // Lets the receive thread go if it's waiting for room
+        self.packets_in.close();
+    }
+}
+
+/// Traffic counters for a connection. See `NetClient::bandwidth`
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthStats {
+    pub bytes_sent: u64,
+    pub packets_sent: u64,
+    pub bytes_received: u64,
+    pub packets_received: u64,
+    /// Total time outgoing packets spent waiting because of the upload cap
+    pub throttled: Duration,
+    /// Reliable packets sent but not yet acknowledged
+    pub unacknowledged: usize,
+    /// How long ago the oldest unacknowledged packet was first sent. Climbs steadily when the
+    /// server has stopped answering
+    pub oldest_unacknowledged: Option<Duration>,
+}
+
+/// Counts traffic and enforces the upload cap with a token bucket
+struct Bandwidth {
+    stats: BandwidthStats,
+    /// Bytes per second
+    limit: Option<u32>,
+    /// Bytes that can be sent without waiting, up to a second's worth. Goes negative when a
+    /// packet is sent on credit
+    allowance: f64,
+    last_refill: Instant,
+}
+
+impl Bandwidth {
+    fn new(now: Instant) -> Self {
+        Self {
+            stats: BandwidthStats::default(),
+            limit: None,
+            allowance: 0.,
+            last_refill: now,
+        }
+    }
+
+    /// Takes `bytes` from the allowance, returning how long to wait before sending them
+    fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        let limit = match self.limit {
+            Some(limit) if limit > 0 => limit as f64,
+            _ => return Duration::from_secs(0),
+        };
+        self.allowance = (self.allowance + elapsed * limit).min(limit);
+        self.allowance -= bytes as f64;
+        if self.allowance >= 0. {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.allowance / limit)
+        }
+    }
+
+    fn sent(&mut self, bytes: usize, waited: Duration) {
+        self.stats.bytes_sent += bytes as u64;
+        self.stats.packets_sent += 1;
+        self.stats.throttled += waited;
+    }
+
+    fn received(&mut self, bytes: usize) {
+        self.stats.bytes_received += bytes as u64;
+        self.stats.packets_received += 1;
+    }
+}
+
+/// Counts a packet from `NetClient::send` as gone out. Acknowledgements are sent straight from
+/// the receive side, so they were never counted
+fn unqueue(queued: &AtomicUsize, packet: &HazelPacketOut) {
+    if !matches!(packet, HazelPacketOut::Acknowledge { .. }) {
+        queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Sends a datagram once the upload cap allows, counting and recording it
+fn send_limited(
+    wire: &Wire,
+    bandwidth: &RwLock<Bandwidth>,
+    clock: &dyn Clock,
+    recorder: &RwLock<Option<Recorder>>,
+    data: &[u8],
+) {
+    let wait = bandwidth.write().unwrap().reserve(data.len(), clock.now());
+    if wait > Duration::from_secs(0) {
+        std::thread::sleep(wait);
+    }
+    // Lost like any other datagram, reliable ones get resent
+    if let Err(error) = wire.send(data) {
+        warn!("Send failed: {}", error);
+        return;
+    }
+    bandwidth.write().unwrap().sent(data.len(), wait);
+    if let Some(recorder) = &*recorder.read().unwrap() {
+        recorder.record(Direction::ToServer, data);
+    }
+}
+
+/// Received packets waiting for the client, limited by an `InboundLimit` so a slow reader can't
+/// make them pile up forever
+struct PacketQueue {
+    state: Mutex<QueueState>,
+    /// Signalled when a packet is added or the queue closes
+    added: Condvar,
+    /// Signalled when a packet is taken or the queue closes
+    taken: Condvar,
+}
+
+struct QueueState {
+    packets: VecDeque<HazelPacket>,
+    limit: InboundLimit,
+    dropped: u64,
+    /// Set once either end has gone
+    closed: bool,
+}
+
+impl PacketQueue {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState {
+                packets: VecDeque::new(),
+                limit: InboundLimit::default(),
+                dropped: 0,
+                closed: false,
+            }),
+            added: Condvar::new(),
+            taken: Condvar::new(),
+        })
+    }
+
+    /// Adds a packet, making room as the limit says. Reliable packets wait for room if `wait` and
+    /// go over the limit otherwise. Returns `false` once the queue is closed
+    fn push(&self, packet: HazelPacket, wait: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        if state.packets.len() >= state.limit.capacity {
+            if let HazelPacket::Unreliable { .. } = packet {
+                let oldest = match state.limit.overflow {
+                    Overflow::DropNewest => None,
+                    Overflow::DropOldest => state
+                        .packets
+                        .iter()
+                        .position(|packet| matches!(packet, HazelPacket::Unreliable { .. })),
+                };
+                state.dropped += 1;
+                debug!("Inbound queue full, dropping an unreliable packet");
+                match oldest {
+                    Some(index) => {
+                        state.packets.remove(index);
+                    }
+                    // Nothing older to drop, so it's this one
+                    None => return true,
+                }
+            } else if wait {
+                while state.packets.len() >= state.limit.capacity && !state.closed {
+                    state = self.taken.wait(state).unwrap();
+                }
+                if state.closed {
+                    return false;
+                }
+            }
+        }
+        state.packets.push_back(packet);
+        self.added.notify_one();
+        true
+    }
+
+    /// Takes the oldest packet, waiting up to `timeout` for one, or forever if `None`. Fails once
+    /// the queue is empty and closed
+    fn pop(&self, timeout: Option<Duration>) -> std::result::Result<HazelPacket, RecvTimeoutError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(packet) = state.packets.pop_front() {
+                self.taken.notify_one();
+                return Ok(packet);
+            }
+            if state.closed {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.added.wait_timeout(state, deadline - now).unwrap().0
+                }
+                None => self.added.wait(state).unwrap(),
+            };
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.added.notify_all();
+        self.taken.notify_all();
+    }
+}
+
+/// The receive thread's end of a `PacketQueue`, closing it when the thread stops so the client
+/// sees the connection is gone
+struct QueueWriter(Arc<PacketQueue>);
+
+impl Drop for QueueWriter {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Turns received datagrams into packets for the client, acknowledging, reassembling and ordering
+/// them on the way
+struct Inbound {
+    reassembler: Reassembler,
+    orderer: Orderer,
+}
+
+impl Inbound {
+    fn new() -> Self {
+        Self {
+            reassembler: Reassembler::new(),
+            orderer: Orderer::new(),
+        }
+    }
+
+    /// Handles a datagram, returning any packets now ready for the client
+    fn receive(
+        &mut self,
+        datagram: Vec<u8>,
+        ack_handler: &RwLock<AckHandler>,
+        packet_out_send: &Sender<HazelPacketOut>,
+        ordered: bool,
+        now: Instant,
+    ) -> Vec<HazelPacket> {
+        // Read packet
+        let mut r = datagram.into_reader();
+        let packet = r.read::<HazelPacket>();
+        if let Err(packet_error) = packet {
+            error!("Error reading hazel packet {}", packet_error);
+            return Vec::new();
+        }
+        let mut packet = packet.unwrap();
+        // How many ack ids the packet covers, more than one for reassembled fragments
+        let mut span = 1;
+
+        // Handle packet
+        match packet {
+            HazelPacket::Unreliable { .. } => (),
+            HazelPacket::Reliable { ack_id, .. } => {
+                packet_out_send
+                    .send(HazelPacketOut::Acknowledge { ack_id })
+                    .unwrap();
+            }
+            HazelPacket::Disconnect { .. } => (),
+            HazelPacket::Hello { ack_id, .. } => {
+                packet_out_send
+                    .send(HazelPacketOut::Acknowledge { ack_id })
+                    .unwrap();
+            }
+            HazelPacket::Acknowledge { ack_id } => {
+                let mut ack_handler = ack_handler.write().unwrap();
+                ack_handler.unconfirmed.remove(&ack_id);
+                if ack_handler.hello_id == Some(ack_id) {
+                    ack_handler.hello_acknowledged = true;
+                }
+                if let Some(probe) = &mut ack_handler.probe {
+                    if probe.sizes.contains_key(&ack_id) {
+                        probe.acknowledged.push(ack_id);
+                    }
+                }
+            }
+            HazelPacket::KeepAlive { ack_id } => {
+                packet_out_send
+                    .send(HazelPacketOut::Acknowledge { ack_id })
+                    .unwrap();
+            }
+            HazelPacket::Fragment {
+                ack_id,
+                fragment_id,
+                index,
+                count,
+                ref mut data,
+            } => {
+                packet_out_send
+                    .send(HazelPacketOut::Acknowledge { ack_id })
+                    .unwrap();
+                self.reassembler.prune(FRAGMENT_TIMEOUT, now);
+                let data = std::mem::take(data);
+                match self.reassembler.add(fragment_id, index, count, data, now) {
+                    // Passed on as if it arrived in one piece, under the first fragment's ack id
+                    // as they're numbered in order
+                    Some(data) => {
+                        let ack_id = ack_id.wrapping_sub(index);
+                        packet = HazelPacket::Reliable { ack_id, data };
+                        span = count;
+                    }
+                    None => return Vec::new(),
+                }
+            }
+        }
+
+        if !ordered {
+            // Pending packets only need releasing if ordering was just turned off
+            let mut ready = self.orderer.flush();
+            ready.push(packet);
+            return ready;
+        }
+        let mut ready = match packet {
+            HazelPacket::Reliable { ack_id, .. } => {
+                self.orderer.push(ack_id, span, Some(packet), now)
+            }
+            // Keep alives use up an ack id too, so nothing should wait for a packet in its place
+            HazelPacket::KeepAlive { ack_id } => {
+                let mut ready = self.orderer.push(ack_id, 1, None, now);
+                ready.push(packet);
+                ready
+            }
+            packet => vec![packet],
+        };
+        // Steady traffic keeps the receive thread from going idle, so gaps are given up on here
+        // too
+        ready.extend(self.orderer.expired(ORDER_TIMEOUT, now));
+        ready
+    }
+
+    /// Called when nothing's been received for a while. Gives up on gaps that aren't getting
+    /// filled
+    fn idle(&mut self, ordered: bool, now: Instant) -> Vec<HazelPacket> {
+        if ordered {
+            self.orderer.expired(ORDER_TIMEOUT, now)
+        } else {
+            self.orderer.flush()
+        }
+    }
+}
+
+/// Holds back reliable packets that arrive ahead of earlier ones, releasing them in ack id order
+struct Orderer {
+    /// The ack id expected next. Hazel numbers from 1
+    next: u16,
+    /// Packets waiting on an earlier one, with how many ack ids each covers and when it arrived.
+    /// `None` for a keep alive, which only fills its ack id
+    pending: HashMap<u16, (u16, Instant, Option<HazelPacket>)>,
+}
+
+impl Orderer {
+    fn new() -> Self {
+        Self {
+            next: 1,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Adds a packet, returning any that are now in order
+    fn push(
+        &mut self,
+        ack_id: u16,
+        span: u16,
+        packet: Option<HazelPacket>,
+        now: Instant,
+    ) -> Vec<HazelPacket> {
+        // Anything behind `next` was already delivered and is a resend
+        if (ack_id.wrapping_sub(self.next) as i16) < 0 {
+            debug!("Dropping repeated reliable packet {}", ack_id);
+            return Vec::new();
+        }
+        self.pending.entry(ack_id).or_insert((span, now, packet));
+        self.ready()
+    }
+
+    /// Removes packets from the front of `pending` while there are no gaps
+    fn ready(&mut self) -> Vec<HazelPacket> {
+        let mut ready = Vec::new();
+        while let Some((span, _, packet)) = self.pending.remove(&self.next) {
+            self.next = self.next.wrapping_add(span);
+            ready.extend(packet);
+        }
+        ready
+    }
+
+    /// Skips over missing packets if anything after them has waited longer than `timeout`
+    fn expired(&mut self, timeout: Duration, now: Instant) -> Vec<HazelPacket> {
+        let mut ready = Vec::new();
+        while self
+            .pending
+            .values()
+            .any(|(_, arrived, _)| now.duration_since(*arrived) >= timeout)
+        {
+            // Jump to the earliest pending packet
+            let next = self.next;
+            let earliest = *self
+                .pending
+                .keys()
+                .min_by_key(|&&ack_id| ack_id.wrapping_sub(next))
+                .unwrap();
+            debug!(
+                "Gave up waiting for reliable packets {}..{}",
+                next, earliest
+            );
+            self.next = earliest;
+            ready.extend(self.ready());
+        }
+        ready
+    }
+
+    /// Everything pending in order, gaps or not. For when ordering is turned off
+    fn flush(&mut self) -> Vec<HazelPacket> {
+        let mut ready = Vec::new();
+        while !self.pending.is_empty() {
+            let next = self.next;
+            self.next = *self
+                .pending
+                .keys()
+                .min_by_key(|&&ack_id| ack_id.wrapping_sub(next))
+                .unwrap();
+            ready.extend(self.ready());
+        }
+        ready
+    }
+}
+
+/// How urgently an outgoing packet should be sent. Higher goes first
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Priority {
+    /// Unreliable packets, mostly movement updates that are replaced by the next one anyway
+    Movement = 0,
+    /// Reliable game messages like chat and votes
+    Game = 1,
+    /// Hazel level packets: hellos, acks, keep alives and disconnects
+    Control = 2,
+}
+
+impl Priority {
+    fn of(packet: &HazelPacketOut) -> Self {
+        match packet {
+            HazelPacketOut::Unreliable { .. } => Priority::Movement,
+            HazelPacketOut::Reliable { .. } | HazelPacketOut::Fragment { .. } => Priority::Game,
+            HazelPacketOut::Disconnect { .. }
+            | HazelPacketOut::Hello { .. }
+            | HazelPacketOut::Acknowledge { .. }
+            | HazelPacketOut::KeepAlive { .. } => Priority::Control,
+        }
+    }
+}
+
+/// Outgoing packets waiting to be sent, highest `Priority` first. Packets of the same priority
+/// keep the order they were queued in
+#[derive(Debug, Default)]
+struct SendQueue {
+    /// Indexed by `Priority`
+    queues: [VecDeque<HazelPacketOut>; 3],
+}
+
+impl SendQueue {
+    fn push(&mut self, packet: HazelPacketOut) {
+        self.queues[Priority::of(&packet) as usize].push_back(packet);
+    }
+
+    fn pop(&mut self) -> Option<HazelPacketOut> {
+        self.queues
+            .iter_mut()
+            .rev()
+            .find_map(|queue| queue.pop_front())
     }
 }
 
 /// Helper struct mainly for thread sync
 struct AckHandler {
     ack_index: u16,
-    unconfirmed: HashMap<u16, (Instant, Vec<u8>)>,
+    fragment_index: u16,
+    /// The last hello sent, and whether it's been acknowledged
+    hello_id: Option<u16>,
+    hello_acknowledged: bool,
+    probe: Option<MtuProbe>,
+    unconfirmed: HashMap<u16, Unconfirmed>,
+}
+
+/// An MTU probe started by `NetClient::start_mtu_probe`
+struct MtuProbe {
+    timeout: Duration,
+    /// Datagram size for each probe's ack id. Empty until the hello's been acknowledged
+    sizes: HashMap<u16, usize>,
+    acknowledged: Vec<u16>,
+    /// When to stop waiting, once the probes have been queued
+    deadline: Option<Instant>,
+}
+
+/// How an MTU probe went. See `NetClient::poll_mtu_probe`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MtuProbeResult {
+    /// The largest probe acknowledged, now `max_packet_size`
+    Probed(usize),
+    /// None were, so `max_packet_size` is unchanged
+    Unanswered,
+}
+
+/// A reliable packet waiting to be acknowledged
+struct Unconfirmed {
+    first_sent: Instant,
+    last_sent: Instant,
+    resends: u32,
+    data: Vec<u8>,
 }
 
 impl AckHandler {
+    fn new() -> Self {
+        Self {
+            ack_index: 1,
+            fragment_index: 0,
+            hello_id: None,
+            hello_acknowledged: false,
+            probe: None,
+            unconfirmed: HashMap::new(),
+        }
+    }
+
+    /// The bytes for `packet`, padded out to its size if it's an MTU probe
+    fn serialize(&self, packet: &HazelPacketOut) -> Vec<u8> {
+        let mut bytes = packet.serialize_bytes();
+        if let HazelPacketOut::KeepAlive { ack_id } = packet {
+            if let Some(&size) = self.probe_size(*ack_id) {
+                bytes.resize(size, 0);
+            }
+        }
+        bytes
+    }
+
+    fn probe_size(&self, ack_id: u16) -> Option<&usize> {
+        self.probe.as_ref()?.sizes.get(&ack_id)
+    }
+
+    /// Remembers a sent packet until it's acknowledged, if it needs to be. MTU probes aren't
+    /// resent, as a lost one is the answer
+    fn sent(&mut self, packet: &HazelPacketOut, packet_bytes: &[u8], now: Instant) {
+        match packet {
+            HazelPacketOut::Unreliable { .. }
+            | HazelPacketOut::Disconnect { .. }
+            | HazelPacketOut::Acknowledge { .. } => (),
+            HazelPacketOut::KeepAlive { ack_id } if self.probe_size(*ack_id).is_some() => (),
+            HazelPacketOut::Reliable { ack_id, .. }
+            | HazelPacketOut::Hello { ack_id, .. }
+            | HazelPacketOut::KeepAlive { ack_id }
+            | HazelPacketOut::Fragment { ack_id, .. } => {
+                // Ids wrap around in long sessions. One still waiting from last time round is
+                // long overdue, so it's given up on
+                let stale = self.unconfirmed.insert(
+                    *ack_id,
+                    Unconfirmed {
+                        first_sent: now,
+                        last_sent: now,
+                        resends: 0,
+                        data: packet_bytes.to_vec(),
+                    },
+                );
+                if let Some(stale) = stale {
+                    warn!(
+                        "Ack id {} reused while unconfirmed for {:?}, giving up on the old packet",
+                        ack_id,
+                        now.duration_since(stale.first_sent)
+                    );
+                }
+                if self.unconfirmed.len() > MAX_UNCONFIRMED {
+                    self.evict_oldest();
+                }
+            }
+        }
+    }
+
+    /// Gives up on the packet that's been waiting longest, so the ids in use never span more
+    /// than `MAX_UNCONFIRMED` and a wrapped id can't be confused with a recent one
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .unconfirmed
+            .iter()
+            .min_by_key(|(_, packet)| packet.first_sent)
+            .map(|(&ack_id, _)| ack_id);
+        if let Some(ack_id) = oldest {
+            warn!(
+                "Over {} packets unconfirmed, giving up on {}",
+                MAX_UNCONFIRMED, ack_id
+            );
+            self.unconfirmed.remove(&ack_id);
+        }
+    }
+
+    /// The unconfirmed packets that are due to be sent again, oldest first so manual clients
+    /// always give them in the same order. They're kept until acknowledged or they've been sent
+    /// `MAX_RESENDS` times
+    fn due_resends(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        self.unconfirmed.retain(|ack_id, packet| {
+            let given_up = packet.resends >= MAX_RESENDS
+                && now.duration_since(packet.last_sent) >= RESEND_INTERVAL;
+            if given_up {
+                warn!("Packet {} never acknowledged, giving up", ack_id);
+            }
+            !given_up
+        });
+        let mut due = self
+            .unconfirmed
+            .iter_mut()
+            .filter(|(_, packet)| now.duration_since(packet.last_sent) >= RESEND_INTERVAL)
+            .collect::<Vec<_>>();
+        due.sort_by_key(|(&ack_id, packet)| (packet.first_sent, ack_id));
+        due.into_iter()
+            .map(|(_, packet)| {
+                packet.last_sent = now;
+                packet.resends += 1;
+                packet.data.clone()
+            })
+            .collect()
+    }
+
+    /// How long the oldest unconfirmed packet has been waiting, if there are any
+    fn oldest_unconfirmed(&self, now: Instant) -> Option<Duration> {
+        self.unconfirmed
+            .values()
+            .map(|packet| now.duration_since(packet.first_sent))
+            .max()
+    }
+
     fn get_next_index(&mut self) -> u16 {
         let value = self.ack_index;
         self.ack_index = self.ack_index.wrapping_add(1);
         value
     }
+
+    fn get_next_fragment_id(&mut self) -> u16 {
+        let value = self.fragment_index;
+        self.fragment_index = self.fragment_index.wrapping_add(1);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_target_from_str() {
+        assert_eq!("EU".parse(), Ok(ServerTarget::Main(MainServer::Europe)));
+        assert_eq!("asia".parse(), Ok(ServerTarget::Main(MainServer::Asia)));
+        assert_eq!(
+            "127.0.0.1:22023".parse(),
+            Ok(ServerTarget::Custom {
+                host: "127.0.0.1".to_string(),
+                port: 22023
+            })
+        );
+        let target: ServerTarget = "impostor.example.com".parse().unwrap();
+        assert_eq!(
+            target,
+            ServerTarget::Custom {
+                host: "impostor.example.com".to_string(),
+                port: DEFAULT_PORT
+            }
+        );
+        assert_eq!(target.to_string(), "impostor.example.com:22023");
+        assert!("impostor.example.com:port".parse::<ServerTarget>().is_err());
+        assert!(":22023".parse::<ServerTarget>().is_err());
+        assert!("127.0.0.1:22023".parse::<MainServer>().is_err());
+    }
+
+    #[test]
+    fn test_server_target_resolve() {
+        let europe = ServerTarget::Main(MainServer::Europe);
+        assert_eq!(europe.resolve().unwrap(), MainServer::Europe.to_addr());
+        assert!(!europe.is_custom());
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], 22023));
+        let custom = ServerTarget::from(addr);
+        assert_eq!(custom.resolve().unwrap(), addr);
+        assert!(custom.is_custom());
+
+        let v6 = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 22023));
+        let error = first_ipv4("localhost", vec![v6].into_iter()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert_eq!(
+            first_ipv4("localhost", vec![v6, addr].into_iter()).unwrap(),
+            addr
+        );
+    }
+
+    #[test]
+    fn test_mtu_probe() {
+        let clock = Arc::new(crate::clock::ManualClock::new());
+        let mut client = NetClient::manual(clock.clone());
+        let ack = |ack_id: u16| HazelPacketOut::Acknowledge { ack_id }.serialize_bytes();
+
+        // Nothing goes out until the hello's acknowledged
+        client.start_mtu_probe(Duration::from_secs(2));
+        let hello_id = client.send_hello(Box::new(Data::Bytes(Vec::new())));
+        assert_eq!(client.poll_mtu_probe(), None);
+        assert!(client.poll_outbound().is_some());
+        assert_eq!(client.poll_outbound(), None);
+        client.feed_datagram(&ack(hello_id));
+        assert_eq!(client.poll_mtu_probe(), None);
+
+        let probes = std::iter::from_fn(|| client.poll_outbound()).collect::<Vec<_>>();
+        let sizes = probes.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, PROBE_SIZES);
+
+        // Only the third largest gets through, so it's used once the time's up
+        client.feed_datagram(&ack(u16::from_be_bytes([probes[2][1], probes[2][2]])));
+        assert_eq!(client.poll_mtu_probe(), None);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(client.poll_mtu_probe(), Some(MtuProbeResult::Probed(1280)));
+        assert_eq!(client.max_packet_size(), 1280);
+        assert_eq!(client.poll_mtu_probe(), None);
+
+        // Lost probes aren't resent
+        clock.advance(RESEND_INTERVAL);
+        assert_eq!(client.poll_outbound(), None);
+    }
+
+    #[test]
+    fn test_resend_cap() {
+        let mut ack_handler = AckHandler::new();
+        let start = Instant::now();
+        let packet = HazelPacketOut::Reliable {
+            ack_id: 7,
+            data: Box::new(Data::Bytes(vec![1, 2, 3])),
+        };
+        let bytes = packet.serialize_bytes();
+        ack_handler.sent(&packet, &bytes, start);
+        assert!(ack_handler.due_resends(start).is_empty());
+
+        for resend in 1..=MAX_RESENDS {
+            let now = start + RESEND_INTERVAL * resend;
+            assert_eq!(ack_handler.due_resends(now), vec![bytes.clone()]);
+            assert!(ack_handler.due_resends(now).is_empty());
+        }
+        let now = start + RESEND_INTERVAL * (MAX_RESENDS + 1);
+        assert!(ack_handler.due_resends(now).is_empty());
+        assert!(ack_handler.unconfirmed.is_empty());
+    }
+
+    #[test]
+    fn test_ack_id_wrap() {
+        let mut ack_handler = AckHandler::new();
+        let start = Instant::now();
+        let send = |ack_handler: &mut AckHandler, now: Instant| {
+            let ack_id = ack_handler.get_next_index();
+            let packet = HazelPacketOut::Reliable {
+                ack_id,
+                data: Box::new(Data::Bytes(vec![ack_id as u8])),
+            };
+            ack_handler.sent(&packet, &packet.serialize_bytes(), now);
+            ack_id
+        };
+
+        // One never gets acknowledged while the rest are, until the counter comes back round
+        let stale = send(&mut ack_handler, start);
+        let later = start + Duration::from_secs(1);
+        for _ in 0..u16::MAX {
+            let ack_id = send(&mut ack_handler, later);
+            ack_handler.unconfirmed.remove(&ack_id);
+        }
+        assert_eq!(ack_handler.unconfirmed.len(), 1);
+        assert_eq!(send(&mut ack_handler, later), stale);
+        assert_eq!(ack_handler.unconfirmed.len(), 1);
+        assert_eq!(ack_handler.unconfirmed[&stale].first_sent, later);
+
+        // Past the limit the oldest is given up on, so ids in use stay within half the space
+        for _ in 0..MAX_UNCONFIRMED {
+            send(&mut ack_handler, later + Duration::from_secs(1));
+        }
+        assert_eq!(ack_handler.unconfirmed.len(), MAX_UNCONFIRMED);
+        assert!(!ack_handler.unconfirmed.contains_key(&stale));
+    }
+
+    #[test]
+    fn test_ordered_keep_alives_and_expiry() {
+        let ack_handler = RwLock::new(AckHandler::new());
+        let (packet_out_send, _packet_out_recv) = channel();
+        let mut inbound = Inbound::new();
+        let start = Instant::now();
+        let mut receive = |packet: HazelPacketOut, now: Instant| {
+            inbound.receive(
+                packet.serialize_bytes(),
+                &ack_handler,
+                &packet_out_send,
+                true,
+                now,
+            )
+        };
+        let reliable = |ack_id: u16| HazelPacketOut::Reliable {
+            ack_id,
+            data: Box::new(Data::Bytes(vec![ack_id as u8])),
+        };
+        let ack_ids = |packets: Vec<HazelPacket>| {
+            packets
+                .into_iter()
+                .map(|packet| match packet {
+                    HazelPacket::Reliable { ack_id, .. } | HazelPacket::KeepAlive { ack_id } => {
+                        ack_id
+                    }
+                    packet => panic!("Unexpected packet {:?}", packet),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ack_ids(receive(reliable(1), start)), [1]);
+        assert_eq!(ack_ids(receive(reliable(3), start)), [0; 0]);
+        // 2 was a keep alive, so there's nothing missing. Keep alives are still passed on
+        let keep_alive = HazelPacketOut::KeepAlive { ack_id: 2 };
+        assert_eq!(ack_ids(receive(keep_alive, start)), [3, 2]);
+
+        // 4 never arrives, and traffic alone is enough to stop waiting for it
+        assert_eq!(ack_ids(receive(reliable(5), start)), [0; 0]);
+        assert_eq!(ack_ids(receive(reliable(6), start + ORDER_TIMEOUT)), [5, 6]);
+    }
 }
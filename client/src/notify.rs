@@ -0,0 +1,179 @@
+use clap::Args;
+use client::{Client, ClientSettings, EventHandler, ScanSettings, ServerTarget};
+use common::data::{DisconnectReason, GameListing};
+use notify_rust::Notification;
+
+/// What to send desktop notifications for
+#[derive(Debug, Default, Args)]
+pub struct Watches {
+    /// Host names to look for in scans. Compared case insensitively
+    #[arg(long = "host", value_name = "NAME")]
+    host_names: Vec<String>,
+
+    /// Words to look for in chat. Compared case insensitively
+    #[arg(long = "keyword", value_name = "WORD")]
+    keywords: Vec<String>,
+
+    /// Lobby to join to watch chat and player count
+    #[arg(long = "lobby")]
+    game_code: Option<String>,
+
+    /// Join the first game a watched host is found hosting and watch it like a --lobby
+    #[arg(long, conflicts_with = "game_code", requires = "host_names")]
+    join: bool,
+}
+
+/// Shows a desktop notification, logging to stderr if that fails
+pub fn show(summary: &str, body: &str) {
+    println!("{}: {}", summary, body);
+    if let Err(error) = Notification::new()
+        .appname("Among Us Client")
+        .summary(summary)
+        .body(body)
+        .show()
+    {
+        eprintln!("Couldn't show notification: {}", error);
+    }
+}
+
+pub fn run(server: ServerTarget, mut watches: Watches) {
+    if watches.host_names.is_empty() && watches.game_code.is_none() {
+        println!("Nothing to watch. Give at least one --host or a --lobby");
+        return;
+    }
+    for name in watches
+        .host_names
+        .iter_mut()
+        .chain(watches.keywords.iter_mut())
+    {
+        *name = name.to_lowercase();
+    }
+
+    let handler = LobbyWatchHandler {
+        keywords: watches.keywords,
+        notified_full: false,
+    };
+    let settings = ClientSettings {
+        connect_username: "watcher".to_string(),
+        game_username: "watcher".to_string(),
+        ..ClientSettings::default()
+    };
+
+    if watches.join {
+        if let Some(listing) = watch_hosts(server.clone(), watches.host_names, true) {
+            println!("Joining {}", listing.id);
+            if let Err(error) = Client::run_game(handler, listing, settings) {
+                log::error!("{}", error);
+            }
+        }
+        return;
+    }
+
+    let scan_thread = if watches.host_names.is_empty() {
+        None
+    } else {
+        let host_names = std::mem::take(&mut watches.host_names);
+        let server = server.clone();
+        Some(
+            std::thread::Builder::new()
+                .name("notify-hosts".to_string())
+                .spawn(move || {
+                    watch_hosts(server, host_names, false);
+                })
+                .unwrap(),
+        )
+    };
+
+    if let Some(game_code) = watches.game_code {
+        if let Err(error) = Client::run_game_code(handler, server, &game_code, settings) {
+            log::error!("{}", error);
+        }
+    }
+
+    if let Some(scan_thread) = scan_thread {
+        scan_thread.join().unwrap();
+    }
+}
+
+/// Scans, notifying once per game hosted by a watched name. Runs forever unless `stop_on_found`,
+/// in which case the first game found is returned
+fn watch_hosts(
+    server: ServerTarget,
+    host_names: Vec<String>,
+    stop_on_found: bool,
+) -> Option<GameListing> {
+    let mut seen = Vec::new();
+    let mut found = None;
+    let settings = ScanSettings {
+        server,
+        connect_username: "watcher".to_string(),
+        ..ScanSettings::default()
+    };
+    let callback = |listings: Vec<GameListing>| {
+        for listing in listings {
+            let host = listing.host_username.to_lowercase();
+            if host_names.contains(&host) && !seen.contains(&listing.id) {
+                seen.push(listing.id);
+                show(
+                    "Host found",
+                    &format!(
+                        "{} is hosting {} ({}/{})",
+                        listing.host_username,
+                        listing.id,
+                        listing.player_count,
+                        listing.max_players
+                    ),
+                );
+                if stop_on_found {
+                    found = Some(listing);
+                    return false;
+                }
+            }
+        }
+        true
+    };
+    if let Err(error) = Client::server_scan(settings, callback) {
+        log::error!("Scan failed: {}", error);
+    }
+    found
+}
+
+struct LobbyWatchHandler {
+    keywords: Vec<String>,
+    notified_full: bool,
+}
+
+impl EventHandler for LobbyWatchHandler {
+    fn disconnect_reason(&mut self, client: &mut Client, reason: DisconnectReason) {
+        show("Disconnected", &format!("{:?}", reason));
+        client.disconnect();
+    }
+
+    fn packet_received(&mut self, client: &mut Client) {
+        let max_players = match &client.game_options {
+            Some(options) => options.max_players as usize,
+            None => return,
+        };
+        let is_full = client.player_ids.len() >= max_players;
+        if is_full && !self.notified_full {
+            show(
+                "Lobby full",
+                &format!("{}/{} players", client.player_ids.len(), max_players),
+            );
+        }
+        self.notified_full = is_full;
+    }
+
+    fn chat_message(
+        &mut self,
+        _client: &mut Client,
+        _player_id: i32,
+        name: String,
+        message: String,
+    ) {
+        let lower = message.to_lowercase();
+        if self.keywords.iter().any(|keyword| lower.contains(keyword)) {
+            show("Chat mention", &format!("{}: {}", name, message));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use common::capture::{CaptureFormat, CaptureWriter, Direction};
+use log::error;
+
+/// Writes every datagram sent and received to a capture, which `CaptureFormat::reader` and
+/// `Simulation::replay` can read. See `ClientSettings::recorder`
+///
+/// Times are taken from when the recorder was made, on the system clock rather than the client's
+///
+/// Cheap to clone. Clones write to the same capture, so one can be shared by the connections a
+/// client makes while redirecting
+#[derive(Clone)]
+pub struct Recorder {
+    writer: Arc<Mutex<CaptureWriter<Box<dyn Write + Send>>>>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Records to a new file at `path`, replacing any that's there
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Records to `w`, writing the capture header straight away
+    pub fn new<W: Write + Send + 'static>(w: W) -> io::Result<Self> {
+        let writer = CaptureFormat::writer(Box::new(w) as Box<dyn Write + Send>)?;
+        Ok(Self {
+            writer: Arc::new(Mutex::new(writer)),
+            started: Instant::now(),
+        })
+    }
+
+    /// Writes out anything buffered. Also happens once the last clone is dropped
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+
+    /// Failures are logged rather than returned, so a full disk doesn't take down the connection
+    pub(crate) fn record(&self, direction: Direction, datagram: &[u8]) {
+        if let Err(error) =
+            self.writer
+                .lock()
+                .unwrap()
+                .write_packet(direction, self.started.elapsed(), datagram)
+        {
+            error!("Couldn't record datagram: {}", error);
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish()
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, ErrorKind},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use common::data::GameId;
+
+/// Which game server the main server last sent each game to, so joining it again by code can skip
+/// the main server. See `ClientSettings::redirect_cache`
+///
+/// Given a path it's kept in a file so it lasts between runs, with one `game_id address
+/// unix_seconds` line per game
+#[derive(Debug, Clone)]
+pub struct RedirectCache {
+    path: Option<PathBuf>,
+    /// How long a mapping is trusted for. Lobbies don't move but codes get reused once they end
+    ttl: Duration,
+    entries: HashMap<i32, (SocketAddr, SystemTime)>,
+}
+
+impl RedirectCache {
+    /// A cache that only lasts as long as it's kept around
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            path: None,
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Reads the cache in `path` if there is one. Changes are saved back to it
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration) -> io::Result<Self> {
+        let mut cache = Self {
+            path: Some(path.as_ref().to_path_buf()),
+            ..Self::new(ttl)
+        };
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(cache),
+            Err(error) => return Err(error),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let (id, addr, seconds) = match parts[..] {
+                [id, addr, seconds] => (id, addr, seconds),
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "Invalid cache line")),
+            };
+            let id = id
+                .parse()
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Invalid cache game id"))?;
+            let addr = addr
+                .parse()
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Invalid cache address"))?;
+            let seconds = seconds
+                .parse()
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Invalid cache time"))?;
+            let seen = UNIX_EPOCH + Duration::from_secs(seconds);
+            cache.entries.insert(id, (addr, seen));
+        }
+        Ok(cache)
+    }
+
+    /// Where `game_id` was joined, unless it's been longer than the ttl
+    pub fn get(&self, game_id: GameId) -> Option<SocketAddr> {
+        let (addr, seen) = self.entries.get(&game_id.id)?;
+        match seen.elapsed() {
+            Ok(age) if age > self.ttl => None,
+            _ => Some(*addr),
+        }
+    }
+
+    pub fn insert(&mut self, game_id: GameId, addr: SocketAddr) -> io::Result<()> {
+        self.entries.insert(game_id.id, (addr, SystemTime::now()));
+        self.save()
+    }
+
+    /// Forgets `game_id`, e.g. once the server says it's gone
+    pub fn remove(&mut self, game_id: GameId) -> io::Result<()> {
+        if self.entries.remove(&game_id.id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Writes fresh entries to the file, if there is one. Expired ones are dropped
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut contents = String::new();
+        for (&id, &(addr, seen)) in &self.entries {
+            if matches!(seen.elapsed(), Ok(age) if age > self.ttl) {
+                continue;
+            }
+            let seconds = seen
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            contents.push_str(&format!("{} {} {}\n", id, addr, seconds));
+        }
+        fs::write(path, contents)
+    }
+}
@@ -0,0 +1,308 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use common::data::Color;
+use resvg::{tiny_skia, usvg};
+
+use crate::{navigation::map_nav, GameState, PlayerState};
+
+/// Game units of empty space around everything drawn
+const MARGIN: f32 = 3.;
+
+const BACKGROUND: &str = "#1b1d22";
+const CORRIDOR: &str = "#3a3f4a";
+const TEXT: &str = "#f0f0f0";
+const IMPOSTOR_RING: &str = "#ff3030";
+
+/// Draws a `GameState` onto its map with everyone's positions and names, to SVG or PNG, for
+/// posting round summaries without running the GUI
+///
+/// Maps are drawn from their `NavGraph`, so rooms and corridors are rough. For a replayed
+/// capture, render `Simulation::client.state()` once `Simulation::replay` has finished, and for
+/// a saved game, `Timeline::state_at`
+#[derive(Debug, Clone)]
+pub struct ObserverRender {
+    /// Pixels per game unit
+    pub scale: f32,
+
+    /// Rings impostors in red. Only known as host or once the game is over
+    pub show_impostors: bool,
+
+    /// Draws dead players faded out rather than leaving them off
+    pub show_dead: bool,
+}
+
+impl Default for ObserverRender {
+    fn default() -> Self {
+        Self {
+            scale: 20.,
+            show_impostors: false,
+            show_dead: true,
+        }
+    }
+}
+
+impl ObserverRender {
+    pub fn svg(&self, state: &GameState) -> String {
+        let nav = state.map_id.and_then(map_nav);
+        let players = state
+            .players
+            .iter()
+            .filter(|player| !player.disconnected && (self.show_dead || !player.is_dead))
+            .filter_map(|player| Some((player, player.position?)))
+            .collect::<Vec<_>>();
+
+        // Fit the map and everyone on it
+        let points = nav
+            .iter()
+            .flat_map(|nav| nav.nodes.iter().copied())
+            .chain(
+                players
+                    .iter()
+                    .map(|(_, position)| (position.x(), position.y())),
+            )
+            .collect::<Vec<_>>();
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (0f32, 0f32, 0f32, 0f32);
+        if let Some(&(x, y)) = points.first() {
+            min_x = x;
+            max_x = x;
+            min_y = y;
+            max_y = y;
+        }
+        for &(x, y) in &points {
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+        }
+        min_x -= MARGIN;
+        min_y -= MARGIN;
+        max_x += MARGIN;
+        max_y += MARGIN;
+        let scale = self.scale;
+        // Up is positive in game and negative in SVG
+        let to_svg = |(x, y): (f32, f32)| ((x - min_x) * scale, (max_y - y) * scale);
+
+        let mut svg = String::new();
+        let (width, height) = ((max_x - min_x) * scale, (max_y - min_y) * scale);
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" font-family="sans-serif">"#,
+            w = width,
+            h = height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            BACKGROUND
+        );
+
+        // Corridors, then rooms over their ends
+        if let Some(nav) = nav {
+            for &(from, to) in nav.edges {
+                let (x1, y1) = to_svg(nav.nodes[from]);
+                let (x2, y2) = to_svg(nav.nodes[to]);
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{:.1}" stroke-linecap="round"/>"#,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    CORRIDOR,
+                    scale * 1.5
+                );
+            }
+            for &node in nav.nodes {
+                let (x, y) = to_svg(node);
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#,
+                    x,
+                    y,
+                    scale * 1.5,
+                    CORRIDOR
+                );
+            }
+        }
+
+        for (player, position) in &players {
+            self.draw_player(&mut svg, player, to_svg((position.x(), position.y())));
+        }
+
+        // What's being looked at, top left
+        let title = match state.game_id {
+            Some(game_id) if state.in_game => format!("{} in game", game_id),
+            Some(game_id) => format!("{} in lobby", game_id),
+            None => "Not in a game".to_string(),
+        };
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="{}">{}</text>"#,
+            scale * 0.5,
+            scale * 1.2,
+            scale * 0.8,
+            TEXT,
+            escape(&title)
+        );
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn draw_player(&self, svg: &mut String, player: &PlayerState, (x, y): (f32, f32)) {
+        let scale = self.scale;
+        let [r, g, b] = Color::ALL
+            .get(player.color as usize)
+            .map_or([0x80, 0x80, 0x80], |color| color.rgb());
+        let opacity = if player.is_dead { 0.35 } else { 1. };
+        let ring = if self.show_impostors && player.is_imposter {
+            IMPOSTOR_RING
+        } else {
+            TEXT
+        };
+        let _ = writeln!(
+            svg,
+            r#"<g opacity="{}"><circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="rgb({},{},{})" stroke="{}" stroke-width="{:.1}"/>"#,
+            opacity,
+            x,
+            y,
+            scale * 0.5,
+            r,
+            g,
+            b,
+            ring,
+            scale * 0.12
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="{}" text-anchor="middle">{}</text></g>"#,
+            x,
+            y + scale * 1.3,
+            scale * 0.6,
+            TEXT,
+            escape(&player.name)
+        );
+    }
+
+    /// Rasterizes what `svg` draws, with names in whatever sans-serif font the system has
+    pub fn png(&self, state: &GameState) -> io::Result<Vec<u8>> {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_str(&self.svg(state), &options).map_err(io::Error::other)?;
+        let size = tree.size().to_int_size();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+            .ok_or_else(|| io::Error::other("Render too large"))?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::identity(),
+            &mut pixmap.as_mut(),
+        );
+        pixmap.encode_png().map_err(io::Error::other)
+    }
+
+    /// Writes an SVG if `path` ends in `.svg`, otherwise a PNG
+    pub fn save<P: AsRef<Path>>(&self, state: &GameState, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let is_svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        if is_svg {
+            fs::write(path, self.svg(state))
+        } else {
+            fs::write(path, self.png(state)?)
+        }
+    }
+}
+
+/// Player names can have anything in them
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Not allowed in XML at all
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::data::{GameId, Vector2};
+
+    fn player(player_id: u8, name: &str, position: (f32, f32)) -> PlayerState {
+        PlayerState {
+            player_id,
+            client_id: Some(player_id as i32),
+            name: name.to_string(),
+            color: player_id,
+            position: Some(Vector2::new(position.0, position.1)),
+            is_dead: false,
+            is_imposter: false,
+            disconnected: false,
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape(r#"<b>&"'"#), "&lt;b&gt;&amp;&quot;&apos;");
+        assert_eq!(escape("a\u{0}\nb"), "ab");
+    }
+
+    #[test]
+    fn test_svg() {
+        let mut impostor = player(0, "<b>Red</b>", (0., 0.));
+        impostor.is_imposter = true;
+        let mut dead = player(1, "Blue", (2., 1.));
+        dead.is_dead = true;
+        let mut gone = player(2, "Gone", (1., 1.));
+        gone.disconnected = true;
+        let state = GameState {
+            game_id: Some(GameId::from_chars("ABCDEF")),
+            client_id: Some(0),
+            host_id: Some(0),
+            is_public: false,
+            map_id: None,
+            in_game: true,
+            players: vec![impostor, dead, gone],
+            sabotaged: Vec::new(),
+        };
+        let render = ObserverRender {
+            scale: 10.,
+            show_impostors: true,
+            show_dead: true,
+        };
+        // The disconnected player is left off and the dead one faded
+        let expected = concat!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="80" height="70" viewBox="0 0 80 70" font-family="sans-serif">"##,
+            "\n",
+            r##"<rect width="100%" height="100%" fill="#1b1d22"/>"##,
+            "\n",
+            r##"<g opacity="1"><circle cx="30.0" cy="40.0" r="5.0" fill="rgb(197,17,17)" stroke="#ff3030" stroke-width="1.2"/>"##,
+            "\n",
+            r##"<text x="30.0" y="53.0" font-size="6.0" fill="#f0f0f0" text-anchor="middle">&lt;b&gt;Red&lt;/b&gt;</text></g>"##,
+            "\n",
+            r##"<g opacity="0.35"><circle cx="50.0" cy="30.0" r="5.0" fill="rgb(19,46,209)" stroke="#f0f0f0" stroke-width="1.2"/>"##,
+            "\n",
+            r##"<text x="50.0" y="43.0" font-size="6.0" fill="#f0f0f0" text-anchor="middle">Blue</text></g>"##,
+            "\n",
+            r##"<text x="5.0" y="12.0" font-size="8.0" fill="#f0f0f0">ABCDEF in game</text>"##,
+            "\n</svg>\n",
+        );
+        assert_eq!(render.svg(&state), expected);
+
+        let render = ObserverRender {
+            show_dead: false,
+            ..render
+        };
+        assert!(!render.svg(&state).contains("Blue"));
+        assert!(render.png(&state).unwrap().starts_with(b"\x89PNG"));
+    }
+}
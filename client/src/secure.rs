@@ -0,0 +1,17 @@
+use std::{io, net::UdpSocket};
+
+/// Wraps a connection's datagrams, like the DTLS current official servers want. See
+/// `ClientSettings::secure_transport`, and `Dtls` with the `dtls` feature
+pub trait SecureTransport: Send + Sync {
+    /// Sets up a session over `socket`, which is already connected to the server
+    fn connect(&self, socket: UdpSocket) -> io::Result<Box<dyn SecureSession>>;
+}
+
+/// One connection's worth of a `SecureTransport`. Used from the send and receive threads at once
+pub trait SecureSession: Send + Sync {
+    fn send(&self, datagram: &[u8]) -> io::Result<()>;
+
+    /// Waits for the next datagram, giving `WouldBlock` or `TimedOut` if none comes for a while
+    /// so the receive thread can do its timers
+    fn recv(&self, buffer: &mut [u8]) -> io::Result<usize>;
+}
@@ -0,0 +1,402 @@
+use std::{
+    io::{self, Read},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use common::{
+    capture::{CaptureReader, Direction},
+    data::GameId,
+};
+
+use crate::{networking::NetClient, Client, ClientSettings, EventHandler, ManualClock};
+
+/// How `Simulation::replay` spaces out the datagrams in a capture
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReplayTiming {
+    /// One after another without waiting. The simulation's clock still moves as recorded, so
+    /// timers fire the same as they did
+    Immediate,
+    /// Waits for the recorded gaps divided by the factor, so 1 is as recorded and 2 is twice as
+    /// fast
+    Scaled(f32),
+}
+
+/// A `Client` with no socket and no threads, driven by hand for tests and fuzzing
+///
+/// Datagrams from the server go in through `feed_datagram` and datagrams for the server come out
+/// of `poll_outbound`. Time only passes on `advance`, so the same inputs always give the same
+/// outputs and events
+pub struct Simulation<H: EventHandler> {
+    pub client: Client,
+    pub handler: H,
+    clock: ManualClock,
+    settings: ClientSettings,
+    game_id: GameId,
+    /// Set once the client has disconnected, after which nothing more is handled
+    finished: bool,
+}
+
+impl<H: EventHandler> Simulation<H> {
+    /// Starts joining `game_id`. The hello and join request are waiting in `poll_outbound`
+    ///
+    /// `ClientSettings::clock` is replaced with a `ManualClock`, and `ClientSettings::seed` is 0 if
+    /// not set, so random choices repeat too
+    pub fn new(handler: H, game_id: GameId, mut settings: ClientSettings) -> Self {
+        let clock = ManualClock::new();
+        settings.clock = Arc::new(clock.clone());
+        settings.seed = settings.seed.or(Some(0));
+        let mut client = Client::new(NetClient::manual(settings.clock.clone()));
+        client.apply_settings(&mut settings);
+        client.start_session(&settings.connect_username, Some(game_id));
+        Self {
+            client,
+            handler,
+            clock,
+            settings,
+            game_id,
+            finished: false,
+        }
+    }
+
+    /// Handles a datagram as if it came from the server
+    pub fn feed_datagram(&mut self, datagram: &[u8]) {
+        self.client.client.feed_datagram(datagram);
+        self.process();
+    }
+
+    /// The next datagram the client would send, if any
+    pub fn poll_outbound(&mut self) -> Option<Vec<u8>> {
+        self.client.client.poll_outbound()
+    }
+
+    /// Moves time forward, running any resends and timers that become due
+    pub fn advance(&mut self, by: Duration) {
+        self.clock.advance(by);
+        self.client.client.poll_timers();
+        self.process();
+    }
+
+    /// Feeds every datagram the server sent in `capture` to the client, moving the clock along
+    /// with the recorded times. Stops early if the client disconnects. Returns how many datagrams
+    /// were fed
+    ///
+    /// What the client sends in reply is thrown away, as the server in the capture won't hear it
+    pub fn replay<R: Read>(
+        &mut self,
+        capture: CaptureReader<R>,
+        timing: ReplayTiming,
+    ) -> io::Result<usize> {
+        let mut last_offset = Duration::from_secs(0);
+        let mut fed = 0;
+        for packet in capture {
+            let packet = packet?;
+            let gap = packet.offset.checked_sub(last_offset).unwrap_or_default();
+            last_offset = last_offset.max(packet.offset);
+            if let ReplayTiming::Scaled(factor) = timing {
+                if factor > 0. {
+                    thread::sleep(gap.div_f32(factor));
+                }
+            }
+            self.advance(gap);
+            if packet.direction == Direction::ToClient {
+                self.feed_datagram(&packet.data);
+                fed += 1;
+            }
+            while self.poll_outbound().is_some() {}
+            if self.finished {
+                break;
+            }
+        }
+        Ok(fed)
+    }
+
+    /// The clock the client runs on
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Whether the client has disconnected
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Does what one pass of `Client::run_game_inner` would for each packet waiting
+    fn process(&mut self) {
+        while !self.finished {
+            if self.client.should_disconnect {
+                // Like `AsyncClient`, a reason waits for everything already sent to be
+                // acknowledged. Resends give up eventually, so this doesn't wait forever
+                if self.client.leave_reason.is_some() && !self.client.client.is_flushed() {
+                    break;
+                }
+                if let Some(reason) = self.client.leave_reason.take() {
+                    self.client.client.send_disconnect_reason(Some(&reason));
+                }
+                self.finished = true;
+                break;
+            }
+            self.client.tick();
+            let packet = match self.client.client.try_read_packet() {
+                Some(packet) => packet,
+                None => break,
+            };
+            match self.client.handle_hazel_packet(
+                &mut self.handler,
+                &self.settings,
+                Some(self.game_id),
+                packet,
+            ) {
+                Ok(true) => (),
+                Ok(false) => self.finished = true,
+                Err(error) => {
+                    self.handler.client_error(&mut self.client, error);
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::SocketAddr, sync::Mutex};
+
+    use common::{
+        capture::CaptureFormat,
+        data::{
+            read_disconnect_reason, Address, DisconnectReason, GameInfoBatch, GenericMessage,
+            HazelPacketOut, HazelType, PacketType,
+        },
+        reader::{Data, PacketWriter, Serialize},
+    };
+
+    use crate::{Client, Clock, Origin, RedirectCache};
+
+    #[derive(Default)]
+    struct Joined {
+        joined: bool,
+        chat: Vec<Option<Origin>>,
+    }
+
+    impl EventHandler for Joined {
+        fn joined_game(&mut self, _client: &mut Client) {
+            self.joined = true;
+        }
+
+        fn raw_chat_message(&mut self, client: &mut Client, _player_id: i32, _message: &str) {
+            self.chat.push(client.origin());
+        }
+    }
+
+    /// Joins a game as its host, answering like a server would, and returns everything the
+    /// client sent with how many steps of the clock in
+    fn join(seed: u64) -> (Simulation<Joined>, Vec<(usize, Vec<u8>)>) {
+        let game_id = GameId::from_chars("ABCDEF");
+        let settings = ClientSettings {
+            seed: Some(seed),
+            initial_info: crate::InitialInfo {
+                jitter: Duration::from_secs(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(Joined::default(), game_id, settings);
+        let hello = exchange(&mut simulation);
+        assert_eq!(hello[0][0], HazelType::Hello as u8);
+        assert_eq!(hello[1][0], HazelType::Reliable as u8);
+        let mut sent = hello
+            .into_iter()
+            .map(|datagram| (0, datagram))
+            .collect::<Vec<_>>();
+
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::JoinedGame as u8);
+        w.write(game_id);
+        w.write_i32(7);
+        w.write_i32(7);
+        w.write_u32_encoded(0);
+        w.end_message();
+        let joined = HazelPacketOut::Reliable {
+            ack_id: 1,
+            data: Box::new(Data::Bytes(w.finish())),
+        };
+        simulation.feed_datagram(&joined.serialize_bytes());
+
+        // Long enough for every bit of initial info, however it's jittered
+        for step in 1..=300 {
+            simulation.advance(Duration::from_millis(100));
+            sent.extend(
+                exchange(&mut simulation)
+                    .into_iter()
+                    .map(|datagram| (step, datagram)),
+            );
+        }
+        (simulation, sent)
+    }
+
+    /// Takes everything the client has to send, acknowledging what needs it
+    fn exchange(simulation: &mut Simulation<Joined>) -> Vec<Vec<u8>> {
+        let sent = std::iter::from_fn(|| simulation.poll_outbound()).collect::<Vec<_>>();
+        for datagram in &sent {
+            if datagram[0] == HazelType::Reliable as u8 || datagram[0] == HazelType::Hello as u8 {
+                let ack_id = u16::from_be_bytes([datagram[1], datagram[2]]);
+                let ack = HazelPacketOut::Acknowledge { ack_id };
+                simulation.feed_datagram(&ack.serialize_bytes());
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn test_simulated_join() {
+        let (simulation, sent) = join(1);
+        assert!(simulation.handler.joined);
+        assert!(!simulation.is_finished());
+        assert_eq!(simulation.client.client_id, Some(7));
+        assert!(simulation.client.is_host());
+
+        // The same seed gives the same traffic, and a different one moves the jitter
+        assert_eq!(join(1).1, sent);
+        assert_ne!(join(2).1, sent);
+    }
+
+    #[test]
+    fn test_echo_origin() {
+        let (mut simulation, _) = join(1);
+        let game_id = GameId::from_chars("ABCDEF");
+        simulation.client.send_chat("hi");
+        exchange(&mut simulation);
+
+        // The server sends it back, then the same again that we didn't send, which the host
+        // (us) could have
+        for ack_id in 2..=3 {
+            let control = simulation.client.net_objects.get_player_control(7).unwrap();
+            let batch = GameInfoBatch::new(game_id).push(control.rpc_chat_message("hi"));
+            let message = GenericMessage {
+                tag: batch.packet_type() as u8,
+                data: Box::new(batch.into_packet()),
+            };
+            let packet = HazelPacketOut::Reliable {
+                ack_id,
+                data: Box::new(message),
+            };
+            simulation.feed_datagram(&packet.serialize_bytes());
+        }
+        assert_eq!(simulation.handler.chat, [Some(Origin::Us), None]);
+    }
+
+    #[test]
+    fn test_disconnect_with_reason() {
+        let (mut simulation, _) = join(1);
+        simulation.client.send_chat("bye");
+        simulation
+            .client
+            .disconnect_with_reason(DisconnectReason::ExitGame);
+        simulation.advance(Duration::from_millis(0));
+        assert!(!simulation.is_finished());
+
+        // The reason only goes out once the chat is acknowledged
+        let sent = exchange(&mut simulation);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][0], HazelType::Reliable as u8);
+        assert!(simulation.is_finished());
+        let sent = exchange(&mut simulation);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][0], HazelType::Disconnect as u8);
+        assert_eq!(
+            read_disconnect_reason(&sent[0][1..]),
+            Some(DisconnectReason::ExitGame)
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let game_id = GameId::from_chars("ABCDEF");
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::JoinedGame as u8);
+        w.write(game_id);
+        w.write_i32(7);
+        w.write_i32(7);
+        w.write_u32_encoded(0);
+        w.end_message();
+        let joined = HazelPacketOut::Reliable {
+            ack_id: 1,
+            data: Box::new(Data::Bytes(w.finish())),
+        };
+        let keep_alive = HazelPacketOut::KeepAlive { ack_id: 2 };
+
+        let mut writer = CaptureFormat::writer(Vec::new()).unwrap();
+        writer
+            .write_packet(Direction::ToServer, Duration::from_secs(0), &[8, 0, 1])
+            .unwrap();
+        writer
+            .write_packet(
+                Direction::ToClient,
+                Duration::from_millis(50),
+                &joined.serialize_bytes(),
+            )
+            .unwrap();
+        // Out of order, which mustn't move the clock back
+        writer
+            .write_packet(
+                Direction::ToClient,
+                Duration::from_millis(20),
+                &keep_alive.serialize_bytes(),
+            )
+            .unwrap();
+        writer
+            .write_packet(
+                Direction::ToClient,
+                Duration::from_secs(2),
+                &keep_alive.serialize_bytes(),
+            )
+            .unwrap();
+        let bytes = writer.into_inner();
+
+        let mut simulation = Simulation::new(Joined::default(), game_id, Default::default());
+        let started = simulation.clock().now();
+        let capture = CaptureFormat::reader(&bytes[..]).unwrap();
+        let fed = simulation.replay(capture, ReplayTiming::Immediate).unwrap();
+        assert_eq!(fed, 3);
+        assert!(simulation.handler.joined);
+        assert!(!simulation.is_finished());
+        assert_eq!(simulation.client.client_id, Some(7));
+        assert_eq!(simulation.clock().now() - started, Duration::from_secs(2));
+        // Replies were thrown away
+        assert_eq!(simulation.poll_outbound(), None);
+    }
+
+    #[test]
+    fn test_redirect_cache() {
+        let game_id = GameId::from_chars("ABCDEF");
+        let cache = Arc::new(Mutex::new(RedirectCache::new(Duration::from_secs(60))));
+        let settings = ClientSettings {
+            redirect_cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(Joined::default(), game_id, settings);
+        exchange(&mut simulation);
+
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::ChangeServer as u8);
+        w.write(&Address {
+            ip: [10, 0, 0, 2],
+            port: 22023,
+        });
+        w.end_message();
+        let redirect = HazelPacketOut::Reliable {
+            ack_id: 1,
+            data: Box::new(Data::Bytes(w.finish())),
+        };
+        simulation.feed_datagram(&redirect.serialize_bytes());
+
+        // Says hello to the game server, and remembers it for the next run with these settings
+        let game_server = SocketAddr::from(([10, 0, 0, 2], 22023));
+        assert_eq!(simulation.client.client.addr(), game_server);
+        assert_eq!(exchange(&mut simulation)[0][0], HazelType::Hello as u8);
+        assert_eq!(cache.lock().unwrap().get(game_id), Some(game_server));
+    }
+}
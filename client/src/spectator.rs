@@ -0,0 +1,186 @@
+use std::{
+    io,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tungstenite::{Message, WebSocket};
+
+/// How often to send everyone's state
+const PLAYERS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a spectator can take to accept a message before it's dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many events can wait for the writer thread before new ones are dropped
+const QUEUE_SIZE: usize = 256;
+
+/// Something that happened in a hosted game, sent to spectators as JSON
+///
+/// Tagged with `"type"`, e.g. `{"type":"kill","victim_player_id":3}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorEvent {
+    /// Everyone's current state. Sent every `PLAYERS_INTERVAL`
+    Players {
+        players: Vec<SpectatorPlayer>,
+    },
+    GameStarted,
+    GameEnded {
+        reason: String,
+    },
+    Chat {
+        player_id: u8,
+        /// See `Client::player_name`
+        name: String,
+        message: String,
+    },
+    Kill {
+        victim_player_id: u8,
+    },
+    /// `body_player_id` is `None` if the emergency button was pressed
+    MeetingStarted {
+        reporter_player_id: u8,
+        body_player_id: Option<u8>,
+    },
+    VotingComplete {
+        exiled_player_id: Option<u8>,
+        tie: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpectatorPlayer {
+    pub player_id: u8,
+    pub name: String,
+    pub color: u8,
+    pub is_imposter: bool,
+    pub is_dead: bool,
+    pub disconnected: bool,
+    /// `None` until the player's first movement update
+    pub position: Option<(f32, f32)>,
+}
+
+/// What the writer thread is given to do
+enum Outgoing {
+    Spectator(Box<WebSocket<TcpStream>>),
+    Event(String),
+}
+
+/// Streams `SpectatorEvent`s to any number of WebSocket clients, e.g. stream overlays
+///
+/// Spectators only receive, anything they send is ignored. Writes happen on a thread of their
+/// own, so a slow spectator never holds up the game. One that doesn't keep up is dropped
+pub struct SpectatorFeed {
+    outgoing: SyncSender<Outgoing>,
+    connected: Arc<AtomicUsize>,
+    last_players: Option<Instant>,
+}
+
+impl SpectatorFeed {
+    /// Starts accepting spectators on `addr` in the background
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let (outgoing, outgoing_recv) = sync_channel(QUEUE_SIZE);
+        let connected = Arc::new(AtomicUsize::new(0));
+        let writer_connected = connected.clone();
+        std::thread::Builder::new()
+            .name("spectator-send".to_string())
+            .spawn(move || write_loop(outgoing_recv, &writer_connected))
+            .expect("Couldn't spawn thread");
+        let accept_outgoing = outgoing.clone();
+        std::thread::Builder::new()
+            .name("spectator-accept".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+                    if let Err(error) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+                        warn!("Couldn't set spectator write timeout: {}", error);
+                        continue;
+                    }
+                    match tungstenite::accept(stream) {
+                        Ok(socket) => {
+                            debug!("Spectator connected");
+                            // Waits for room, which is fine on this thread
+                            if accept_outgoing
+                                .send(Outgoing::Spectator(Box::new(socket)))
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                        Err(error) => warn!("Spectator handshake failed: {}", error),
+                    }
+                }
+            })
+            .expect("Couldn't spawn thread");
+        Ok(Self {
+            outgoing,
+            connected,
+            last_players: None,
+        })
+    }
+
+    /// Queues an event for every spectator. Dropped if the writer thread is too far behind
+    pub fn broadcast(&self, event: &SpectatorEvent) {
+        if self.is_empty() {
+            return;
+        }
+        let json = serde_json::to_string(event).unwrap();
+        match self.outgoing.try_send(Outgoing::Event(json)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => debug!("Spectator queue full, dropping event"),
+            Err(TrySendError::Disconnected(_)) => warn!("Spectator writer stopped"),
+        }
+    }
+
+    /// Whether it's time for another `SpectatorEvent::Players`. Assumes one will be sent if so
+    pub(crate) fn players_due(&mut self, now: Instant) -> bool {
+        let due = self
+            .last_players
+            .is_none_or(|last| now.duration_since(last) >= PLAYERS_INTERVAL);
+        if due {
+            self.last_players = Some(now);
+        }
+        due
+    }
+
+    /// The number of connected spectators
+    pub fn len(&self) -> usize {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Writes each event to every spectator, dropping any that have gone away or time out
+fn write_loop(outgoing: Receiver<Outgoing>, connected: &AtomicUsize) {
+    let mut sockets: Vec<WebSocket<TcpStream>> = Vec::new();
+    for message in outgoing {
+        match message {
+            Outgoing::Spectator(socket) => sockets.push(*socket),
+            Outgoing::Event(json) => {
+                sockets.retain_mut(|socket| match socket.send(Message::text(json.clone())) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        debug!("Spectator disconnected: {}", error);
+                        false
+                    }
+                });
+            }
+        }
+        connected.store(sockets.len(), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,55 @@
+use common::data::{GameId, SystemType, Vector2};
+
+/// Everything known about the game at one moment, gathered from the net objects. See
+/// `Client::state`
+#[derive(Debug, Clone)]
+pub struct GameState {
+    pub game_id: Option<GameId>,
+    /// Ours
+    pub client_id: Option<i32>,
+    pub host_id: Option<i32>,
+    pub is_public: bool,
+    /// From the game options. `None` until the host sends them
+    pub map_id: Option<u8>,
+    /// Whether a game is being played rather than waiting in the lobby
+    pub in_game: bool,
+    /// Everyone in the game data, including us, by player id
+    pub players: Vec<PlayerState>,
+    /// Systems under sabotage right now. Empty outside of games
+    pub sabotaged: Vec<SystemType>,
+}
+
+/// One player in a `GameState`
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub player_id: u8,
+    /// `None` until the player's control spawns
+    pub client_id: Option<i32>,
+    pub name: String,
+    pub color: u8,
+    /// Where the player is headed. `None` until their first movement update
+    pub position: Option<Vector2>,
+    pub is_dead: bool,
+    pub is_imposter: bool,
+    pub disconnected: bool,
+}
+
+impl GameState {
+    pub fn player(&self, player_id: u8) -> Option<&PlayerState> {
+        self.players
+            .iter()
+            .find(|player| player.player_id == player_id)
+    }
+
+    /// The player a client controls
+    pub fn player_by_client(&self, client_id: i32) -> Option<&PlayerState> {
+        self.players
+            .iter()
+            .find(|player| player.client_id == Some(client_id))
+    }
+
+    /// Our own player, once it's spawned
+    pub fn me(&self) -> Option<&PlayerState> {
+        self.player_by_client(self.client_id?)
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use common::data::{GameData, GameId, GameOverReason};
+use serde::Serialize;
+
+use crate::networking::BandwidthStats;
+
+/// Summary of a single game, recorded once the game ends
+#[derive(Debug, Clone, Serialize)]
+pub struct GameStats {
+    pub game_id: String,
+    /// Unix timestamp (seconds) of when the game started
+    pub started_at: u64,
+    pub duration_secs: f32,
+    /// `None` if the host never sent the game options
+    pub map_id: Option<u8>,
+    pub players: Vec<PlayerStats>,
+    /// `"Crewmates"` or `"Impostors"`
+    pub winner: String,
+    pub end_reason: String,
+    pub kills: u32,
+    pub meetings: u32,
+    pub chat_messages: u32,
+    /// Traffic during the game, including resends
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerStats {
+    pub player_id: u8,
+    pub name: String,
+    pub color: u8,
+    pub is_imposter: bool,
+    pub is_dead: bool,
+    pub disconnected: bool,
+}
+
+/// Somewhere to store finished game summaries
+pub trait StatsSink: Send {
+    fn record(&mut self, stats: &GameStats) -> io::Result<()>;
+}
+
+/// Lets several clients share one sink
+impl<S: StatsSink> StatsSink for Arc<Mutex<S>> {
+    fn record(&mut self, stats: &GameStats) -> io::Result<()> {
+        self.lock().unwrap().record(stats)
+    }
+}
+
+/// Appends each game as a line of JSON to a file
+pub struct JsonlStatsSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonlStatsSink {
+    /// Opens `path` for appending, creating it if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+impl StatsSink for JsonlStatsSink {
+    fn record(&mut self, stats: &GameStats) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, stats)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Counts what happens during a game and hands the summary to a `StatsSink` when it ends
+pub(crate) struct StatsCollector {
+    sink: Box<dyn StatsSink>,
+    current: Option<GameRecord>,
+}
+
+struct GameRecord {
+    game_id: GameId,
+    started: Instant,
+    started_at: u64,
+    kills: u32,
+    meetings: u32,
+    chat_messages: u32,
+    /// Connection traffic when the game started
+    bandwidth: BandwidthStats,
+}
+
+impl StatsCollector {
+    pub(crate) fn new(sink: Box<dyn StatsSink>) -> Self {
+        Self {
+            sink,
+            current: None,
+        }
+    }
+
+    pub(crate) fn game_started(
+        &mut self,
+        game_id: GameId,
+        bandwidth: BandwidthStats,
+        now: Instant,
+    ) {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.current = Some(GameRecord {
+            game_id,
+            started: now,
+            started_at,
+            kills: 0,
+            meetings: 0,
+            chat_messages: 0,
+            bandwidth,
+        });
+    }
+
+    pub(crate) fn kill(&mut self) {
+        if let Some(record) = &mut self.current {
+            record.kills += 1;
+        }
+    }
+
+    pub(crate) fn meeting(&mut self) {
+        if let Some(record) = &mut self.current {
+            record.meetings += 1;
+        }
+    }
+
+    pub(crate) fn chat_message(&mut self) {
+        if let Some(record) = &mut self.current {
+            record.chat_messages += 1;
+        }
+    }
+
+    /// Finishes the current game and records it. Does nothing if the start of the game wasn't seen
+    pub(crate) fn game_ended(
+        &mut self,
+        reason: GameOverReason,
+        game_data: Option<&GameData>,
+        map_id: Option<u8>,
+        bandwidth: &BandwidthStats,
+        now: Instant,
+    ) -> io::Result<()> {
+        let record = match self.current.take() {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+        let mut players = game_data
+            .map(|game_data| {
+                game_data
+                    .players
+                    .iter()
+                    .map(|(&player_id, data)| PlayerStats {
+                        player_id,
+                        name: data.name.clone(),
+                        color: data.color,
+                        is_imposter: data.is_imposter,
+                        is_dead: data.is_dead,
+                        disconnected: data.disconnected,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        players.sort_by_key(|player| player.player_id);
+        let stats = GameStats {
+            game_id: record.game_id.to_string(),
+            started_at: record.started_at,
+            duration_secs: now.duration_since(record.started).as_secs_f32(),
+            map_id,
+            players,
+            winner: format!("{:?}", reason.winner()),
+            end_reason: format!("{:?}", reason),
+            kills: record.kills,
+            meetings: record.meetings,
+            chat_messages: record.chat_messages,
+            bytes_sent: bandwidth
+                .bytes_sent
+                .saturating_sub(record.bandwidth.bytes_sent),
+            bytes_received: bandwidth
+                .bytes_received
+                .saturating_sub(record.bandwidth.bytes_received),
+        };
+        self.sink.record(&stats)
+    }
+}
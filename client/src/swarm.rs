@@ -0,0 +1,155 @@
+use std::{f32::consts::PI, io};
+
+use common::data::Vector2;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    async_client::stopped, AsyncClient, Client, ClientEvent, ClientSettings, ServerTarget,
+};
+
+/// An event from one member of a `Swarm`, with the index `Swarm::join` gave it
+#[derive(Debug, Clone)]
+pub struct SwarmEvent {
+    pub member: usize,
+    pub event: ClientEvent,
+}
+
+/// Many `AsyncClient`s run together, in the same game or different ones
+///
+/// Their events come out of one stream and commands can be sent to every member at once. Members
+/// that have stopped are skipped
+pub struct Swarm {
+    members: Vec<AsyncClient>,
+    /// `None` once a member's events have ended
+    sender: mpsc::UnboundedSender<(usize, Option<ClientEvent>)>,
+    receiver: mpsc::UnboundedReceiver<(usize, Option<ClientEvent>)>,
+    running: usize,
+}
+
+impl Swarm {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            members: Vec::new(),
+            sender,
+            receiver,
+            running: 0,
+        }
+    }
+
+    /// Adds a member joining `game_code` on `server`. Returns its index. Must be called from
+    /// within a tokio runtime
+    pub async fn join<S: Into<ServerTarget>>(
+        &mut self,
+        server: S,
+        game_code: &str,
+        settings: ClientSettings,
+    ) -> io::Result<usize> {
+        let (client, mut events) = AsyncClient::connect(server, game_code, settings).await?;
+        let member = self.members.len();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                let _ = sender.send((member, Some(event)));
+            }
+            let _ = sender.send((member, None));
+        });
+        self.members.push(client);
+        self.running += 1;
+        Ok(member)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// How many members haven't stopped yet
+    pub fn running(&self) -> usize {
+        self.running
+    }
+
+    pub fn member(&self, index: usize) -> Option<&AsyncClient> {
+        self.members.get(index)
+    }
+
+    /// The next event from any member, or `None` once they've all stopped
+    pub async fn recv(&mut self) -> Option<SwarmEvent> {
+        while self.running > 0 {
+            match self.receiver.recv().await? {
+                (member, Some(event)) => return Some(SwarmEvent { member, event }),
+                (_, None) => self.running -= 1,
+            }
+        }
+        None
+    }
+
+    /// Runs `f` on every member and returns what each returned, in member order. Stopped members
+    /// give `NotConnected`
+    ///
+    /// Every command is queued before any is waited on, so members act at nearly the same time
+    pub async fn call_all<T, F>(&self, f: F) -> Vec<io::Result<T>>
+    where
+        T: Send + 'static,
+        F: Fn(usize, &mut Client) -> T + Clone + Send + 'static,
+    {
+        let pending: Vec<io::Result<oneshot::Receiver<T>>> = self
+            .members
+            .iter()
+            .enumerate()
+            .map(|(member, client)| {
+                let f = f.clone();
+                client.dispatch(move |client| f(member, client))
+            })
+            .collect();
+        let mut results = Vec::with_capacity(pending.len());
+        for result in pending {
+            results.push(match result {
+                Ok(receiver) => receiver.await.map_err(|_| stopped()),
+                Err(error) => Err(error),
+            });
+        }
+        results
+    }
+
+    /// Every member says `message`
+    pub async fn broadcast_chat(&self, message: &str) {
+        let message = message.to_string();
+        self.call_all(move |_, client| client.send_chat(&message))
+            .await;
+    }
+
+    /// Every member starts walking to `target` at the same time. See `Client::walk_to`
+    pub async fn walk_to(&self, target: Vector2) {
+        self.call_all(move |_, client| client.walk_to(target)).await;
+    }
+
+    /// Every member walks to its own spot on a circle of `radius` around `center`, so they
+    /// don't all end up on top of each other
+    pub async fn walk_in_formation(&self, center: Vector2, radius: f32) {
+        let count = self.members.len() as f32;
+        self.call_all(move |member, client| {
+            let angle = 2. * PI * member as f32 / count;
+            let target = Vector2::new(
+                center.x() + radius * angle.cos(),
+                center.y() + radius * angle.sin(),
+            );
+            client.walk_to(target)
+        })
+        .await;
+    }
+
+    /// Every member leaves. Events keep coming until each has stopped
+    pub async fn disconnect_all(&self) {
+        self.call_all(|_, client| client.disconnect()).await;
+    }
+}
+
+impl Default for Swarm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use common::data::{GameId, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, PlayerState, SpectatorEvent, SpectatorPlayer};
+
+/// How often everyone's positions go in the timeline
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Everything that happened in one game, like a replay file. Written as JSON when the game ends,
+/// see `ClientSettings::timeline_dir`
+///
+/// Events are the same as spectators get, with everyone's positions every `SNAPSHOT_INTERVAL` as
+/// `SpectatorEvent::Players`. Whether or not we're host, so an observer bot gets the lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    pub game_id: String,
+    /// Unix timestamp (seconds) of when the game started
+    pub started_at: u64,
+    /// `None` if the host never sent the game options
+    pub map_id: Option<u8>,
+    /// Everyone as they were at the end, with who the impostors were
+    pub players: Vec<SpectatorPlayer>,
+    pub events: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Seconds since the game started
+    pub time: f32,
+    #[serde(flatten)]
+    pub event: SpectatorEvent,
+}
+
+impl Timeline {
+    /// Reads a timeline written by a client
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Seconds from the start to the last event
+    pub fn duration(&self) -> f32 {
+        self.events.last().map_or(0., |event| event.time)
+    }
+
+    /// Everyone as of the last snapshot at or before `time` seconds in, e.g. for
+    /// `ObserverRender`. Players are as they were at the end if there's no snapshot yet
+    pub fn state_at(&self, time: f32) -> GameState {
+        let events = self.events.iter().take_while(|event| event.time <= time);
+        let mut players = &self.players;
+        let mut in_game = true;
+        for event in events {
+            match &event.event {
+                SpectatorEvent::Players { players: snapshot } => players = snapshot,
+                SpectatorEvent::GameEnded { .. } => in_game = false,
+                _ => (),
+            }
+        }
+        GameState {
+            game_id: parse_game_id(&self.game_id),
+            client_id: None,
+            host_id: None,
+            is_public: false,
+            map_id: self.map_id,
+            in_game,
+            players: players
+                .iter()
+                .map(|player| PlayerState {
+                    player_id: player.player_id,
+                    client_id: None,
+                    name: player.name.clone(),
+                    color: player.color,
+                    position: player.position.map(|(x, y)| Vector2::new(x, y)),
+                    is_dead: player.is_dead,
+                    is_imposter: player.is_imposter,
+                    disconnected: player.disconnected,
+                })
+                .collect(),
+            sabotaged: Vec::new(),
+        }
+    }
+}
+
+/// Builds the current game's `Timeline` and writes it out when the game ends
+pub(crate) struct TimelineRecorder {
+    dir: PathBuf,
+    current: Option<(Instant, Timeline)>,
+    last_snapshot: Option<Instant>,
+}
+
+impl TimelineRecorder {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            current: None,
+            last_snapshot: None,
+        }
+    }
+
+    pub(crate) fn game_started(&mut self, game_id: GameId, map_id: Option<u8>, now: Instant) {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let timeline = Timeline {
+            game_id: game_id.to_string(),
+            started_at,
+            map_id,
+            players: Vec::new(),
+            events: Vec::new(),
+        };
+        self.current = Some((now, timeline));
+        self.last_snapshot = None;
+    }
+
+    /// Does nothing outside of a game
+    pub(crate) fn record(&mut self, event: SpectatorEvent, now: Instant) {
+        if let Some((started, timeline)) = &mut self.current {
+            timeline.events.push(TimelineEvent {
+                time: now.duration_since(*started).as_secs_f32(),
+                event,
+            });
+        }
+    }
+
+    /// Whether it's time for another snapshot. Assumes one will be recorded if so
+    pub(crate) fn snapshot_due(&mut self, now: Instant) -> bool {
+        if self.current.is_none() {
+            return false;
+        }
+        let due = self
+            .last_snapshot
+            .is_none_or(|last| now.duration_since(last) >= SNAPSHOT_INTERVAL);
+        if due {
+            self.last_snapshot = Some(now);
+        }
+        due
+    }
+
+    /// Finishes the current game and writes it to `<game id>-<start time>.json`, returning the
+    /// path. Does nothing if the start of the game wasn't seen
+    pub(crate) fn game_ended(
+        &mut self,
+        players: Vec<SpectatorPlayer>,
+        map_id: Option<u8>,
+    ) -> io::Result<Option<PathBuf>> {
+        let mut timeline = match self.current.take() {
+            Some((_, timeline)) => timeline,
+            None => return Ok(None),
+        };
+        timeline.players = players;
+        // The options can arrive after the game starts when joining late
+        timeline.map_id = timeline.map_id.or(map_id);
+        fs::create_dir_all(&self.dir)?;
+        let path = self
+            .dir
+            .join(format!("{}-{}.json", timeline.game_id, timeline.started_at));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer(&mut writer, &timeline)?;
+        writer.flush()?;
+        Ok(Some(path))
+    }
+}
+
+/// `GameId::from_chars` panics on anything that isn't a game code, and timelines can come from
+/// anywhere
+fn parse_game_id(chars: &str) -> Option<GameId> {
+    let valid = match chars.len() {
+        6 => chars.chars().all(|c| c.is_ascii_uppercase()),
+        4 => chars.is_ascii(),
+        _ => false,
+    };
+    if valid {
+        Some(GameId::from_chars(chars))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(is_dead: bool, position: (f32, f32)) -> SpectatorPlayer {
+        SpectatorPlayer {
+            player_id: 3,
+            name: "otter".to_string(),
+            color: 2,
+            is_imposter: false,
+            is_dead,
+            disconnected: false,
+            position: Some(position),
+        }
+    }
+
+    fn position(state: &GameState) -> Option<(f32, f32)> {
+        let position = state.players[0].position?;
+        Some((position.x(), position.y()))
+    }
+
+    #[test]
+    fn test_timeline_round_trip() {
+        let dir = std::env::temp_dir().join("among-us-test-timeline");
+        let _ = fs::remove_dir_all(&dir);
+        let mut recorder = TimelineRecorder::new(dir.clone());
+        let started = Instant::now();
+        let at = |millis| started + Duration::from_millis(millis);
+
+        // Nothing happens outside of a game
+        recorder.record(SpectatorEvent::GameStarted, started);
+        assert!(!recorder.snapshot_due(started));
+        assert!(recorder.game_ended(Vec::new(), None).unwrap().is_none());
+
+        recorder.game_started(GameId::from_chars("ABCDEF"), None, started);
+        assert!(recorder.snapshot_due(at(0)));
+        recorder.record(
+            SpectatorEvent::Players {
+                players: vec![player(false, (1., 2.))],
+            },
+            at(0),
+        );
+        assert!(!recorder.snapshot_due(at(100)));
+        recorder.record(
+            SpectatorEvent::Kill {
+                victim_player_id: 3,
+            },
+            at(1000),
+        );
+        assert!(recorder.snapshot_due(at(2000)));
+        recorder.record(
+            SpectatorEvent::Players {
+                players: vec![player(true, (3., 4.))],
+            },
+            at(2000),
+        );
+        recorder.record(
+            SpectatorEvent::GameEnded {
+                reason: "ImpostorByKill".to_string(),
+            },
+            at(3000),
+        );
+        let path = recorder
+            .game_ended(vec![player(true, (5., 6.))], Some(1))
+            .unwrap()
+            .unwrap();
+        let timeline = Timeline::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(timeline.game_id, "ABCDEF");
+        assert_eq!(timeline.map_id, Some(1));
+        assert_eq!(timeline.events.len(), 4);
+        assert_eq!(timeline.duration(), 3.);
+
+        let state = timeline.state_at(0.5);
+        assert_eq!(state.game_id, Some(GameId::from_chars("ABCDEF")));
+        assert!(state.in_game);
+        assert!(!state.players[0].is_dead);
+        assert_eq!(position(&state), Some((1., 2.)));
+        let state = timeline.state_at(2.5);
+        assert!(state.in_game);
+        assert!(state.players[0].is_dead);
+        assert_eq!(position(&state), Some((3., 4.)));
+        // Before the first snapshot, everyone's as they ended
+        let state = timeline.state_at(-1.);
+        assert_eq!(position(&state), Some((5., 6.)));
+        assert!(!timeline.state_at(3.).in_game);
+    }
+
+    #[test]
+    fn test_parse_game_id() {
+        assert_eq!(parse_game_id("ABCDEF"), Some(GameId::from_chars("ABCDEF")));
+        assert_eq!(parse_game_id("abcdef"), None);
+        assert_eq!(parse_game_id(""), None);
+        assert_eq!(parse_game_id("ABCDEFG"), None);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    io,
+    sync::mpsc::{self, TryRecvError},
+    time::Duration,
+};
+
+use client::{Client, ClientSettings, EventHandler, ListingChecker, ScanSettings, ServerTarget};
+use common::data::{DisconnectReason, GameListing, ListingFlags};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+/// How long to wait between game list requests
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn run(server: ServerTarget) {
+    // Game scanning
+    let (scan_results_send, scan_results_recv) = mpsc::channel();
+    let _scan_thread = std::thread::Builder::new()
+        .name("tui-scan".to_string())
+        .spawn(move || {
+            let settings = ScanSettings {
+                server,
+                connect_username: "scan".to_string(),
+                max_requests: 1,
+                cache_size: 1,
+                ..ScanSettings::default()
+            };
+
+            let callback = |listings: Vec<GameListing>| {
+                if scan_results_send.send(listings).is_err() {
+                    return false;
+                }
+                std::thread::sleep(REFRESH_INTERVAL);
+                true
+            };
+
+            if let Err(error) = Client::server_scan(settings, callback) {
+                log::error!("Scan failed: {}", error);
+            }
+        })
+        .unwrap();
+
+    let mut terminal = ratatui::init();
+    let result = Browser::default().run(&mut terminal, scan_results_recv);
+    ratatui::restore();
+
+    match result {
+        Ok(Some(listing)) => join(listing),
+        Ok(None) => (),
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
+fn join(listing: GameListing) {
+    println!("Joining {} hosted by {}", listing.id, listing.host_username);
+    let settings = ClientSettings {
+        connect_username: "browser".to_string(),
+        game_username: "browser".to_string(),
+        ..ClientSettings::default()
+    };
+    if let Err(error) = Client::run_game(JoinHandler {}, listing, settings) {
+        log::error!("{}", error);
+    }
+}
+
+#[derive(Default)]
+struct Browser {
+    listings: Vec<(GameListing, ListingFlags)>,
+    checker: ListingChecker,
+    list_state: ListState,
+    filter: String,
+    editing_filter: bool,
+}
+
+impl Browser {
+    /// Runs until the user quits or picks a game, returning the picked game
+    fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+        scan_results_recv: mpsc::Receiver<Vec<GameListing>>,
+    ) -> io::Result<Option<GameListing>> {
+        loop {
+            // Read new listings
+            loop {
+                match scan_results_recv.try_recv() {
+                    Ok(results) => self.add_listings(results),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::BrokenPipe,
+                            "Scan thread stopped",
+                        ))
+                    }
+                }
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            if self.editing_filter {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => (),
+                }
+                self.clamp_selection();
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                KeyCode::Char('/') => self.editing_filter = true,
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::PageDown => self.move_selection(10),
+                KeyCode::PageUp => self.move_selection(-10),
+                KeyCode::Enter => {
+                    if let Some((listing, _)) = self.selected() {
+                        return Ok(Some(listing.clone()));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Adds or replaces listings, keyed by game id
+    fn add_listings(&mut self, results: Vec<GameListing>) {
+        for listing in results {
+            let flags = self.checker.check(&listing);
+            match self
+                .listings
+                .iter_mut()
+                .find(|(other, _)| other.id == listing.id)
+            {
+                Some(existing) => *existing = (listing, flags),
+                None => self.listings.push((listing, flags)),
+            }
+        }
+        self.clamp_selection();
+    }
+
+    fn filtered(&self) -> Vec<&(GameListing, ListingFlags)> {
+        let filter = self.filter.to_lowercase();
+        self.listings
+            .iter()
+            .filter(|(listing, _)| {
+                filter.is_empty()
+                    || listing.host_username.to_lowercase().contains(&filter)
+                    || listing.id.to_string().to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&(GameListing, ListingFlags)> {
+        let index = self.list_state.selected()?;
+        self.filtered().get(index).copied()
+    }
+
+    fn move_selection(&mut self, offset: i32) {
+        let count = self.filtered().len() as i32;
+        if count == 0 {
+            self.list_state.select(None);
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as i32;
+        let new = (current + offset).max(0).min(count - 1);
+        self.list_state.select(Some(new as usize));
+    }
+
+    fn clamp_selection(&mut self) {
+        self.move_selection(0);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [filter_area, main_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main_area);
+
+        // Filter
+        let filter_style = if self.editing_filter {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        frame.render_widget(
+            Paragraph::new(self.filter.as_str())
+                .style(filter_style)
+                .block(Block::bordered().title("Filter")),
+            filter_area,
+        );
+
+        // Game list
+        let items = self
+            .filtered()
+            .iter()
+            .map(|(listing, flags)| {
+                let style = if flags.is_empty() {
+                    Style::default()
+                } else {
+                    Style::default().add_modifier(Modifier::DIM)
+                };
+                ListItem::new(format!(
+                    "{:<6} {:>2}/{:<2} {:<8} {} {}",
+                    listing.id,
+                    listing.player_count,
+                    listing.max_players,
+                    format!("{:?}", listing.map_id),
+                    listing.num_imposters,
+                    listing.host_username,
+                ))
+                .style(style)
+            })
+            .collect::<Vec<_>>();
+        let title = format!("Games ({})", items.len());
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        // Details
+        let details = match self.selected() {
+            Some((listing, flags)) => vec![
+                Line::from(format!("Code:      {}", listing.id)),
+                Line::from(format!("Host:      {}", listing.host_username)),
+                Line::from(format!("Address:   {}", listing.address)),
+                Line::from(format!(
+                    "Players:   {}/{}",
+                    listing.player_count, listing.max_players
+                )),
+                Line::from(format!("Map:       {:?}", listing.map_id)),
+                Line::from(format!("Impostors: {}", listing.num_imposters)),
+                Line::from(format!("Age:       {}", listing.age)),
+                Line::from(format!("Flags:     {:?}", flags)),
+            ],
+            None => vec![Line::from("No game selected")],
+        };
+        frame.render_widget(
+            Paragraph::new(details).block(Block::bordered().title("Details")),
+            details_area,
+        );
+
+        // Help
+        frame.render_widget(
+            Paragraph::new("q: quit  /: filter  up/down: select  enter: join"),
+            help_area,
+        );
+    }
+}
+
+struct JoinHandler {}
+
+impl EventHandler for JoinHandler {
+    fn disconnect_reason(&mut self, client: &mut Client, reason: DisconnectReason) {
+        println!("Disconnected: {:?}", reason);
+        client.disconnect();
+    }
+
+    fn joined_game(&mut self, _client: &mut Client) {
+        println!("Joined");
+    }
+
+    fn chat_message(
+        &mut self,
+        _client: &mut Client,
+        _player_id: i32,
+        name: String,
+        message: String,
+    ) {
+        println!("{}: {}", name, message);
+    }
+}
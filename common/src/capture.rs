@@ -0,0 +1,341 @@
+//! Recorded datagrams, as written by `dump-transformer` and the client's recorder
+//!
+//! Laid out in 16 byte rows so it lines up nicely in a hex editor. A file starts with a header
+//! row:
+//!
+//! | Bytes | Field                                |
+//! |-------|--------------------------------------|
+//! | 4     | `MAGIC`                              |
+//! | 2     | Format version, little endian        |
+//! | 10    | Zero                                 |
+//!
+//! Then each datagram is a header row followed by its data padded with zeros to a multiple of 16
+//! bytes, always with at least one byte of padding:
+//!
+//! | Bytes | Field                                |
+//! |-------|--------------------------------------|
+//! | 1     | 1 if sent to the server, 0 if not    |
+//! | 4     | Data length, little endian           |
+//! | 4     | Padded data length, little endian    |
+//! | 7     | Microseconds since the capture began |
+//!
+//! The time is little endian and was added in version 2. Before that it was zero, so packets from
+//! older captures all read as sent at the start. Files from before the header start straight with
+//! a datagram and are read as version 0
+
+use std::{
+    convert::TryInto,
+    io::{self, ErrorKind, Read, Write},
+    time::Duration,
+};
+
+/// Which way a captured datagram went
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    ToServer,
+    ToClient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub direction: Direction,
+    /// When the datagram was sent or received, from the start of the capture. Zero for captures
+    /// older than version 2. See `CaptureReader::is_timed`
+    pub offset: Duration,
+    pub data: Vec<u8>,
+}
+
+/// The capture file format. See the module docs for the layout
+#[derive(Debug, Copy, Clone)]
+pub struct CaptureFormat;
+
+impl CaptureFormat {
+    /// The first bytes of a capture with a header
+    pub const MAGIC: [u8; 4] = *b"AUCP";
+
+    /// The version written
+    pub const VERSION: u16 = 2;
+
+    /// Starts reading a capture, checking its header if it has one
+    pub fn reader<R: Read>(r: R) -> io::Result<CaptureReader<R>> {
+        CaptureReader::new(r)
+    }
+
+    /// Starts a capture, writing its header
+    pub fn writer<W: Write>(w: W) -> io::Result<CaptureWriter<W>> {
+        CaptureWriter::new(w)
+    }
+}
+
+/// Bytes in a row, which everything is aligned to
+const ROW: usize = 16;
+
+/// The first version with packet times
+const TIMED_VERSION: u16 = 2;
+
+/// The largest UDP payload, so the most data a captured datagram can have
+const BUFFER_SIZE: usize = 65_507;
+
+/// The largest time that fits in a row's 7 bytes, over 2000 years
+const MAX_OFFSET_MICROS: u64 = (1 << 56) - 1;
+
+/// Reads a capture a datagram at a time. Iterating stops after the first error
+pub struct CaptureReader<R: Read> {
+    inner: R,
+    version: u16,
+    /// The first row of a headerless file, already read while looking for a header
+    first_row: Option<[u8; ROW]>,
+    failed: bool,
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut row = [0; ROW];
+        // An empty file is an empty capture
+        if !read_row(&mut inner, &mut row)? {
+            return Ok(Self {
+                inner,
+                version: CaptureFormat::VERSION,
+                first_row: None,
+                failed: false,
+            });
+        }
+        if row[..4] != CaptureFormat::MAGIC {
+            return Ok(Self {
+                inner,
+                version: 0,
+                first_row: Some(row),
+                failed: false,
+            });
+        }
+        let version = u16::from_le_bytes([row[4], row[5]]);
+        if version > CaptureFormat::VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported capture version {}", version),
+            ));
+        }
+        Ok(Self {
+            inner,
+            version,
+            first_row: None,
+            failed: false,
+        })
+    }
+
+    /// The format version of the file being read, 0 for headerless files
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Whether packets have times, rather than all being at zero
+    pub fn is_timed(&self) -> bool {
+        self.version >= TIMED_VERSION
+    }
+
+    /// The next datagram, or `None` at the end of the capture
+    pub fn read_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        let row = match self.first_row.take() {
+            Some(row) => row,
+            None => {
+                let mut row = [0; ROW];
+                if !read_row(&mut self.inner, &mut row)? {
+                    return Ok(None);
+                }
+                row
+            }
+        };
+        let direction = match row[0] {
+            0 => Direction::ToClient,
+            1 => Direction::ToServer,
+            other => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid capture direction {}", other),
+                ))
+            }
+        };
+        let length = u32::from_le_bytes([row[1], row[2], row[3], row[4]]) as usize;
+        let padded_length = u32::from_le_bytes([row[5], row[6], row[7], row[8]]) as usize;
+        if length > padded_length {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Capture data longer than its padding",
+            ));
+        }
+        // Checked before allocating, so a corrupt header can't ask for gigabytes
+        if length > BUFFER_SIZE || padded_length > length + ROW {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Capture datagram too long, {} bytes", padded_length),
+            ));
+        }
+        let mut micros = [0; 8];
+        micros[..7].copy_from_slice(&row[9..]);
+        let offset = if self.is_timed() {
+            Duration::from_micros(u64::from_le_bytes(micros))
+        } else {
+            Duration::from_secs(0)
+        };
+        let mut data = vec![0; padded_length];
+        self.inner.read_exact(&mut data)?;
+        data.truncate(length);
+        Ok(Some(CapturedPacket {
+            direction,
+            offset,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_packet().transpose();
+        if let Some(Err(_)) = result {
+            self.failed = true;
+        }
+        result
+    }
+}
+
+/// Reads a whole row, returning `false` if the input ended cleanly before it
+fn read_row<R: Read>(r: &mut R, row: &mut [u8; ROW]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < ROW {
+        match r.read(&mut row[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(count) => filled += count,
+            Err(error) if error.kind() == ErrorKind::Interrupted => (),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(true)
+}
+
+/// Writes datagrams to a capture
+pub struct CaptureWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        let mut row = [0; ROW];
+        row[..4].copy_from_slice(&CaptureFormat::MAGIC);
+        row[4..6].copy_from_slice(&CaptureFormat::VERSION.to_le_bytes());
+        inner.write_all(&row)?;
+        Ok(Self { inner })
+    }
+
+    /// Writes a datagram sent or received `offset` after the capture began
+    pub fn write_packet(
+        &mut self,
+        direction: Direction,
+        offset: Duration,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let padded_length = data.len() + ROW - data.len() % ROW;
+        let micros = offset
+            .as_micros()
+            .try_into()
+            .unwrap_or(u64::MAX)
+            .min(MAX_OFFSET_MICROS);
+        let mut row = [0; ROW];
+        row[0] = (direction == Direction::ToServer) as u8;
+        row[1..5].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        row[5..9].copy_from_slice(&(padded_length as u32).to_le_bytes());
+        row[9..].copy_from_slice(&micros.to_le_bytes()[..7]);
+        self.inner.write_all(&row)?;
+        self.inner.write_all(data)?;
+        self.inner
+            .write_all(&[0; ROW][..padded_length - data.len()])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_round_trip() {
+        let packets = vec![
+            CapturedPacket {
+                direction: Direction::ToServer,
+                offset: Duration::from_secs(0),
+                data: vec![8, 0, 1, 0],
+            },
+            // Already a multiple of 16, so gets a whole row of padding
+            CapturedPacket {
+                direction: Direction::ToClient,
+                offset: Duration::from_micros(1_500),
+                data: (0..32).collect(),
+            },
+            CapturedPacket {
+                direction: Direction::ToClient,
+                offset: Duration::from_secs(90_000),
+                data: Vec::new(),
+            },
+        ];
+        let mut writer = CaptureFormat::writer(Vec::new()).unwrap();
+        for packet in &packets {
+            writer
+                .write_packet(packet.direction, packet.offset, &packet.data)
+                .unwrap();
+        }
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.len() % ROW, 0);
+        assert_eq!(bytes.len(), ROW * (1 + 2 + 1 + 3 + 1 + 1));
+
+        let reader = CaptureFormat::reader(&bytes[..]).unwrap();
+        assert_eq!(reader.version(), CaptureFormat::VERSION);
+        assert!(reader.is_timed());
+        let read = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, packets);
+
+        // Without the header, as older dumps were written. Their times are ignored
+        let reader = CaptureFormat::reader(&bytes[ROW..]).unwrap();
+        assert_eq!(reader.version(), 0);
+        assert!(!reader.is_timed());
+        let read = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read.len(), packets.len());
+        for (read, packet) in read.iter().zip(packets.iter()) {
+            assert_eq!(read.offset, Duration::from_secs(0));
+            assert_eq!(read.data, packet.data);
+        }
+
+        // Cut off part way through a datagram
+        let mut reader = CaptureFormat::reader(&bytes[..ROW * 4]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_capture_length_cap() {
+        let mut bytes = [0; ROW];
+        bytes[1..5].copy_from_slice(&4u32.to_le_bytes());
+        bytes[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = CaptureFormat::reader(&bytes[..]).unwrap();
+        let error = reader.read_packet().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        bytes[1..5].copy_from_slice(&(BUFFER_SIZE as u32 + 1).to_le_bytes());
+        bytes[5..9].copy_from_slice(&(BUFFER_SIZE as u32 + ROW as u32).to_le_bytes());
+        let mut reader = CaptureFormat::reader(&bytes[..]).unwrap();
+        let error = reader.read_packet().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,217 @@
+macro_rules! cosmetics {
+    ($(#[$meta:meta])* $kind:ident { $($variant:ident => $name:expr,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, FromPrimitive)]
+        pub enum $kind {
+            $($variant,)*
+        }
+
+        impl $kind {
+            /// Every one there is, ordered by id
+            pub const ALL: &'static [$kind] = &[$($kind::$variant,)*];
+
+            /// The name shown in game
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($kind::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+cosmetics! {
+    /// A player's color. Its id is what `SetColor` sends
+    Color {
+        Red => "Red",
+        Blue => "Blue",
+        Green => "Green",
+        Pink => "Pink",
+        Orange => "Orange",
+        Yellow => "Yellow",
+        Black => "Black",
+        White => "White",
+        Purple => "Purple",
+        Brown => "Brown",
+        Cyan => "Cyan",
+        Lime => "Lime",
+    }
+}
+
+impl Color {
+    /// The body color in game
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            Color::Red => [0xc5, 0x11, 0x11],
+            Color::Blue => [0x13, 0x2e, 0xd1],
+            Color::Green => [0x11, 0x7f, 0x2d],
+            Color::Pink => [0xed, 0x54, 0xba],
+            Color::Orange => [0xef, 0x7d, 0x0d],
+            Color::Yellow => [0xf5, 0xf5, 0x57],
+            Color::Black => [0x3f, 0x47, 0x4e],
+            Color::White => [0xd6, 0xe0, 0xf0],
+            Color::Purple => [0x6b, 0x2f, 0xbb],
+            Color::Brown => [0x71, 0x49, 0x1e],
+            Color::Cyan => [0x38, 0xfe, 0xdc],
+            Color::Lime => [0x50, 0xef, 0x39],
+        }
+    }
+}
+
+cosmetics! {
+    /// Its id is what `SetHat` sends
+    Hat {
+        None => "None",
+        Astronaut => "Astronaut",
+        BaseballCap => "Baseball Cap",
+        BrainSlug => "Brain Slug",
+        BushHat => "Bush Hat",
+        CaptainsHat => "Captain's Hat",
+        DoubleTopHat => "Double Top Hat",
+        Flowerpot => "Flowerpot",
+        Goggles => "Goggles",
+        HardHat => "Hard Hat",
+        Military => "Military",
+        PaperHat => "Paper Hat",
+        PartyHat => "Party Hat",
+        Police => "Police",
+        Stethoscope => "Stethoscope",
+        TopHat => "Top Hat",
+        TowelWizard => "Towel Wizard",
+        Ushanka => "Ushanka",
+        Viking => "Viking",
+        WallCap => "Wall Guard Cap",
+        Snowman => "Snowman",
+        Reindeer => "Reindeer Antlers",
+        Lights => "Christmas Lights",
+        Santa => "Santa Hat",
+        Tree => "Tree Hat",
+        Present => "Present",
+        Candycanes => "Candy Canes",
+        ElfHat => "Elf Hat",
+        NewYears2018 => "2018 Party Hat",
+        WhiteHat => "White Hat",
+        Crown => "Crown",
+        Eyebrows => "Eyebrows",
+        Halo => "Halo",
+        HeroCap => "Hero Cap",
+        PipCap => "Pip Cap",
+        Plunger => "Plunger",
+        Scuba => "Scuba Mask",
+        Stickmin => "Henry Stickmin",
+        StrawHat => "Straw Hat",
+        TenGallonHat => "Ten Gallon Hat",
+        ThirdEye => "Third Eye",
+        ToiletPaper => "Toilet Paper",
+        Toppat => "Toppat Clan Leader",
+        Fedora => "Black Fedora",
+        Goggles2 => "Ski Goggles",
+        Headphones => "Headphones",
+        MaskHat => "Fire Helmet",
+        PaperMask => "Paper Mask",
+        Security => "Security Cap",
+        StrapHat => "Strap Hat",
+        Banana => "Banana",
+        Beanie => "Beanie",
+        Bear => "Bear Ears",
+        Cheese => "Cheese",
+        Cherry => "Cherry",
+        Egg => "Egg",
+        Fedora2 => "Green Fedora",
+        Flamingo => "Flamingo",
+        FlowerPin => "Flower Pin",
+        Helmet => "Knight Helmet",
+        Plant => "Plant",
+        BatEyes => "Bat Eyes",
+        BatWings => "Bat Wings",
+        Horns => "Horns",
+        Mohawk => "Mohawk",
+        Pumpkin => "Pumpkin",
+        ScaryBag => "Scary Paper Bag",
+        Witch => "Witch Hat",
+        Wolf => "Wolf Ears",
+        Pirate => "Pirate Hat",
+        Plague => "Plague Doctor",
+        Machete => "Machete",
+        Fred => "Hockey Mask",
+        MinerCap => "Miner Cap",
+        WinterHat => "Winter Hat",
+        Archae => "Archaeologist",
+        Antenna => "Antenna",
+        Balloon => "Balloon",
+        BirdNest => "Bird Nest",
+        BlackBelt => "Black Belt",
+        Caution => "Caution Sign",
+        Chef => "Chef Hat",
+        CopHat => "Cop Hat",
+        DoRag => "Do-rag",
+        DumSticker => "Dum Sticker",
+        Fez => "Fez",
+        GeneralHat => "General Hat",
+        GreyThing => "Pompadour",
+        HunterCap => "Hunter Cap",
+        JungleHat => "Jungle Hat",
+        MiniCrewmate => "Mini Crewmate",
+        NinjaMask => "Ninja Mask",
+        RamHorns => "Ram Horns",
+        Snowman2 => "Mini Snowman",
+    }
+}
+
+cosmetics! {
+    /// Its id is what `SetSkin` sends
+    Skin {
+        None => "None",
+        Astronaut => "Astronaut",
+        Captain => "Captain",
+        Mechanic => "Mechanic",
+        Military => "Military",
+        Police => "Police",
+        Scientist => "Scientist",
+        BlackSuit => "Black Suit",
+        WhiteSuit => "White Suit",
+        WallGuard => "Wall Guard",
+        Hazmat => "Hazmat",
+        Security => "Security Guard",
+        Tarmac => "Tarmac",
+        Miner => "Miner",
+        Winter => "Winter",
+        Archae => "Archaeologist",
+    }
+}
+
+cosmetics! {
+    /// Its id is what `SetPet` sends
+    Pet {
+        None => "None",
+        Alien => "Brain Slug",
+        MiniCrewmate => "Mini Crewmate",
+        Doggy => "Dog",
+        Stickmin => "Henry Stickmin",
+        Hamster => "Hamster",
+        Robot => "Robot",
+        Ufo => "UFO",
+        Ellie => "Ellie Rose",
+        Squig => "Squig",
+        Bedcrab => "Bedcrab",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::FromPrimitive;
+
+    #[test]
+    fn test_cosmetic_ids() {
+        assert_eq!(Color::ALL.len(), 12);
+        assert_eq!(Color::from_u8(11), Some(Color::Lime));
+        assert_eq!(Hat::from_u32(42), Some(Hat::Toppat));
+        assert_eq!(Hat::from_u32(Hat::ALL.len() as u32), None);
+        assert_eq!(Skin::from_u32(15).map(Skin::name), Some("Archaeologist"));
+        assert_eq!(Pet::ALL.last(), Some(&Pet::Bedcrab));
+        for (index, hat) in Hat::ALL.iter().enumerate() {
+            assert_eq!(*hat as usize, index);
+        }
+    }
+}
@@ -1,16 +1,51 @@
-use std::io;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io,
+    time::{Duration, Instant},
+};
 
-use crate::reader::{Deserialize, PacketRead, PacketReader, PacketWriter, Serialize};
+use crate::{
+    data::DisconnectReason,
+    reader::{Deserialize, PacketRead, PacketReader, PacketWriter, Serialize},
+};
 use num_traits::FromPrimitive;
 
+/// A packet as received. Bytes after the fields a type is known to have are ignored, so newer
+/// Hazel revisions can add to them without breaking older readers
 #[derive(Debug)]
 pub enum HazelPacket {
-    Unreliable { data: Vec<u8> },
-    Reliable { ack_id: u16, data: Vec<u8> },
-    Disconnect,
-    Hello { ack_id: u16, data: Vec<u8> },
-    Acknowledge { ack_id: u16 },
-    KeepAlive { ack_id: u16 },
+    Unreliable {
+        data: Vec<u8>,
+    },
+    Reliable {
+        ack_id: u16,
+        data: Vec<u8>,
+    },
+    /// Newer revisions say why, see `read_disconnect_reason`. `data` is everything after the type
+    Disconnect {
+        reason: Option<DisconnectReason>,
+        data: Vec<u8>,
+    },
+    Hello {
+        ack_id: u16,
+        data: Vec<u8>,
+    },
+    Acknowledge {
+        ack_id: u16,
+    },
+    KeepAlive {
+        ack_id: u16,
+    },
+    /// Part of a reliable payload too big for one datagram. Each fragment is acknowledged on its
+    /// own. See `Reassembler`
+    Fragment {
+        ack_id: u16,
+        fragment_id: u16,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
 }
 
 #[derive(Debug)]
@@ -22,7 +57,10 @@ pub enum HazelPacketOut {
         ack_id: u16,
         data: Box<dyn Serialize>,
     },
-    Disconnect,
+    /// `data` is written after the type. Empty for older revisions
+    Disconnect {
+        data: Vec<u8>,
+    },
     Hello {
         ack_id: u16,
         data: Box<dyn Serialize>,
@@ -33,6 +71,13 @@ pub enum HazelPacketOut {
     KeepAlive {
         ack_id: u16,
     },
+    Fragment {
+        ack_id: u16,
+        fragment_id: u16,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
 }
 
 impl Serialize for HazelPacketOut {
@@ -47,8 +92,9 @@ impl Serialize for HazelPacketOut {
                 w.write_u16_be(*ack_id);
                 data.serialize(w);
             }
-            HazelPacketOut::Disconnect => {
+            HazelPacketOut::Disconnect { data } => {
                 w.write_u8(HazelType::Disconnect as u8);
+                w.write_bytes_raw(data);
             }
             HazelPacketOut::Hello { ack_id, data } => {
                 w.write_u8(HazelType::Hello as u8);
@@ -65,6 +111,20 @@ impl Serialize for HazelPacketOut {
                 w.write_u8(HazelType::KeepAlive as u8);
                 w.write_u16_be(*ack_id);
             }
+            HazelPacketOut::Fragment {
+                ack_id,
+                fragment_id,
+                index,
+                count,
+                data,
+            } => {
+                w.write_u8(HazelType::Fragment as u8);
+                w.write_u16_be(*ack_id);
+                w.write_u16_be(*fragment_id);
+                w.write_u16_be(*index);
+                w.write_u16_be(*count);
+                w.write_bytes_raw(data);
+            }
         }
     }
 }
@@ -84,14 +144,32 @@ impl Deserialize for HazelPacket {
                 ack_id: r.read_u16_be()?,
                 data: r.remaining_bytes()?,
             },
-            Some(HazelType::Disconnect) => HazelPacket::Disconnect,
+            Some(HazelType::Disconnect) => {
+                let data = r.remaining_bytes()?;
+                HazelPacket::Disconnect {
+                    reason: read_disconnect_reason(&data),
+                    data,
+                }
+            }
             Some(HazelType::Acknowledge) => HazelPacket::Acknowledge {
                 ack_id: r.read_u16_be()?,
             },
             Some(HazelType::KeepAlive) => HazelPacket::KeepAlive {
                 ack_id: r.read_u16_be()?,
             },
-            None => panic!("Unknown packet type"),
+            Some(HazelType::Fragment) => HazelPacket::Fragment {
+                ack_id: r.read_u16_be()?,
+                fragment_id: r.read_u16_be()?,
+                index: r.read_u16_be()?,
+                count: r.read_u16_be()?,
+                data: r.remaining_bytes()?,
+            },
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown packet type {}", packet_type),
+                ))
+            }
         })
     }
 }
@@ -103,5 +181,240 @@ pub enum HazelType {
     Hello = 8,
     Disconnect = 9,
     Acknowledge = 10,
+    Fragment = 11,
     KeepAlive = 12,
 }
+
+/// Reads the reason from a disconnect's payload, which newer revisions send as a flag byte then a
+/// message tagged 0 holding the reason. `None` if there's no reason or it can't be read
+pub fn read_disconnect_reason(data: &[u8]) -> Option<DisconnectReason> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut r = PacketReader::new(data);
+    r.read_u8().ok()?;
+    let (tag, mut r) = r.read_message().ok()?;
+    if tag != 0 {
+        return None;
+    }
+    let code = r.read_u8().ok()?;
+    DisconnectReason::from_value_and_reader(code as i32, &mut r).ok()
+}
+
+/// Writes a disconnect payload saying why, the way `read_disconnect_reason` reads it
+pub fn write_disconnect_reason(w: &mut PacketWriter, reason: &DisconnectReason) {
+    w.write_u8(1);
+    w.start_message(0);
+    w.write_u8(reason.code());
+    if let DisconnectReason::Custom { message } = reason {
+        w.write_string(message);
+    }
+    w.end_message();
+}
+
+/// Adds fields to outgoing packets, for servers on Hazel revisions that expect more than this
+/// sends. Whatever is written goes after the fields already there
+#[allow(unused_variables)]
+pub trait HazelExtension: Debug + Send + Sync {
+    /// Called for every hello, after its payload
+    fn extend_hello(&self, w: &mut PacketWriter) {}
+
+    /// Called for every disconnect, after its reason if it has one
+    fn extend_disconnect(&self, w: &mut PacketWriter) {}
+}
+
+/// Bytes a fragment's header takes up: type, ack id, fragment id, index and count
+pub const FRAGMENT_HEADER_SIZE: usize = 9;
+
+/// The most fragments a payload can have: enough for 64 KiB in 548 byte datagrams, the smallest
+/// MTU IPv4 guarantees. Anything claiming more is dropped rather than allocated for
+pub const MAX_FRAGMENTS: u16 = (65_536 / (548 - FRAGMENT_HEADER_SIZE) + 1) as u16;
+
+/// The most payloads that can be waiting on fragments at once. Starting another forgets the
+/// oldest
+pub const MAX_PARTIALS: usize = 16;
+
+/// Splits a reliable payload into pieces that fit in `max_packet_size` once given a fragment
+/// header
+pub fn fragment(data: &[u8], max_packet_size: usize) -> Vec<&[u8]> {
+    let chunk_size = max_packet_size.saturating_sub(FRAGMENT_HEADER_SIZE).max(1);
+    data.chunks(chunk_size).collect()
+}
+
+/// Puts fragmented payloads back together as their fragments arrive, in any order
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    partial: HashMap<u16, Partial>,
+}
+
+#[derive(Debug)]
+struct Partial {
+    started: Instant,
+    parts: Vec<Option<Vec<u8>>>,
+    missing: usize,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a fragment, returning the whole payload once every fragment of it has arrived
+    ///
+    /// Repeated fragments are ignored, as are ones that don't fit the rest of their payload and
+    /// ones with more than `MAX_FRAGMENTS` siblings
+    pub fn add(
+        &mut self,
+        fragment_id: u16,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+        now: Instant,
+    ) -> Option<Vec<u8>> {
+        if index >= count || count > MAX_FRAGMENTS {
+            return None;
+        }
+        if !self.partial.contains_key(&fragment_id) && self.partial.len() >= MAX_PARTIALS {
+            let oldest = self
+                .partial
+                .iter()
+                .min_by_key(|(_, partial)| partial.started)
+                .map(|(&fragment_id, _)| fragment_id)
+                .unwrap();
+            self.partial.remove(&oldest);
+        }
+        let partial = self.partial.entry(fragment_id).or_insert_with(|| Partial {
+            started: now,
+            parts: vec![None; count as usize],
+            missing: count as usize,
+        });
+        if partial.parts.len() != count as usize {
+            return None;
+        }
+        let part = &mut partial.parts[index as usize];
+        if part.is_some() {
+            return None;
+        }
+        *part = Some(data);
+        partial.missing -= 1;
+        if partial.missing > 0 {
+            return None;
+        }
+        let partial = self.partial.remove(&fragment_id).unwrap();
+        Some(partial.parts.into_iter().flatten().flatten().collect())
+    }
+
+    /// Forgets payloads that have been missing fragments for longer than `timeout`
+    pub fn prune(&mut self, timeout: Duration, now: Instant) {
+        self.partial
+            .retain(|_, partial| now.duration_since(partial.started) < timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::IntoReader;
+
+    #[test]
+    fn test_fragment_round_trip() {
+        let payload = (0..100u8).collect::<Vec<_>>();
+        let pieces = fragment(&payload, FRAGMENT_HEADER_SIZE + 30);
+        assert_eq!(pieces.len(), 4);
+
+        let count = pieces.len() as u16;
+        let packets = pieces
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                HazelPacketOut::Fragment {
+                    ack_id: index as u16 + 1,
+                    fragment_id: 7,
+                    index: index as u16,
+                    count,
+                    data: data.to_vec(),
+                }
+                .serialize_bytes()
+            })
+            .collect::<Vec<_>>();
+        assert!(packets
+            .iter()
+            .all(|packet| packet.len() <= FRAGMENT_HEADER_SIZE + 30));
+
+        // Out of order, with a repeat
+        let mut reassembler = Reassembler::new();
+        let mut result = None;
+        for &i in &[2, 0, 2, 3, 1] {
+            match packets[i]
+                .clone()
+                .into_reader()
+                .read::<HazelPacket>()
+                .unwrap()
+            {
+                HazelPacket::Fragment {
+                    fragment_id,
+                    index,
+                    count,
+                    data,
+                    ..
+                } => {
+                    assert!(result.is_none());
+                    result = reassembler.add(fragment_id, index, count, data, Instant::now());
+                }
+                other => panic!("Expected fragment, got {:?}", other),
+            }
+        }
+        assert_eq!(result, Some(payload));
+    }
+
+    #[test]
+    fn test_reassembler_limits() {
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new();
+        assert_eq!(reassembler.add(1, 0, MAX_FRAGMENTS + 1, vec![1], now), None);
+        assert!(reassembler.partial.is_empty());
+
+        // The oldest partial payload makes way for new ones
+        for fragment_id in 0..MAX_PARTIALS as u16 + 1 {
+            let started = now + Duration::from_millis(fragment_id as u64);
+            assert_eq!(reassembler.add(fragment_id, 0, 2, vec![1], started), None);
+        }
+        assert_eq!(reassembler.partial.len(), MAX_PARTIALS);
+        assert!(!reassembler.partial.contains_key(&0));
+        let newest = MAX_PARTIALS as u16;
+        assert_eq!(
+            reassembler.add(newest, 1, 2, vec![2], now),
+            Some(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn test_disconnect_reason() {
+        let read = |bytes: Vec<u8>| match bytes.into_reader().read::<HazelPacket>().unwrap() {
+            HazelPacket::Disconnect { reason, .. } => reason,
+            other => panic!("Expected disconnect, got {:?}", other),
+        };
+        // Older revisions send the type alone
+        assert_eq!(read(vec![9]), None);
+        assert_eq!(read(vec![9, 1, 1, 0, 0, 6]), Some(DisconnectReason::Banned));
+        let custom = DisconnectReason::Custom {
+            message: "Server closing".to_string(),
+        };
+        let mut w = PacketWriter::new();
+        write_disconnect_reason(&mut w, &custom);
+        let packet = HazelPacketOut::Disconnect { data: w.finish() }.serialize_bytes();
+        assert_eq!(read(packet), Some(custom));
+        // Unreadable payloads still give a disconnect
+        assert_eq!(read(vec![9, 1, 0xff]), None);
+
+        // Trailing data on known types is ignored
+        match vec![10, 0, 5, 0xff, 0x01]
+            .into_reader()
+            .read::<HazelPacket>()
+            .unwrap()
+        {
+            HazelPacket::Acknowledge { ack_id } => assert_eq!(ack_id, 5),
+            other => panic!("Expected acknowledge, got {:?}", other),
+        }
+    }
+}
@@ -3,17 +3,25 @@ use std::{
     net::SocketAddr,
 };
 
+pub use cosmetics::*;
 pub use hazel::*;
 pub use netobjects::*;
 pub use objects::*;
 pub use packets::*;
+pub use registry::*;
+pub use tasks::*;
+pub use version::*;
 
 use crate::reader::{Deserialize, PacketRead, PacketReader};
 
+mod cosmetics;
 mod hazel;
 mod netobjects;
 mod objects;
 mod packets;
+mod registry;
+mod tasks;
+mod version;
 
 impl Deserialize for SocketAddr {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
@@ -1,11 +1,11 @@
-use std::{collections::hash_map::Entry, collections::HashMap, fmt::Debug, io};
+use std::{collections::HashMap, fmt::Debug, io};
 
 use log::{info, warn};
 use num_traits::FromPrimitive;
 
 use crate::reader::{Data, PacketRead, PacketReader, PacketWriter};
 
-use super::{GameInfo, PlayerData, Vector2};
+use super::{GameInfo, GameOptions, PlayerData, TaskInfo, Vector2};
 
 pub trait NetObject: Debug {
     fn update_data(&mut self, r: &mut PacketReader<&[u8]>) -> io::Result<()>;
@@ -43,11 +43,66 @@ macro_rules! net_obj_funcs {
 
 #[derive(Debug)]
 pub enum RPCCallback {
-    ChatMessage { message: String },
+    ChatMessage {
+        message: String,
+    },
+    GameOptions(GameOptions),
+    /// `task_index` is an index into the sender's `PlayerData::tasks`
+    CompleteTask {
+        task_index: u32,
+    },
+    MurderPlayer {
+        target_net_id: u32,
+    },
+    /// Sent by the host as the game starts. The impostors' player ids
+    SetInfected {
+        impostors: Vec<u8>,
+    },
+    /// `reported_player_id` is `None` if the emergency button was pressed
+    MeetingCalled {
+        reported_player_id: Option<u8>,
+    },
+    /// Sent to the host to ask for a meeting. `target_player_id` is `None` if the emergency
+    /// button was pressed
+    ReportBody {
+        target_player_id: Option<u8>,
+    },
+    /// A vote on the meeting screen. `suspect_player_id` is `None` for a skip
+    CastVote {
+        voter_player_id: u8,
+        suspect_player_id: Option<u8>,
+    },
+    /// Sent by the host to take back a vote it couldn't count
+    ClearVote,
+    /// Voting is over. `exiled_player_id` is `None` if no one was voted out. The final votes are
+    /// in `MeetingHud::states`
+    VotingComplete {
+        exiled_player_id: Option<u8>,
+        tie: bool,
+    },
+    /// The voting screen is closing
+    MeetingClosed,
+    /// Sent to the host to ask for a name. The host replies with `SetName`
+    CheckName {
+        name: String,
+    },
+    /// Sent to the host to ask for a color. The host replies with `SetColor`
+    CheckColor {
+        color: u8,
+    },
+    /// Sent to the host by an impostor to shut every door of `room`
+    CloseDoors {
+        room: SystemType,
+    },
+    /// A call id the object doesn't know, e.g. after a game update. The payload is left for the
+    /// caller to keep
+    Unknown {
+        call_id: u8,
+    },
     None,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlayerControl {
     net_id: u32,
     owner_id: i32,
@@ -56,6 +111,15 @@ pub struct PlayerControl {
 }
 
 impl PlayerControl {
+    pub fn new(net_id: u32, owner_id: i32, player_id: u8) -> Self {
+        Self {
+            net_id,
+            owner_id,
+            player_id,
+            name: None,
+        }
+    }
+
     pub fn initialize<T: PacketRead>(
         net_id: u32,
         owner_id: i32,
@@ -70,6 +134,12 @@ impl PlayerControl {
         })
     }
 
+    /// The data sent when the host spawns the player
+    pub fn spawn_data(&self) -> Vec<u8> {
+        // Not new, so there's no spawn animation
+        vec![0, self.player_id]
+    }
+
     pub fn rpc_check_name(&self, name: &str) -> GameInfo {
         let mut w = PacketWriter::new();
         w.write_string(name);
@@ -90,6 +160,17 @@ impl PlayerControl {
         }
     }
 
+    /// `task_index` is an index into the sender's `PlayerData::tasks`
+    pub fn rpc_complete_task(&self, task_index: u32) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(task_index);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::CompleteTask as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
     pub fn rpc_chat_message(&self, message: &str) -> GameInfo {
         let mut w = PacketWriter::new();
         w.write_string(message);
@@ -110,6 +191,65 @@ impl PlayerControl {
         }
     }
 
+    pub fn rpc_set_game_options(&self, options: &GameOptions) -> GameInfo {
+        let mut options_w = PacketWriter::new();
+        options_w.write(options);
+        let options_data = options_w.finish();
+
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(options_data.len() as u32);
+        w.write_bytes_raw(&options_data);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::SetGameOptions as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    pub fn rpc_set_infected(&self, impostor_ids: &[u8]) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(impostor_ids.len() as u32);
+        w.write_bytes_raw(impostor_ids);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::SetInfected as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    /// Sent to the host to ask for a meeting. `target_player_id` is `None` for the emergency
+    /// button
+    pub fn rpc_report_body(&self, target_player_id: Option<u8>) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(target_player_id.unwrap_or(255));
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::ReportBody as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    /// `reported_player_id` is `None` for the emergency button
+    pub fn rpc_meeting_called(&self, reported_player_id: Option<u8>) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(reported_player_id.unwrap_or(255));
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::MeetingCalled as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    pub fn rpc_set_color(&self, color_index: u8) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(color_index);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: PlayerControlRPCType::SetColor as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
     pub fn rpc_set_skin(&self, skin_index: u32) -> GameInfo {
         let mut w = PacketWriter::new();
         w.write_u32_encoded(skin_index);
@@ -175,10 +315,7 @@ impl NetObject for PlayerControl {
     fn handle_rpc(&mut self, call_id: u8, r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
         let call_type = match PlayerControlRPCType::from_u8(call_id) {
             Some(value) => value,
-            None => {
-                warn!("Unknown PlayerControl RPC call id: {}", call_id);
-                return Ok(RPCCallback::None);
-            }
+            None => return Ok(RPCCallback::Unknown { call_id }),
         };
         match call_type {
             PlayerControlRPCType::PlayAnimation => {
@@ -187,32 +324,69 @@ impl NetObject for PlayerControl {
             }
             PlayerControlRPCType::SetInfected => {
                 let count = r.read_u32_encoded()?;
-                for _ in 0..count {
-                    let player_id = r.read_u8()?;
-                    warn!("Unhandled imposter {}", player_id);
-                }
+                let impostors = r.read_slice(count as usize)?.to_vec();
+                return Ok(RPCCallback::SetInfected { impostors });
+            }
+            PlayerControlRPCType::CompleteTask => {
+                let task_index = r.read_u32_encoded()?;
+                return Ok(RPCCallback::CompleteTask { task_index });
             }
             PlayerControlRPCType::SendChat => {
                 let message = r.read_string()?;
                 return Ok(RPCCallback::ChatMessage { message });
             }
+            PlayerControlRPCType::SetGameOptions => {
+                let length = r.read_u32_encoded()?;
+                let options = PacketReader::new(r.read_slice(length as usize)?).read()?;
+                return Ok(RPCCallback::GameOptions(options));
+            }
+            PlayerControlRPCType::MurderPlayer => {
+                let target_net_id = r.read_u32_encoded()?;
+                return Ok(RPCCallback::MurderPlayer { target_net_id });
+            }
+            PlayerControlRPCType::ReportBody => {
+                let target_player_id = match r.read_u8()? {
+                    255 => None,
+                    player_id => Some(player_id),
+                };
+                return Ok(RPCCallback::ReportBody { target_player_id });
+            }
+            PlayerControlRPCType::MeetingCalled => {
+                let reported_player_id = match r.read_u8()? {
+                    255 => None,
+                    player_id => Some(player_id),
+                };
+                return Ok(RPCCallback::MeetingCalled { reported_player_id });
+            }
+            PlayerControlRPCType::CheckName => {
+                let name = r.read_string()?;
+                return Ok(RPCCallback::CheckName { name });
+            }
             PlayerControlRPCType::SetName => {
                 let name = r.read_string()?;
                 self.name = Some(name);
             }
+            PlayerControlRPCType::CheckColor => {
+                let color = r.read_u8()?;
+                return Ok(RPCCallback::CheckColor { color });
+            }
             _ => warn!("Unread PlayerControl RPC call type: {:?}", call_type),
         }
         Ok(RPCCallback::None)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlayerPhysics {
     net_id: u32,
     owner_id: i32,
 }
 
 impl PlayerPhysics {
+    pub fn new(net_id: u32, owner_id: i32) -> Self {
+        Self { net_id, owner_id }
+    }
+
     pub fn initialize<T: PacketRead>(
         net_id: u32,
         owner_id: i32,
@@ -252,8 +426,7 @@ impl NetObject for PlayerPhysics {
     fn handle_rpc(&mut self, call_id: u8, r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
         let call_type = PlayerPhysicsRPCType::from_u8(call_id);
         if call_type.is_none() {
-            warn!("Unknown PlayerPhysics rpc type {}", call_id);
-            return Ok(RPCCallback::None);
+            return Ok(RPCCallback::Unknown { call_id });
         }
         match call_type.unwrap() {
             PlayerPhysicsRPCType::EnterVent => {
@@ -281,7 +454,7 @@ enum PlayerPhysicsRPCType {
     ExitVent = 0x14,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlayerTransform {
     net_id: u32,
     owner_id: i32,
@@ -291,6 +464,16 @@ pub struct PlayerTransform {
 }
 
 impl PlayerTransform {
+    pub fn new(net_id: u32, owner_id: i32, position: Vector2) -> Self {
+        Self {
+            net_id,
+            owner_id,
+            last_seq_id: 0,
+            target_position: position,
+            velocity: Vector2::ZERO,
+        }
+    }
+
     pub fn initialize<T: PacketRead>(
         net_id: u32,
         owner_id: i32,
@@ -305,6 +488,15 @@ impl PlayerTransform {
         })
     }
 
+    /// The data sent when the host spawns the player
+    pub fn spawn_data(&self) -> Vec<u8> {
+        let mut w = PacketWriter::new();
+        w.write_u16(self.last_seq_id);
+        w.write(self.target_position);
+        w.write(self.velocity);
+        w.finish()
+    }
+
     pub fn rpc_snap_to(&mut self, new_pos: Vector2) -> GameInfo {
         let mut w = PacketWriter::new();
         w.write(new_pos);
@@ -315,6 +507,22 @@ impl PlayerTransform {
             data: Data::Bytes(w.finish()),
         }
     }
+
+    /// A movement snapshot, like the ones the game streams while walking around. Advances the
+    /// sequence id so the newest snapshot wins when they arrive out of order
+    pub fn data_movement(&mut self, position: Vector2, velocity: Vector2) -> GameInfo {
+        self.last_seq_id = self.last_seq_id.wrapping_add(1);
+        self.target_position = position;
+        self.velocity = velocity;
+        let mut w = PacketWriter::new();
+        w.write_u16(self.last_seq_id);
+        w.write(position);
+        w.write(velocity);
+        GameInfo::UpdateData {
+            net_id: self.net_id,
+            data: Data::Bytes(w.finish()),
+        }
+    }
 }
 
 impl NetObject for PlayerTransform {
@@ -334,7 +542,7 @@ impl NetObject for PlayerTransform {
                 self.last_seq_id = r.read_u16()?;
                 self.velocity = Vector2::ZERO;
             }
-            _ => warn!("Unknown PlayerTransform call id: {}", call_id),
+            _ => return Ok(RPCCallback::Unknown { call_id }),
         }
         Ok(RPCCallback::None)
     }
@@ -348,7 +556,7 @@ enum PlayerTransformRPCType {
 /// The game world
 ///
 /// Also known as ShipStatus, Ship and would probably just be considered a scene in Unity
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct World {
     net_id: u32,
     owner_id: i32,
@@ -383,6 +591,45 @@ pub struct World {
 }
 
 impl World {
+    /// The Skeld at the start of a game, with nothing sabotaged and every door open
+    pub fn new(net_id: u32, owner_id: i32) -> Self {
+        Self {
+            net_id,
+            owner_id,
+            reactor_countdown: 10000.,
+            user_console_pairs: Vec::new(),
+            expected_switches: 0,
+            actual_switches: 0,
+            elec_value: 255,
+            life_supp_countdown: 10000.,
+            completed_consoles: Vec::new(),
+            med_user_list: Vec::new(),
+            camera_in_use: false,
+            comms_active: false,
+            door_open: vec![true; 13],
+            sabotage_timer: 0.,
+        }
+    }
+
+    /// The systems currently sabotaged. Reactor and life support count down from 10000 when
+    /// they are
+    pub fn sabotaged_systems(&self) -> Vec<SystemType> {
+        let mut systems = Vec::new();
+        if self.reactor_countdown < 10000. {
+            systems.push(SystemType::Reactor);
+        }
+        if self.life_supp_countdown < 10000. {
+            systems.push(SystemType::LifeSupport);
+        }
+        if self.actual_switches != self.expected_switches {
+            systems.push(SystemType::Electrical);
+        }
+        if self.comms_active {
+            systems.push(SystemType::Communications);
+        }
+        systems
+    }
+
     pub fn initialize<T: PacketRead>(
         net_id: u32,
         owner_id: i32,
@@ -429,6 +676,75 @@ impl World {
             sabotage_timer: r.read_f32()?,
         })
     }
+
+    /// The data sent when the host spawns the world, in the same order `initialize` reads it
+    pub fn spawn_data(&self) -> Vec<u8> {
+        let mut w = PacketWriter::new();
+        w.write_f32(self.reactor_countdown);
+        w.write_u32_encoded(self.user_console_pairs.len() as u32);
+        for (user, console) in self.user_console_pairs.iter() {
+            w.write_u8(*user);
+            w.write_u8(*console);
+        }
+        w.write_u8(self.expected_switches);
+        w.write_u8(self.actual_switches);
+        w.write_u8(self.elec_value);
+        w.write_f32(self.life_supp_countdown);
+        w.write_u32_encoded(self.completed_consoles.len() as u32);
+        for console in self.completed_consoles.iter() {
+            w.write_u32_encoded(*console);
+        }
+        w.write_u32_encoded(self.med_user_list.len() as u32);
+        for user in self.med_user_list.iter() {
+            w.write_i8(*user);
+        }
+        w.write_bool(self.camera_in_use);
+        w.write_bool(self.comms_active);
+        for open in self.door_open.iter() {
+            w.write_bool(*open);
+        }
+        w.write_f32(self.sabotage_timer);
+        w.finish()
+    }
+
+    /// The room door `door` is in. `door` is an index into `door_open`
+    pub fn door_room(door: usize) -> Option<SystemType> {
+        SKELD_DOORS.get(door).copied()
+    }
+
+    /// Indexes into `door_open` of every door in `room`
+    pub fn doors_of(room: SystemType) -> Vec<usize> {
+        (0..SKELD_DOORS.len())
+            .filter(|&door| SKELD_DOORS[door] == room)
+            .collect()
+    }
+
+    /// Sends the doors that have changed. Bit `n` of `dirty` is set if `door_open[n]` has changed
+    pub fn doors_update_info(&self, dirty: u32) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(1 << SystemType::Doors as u32);
+        w.write_u32_encoded(dirty);
+        for (index, open) in self.door_open.iter().enumerate() {
+            if dirty & (1 << index) > 0 {
+                w.write_bool(*open);
+            }
+        }
+        GameInfo::UpdateData {
+            net_id: self.net_id,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    /// Sent to the host by impostors to shut every door of a room for a while
+    pub fn rpc_close_doors(&self, room: SystemType) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(room as u8);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: WorldRPCType::CloseDoorsOfType as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
 }
 
 impl NetObject for World {
@@ -492,12 +808,15 @@ impl NetObject for World {
     }
 
     fn handle_rpc(&mut self, call_id: u8, r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
-        match call_id {
-            0 => {
+        match WorldRPCType::from_u8(call_id) {
+            Some(WorldRPCType::CloseDoorsOfType) => {
                 let room_type = r.read_u8()?;
-                warn!("Unhandled door close Room Type: {}", room_type);
+                match SystemType::from_u8(room_type) {
+                    Some(room) => return Ok(RPCCallback::CloseDoors { room }),
+                    None => warn!("Door close for unknown room type {}", room_type),
+                }
             }
-            1 => {
+            Some(WorldRPCType::RepairSystem) => {
                 let system_type = r.read_u8()?;
                 let player_net_id = r.read_u32_encoded()?;
                 let amount = r.read_u8()?;
@@ -506,19 +825,81 @@ impl NetObject for World {
                     system_type, player_net_id, amount
                 );
             }
-            _ => warn!("Unknown World rpc call {}", call_id),
+            None => return Ok(RPCCallback::Unknown { call_id }),
         }
         Ok(RPCCallback::None)
     }
 }
 
-#[derive(Debug)]
+/// The room each of the Skeld's doors is in, in `World::door_open` order
+const SKELD_DOORS: [SystemType; 13] = [
+    SystemType::Cafeteria,
+    SystemType::Storage,
+    SystemType::UpperEngine,
+    SystemType::Cafeteria,
+    SystemType::LowerEngine,
+    SystemType::Storage,
+    SystemType::UpperEngine,
+    SystemType::Security,
+    SystemType::Cafeteria,
+    SystemType::Electrical,
+    SystemType::MedBay,
+    SystemType::LowerEngine,
+    SystemType::Storage,
+];
+
+#[derive(Debug, Copy, Clone, FromPrimitive)]
+enum WorldRPCType {
+    CloseDoorsOfType = 0,
+    RepairSystem = 1,
+}
+
+/// Rooms and the systems in them. `World::update_data` uses these as bit indexes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum SystemType {
+    Hallway = 0,
+    Storage = 1,
+    Cafeteria = 2,
+    Reactor = 3,
+    UpperEngine = 4,
+    Navigation = 5,
+    Admin = 6,
+    Electrical = 7,
+    LifeSupport = 8,
+    Shields = 9,
+    MedBay = 10,
+    Security = 11,
+    Weapons = 12,
+    LowerEngine = 13,
+    Communications = 14,
+    ShipTasks = 15,
+    Doors = 16,
+    Sabotage = 17,
+    Decontamination = 18,
+    Launchpad = 19,
+    LockerRoom = 20,
+    Laboratory = 21,
+    Balcony = 22,
+    Office = 23,
+    Greenhouse = 24,
+    Dropship = 25,
+    Decontamination2 = 26,
+    Outside = 27,
+    Specimens = 28,
+    BoilerRoom = 29,
+}
+
+#[derive(Debug, Clone)]
 pub struct Lobby {
     net_id: u32,
     owner_id: i32,
 }
 
 impl Lobby {
+    pub fn new(net_id: u32, owner_id: i32) -> Self {
+        Self { net_id, owner_id }
+    }
+
     pub fn initialize<T: PacketRead>(net_id: u32, owner_id: i32, _: &mut PacketReader<T>) -> Self {
         Self { net_id, owner_id }
     }
@@ -533,37 +914,90 @@ impl NetObject for Lobby {
     }
 
     fn handle_rpc(&mut self, call_id: u8, _r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
-        warn!("Unknown Lobby RPC call {}", call_id);
-        Ok(RPCCallback::None)
+        Ok(RPCCallback::Unknown { call_id })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GameData {
     net_id: u32,
     owner_id: i32,
     pub players: HashMap<u8, PlayerData>,
+    /// Player ids in the order they were added, which is the order the game lists them in
+    order: Vec<u8>,
 }
 
 impl GameData {
+    pub fn new(net_id: u32, owner_id: i32) -> Self {
+        Self {
+            net_id,
+            owner_id,
+            players: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
     pub fn initialize<T: PacketRead>(
         net_id: u32,
         owner_id: i32,
         data: &mut PacketReader<T>,
     ) -> io::Result<Self> {
-        Ok(Self {
-            net_id,
-            owner_id,
-            players: {
-                (0..data.read_u32_encoded()?)
-                    .map(|_| match (data.read_u8(), data.read()) {
-                        (Ok(id), Ok(data)) => Ok((id, data)),
-                        (Err(a), _) => Err(a),
-                        (_, Err(b)) => Err(b),
-                    })
-                    .collect::<io::Result<_>>()?
-            },
-        })
+        let mut game_data = Self::new(net_id, owner_id);
+        for _ in 0..data.read_u32_encoded()? {
+            let player_id = data.read_u8()?;
+            game_data.add_player(player_id, data.read()?);
+        }
+        Ok(game_data)
+    }
+
+    /// Adds or replaces a player. New ones go on the end of `player_ids`
+    pub fn add_player(&mut self, player_id: u8, data: PlayerData) {
+        if self.players.insert(player_id, data).is_none() {
+            self.order.push(player_id);
+        }
+    }
+
+    /// Every player id in the order the game lists them, which is the order they were added.
+    /// Players put straight into `players` come last, by player id
+    pub fn player_ids(&self) -> Vec<u8> {
+        let mut player_ids = self
+            .order
+            .iter()
+            .copied()
+            .filter(|player_id| self.players.contains_key(player_id))
+            .collect::<Vec<_>>();
+        let mut rest = self
+            .players
+            .keys()
+            .copied()
+            .filter(|player_id| !self.order.contains(player_id))
+            .collect::<Vec<_>>();
+        rest.sort_unstable();
+        player_ids.extend(rest);
+        player_ids
+    }
+
+    /// The data sent when the host spawns the game data, which is every player
+    pub fn spawn_data(&self) -> Vec<u8> {
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(self.players.len() as u32);
+        for player_id in self.player_ids() {
+            w.write_u8(player_id);
+            w.write(&self.players[&player_id]);
+        }
+        w.finish()
+    }
+
+    pub fn rpc_set_tasks(&self, player_id: u8, task_ids: &[u8]) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(player_id);
+        w.write_u32_encoded(task_ids.len() as u32);
+        w.write_bytes_raw(task_ids);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: GameDataRPCType::SetTasks as u8,
+            data: Data::Bytes(w.finish()),
+        }
     }
 
     pub fn rpc_update_player_info(&mut self) -> GameInfo {
@@ -590,28 +1024,37 @@ impl NetObject for GameData {
     fn update_data(&mut self, r: &mut PacketReader<&[u8]>) -> io::Result<()> {
         for _ in 0..r.read_u8()? {
             let player_id = r.read_u8()?;
-            self.players.insert(player_id, r.read()?);
+            self.add_player(player_id, r.read()?);
         }
         Ok(())
     }
 
     fn handle_rpc(&mut self, call_id: u8, r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
         match GameDataRPCType::from_u8(call_id) {
+            Some(GameDataRPCType::SetTasks) => {
+                let player_id = r.read_u8()?;
+                let count = r.read_u32_encoded()?;
+                let task_ids = r.read_slice(count as usize)?;
+                match self.players.get_mut(&player_id) {
+                    Some(data) => {
+                        data.tasks = task_ids
+                            .iter()
+                            .map(|&task_id| TaskInfo::new(task_id as u32))
+                            .collect();
+                    }
+                    None => warn!("Tasks for unknown player {}", player_id),
+                }
+            }
             Some(GameDataRPCType::UpdatePlayerInfo) => {
                 while r.remaining() > 0 {
                     let (tag, mut r) = r.read_message()?;
-                    match self.players.entry(tag) {
-                        Entry::Occupied(mut value) => {
-                            value.get_mut().update_data(&mut r)?;
-                        }
-                        Entry::Vacant(value) => {
-                            value.insert(r.read()?);
-                        }
-                    };
+                    match self.players.get_mut(&tag) {
+                        Some(data) => data.update_data(&mut r)?,
+                        None => self.add_player(tag, r.read()?),
+                    }
                 }
             }
-            Some(value) => warn!("Unhandled GameData RPC call {:?}", value),
-            None => warn!("Unknown GameData RPC call {}", call_id),
+            None => return Ok(RPCCallback::Unknown { call_id }),
         }
         Ok(RPCCallback::None)
     }
@@ -623,13 +1066,234 @@ enum GameDataRPCType {
     UpdatePlayerInfo = 0x1e,
 }
 
-#[derive(Debug)]
+/// The voting screen. Spawned by the host when a meeting is called and despawned once it's over
+#[derive(Debug, Clone)]
+pub struct MeetingHud {
+    net_id: u32,
+    owner_id: i32,
+    /// One per player, in the order `GameData::player_ids` gives them
+    pub states: Vec<VoteState>,
+}
+
+/// A player's area on the voting screen
+#[derive(Debug, Copy, Clone)]
+pub struct VoteState {
+    pub player_id: u8,
+    /// Who they voted for. `None` with `did_vote` set means they skipped, sent as 0x0f
+    pub voted_for: Option<u8>,
+    pub is_dead: bool,
+    pub did_vote: bool,
+    pub did_report: bool,
+}
+
+impl VoteState {
+    /// Packs the state into a byte, without the player id which is implied by the order
+    pub fn to_byte(&self) -> u8 {
+        let voted_for = match self.voted_for {
+            Some(player_id) => player_id.wrapping_add(1) & 0x0f,
+            None if self.did_vote => 0x0f,
+            None => 0,
+        };
+        voted_for
+            | if self.is_dead { 0x80 } else { 0 }
+            | if self.did_vote { 0x40 } else { 0 }
+            | if self.did_report { 0x20 } else { 0 }
+    }
+
+    pub fn from_byte(player_id: u8, value: u8) -> Self {
+        Self {
+            player_id,
+            voted_for: match value & 0x0f {
+                0 | 0x0f => None,
+                voted_for => Some(voted_for - 1),
+            },
+            is_dead: value & 0x80 > 0,
+            did_vote: value & 0x40 > 0,
+            did_report: value & 0x20 > 0,
+        }
+    }
+}
+
+impl MeetingHud {
+    pub fn new(net_id: u32, owner_id: i32, states: Vec<VoteState>) -> Self {
+        Self {
+            net_id,
+            owner_id,
+            states,
+        }
+    }
+
+    /// Reads the states sent on spawn. They don't say whose they are, so player ids count up from
+    /// 0 until `set_player_ids` is called
+    pub fn initialize<T: PacketRead>(
+        net_id: u32,
+        owner_id: i32,
+        r: &mut PacketReader<T>,
+    ) -> io::Result<Self> {
+        let states = r
+            .remaining_bytes()?
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| VoteState::from_byte(index as u8, value))
+            .collect();
+        Ok(Self::new(net_id, owner_id, states))
+    }
+
+    /// Gives the states their players, in the same order as `states`. That's
+    /// `GameData::player_ids`
+    pub fn set_player_ids(&mut self, player_ids: &[u8]) {
+        for (state, player_id) in self.states.iter_mut().zip(player_ids) {
+            state.player_id = *player_id;
+        }
+    }
+
+    /// Replaces every state from the packed bytes, keeping player ids
+    fn read_states<T: PacketRead>(&mut self, r: &mut PacketReader<T>) -> io::Result<()> {
+        let count = r.read_u32_encoded()? as usize;
+        let values = r.read_slice(count)?;
+        for (state, value) in self.states.iter_mut().zip(values) {
+            *state = VoteState::from_byte(state.player_id, *value);
+        }
+        Ok(())
+    }
+
+    /// The data sent when the host spawns the meeting
+    pub fn spawn_data(&self) -> Vec<u8> {
+        self.states.iter().map(VoteState::to_byte).collect()
+    }
+
+    /// Sends the states that have changed. Bit `n` of `dirty` is set if `states[n]` has changed
+    pub fn update_data_info(&self, dirty: u32) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(dirty);
+        for (index, state) in self.states.iter().enumerate() {
+            if dirty & (1 << index) > 0 {
+                w.write_u8(state.to_byte());
+            }
+        }
+        GameInfo::UpdateData {
+            net_id: self.net_id,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    /// Ends voting. `exiled_player_id` is `None` if no one was voted out
+    pub fn rpc_voting_complete(&self, exiled_player_id: Option<u8>, tie: bool) -> GameInfo {
+        let states = self.spawn_data();
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(states.len() as u32);
+        w.write_bytes_raw(&states);
+        w.write_u8(exiled_player_id.unwrap_or(255));
+        w.write_bool(tie);
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: MeetingHudRPCType::VotingComplete as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    pub fn rpc_close(&self) -> GameInfo {
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: MeetingHudRPCType::Close as u8,
+            data: Data::Bytes(Vec::new()),
+        }
+    }
+
+    /// Sent to the host. `suspect_player_id` is `None` to skip
+    pub fn rpc_cast_vote(&self, voter_player_id: u8, suspect_player_id: Option<u8>) -> GameInfo {
+        let mut w = PacketWriter::new();
+        w.write_u8(voter_player_id);
+        w.write_u8(suspect_player_id.unwrap_or(255));
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: MeetingHudRPCType::CastVote as u8,
+            data: Data::Bytes(w.finish()),
+        }
+    }
+
+    /// Sent by the host to the voter whose vote is taken back
+    pub fn rpc_clear_vote(&self) -> GameInfo {
+        GameInfo::RPC {
+            net_id: self.net_id,
+            call_id: MeetingHudRPCType::ClearVote as u8,
+            data: Data::Bytes(Vec::new()),
+        }
+    }
+}
+
+impl NetObject for MeetingHud {
+    net_obj_funcs!();
+
+    fn update_data(&mut self, r: &mut PacketReader<&[u8]>) -> io::Result<()> {
+        let dirty = r.read_u32_encoded()?;
+        for (index, state) in self.states.iter_mut().enumerate() {
+            if dirty & (1 << index) > 0 {
+                *state = VoteState::from_byte(state.player_id, r.read_u8()?);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_rpc(&mut self, call_id: u8, r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
+        match MeetingHudRPCType::from_u8(call_id) {
+            Some(MeetingHudRPCType::CastVote) => {
+                let voter_player_id = r.read_u8()?;
+                let suspect_player_id = match r.read_u8()? {
+                    255 => None,
+                    player_id => Some(player_id),
+                };
+                return Ok(RPCCallback::CastVote {
+                    voter_player_id,
+                    suspect_player_id,
+                });
+            }
+            Some(MeetingHudRPCType::ClearVote) => return Ok(RPCCallback::ClearVote),
+            Some(MeetingHudRPCType::VotingComplete) => {
+                self.read_states(r)?;
+                let exiled_player_id = match r.read_u8()? {
+                    255 => None,
+                    player_id => Some(player_id),
+                };
+                let tie = r.read_bool()?;
+                return Ok(RPCCallback::VotingComplete {
+                    exiled_player_id,
+                    tie,
+                });
+            }
+            Some(MeetingHudRPCType::Close) => return Ok(RPCCallback::MeetingClosed),
+            Some(value) => warn!("Unhandled MeetingHud RPC call {:?}", value),
+            None => return Ok(RPCCallback::Unknown { call_id }),
+        }
+        Ok(RPCCallback::None)
+    }
+}
+
+#[derive(Debug, Copy, Clone, FromPrimitive)]
+enum MeetingHudRPCType {
+    Close = 22,
+    VotingComplete = 23,
+    CastVote = 24,
+    ClearVote = 25,
+    AddVote = 26,
+}
+
+#[derive(Debug, Clone)]
 pub struct VoteBanSystem {
     net_id: u32,
     owner_id: i32,
 }
 
 impl VoteBanSystem {
+    pub fn new(net_id: u32, owner_id: i32) -> Self {
+        Self { net_id, owner_id }
+    }
+
+    /// No votes, which is all that's tracked
+    pub fn spawn_data(&self) -> Vec<u8> {
+        vec![0]
+    }
+
     pub fn initialize(net_id: u32, owner_id: i32, r: &mut PacketReader<&[u8]>) -> io::Result<Self> {
         let mut obj = Self { net_id, owner_id };
         obj.update_data(r)?;
@@ -650,7 +1314,6 @@ impl NetObject for VoteBanSystem {
     }
 
     fn handle_rpc(&mut self, call_id: u8, _r: &mut PacketReader<&[u8]>) -> io::Result<RPCCallback> {
-        warn!("Unknown VoteBanSystem RPC call {}", call_id);
-        Ok(RPCCallback::None)
+        Ok(RPCCallback::Unknown { call_id })
     }
 }
@@ -121,7 +121,20 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Maps {
+    /// The map for a `GameOptions::map_id`, where 0 is the Skeld, 1 Mira HQ and 2 Polus. The ids
+    /// don't line up with the flags' bits
+    pub fn from_map_id(map_id: u8) -> Option<Self> {
+        match map_id {
+            0 => Some(Self::SKELD),
+            1 => Some(Self::MIRA_HQ),
+            2 => Some(Self::PORUS),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Address {
     pub ip: [u8; 4],
     pub port: u16,
@@ -143,6 +156,13 @@ impl Display for Address {
     }
 }
 
+impl Serialize for Address {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_bytes_raw(&self.ip);
+        w.write_u16(self.port);
+    }
+}
+
 impl Deserialize for Address {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
         Ok(Address {
@@ -152,6 +172,10 @@ impl Deserialize for Address {
     }
 }
 
+/// One game in a `GameListPacket`
+///
+/// There's no language here. Servers only use a lobby's language to filter what `GetGameList`
+/// returns, and don't send it back in the listing
 #[derive(Debug, Clone)]
 pub struct GameListing {
     pub address: Address,
@@ -164,6 +188,58 @@ pub struct GameListing {
     pub max_players: u8,
 }
 
+bitflags! {
+    /// Reasons a game listing looks suspicious. Crasher and honeypot lobbies often show up with
+    /// values the official server would never list
+    pub struct ListingFlags: u8 {
+        /// More players than the max, or a max outside what the game allows
+        const IMPOSSIBLE_PLAYER_COUNT = 0x1;
+        /// No impostors, or more than the game allows
+        const IMPOSSIBLE_IMPOSTER_COUNT = 0x2;
+        /// Listed for far longer than any real lobby stays open
+        const ABSURD_AGE = 0x4;
+        /// The same code was seen hosted on a different address
+        const DUPLICATE_CODE = 0x8;
+        /// The host name matches a blacklisted pattern
+        const BLACKLISTED_HOST = 0x10;
+    }
+}
+
+/// Game listings older than this are flagged with `ListingFlags::ABSURD_AGE`
+pub const MAX_LISTING_AGE: u32 = 60 * 60 * 24;
+
+impl GameListing {
+    /// Flags anything about this listing on its own that couldn't come from a real lobby
+    ///
+    /// `DUPLICATE_CODE` and `BLACKLISTED_HOST` need more context so are never set here
+    pub fn flags(&self) -> ListingFlags {
+        let mut flags = ListingFlags::empty();
+        if self.player_count > self.max_players || !(4..=10).contains(&self.max_players) {
+            flags |= ListingFlags::IMPOSSIBLE_PLAYER_COUNT;
+        }
+        if !(1..=3).contains(&self.num_imposters) {
+            flags |= ListingFlags::IMPOSSIBLE_IMPOSTER_COUNT;
+        }
+        if self.age > MAX_LISTING_AGE {
+            flags |= ListingFlags::ABSURD_AGE;
+        }
+        flags
+    }
+}
+
+impl Serialize for GameListing {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write(&self.address);
+        w.write(self.id);
+        w.write_string(&self.host_username);
+        w.write_u8(self.player_count);
+        w.write_u32_encoded(self.age);
+        w.write_u8(self.map_id.bits());
+        w.write_u8(self.num_imposters);
+        w.write_u8(self.max_players);
+    }
+}
+
 impl Deserialize for GameListing {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
         Ok(Self {
@@ -180,7 +256,10 @@ impl Deserialize for GameListing {
     }
 }
 
-#[derive(Debug)]
+/// Lobby settings, laid out as `game_settings_version` says. Fields newer than the version are
+/// skipped when written and left at their defaults when read. See
+/// `GameVersion::game_options_version`
+#[derive(Debug, Clone)]
 pub struct GameOptions {
     pub game_settings_version: u8,
     pub max_players: u8,
@@ -199,7 +278,16 @@ pub struct GameOptions {
     pub discussion_time: i32,
     pub voting_time: i32,
     pub is_defaults: u8,
+    /// Version 2 on
     pub emergency_cooldown: u8,
+    /// Version 3 on
+    pub confirm_ejects: bool,
+    /// Version 3 on
+    pub visual_tasks: bool,
+    /// Version 4 on
+    pub anonymous_votes: bool,
+    /// 0 always, 1 during meetings, 2 never. Version 4 on
+    pub task_bar_updates: u8,
 }
 
 impl Default for GameOptions {
@@ -223,16 +311,21 @@ impl Default for GameOptions {
             voting_time: 120,
             is_defaults: 1,
             emergency_cooldown: 15,
+            confirm_ejects: true,
+            visual_tasks: true,
+            anonymous_votes: false,
+            task_bar_updates: 0,
         }
     }
 }
 
 impl Deserialize for GameOptions {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
-        Ok(Self {
+        let mut options = Self {
             game_settings_version: r.read_u8()?,
             max_players: r.read_u8()?,
-            language: Languages::from_bits(r.read_u32()?).unwrap(),
+            language: Languages::from_bits(r.read_u32()?)
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid language"))?,
             map_id: r.read_u8()?,
             player_speed: r.read_f32()?,
             crew_light: r.read_f32()?,
@@ -247,8 +340,20 @@ impl Deserialize for GameOptions {
             discussion_time: r.read_i32()?,
             voting_time: r.read_i32()?,
             is_defaults: r.read_u8()?,
-            emergency_cooldown: r.read_u8()?,
-        })
+            ..Default::default()
+        };
+        if options.game_settings_version >= 2 {
+            options.emergency_cooldown = r.read_u8()?;
+        }
+        if options.game_settings_version >= 3 {
+            options.confirm_ejects = r.read_bool()?;
+            options.visual_tasks = r.read_bool()?;
+        }
+        if options.game_settings_version >= 4 {
+            options.anonymous_votes = r.read_bool()?;
+            options.task_bar_updates = r.read_u8()?;
+        }
+        Ok(options)
     }
 }
 
@@ -271,7 +376,201 @@ impl Serialize for &GameOptions {
         w.write_i32(self.discussion_time);
         w.write_i32(self.voting_time);
         w.write_u8(self.is_defaults);
-        w.write_u8(self.emergency_cooldown);
+        if self.game_settings_version >= 2 {
+            w.write_u8(self.emergency_cooldown);
+        }
+        if self.game_settings_version >= 3 {
+            w.write_bool(self.confirm_ejects);
+            w.write_bool(self.visual_tasks);
+        }
+        if self.game_settings_version >= 4 {
+            w.write_bool(self.anonymous_votes);
+            w.write_u8(self.task_bar_updates);
+        }
+    }
+}
+
+/// Named sets of `GameOptions` for hosting
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OptionsPreset {
+    /// The official defaults for a full lobby
+    Classic,
+    /// One impostor who can barely see, crew who can't call meetings
+    HideAndSeek,
+    /// Settings most ranked and league lobbies settle on
+    Competitive,
+}
+
+impl OptionsPreset {
+    pub const ALL: [OptionsPreset; 3] = [
+        OptionsPreset::Classic,
+        OptionsPreset::HideAndSeek,
+        OptionsPreset::Competitive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OptionsPreset::Classic => "Classic",
+            OptionsPreset::HideAndSeek => "Hide & Seek",
+            OptionsPreset::Competitive => "Competitive",
+        }
+    }
+}
+
+/// A `GameOptions` value the game would reject
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidOption {
+    MaxPlayers(u8),
+    Map(u8),
+    PlayerSpeed(f32),
+    CrewLight(f32),
+    ImposterLight(f32),
+    KillCooldown(f32),
+    CommonTasks(u8),
+    LongTasks(u8),
+    ShortTasks(u8),
+    EmergencyMeetings(i32),
+    /// Out of range, or too many for `max_players`
+    Imposters(i8),
+    KillDistance(i8),
+    DiscussionTime(i32),
+    VotingTime(i32),
+    EmergencyCooldown(u8),
+}
+
+impl Display for InvalidOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidOption::MaxPlayers(v) => write!(f, "Max players {} not in 4-10", v),
+            InvalidOption::Map(v) => write!(f, "Unknown map {}", v),
+            InvalidOption::PlayerSpeed(v) => write!(f, "Player speed {} not in 0.5-3", v),
+            InvalidOption::CrewLight(v) => write!(f, "Crew light {} not in 0.25-5", v),
+            InvalidOption::ImposterLight(v) => write!(f, "Imposter light {} not in 0.25-5", v),
+            InvalidOption::KillCooldown(v) => write!(f, "Kill cooldown {} not in 10-60", v),
+            InvalidOption::CommonTasks(v) => write!(f, "Common tasks {} not in 0-2", v),
+            InvalidOption::LongTasks(v) => write!(f, "Long tasks {} not in 0-3", v),
+            InvalidOption::ShortTasks(v) => write!(f, "Short tasks {} not in 0-5", v),
+            InvalidOption::EmergencyMeetings(v) => {
+                write!(f, "Emergency meetings {} not in 0-9", v)
+            }
+            InvalidOption::Imposters(v) => write!(f, "{} imposters not allowed", v),
+            InvalidOption::KillDistance(v) => write!(f, "Kill distance {} not in 0-2", v),
+            InvalidOption::DiscussionTime(v) => write!(f, "Discussion time {} not in 0-120", v),
+            InvalidOption::VotingTime(v) => write!(f, "Voting time {} not in 0-300", v),
+            InvalidOption::EmergencyCooldown(v) => {
+                write!(f, "Emergency cooldown {} not in 0-60", v)
+            }
+        }
+    }
+}
+
+impl GameOptions {
+    /// The options for a preset, on the default map and language
+    pub fn preset(preset: OptionsPreset) -> Self {
+        let classic = Self {
+            num_imposters: 2,
+            ..Default::default()
+        };
+        match preset {
+            OptionsPreset::Classic => classic,
+            OptionsPreset::HideAndSeek => Self {
+                player_speed: 1.5,
+                crew_light: 1.,
+                imposter_light: 0.25,
+                kill_cooldown: 10.,
+                num_emergency_meetings: 0,
+                num_imposters: 1,
+                kill_distance: 0,
+                discussion_time: 0,
+                voting_time: 15,
+                is_defaults: 0,
+                ..classic
+            },
+            OptionsPreset::Competitive => Self {
+                player_speed: 1.25,
+                crew_light: 0.75,
+                imposter_light: 1.5,
+                kill_cooldown: 25.,
+                num_common_tasks: 1,
+                num_long_tasks: 1,
+                num_short_tasks: 3,
+                num_emergency_meetings: 1,
+                kill_distance: 0,
+                discussion_time: 15,
+                voting_time: 120,
+                is_defaults: 0,
+                emergency_cooldown: 20,
+                ..classic
+            },
+        }
+    }
+
+    /// The most imposters the game allows with `max_players`
+    pub fn max_imposters(max_players: u8) -> i8 {
+        match max_players {
+            0..=6 => 1,
+            7..=8 => 2,
+            _ => 3,
+        }
+    }
+
+    /// Checks every value is one the game accepts from a host
+    ///
+    /// `num_imposters` of 0 only means "any" when searching so is rejected here
+    pub fn validate(&self) -> Result<(), Vec<InvalidOption>> {
+        let mut invalid = Vec::new();
+        if !(4..=10).contains(&self.max_players) {
+            invalid.push(InvalidOption::MaxPlayers(self.max_players));
+        }
+        if Maps::from_map_id(self.map_id).is_none() {
+            invalid.push(InvalidOption::Map(self.map_id));
+        }
+        if !(0.5..=3.).contains(&self.player_speed) {
+            invalid.push(InvalidOption::PlayerSpeed(self.player_speed));
+        }
+        if !(0.25..=5.).contains(&self.crew_light) {
+            invalid.push(InvalidOption::CrewLight(self.crew_light));
+        }
+        if !(0.25..=5.).contains(&self.imposter_light) {
+            invalid.push(InvalidOption::ImposterLight(self.imposter_light));
+        }
+        if !(10.0..=60.).contains(&self.kill_cooldown) {
+            invalid.push(InvalidOption::KillCooldown(self.kill_cooldown));
+        }
+        if self.num_common_tasks > 2 {
+            invalid.push(InvalidOption::CommonTasks(self.num_common_tasks));
+        }
+        if self.num_long_tasks > 3 {
+            invalid.push(InvalidOption::LongTasks(self.num_long_tasks));
+        }
+        if self.num_short_tasks > 5 {
+            invalid.push(InvalidOption::ShortTasks(self.num_short_tasks));
+        }
+        if !(0..=9).contains(&self.num_emergency_meetings) {
+            invalid.push(InvalidOption::EmergencyMeetings(
+                self.num_emergency_meetings,
+            ));
+        }
+        if !(1..=Self::max_imposters(self.max_players)).contains(&self.num_imposters) {
+            invalid.push(InvalidOption::Imposters(self.num_imposters));
+        }
+        if !(0..=2).contains(&self.kill_distance) {
+            invalid.push(InvalidOption::KillDistance(self.kill_distance));
+        }
+        if !(0..=120).contains(&self.discussion_time) {
+            invalid.push(InvalidOption::DiscussionTime(self.discussion_time));
+        }
+        if !(0..=300).contains(&self.voting_time) {
+            invalid.push(InvalidOption::VotingTime(self.voting_time));
+        }
+        if self.emergency_cooldown > 60 {
+            invalid.push(InvalidOption::EmergencyCooldown(self.emergency_cooldown));
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
     }
 }
 
@@ -309,28 +608,36 @@ impl Vector2 {
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
 }
 
 impl Deserialize for Vector2 {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
         let v = r.read_u16()? as f32 / 65535.;
         let v2 = r.read_u16()? as f32 / 65535.;
-        let x = (v.max(0.).min(1.) * 80.) - 40.;
-        let y = (v2.max(0.).min(1.) * 80.) - 40.;
+        let x = (v.clamp(0., 1.) * 80.) - 40.;
+        let y = (v2.clamp(0., 1.) * 80.) - 40.;
         Ok(Self { x, y })
     }
 }
 
 impl Serialize for Vector2 {
     fn serialize(&self, w: &mut PacketWriter) {
-        let v = ((self.x / 80.) + 40.).max(0.).min(1.);
-        let v2 = ((self.y / 80.) + 40.).max(0.).min(1.);
-        w.write_u16((v * 65555.) as u16);
-        w.write_u16((v2 * 65555.) as u16);
+        let v = ((self.x + 40.) / 80.).clamp(0., 1.);
+        let v2 = ((self.y + 40.) / 80.).clamp(0., 1.);
+        w.write_u16((v * 65535.).round() as u16);
+        w.write_u16((v2 * 65535.).round() as u16);
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct PlayerData {
     pub name: String,
     pub color: u8,
@@ -394,12 +701,34 @@ impl Deserialize for PlayerData {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TaskInfo {
     id: u32,
     complete: bool,
 }
 
+impl TaskInfo {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            complete: false,
+        }
+    }
+
+    /// The task's id in the map's `TaskPool`
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn set_complete(&mut self) {
+        self.complete = true;
+    }
+}
+
 impl Serialize for TaskInfo {
     fn serialize(&self, w: &mut PacketWriter) {
         w.write_u32_encoded(self.id);
@@ -428,6 +757,164 @@ mod tests {
         assert_eq!(&id.id.to_le_bytes(), &[0x19, 0xdc, 0x06, 0x80]);
     }
 
+    fn listing(player_count: u8, max_players: u8, num_imposters: u8, age: u32) -> GameListing {
+        GameListing {
+            address: Address {
+                ip: [127, 0, 0, 1],
+                port: 22023,
+            },
+            id: GameId::from_chars("AQNKQQ"),
+            host_username: "host".to_string(),
+            player_count,
+            age,
+            map_id: Maps::SKELD,
+            num_imposters,
+            max_players,
+        }
+    }
+
+    #[test]
+    fn test_listing_round_trip() {
+        let original = listing(5, 10, 2, 30);
+        let mut w = PacketWriter::new();
+        w.write(&original);
+        let read = PacketReader::new(&w.finish()[..])
+            .read::<GameListing>()
+            .unwrap();
+        assert_eq!(read.address, original.address);
+        assert_eq!(read.id, original.id);
+        assert_eq!(read.host_username, original.host_username);
+        assert_eq!(read.player_count, original.player_count);
+        assert_eq!(read.age, original.age);
+        assert_eq!(read.map_id, original.map_id);
+        assert_eq!(read.num_imposters, original.num_imposters);
+        assert_eq!(read.max_players, original.max_players);
+    }
+
+    #[test]
+    fn test_game_list_keeps_unparseable() {
+        let mut w = PacketWriter::new();
+        w.start_message(0);
+        w.start_message(0);
+        w.write(listing(5, 10, 2, 30));
+        w.end_message();
+        w.start_message(0);
+        // Cut short
+        w.write_bytes_raw(&[0x01; 5]);
+        w.end_message();
+        w.start_message(0);
+        w.write(listing(6, 10, 1, 30));
+        w.end_message();
+        w.end_message();
+
+        let packet = PacketReader::new(&w.finish()[..])
+            .read::<crate::data::GameListPacket>()
+            .unwrap();
+        assert_eq!(packet.games.len(), 2);
+        assert_eq!(packet.unparsed.len(), 1);
+        assert_eq!(packet.unparsed[0].data, vec![0x01; 5]);
+    }
+
+    #[test]
+    fn test_listing_flags() {
+        assert_eq!(listing(5, 10, 2, 30).flags(), ListingFlags::empty());
+        assert_eq!(
+            listing(11, 10, 2, 30).flags(),
+            ListingFlags::IMPOSSIBLE_PLAYER_COUNT
+        );
+        assert_eq!(
+            listing(5, 100, 2, 30).flags(),
+            ListingFlags::IMPOSSIBLE_PLAYER_COUNT
+        );
+        assert_eq!(
+            listing(5, 10, 0, 30).flags(),
+            ListingFlags::IMPOSSIBLE_IMPOSTER_COUNT
+        );
+        assert_eq!(
+            listing(5, 10, 2, u32::MAX).flags(),
+            ListingFlags::ABSURD_AGE
+        );
+    }
+
+    #[test]
+    fn test_presets_valid() {
+        for preset in OptionsPreset::ALL.iter() {
+            assert_eq!(
+                GameOptions::preset(*preset).validate(),
+                Ok(()),
+                "{:?}",
+                preset
+            );
+        }
+    }
+
+    #[test]
+    fn test_options_validate() {
+        let options = GameOptions {
+            max_players: 6,
+            num_imposters: 2,
+            player_speed: 10.,
+            ..GameOptions::preset(OptionsPreset::Classic)
+        };
+        assert_eq!(
+            options.validate(),
+            Err(vec![
+                InvalidOption::PlayerSpeed(10.),
+                InvalidOption::Imposters(2)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_maps_from_map_id() {
+        assert_eq!(Maps::from_map_id(0), Some(Maps::SKELD));
+        assert_eq!(Maps::from_map_id(1), Some(Maps::MIRA_HQ));
+        assert_eq!(Maps::from_map_id(2), Some(Maps::PORUS));
+        assert_eq!(Maps::from_map_id(3), None);
+    }
+
+    #[test]
+    fn test_options_versions() {
+        let options = GameOptions {
+            emergency_cooldown: 30,
+            confirm_ejects: false,
+            anonymous_votes: true,
+            task_bar_updates: 2,
+            ..GameOptions::default()
+        };
+        let mut lengths = Vec::new();
+        for version in 1..=4 {
+            let options = GameOptions {
+                game_settings_version: version,
+                ..options.clone()
+            };
+            let mut w = PacketWriter::new();
+            w.write(&options);
+            let data = w.finish();
+            lengths.push(data.len());
+            let read = PacketReader::new(&data[..]).read::<GameOptions>().unwrap();
+            assert_eq!(read.game_settings_version, version);
+            assert_eq!(read.emergency_cooldown == 30, version >= 2);
+            assert_eq!(!read.confirm_ejects, version >= 3);
+            assert_eq!(read.anonymous_votes, version >= 4);
+            assert_eq!(read.task_bar_updates == 2, version >= 4);
+        }
+        assert_eq!(lengths, vec![41, 42, 44, 46]);
+    }
+
+    #[test]
+    fn test_options_unknown_language() {
+        let mut w = PacketWriter::new();
+        w.write(&GameOptions::default());
+        let mut data = w.finish();
+        // After the settings version and max players
+        data[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = PacketReader::new(&data[..])
+            .read::<GameOptions>()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
     /// Will take too long if not in release as this iterates through all ~300 million (26**6) possible game codes
     #[test]
     fn test_gameid_all_nums() {
@@ -1,14 +1,23 @@
-use std::io::{self, Read};
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read},
+};
 
 use crate::{
-    data::{Address, GameId, GameListing, GameOptions, Languages, ServerInfo},
-    reader::{Data, Deserialize, PacketRead, PacketReader, PacketWriter, Serialize},
+    data::{Address, GameId, GameListing, GameOptions, GameVersion, Languages, Maps, ServerInfo},
+    reader::{
+        Data, Deserialize, GetReader, PacketRead, PacketReader, PacketWriter, ParseAnomaly,
+        Serialize,
+    },
 };
 
-use log::warn;
+use log::{debug, warn};
 use num_traits::FromPrimitive;
 
-use super::{GameData, Lobby, PlayerControl, PlayerPhysics, PlayerTransform, VoteBanSystem, World};
+use super::{
+    GameData, Lobby, MeetingHud, NetObject, PlayerControl, PlayerPhysics, PlayerTransform,
+    VoteBanSystem, World,
+};
 
 #[derive(Debug)]
 pub enum Packet {
@@ -20,21 +29,31 @@ pub enum Packet {
         game_id: GameId,
         player_id: i32,
         host_id: i32,
+        /// From their hello. `None` if not sent or not one we know
+        chat_mode: Option<ChatMode>,
     },
     PlayerLeft {
         game_id: GameId,
         player_id: i32,
         host_id: i32,
-        reason: Option<u8>,
+        /// `None` if not sent or not one we know
+        reason: Option<DisconnectReason>,
     },
     ClientJoinedGame(JoinedGamePacket),
     GameList(GameListPacket),
     ServerList(ServerListPacket),
     GameAltered {
         game_id: GameId,
-        is_public: bool,
+        alteration: AlterGameTag,
+    },
+    GameStarted {
+        game_id: GameId,
+    },
+    GameEnded {
+        game_id: GameId,
+        reason: GameOverReason,
+        show_ad: bool,
     },
-    GameStarted,
     ChangeServer {
         address: Address,
     },
@@ -65,6 +84,7 @@ pub enum PacketType {
     GameInfo = 0x05,
     GameInfoTo = 0x06,
     JoinedGame = 0x07,
+    EndGame = 0x08,
     AlterGameInfo = 0x0a,
     KickPlayer = 0x0b,
     ChangeServer = 0x0d,
@@ -92,6 +112,18 @@ impl Serialize for Packet {
                     w.write(info);
                 }
             }
+            Packet::GameStarted { game_id } => w.write(game_id),
+            Packet::GameAltered {
+                game_id,
+                alteration,
+            } => {
+                w.write(game_id);
+                w.write_u8(alteration.tag());
+                match alteration {
+                    AlterGameTag::ChangePrivacy { is_public } => w.write_bool(*is_public),
+                    AlterGameTag::Unknown { data, .. } => w.write_bytes_raw(data),
+                }
+            }
             Packet::KickPlayer {
                 game_id,
                 player_id,
@@ -101,6 +133,15 @@ impl Serialize for Packet {
                 w.write_i32_encoded(*player_id);
                 w.write_bool(*ban);
             }
+            Packet::GameEnded {
+                game_id,
+                reason,
+                show_ad,
+            } => {
+                w.write(game_id);
+                w.write_u8(*reason as u8);
+                w.write_bool(*show_ad);
+            }
             _ => todo!(),
         }
     }
@@ -108,10 +149,33 @@ impl Serialize for Packet {
 
 impl Deserialize for Packet {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        Packet::read(r, None)
+    }
+}
+
+impl Packet {
+    /// Reads packets until no data left. A `GameInfo` message that can't be parsed is skipped
+    /// and added to `anomalies` instead of failing the whole packet
+    pub fn read_all_lenient<T: PacketRead + Read>(
+        r: &mut PacketReader<T>,
+        anomalies: &mut Vec<ParseAnomaly>,
+    ) -> io::Result<Vec<Self>> {
+        let mut packets = Vec::new();
+        while r.remaining() != 0 {
+            packets.push(Packet::read(r, Some(anomalies))?);
+        }
+        Ok(packets)
+    }
+
+    /// Reads a packet, leniently if given somewhere to put anomalies
+    fn read<T: PacketRead + Read>(
+        r: &mut PacketReader<T>,
+        anomalies: Option<&mut Vec<ParseAnomaly>>,
+    ) -> io::Result<Self> {
         let (tag, mut r) = r.read_message()?;
         Ok(match PacketType::from_u8(tag) {
             Some(PacketType::HostingGame) => Packet::HostingGame { game_id: r.read()? },
-            Some(PacketType::GameStarted) => Packet::GameStarted,
+            Some(PacketType::GameStarted) => Packet::GameStarted { game_id: r.read()? },
             Some(PacketType::GameJoinDisconnect) => {
                 // Packet type depends on how large the first int is
                 // They could have just used a different packet but this is more fun
@@ -122,10 +186,17 @@ impl Deserialize for Packet {
                     let game_id = GameId { id: value };
                     let player_id = r.read_i32()?;
                     let host_id = r.read_i32()?;
+                    // Anything other than a single byte after is something else, and ignored
+                    let chat_mode = if r.remaining() == 1 {
+                        read_chat_mode(&mut r)?
+                    } else {
+                        None
+                    };
                     Packet::PlayerJoined {
                         game_id,
                         player_id,
                         host_id,
+                        chat_mode,
                     }
                 }
             }
@@ -135,19 +206,44 @@ impl Deserialize for Packet {
                 host_id: r.read_i32()?,
                 reason: {
                     if r.remaining() > 0 {
-                        Some(r.read_u8()?)
+                        let code = r.read_u8()?;
+                        let reason = DisconnectReason::from_code(code);
+                        if reason.is_none() {
+                            warn!("Unknown player left reason {}", code);
+                        }
+                        reason
                     } else {
                         None
                     }
                 },
             },
             Some(PacketType::JoinedGame) => Packet::ClientJoinedGame(r.read::<JoinedGamePacket>()?),
+            Some(PacketType::EndGame) => {
+                let game_id = r.read::<GameId>()?;
+                let reason = r.read_u8()?;
+                Packet::GameEnded {
+                    game_id,
+                    reason: GameOverReason::from_u8(reason).ok_or_else(|| {
+                        io::Error::new(ErrorKind::InvalidData, "Unknown game over reason")
+                    })?,
+                    show_ad: r.read_bool()?,
+                }
+            }
             Some(PacketType::AlterGameInfo) => {
                 let game_id = r.read::<GameId>()?;
-                let to_alter = r.read_u8()?;
-                assert_eq!(to_alter, 1);
-                let is_public = r.read_bool()?;
-                Packet::GameAltered { game_id, is_public }
+                let alteration = match r.read_u8()? {
+                    1 => AlterGameTag::ChangePrivacy {
+                        is_public: r.read_bool()?,
+                    },
+                    tag => AlterGameTag::Unknown {
+                        tag,
+                        data: r.remaining_bytes()?,
+                    },
+                };
+                Packet::GameAltered {
+                    game_id,
+                    alteration,
+                }
             }
             Some(PacketType::ChangeServer) => Packet::ChangeServer {
                 address: r.read::<Address>()?,
@@ -157,11 +253,11 @@ impl Deserialize for Packet {
             Some(PacketType::GameInfoTo) => Packet::GameInfoTo {
                 game_id: r.read::<GameId>()?,
                 client_id: r.read_i32_encoded()?,
-                data: r.read_all::<GameInfo>()?,
+                data: read_game_infos(&mut r, anomalies)?,
             },
             Some(PacketType::GameInfo) => Packet::GameInfo {
                 game_id: r.read::<GameId>()?,
-                data: r.read_all::<GameInfo>()?,
+                data: read_game_infos(&mut r, anomalies)?,
             },
             Some(packet_type) => {
                 warn!("Unread packet type {:?}", packet_type);
@@ -175,6 +271,16 @@ impl Deserialize for Packet {
     }
 }
 
+fn read_game_infos<T: PacketRead>(
+    r: &mut PacketReader<T>,
+    anomalies: Option<&mut Vec<ParseAnomaly>>,
+) -> io::Result<Vec<GameInfo>> {
+    match anomalies {
+        Some(anomalies) => r.read_all_lenient(anomalies),
+        None => r.read_all(),
+    }
+}
+
 #[derive(Debug)]
 pub struct GenericMessage {
     pub tag: u8,
@@ -217,6 +323,117 @@ pub enum GameInfo {
     Unknown,
 }
 
+/// Puts several `GameInfo`s in one packet, to everyone or with `to` to one client
+///
+/// ```ignore
+/// let packet = GameInfoBatch::new(game_id)
+///     .push(control.rpc_set_name("name"))
+///     .destroy(net_id)
+///     .into_packet();
+/// ```
+#[derive(Debug)]
+pub struct GameInfoBatch {
+    game_id: GameId,
+    to: Option<i32>,
+    data: Vec<GameInfo>,
+}
+
+impl GameInfoBatch {
+    pub fn new(game_id: GameId) -> Self {
+        Self {
+            game_id,
+            to: None,
+            data: Vec::new(),
+        }
+    }
+
+    /// Sends to just `client_id` instead of everyone
+    pub fn to(mut self, client_id: i32) -> Self {
+        self.to = Some(client_id);
+        self
+    }
+
+    /// Adds an entry made elsewhere, e.g. by one of the net objects' `rpc_` functions
+    pub fn push(mut self, info: GameInfo) -> Self {
+        self.data.push(info);
+        self
+    }
+
+    pub fn extend<I: IntoIterator<Item = GameInfo>>(mut self, infos: I) -> Self {
+        self.data.extend(infos);
+        self
+    }
+
+    /// Calls `call_id` on `obj`
+    pub fn rpc<O: NetObject + ?Sized>(self, obj: &O, call_id: u8, data: Data) -> Self {
+        self.push(GameInfo::RPC {
+            net_id: obj.net_id(),
+            call_id,
+            data,
+        })
+    }
+
+    /// Sends new data for `obj`
+    pub fn update<O: NetObject + ?Sized>(self, obj: &O, data: Data) -> Self {
+        self.push(GameInfo::UpdateData {
+            net_id: obj.net_id(),
+            data,
+        })
+    }
+
+    pub fn destroy(self, net_id: u32) -> Self {
+        self.push(GameInfo::Destroy { net_id })
+    }
+
+    pub fn spawn(self, spawn_flags: u8, prefab: Prefab) -> Self {
+        self.push(GameInfo::CreateFromPrefab {
+            spawn_flags,
+            prefab,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The entries added so far
+    pub fn infos(&self) -> &[GameInfo] {
+        &self.data
+    }
+
+    /// Who it's for if `to` was called. `None` is everyone
+    pub fn target(&self) -> Option<i32> {
+        self.to
+    }
+
+    /// What the packet has to be sent as
+    pub fn packet_type(&self) -> PacketType {
+        match self.to {
+            Some(_) => PacketType::GameInfoTo,
+            None => PacketType::GameInfo,
+        }
+    }
+
+    /// `Packet::GameInfoTo` if `to` was called, otherwise `Packet::GameInfo`
+    pub fn into_packet(self) -> Packet {
+        match self.to {
+            Some(client_id) => Packet::GameInfoTo {
+                game_id: self.game_id,
+                client_id,
+                data: self.data,
+            },
+            None => Packet::GameInfo {
+                game_id: self.game_id,
+                data: self.data,
+            },
+        }
+    }
+}
+
 impl Serialize for GameInfo {
     fn serialize(&self, w: &mut PacketWriter) {
         match self {
@@ -247,6 +464,64 @@ impl Serialize for GameInfo {
                 w.write_u32_encoded(*net_id);
                 w.end_message();
             }
+            GameInfo::UpdateData { net_id, data } => {
+                w.start_message(GameInfoType::UpdateData as u8);
+                w.write_u32_encoded(*net_id);
+                w.write(data);
+                w.end_message();
+            }
+            GameInfo::CreateFromPrefab {
+                spawn_flags,
+                prefab,
+            } => {
+                let (prefab_type, owner_id, children) = match prefab {
+                    Prefab::World(world) => (
+                        PrefabType::World,
+                        world.owner_id(),
+                        vec![(world.net_id(), world.spawn_data())],
+                    ),
+                    Prefab::Player(control, physics, transform) => (
+                        PrefabType::Player,
+                        control.owner_id(),
+                        vec![
+                            (control.net_id(), control.spawn_data()),
+                            (physics.net_id(), Vec::new()),
+                            (transform.net_id(), transform.spawn_data()),
+                        ],
+                    ),
+                    Prefab::Lobby(lobby) => (
+                        PrefabType::Lobby,
+                        lobby.owner_id(),
+                        vec![(lobby.net_id(), Vec::new())],
+                    ),
+                    Prefab::GameData(game_data, vote_ban) => (
+                        PrefabType::GameData,
+                        game_data.owner_id(),
+                        vec![
+                            (game_data.net_id(), game_data.spawn_data()),
+                            (vote_ban.net_id(), vote_ban.spawn_data()),
+                        ],
+                    ),
+                    Prefab::MeetingHud(meeting_hud) => (
+                        PrefabType::MeetingHub,
+                        meeting_hud.owner_id(),
+                        vec![(meeting_hud.net_id(), meeting_hud.spawn_data())],
+                    ),
+                    Prefab::Unknown => panic!("Can't spawn an unknown prefab"),
+                };
+                w.start_message(GameInfoType::CreateFromPrefab as u8);
+                w.write_u32_encoded(prefab_type as u32);
+                w.write_i32_encoded(owner_id);
+                w.write_u8(*spawn_flags);
+                w.write_u32_encoded(children.len() as u32);
+                for (net_id, data) in children {
+                    w.write_u32_encoded(net_id);
+                    w.start_message(1);
+                    w.write_bytes_raw(&data);
+                    w.end_message();
+                }
+                w.end_message();
+            }
             _ => todo!(),
         }
     }
@@ -282,54 +557,45 @@ impl Deserialize for GameInfo {
                 let num_children = r.read_u32_encoded()?;
                 let prefab = match PrefabType::from_u32(prefab_id) {
                     Some(PrefabType::World) => {
-                        assert_eq!(num_children, 1);
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        check_children(PrefabType::World, num_children, 1)?;
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let map = World::initialize(net_id, owner_id, &mut data)?;
                         Prefab::World(map)
                     }
                     Some(PrefabType::Player) => {
-                        assert_eq!(num_children, 3);
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        check_children(PrefabType::Player, num_children, 3)?;
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let player_control =
                             PlayerControl::initialize(net_id, owner_id, &mut data)?;
 
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let player_physics =
                             PlayerPhysics::initialize(net_id, owner_id, &mut data)?;
 
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let player_transform =
                             PlayerTransform::initialize(net_id, owner_id, &mut data)?;
 
                         Prefab::Player(player_control, player_physics, player_transform)
                     }
                     Some(PrefabType::Lobby) => {
-                        assert_eq!(num_children, 1);
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        check_children(PrefabType::Lobby, num_children, 1)?;
+                        let (net_id, mut data) = read_component(&mut r)?;
                         Prefab::Lobby(Lobby::initialize(net_id, owner_id, &mut data))
                     }
                     Some(PrefabType::GameData) => {
-                        assert_eq!(num_children, 2);
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        check_children(PrefabType::GameData, num_children, 2)?;
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let game_data = GameData::initialize(net_id, owner_id, &mut data)?;
-                        let net_id = r.read_u32_encoded()?;
-                        let (tag, mut data) = r.read_message()?;
-                        assert_eq!(tag, 1);
+                        let (net_id, mut data) = read_component(&mut r)?;
                         let vote_ban = VoteBanSystem::initialize(net_id, owner_id, &mut data)?;
                         Prefab::GameData(game_data, vote_ban)
                     }
+                    Some(PrefabType::MeetingHub) => {
+                        check_children(PrefabType::MeetingHub, num_children, 1)?;
+                        let (net_id, mut data) = read_component(&mut r)?;
+                        Prefab::MeetingHud(MeetingHud::initialize(net_id, owner_id, &mut data)?)
+                    }
                     None => {
                         warn!("Unkown prefab id {}", prefab_id);
                         Prefab::Unknown
@@ -352,6 +618,35 @@ impl Deserialize for GameInfo {
     }
 }
 
+/// Fails unless a prefab has as many components as its type should
+fn check_children(prefab_type: PrefabType, num_children: u32, expected: u32) -> io::Result<()> {
+    if num_children == expected {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "{:?} prefab with {} components, expected {}",
+            prefab_type, num_children, expected
+        ),
+    ))
+}
+
+/// Reads a prefab component's net id and spawn data, which is always tagged 1
+fn read_component<T: PacketRead>(
+    r: &mut PacketReader<T>,
+) -> io::Result<(u32, PacketReader<&[u8]>)> {
+    let net_id = r.read_u32_encoded()?;
+    let (tag, data) = r.read_message()?;
+    if tag != 1 {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Component {} spawned with tag {}, expected 1", net_id, tag),
+        ));
+    }
+    Ok((net_id, data))
+}
+
 #[derive(Debug, Copy, Clone, FromPrimitive)]
 pub enum GameInfoType {
     UpdateData = 1,
@@ -373,15 +668,96 @@ pub enum PrefabType {
 }
 
 // TODO: Improve name. If it's been initialized it's not really a prefab
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Prefab {
     World(World),
     Player(PlayerControl, PlayerPhysics, PlayerTransform),
     Lobby(Lobby),
     GameData(GameData, VoteBanSystem),
+    MeetingHud(MeetingHud),
     Unknown,
 }
 
+/// Whether a player can type in chat or only use quick chat phrases
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum ChatMode {
+    FreeChat = 1,
+    QuickChatOnly = 2,
+}
+
+/// What a client says hello to a game server with. The fields after `username` are only sent by
+/// versions from `GameVersion::HELLO_CHAT_MODE`, and are left as `None` when read from older ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelloPacket {
+    /// Always 0 from the game. Newer Hazel revisions put their own version here
+    pub hazel_version: u8,
+    pub version: GameVersion,
+    pub username: String,
+    /// From the auth server. Written as 0 if `None`
+    pub last_nonce: Option<u32>,
+    /// Written as English if `None`
+    pub language: Option<Languages>,
+    /// Written as free chat if `None`
+    pub chat_mode: Option<ChatMode>,
+    /// Anything after the fields above, like an identity or token, as is
+    pub extra: Vec<u8>,
+}
+
+impl HelloPacket {
+    pub fn new(version: GameVersion, username: &str) -> Self {
+        Self {
+            hazel_version: 0,
+            version,
+            username: username.to_string(),
+            last_nonce: None,
+            language: None,
+            chat_mode: None,
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl Serialize for HelloPacket {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_u8(self.hazel_version);
+        w.write_u32(self.version.to_u32());
+        w.write_string(&self.username);
+        if self.version.hello_has_chat_mode() {
+            w.write_u32(self.last_nonce.unwrap_or(0));
+            w.write_u32(self.language.unwrap_or(Languages::ENGLISH).bits());
+            w.write_u8(self.chat_mode.unwrap_or(ChatMode::FreeChat) as u8);
+        }
+        w.write_bytes_raw(&self.extra);
+    }
+}
+
+impl Deserialize for HelloPacket {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        let mut hello = Self {
+            hazel_version: r.read_u8()?,
+            version: GameVersion::from_u32(r.read_u32()?),
+            username: r.read_string()?,
+            last_nonce: None,
+            language: None,
+            chat_mode: None,
+            extra: Vec::new(),
+        };
+        if hello.version.hello_has_chat_mode() {
+            hello.last_nonce = Some(r.read_u32()?);
+            hello.language = Some(
+                Languages::from_bits(r.read_u32()?)
+                    .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid language"))?,
+            );
+            hello.chat_mode = Some(
+                ChatMode::from_u8(r.read_u8()?)
+                    .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid chat mode"))?,
+            );
+        }
+        hello.extra = r.remaining_bytes()?;
+        Ok(hello)
+    }
+}
+
 #[derive(Debug)]
 pub struct JoinGamePacket {
     pub game_id: GameId,
@@ -401,21 +777,46 @@ pub struct JoinedGamePacket {
     pub client_id: i32,
     pub host_id: i32,
     pub player_ids: Vec<i32>,
+    /// Chat modes of the players in `player_ids`, for servers that send them after the ids
+    pub chat_modes: HashMap<i32, ChatMode>,
 }
 
 impl Deserialize for JoinedGamePacket {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
-        Ok(Self {
+        let mut packet = Self {
             game_id: r.read()?,
             client_id: r.read_i32()?,
             host_id: r.read_i32()?,
             player_ids: (0..r.read_u32_encoded()?)
                 .map(|_| r.read_i32_encoded())
                 .collect::<io::Result<_>>()?,
-        })
+            chat_modes: HashMap::new(),
+        };
+        // Only read as chat modes if there's one byte for each player, so other trailing data
+        // doesn't stop the join
+        if r.remaining() == packet.player_ids.len() {
+            for &player_id in &packet.player_ids {
+                if let Some(chat_mode) = read_chat_mode(r)? {
+                    packet.chat_modes.insert(player_id, chat_mode);
+                }
+            }
+        } else if r.remaining() > 0 {
+            debug!("Ignoring {} bytes after the joined game", r.remaining());
+        }
+        Ok(packet)
     }
 }
 
+/// Warns about and skips modes we don't know, as newer versions might add some
+fn read_chat_mode<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Option<ChatMode>> {
+    let value = r.read_u8()?;
+    let chat_mode = ChatMode::from_u8(value);
+    if chat_mode.is_none() {
+        warn!("Unknown chat mode {}", value);
+    }
+    Ok(chat_mode)
+}
+
 #[derive(Debug)]
 pub struct ServerListPacket {
     pub servers: Vec<ServerInfo>,
@@ -439,24 +840,159 @@ impl Deserialize for ServerListPacket {
 #[derive(Debug)]
 pub struct GameListPacket {
     pub games: Vec<GameListing>,
+    /// Listings that couldn't be parsed, usually because a game update changed the format
+    pub unparsed: Vec<GameListingRaw>,
+    /// How many public games there are per map, if the server sent them
+    pub counts: Option<GameCounts>,
+}
+
+/// Public game counts per map, sent alongside game listings
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct GameCounts {
+    pub skeld: u32,
+    pub mira_hq: u32,
+    pub porus: u32,
+}
+
+impl GameCounts {
+    /// Counts one more game on `map`
+    pub fn add(&mut self, map: Maps) {
+        if map == Maps::PORUS {
+            self.porus += 1;
+        } else if map == Maps::MIRA_HQ {
+            self.mira_hq += 1;
+        } else {
+            self.skeld += 1;
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.skeld + self.mira_hq + self.porus
+    }
+}
+
+impl Serialize for GameCounts {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_u32(self.skeld);
+        w.write_u32(self.mira_hq);
+        w.write_u32(self.porus);
+    }
+}
+
+impl Deserialize for GameCounts {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        Ok(Self {
+            skeld: r.read_u32()?,
+            mira_hq: r.read_u32()?,
+            porus: r.read_u32()?,
+        })
+    }
+}
+
+/// A game listing kept as the bytes it arrived as, so one bad listing doesn't lose the rest
+#[derive(Debug, Clone)]
+pub struct GameListingRaw {
+    pub tag: u8,
+    pub data: Vec<u8>,
+    /// Why it couldn't be parsed
+    pub error: String,
+}
+
+impl Serialize for GameListPacket {
+    fn serialize(&self, w: &mut PacketWriter) {
+        if let Some(counts) = &self.counts {
+            w.start_message(1);
+            w.write(counts);
+            w.end_message();
+        }
+        w.start_message(0);
+        for game in self.games.iter() {
+            w.start_message(0);
+            w.write(game);
+            w.end_message();
+        }
+        for raw in self.unparsed.iter() {
+            w.start_message(raw.tag);
+            w.write_bytes_raw(&raw.data);
+            w.end_message();
+        }
+        w.end_message();
+    }
 }
 
 impl Deserialize for GameListPacket {
     fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
         let mut games = Vec::new();
-        let (tag, mut inner_data) = r.read_message()?;
-        assert_eq!(tag, 0);
-        while inner_data.remaining() != 0 {
-            let (list_tag, mut list_data) = inner_data.read_message()?;
-            assert_eq!(list_tag, 0);
-            games.push(list_data.read::<GameListing>()?);
+        let mut unparsed = Vec::new();
+        let mut counts = None;
+        while r.remaining() != 0 {
+            let (tag, mut inner_data) = r.read_message()?;
+            match tag {
+                0 => read_listings(&mut inner_data, &mut games, &mut unparsed)?,
+                1 => counts = Some(inner_data.read::<GameCounts>()?),
+                _ => warn!("Unknown game list section {}", tag),
+            }
         }
 
-        Ok(Self { games })
+        Ok(Self {
+            games,
+            unparsed,
+            counts,
+        })
     }
 }
 
-#[derive(Debug)]
+/// Reads the listings section of a game list
+fn read_listings(
+    inner_data: &mut PacketReader<&[u8]>,
+    games: &mut Vec<GameListing>,
+    unparsed: &mut Vec<GameListingRaw>,
+) -> io::Result<()> {
+    while inner_data.remaining() != 0 {
+        let (list_tag, mut list_data) = inner_data.read_message()?;
+        let data = list_data.remaining_bytes()?;
+        let listing = if list_tag == 0 {
+            (&data[..]).get_reader().read::<GameListing>()
+        } else {
+            Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Unknown listing tag",
+            ))
+        };
+        match listing {
+            Ok(listing) => games.push(listing),
+            Err(error) => {
+                warn!("Unparseable game listing {}", error);
+                unparsed.push(GameListingRaw {
+                    tag: list_tag,
+                    data,
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What an `AlterGameInfo` packet changes about a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlterGameTag {
+    /// Whether the game is listed publicly
+    ChangePrivacy { is_public: bool },
+    /// A tag we don't know, with everything after it
+    Unknown { tag: u8, data: Vec<u8> },
+}
+
+impl AlterGameTag {
+    pub fn tag(&self) -> u8 {
+        match self {
+            AlterGameTag::ChangePrivacy { .. } => 1,
+            AlterGameTag::Unknown { tag, .. } => *tag,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum DisconnectReason {
     ExitGame,
     GameFull,
@@ -478,11 +1014,26 @@ pub enum DisconnectReason {
 }
 
 impl DisconnectReason {
-    fn from_value_and_reader<T: PacketRead>(
+    pub(crate) fn from_value_and_reader<T: PacketRead>(
         value: i32,
         r: &mut PacketReader<T>,
     ) -> io::Result<Self> {
-        Ok(match value {
+        if value == 8 {
+            return Ok(DisconnectReason::Custom {
+                message: r.read_string()?,
+            });
+        }
+        Self::from_code(value as u8).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown disconnect reason {}", value),
+            )
+        })
+    }
+
+    /// Any reason but `Custom`, which needs a message
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
             0 => DisconnectReason::ExitGame,
             1 => DisconnectReason::GameFull,
             2 => DisconnectReason::GameStarted,
@@ -490,9 +1041,6 @@ impl DisconnectReason {
             5 => DisconnectReason::IncorrectVersion,
             6 => DisconnectReason::Banned,
             7 => DisconnectReason::Kicked,
-            8 => DisconnectReason::Custom {
-                message: r.read_string()?,
-            },
             16 => DisconnectReason::Destroy,
             17 => DisconnectReason::Error,
             18 => DisconnectReason::IncorrectGame,
@@ -502,14 +1050,94 @@ impl DisconnectReason {
             208 => DisconnectReason::IntentionalLeaving,
             209 => DisconnectReason::FocusLost,
             210 => DisconnectReason::NewConnection,
-            _ => unreachable!(),
+            _ => return None,
         })
     }
+
+    /// The number sent on the wire. `Custom` is 8, followed by its message
+    pub fn code(&self) -> u8 {
+        match self {
+            DisconnectReason::ExitGame => 0,
+            DisconnectReason::GameFull => 1,
+            DisconnectReason::GameStarted => 2,
+            DisconnectReason::GameNotFound => 3,
+            DisconnectReason::IncorrectVersion => 5,
+            DisconnectReason::Banned => 6,
+            DisconnectReason::Kicked => 7,
+            DisconnectReason::Custom { .. } => 8,
+            DisconnectReason::Destroy => 16,
+            DisconnectReason::Error => 17,
+            DisconnectReason::IncorrectGame => 18,
+            DisconnectReason::ServerRequest => 19,
+            DisconnectReason::ServerFull => 20,
+            DisconnectReason::FocusLostBackground => 207,
+            DisconnectReason::IntentionalLeaving => 208,
+            DisconnectReason::FocusLost => 209,
+            DisconnectReason::NewConnection => 210,
+        }
+    }
+}
+
+/// Why a game ended, sent with `EndGame`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
+pub enum GameOverReason {
+    HumansByVote = 0,
+    HumansByTask = 1,
+    ImpostorByVote = 2,
+    ImpostorByKill = 3,
+    ImpostorBySabotage = 4,
+    ImpostorDisconnect = 5,
+    HumansDisconnect = 6,
+}
+
+impl GameOverReason {
+    /// The side that won the game
+    pub fn winner(&self) -> Team {
+        match self {
+            GameOverReason::HumansByVote
+            | GameOverReason::HumansByTask
+            | GameOverReason::ImpostorDisconnect => Team::Crewmates,
+            GameOverReason::ImpostorByVote
+            | GameOverReason::ImpostorByKill
+            | GameOverReason::ImpostorBySabotage
+            | GameOverReason::HumansDisconnect => Team::Impostors,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Team {
+    Crewmates,
+    Impostors,
+}
+
+/// Asks the server to create a game. Answered with `Packet::HostingGame`
+#[derive(Debug)]
+pub struct HostGamePacket {
+    pub game_options: GameOptions,
+}
+
+impl Serialize for HostGamePacket {
+    fn serialize(&self, w: &mut PacketWriter) {
+        let mut inner_w = PacketWriter::new();
+        inner_w.write(&self.game_options);
+        w.write_u32_encoded(inner_w.len() as u32);
+        w.write_bytes_raw(&inner_w.finish());
+    }
+}
+
+impl Deserialize for HostGamePacket {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        let length = r.read_u32_encoded()?;
+        let game_options = PacketReader::new(r.read_slice(length as usize)?).read()?;
+        Ok(Self { game_options })
+    }
 }
 
 #[derive(Debug)]
 pub struct RequestGameListPacket {
-    game_options: GameOptions,
+    /// Used as a filter. `map_id` is a bitfield of maps, and `num_imposters` is 0 for any
+    pub game_options: GameOptions,
 }
 
 impl RequestGameListPacket {
@@ -534,3 +1162,557 @@ impl Serialize for RequestGameListPacket {
         w.write_bytes_raw(&inner_w.finish());
     }
 }
+
+impl Deserialize for RequestGameListPacket {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        r.read_u8()?;
+        let length = r.read_u32_encoded()?;
+        let game_options = PacketReader::new(r.read_slice(length as usize)?).read()?;
+        Ok(Self { game_options })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{PlayerData, RPCCallback, SystemType, TaskInfo, Vector2, VoteState, World};
+
+    /// Has `obj` handle an RPC made by one of the `rpc_` functions, as if it came over the wire
+    fn handle_rpc<O: NetObject>(obj: &mut O, info: GameInfo) -> RPCCallback {
+        match info {
+            GameInfo::RPC {
+                call_id,
+                data: Data::Bytes(data),
+                ..
+            } => obj
+                .handle_rpc(call_id, &mut (&data[..]).get_reader())
+                .unwrap(),
+            info => panic!("Unexpected game info {:?}", info),
+        }
+    }
+
+    #[test]
+    fn test_hello_versions() {
+        let old = HelloPacket {
+            extra: vec![1, 2, 3],
+            ..HelloPacket::new(GameVersion::new(2020, 9, 7, 0), "client")
+        };
+        let mut w = PacketWriter::new();
+        w.write(&old);
+        let data = w.finish();
+        assert_eq!(data.len(), 1 + 4 + 7 + 3);
+        let read = (&data[..]).get_reader().read::<HelloPacket>().unwrap();
+        assert_eq!(read, old);
+
+        let new = HelloPacket {
+            last_nonce: Some(77),
+            language: Some(Languages::KOREAN),
+            chat_mode: Some(ChatMode::QuickChatOnly),
+            ..HelloPacket::new(GameVersion::HELLO_CHAT_MODE, "client")
+        };
+        let mut w = PacketWriter::new();
+        w.write(&new);
+        let data = w.finish();
+        assert_eq!(data.len(), 1 + 4 + 7 + 9);
+        let read = (&data[..]).get_reader().read::<HelloPacket>().unwrap();
+        assert_eq!(read, new);
+
+        // Unset fields are filled in with what the game sends by default
+        let mut w = PacketWriter::new();
+        w.write(HelloPacket::new(GameVersion::HELLO_CHAT_MODE, "client"));
+        let data = w.finish();
+        let read = (&data[..]).get_reader().read::<HelloPacket>().unwrap();
+        assert_eq!(read.last_nonce, Some(0));
+        assert_eq!(read.language, Some(Languages::ENGLISH));
+        assert_eq!(read.chat_mode, Some(ChatMode::FreeChat));
+    }
+
+    #[test]
+    fn test_game_info_batch() {
+        let game_id = GameId::from_chars("AQNKQQ");
+        let control = PlayerControl::new(5, 1, 0);
+        let batch = GameInfoBatch::new(game_id)
+            .rpc(&control, 8, Data::Bytes(vec![1]))
+            .update(&control, Data::Bytes(vec![2]))
+            .destroy(7);
+        assert_eq!(batch.len(), 3);
+        assert!(matches!(batch.packet_type(), PacketType::GameInfo));
+        match batch.into_packet() {
+            Packet::GameInfo { data, .. } => match &data[..] {
+                [GameInfo::RPC {
+                    net_id: 5,
+                    call_id: 8,
+                    ..
+                }, GameInfo::UpdateData { net_id: 5, .. }, GameInfo::Destroy { net_id: 7 }] => (),
+                other => panic!("Unexpected entries {:?}", other),
+            },
+            other => panic!("Unexpected packet {:?}", other),
+        }
+
+        let batch = GameInfoBatch::new(game_id).to(3);
+        assert!(batch.is_empty());
+        assert!(matches!(batch.packet_type(), PacketType::GameInfoTo));
+        match batch.into_packet() {
+            Packet::GameInfoTo { client_id: 3, .. } => (),
+            other => panic!("Unexpected packet {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lenient_skips_bad_game_info() {
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameInfo as u8);
+        w.write(GameId::from_chars("AQNKQQ"));
+        w.write(GameInfo::ClientReady { client_id: 1 });
+        // Packed int cut short
+        w.start_message(GameInfoType::ChangeScene as u8);
+        w.write_u8(0x80);
+        w.end_message();
+        w.write(GameInfo::ClientReady { client_id: 2 });
+        w.end_message();
+        let data = w.finish();
+
+        assert!((&data[..]).get_reader().read::<Packet>().is_err());
+
+        let mut anomalies = Vec::new();
+        let packets =
+            Packet::read_all_lenient(&mut (&data[..]).get_reader(), &mut anomalies).unwrap();
+        match &packets[..] {
+            [Packet::GameInfo { data, .. }] => assert_eq!(data.len(), 2),
+            packets => panic!("Unexpected packets {:?}", packets),
+        }
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].tag, GameInfoType::ChangeScene as u8);
+        assert_eq!(anomalies[0].data, vec![0x80]);
+    }
+
+    #[test]
+    fn test_lenient_skips_bad_prefabs() {
+        // A lobby with a component too many, then one with its spawn data under the wrong tag
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameInfo as u8);
+        w.write(GameId::from_chars("AQNKQQ"));
+        for (num_children, tag) in [(2, 1), (1, 0)] {
+            w.start_message(GameInfoType::CreateFromPrefab as u8);
+            w.write_u32_encoded(PrefabType::Lobby as u32);
+            w.write_i32_encoded(-2);
+            w.write_u8(0);
+            w.write_u32_encoded(num_children);
+            w.write_u32_encoded(1);
+            w.start_message(tag);
+            w.end_message();
+            w.end_message();
+        }
+        w.write(GameInfo::ClientReady { client_id: 2 });
+        w.end_message();
+        let data = w.finish();
+
+        let error = (&data[..]).get_reader().read::<Packet>().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut anomalies = Vec::new();
+        let packets =
+            Packet::read_all_lenient(&mut (&data[..]).get_reader(), &mut anomalies).unwrap();
+        match &packets[..] {
+            [Packet::GameInfo { data, .. }] => assert_eq!(data.len(), 1),
+            packets => panic!("Unexpected packets {:?}", packets),
+        }
+        assert_eq!(anomalies.len(), 2);
+    }
+
+    fn player_left(reason: Option<u8>) -> Packet {
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::PlayerLeft as u8);
+        w.write(GameId::from_chars("AQNKQQ"));
+        w.write_i32(3);
+        w.write_i32(1);
+        if let Some(reason) = reason {
+            w.write_u8(reason);
+        }
+        w.end_message();
+        (&w.finish()[..]).get_reader().read().unwrap()
+    }
+
+    #[test]
+    fn test_player_left_reason() {
+        for (code, expected) in &[
+            (None, None),
+            (Some(7), Some(DisconnectReason::Kicked)),
+            (Some(6), Some(DisconnectReason::Banned)),
+            (Some(0), Some(DisconnectReason::ExitGame)),
+            (Some(100), None),
+        ] {
+            match player_left(*code) {
+                Packet::PlayerLeft { reason, .. } => assert_eq!(&reason, expected),
+                packet => panic!("Unexpected packet {:?}", packet),
+            }
+        }
+    }
+
+    #[test]
+    fn test_chat_modes() {
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameJoinDisconnect as u8);
+        w.write(GameId::from_chars("AQNKQQ"));
+        w.write_i32(3);
+        w.write_i32(1);
+        w.write_u8(ChatMode::QuickChatOnly as u8);
+        w.end_message();
+        match (&w.finish()[..]).get_reader().read().unwrap() {
+            Packet::PlayerJoined { chat_mode, .. } => {
+                assert_eq!(chat_mode, Some(ChatMode::QuickChatOnly))
+            }
+            packet => panic!("Unexpected packet {:?}", packet),
+        }
+
+        let joined = |modes: &[u8]| {
+            let mut w = PacketWriter::new();
+            w.write(GameId::from_chars("AQNKQQ"));
+            w.write_i32(3);
+            w.write_i32(1);
+            w.write_u32_encoded(2);
+            w.write_i32_encoded(1);
+            w.write_i32_encoded(2);
+            w.write_bytes_raw(modes);
+            (&w.finish()[..])
+                .get_reader()
+                .read::<JoinedGamePacket>()
+                .unwrap()
+        };
+        assert!(joined(&[]).chat_modes.is_empty());
+        let packet = joined(&[ChatMode::FreeChat as u8, 9]);
+        assert_eq!(packet.chat_modes.len(), 1);
+        assert_eq!(packet.chat_modes[&1], ChatMode::FreeChat);
+        // Not one byte a player, so not chat modes
+        let packet = joined(&[ChatMode::FreeChat as u8, 1, 2, 3]);
+        assert_eq!(packet.player_ids, [1, 2]);
+        assert!(packet.chat_modes.is_empty());
+    }
+
+    #[test]
+    fn test_game_list_counts() {
+        let mut counts = GameCounts::default();
+        counts.add(Maps::SKELD);
+        counts.add(Maps::PORUS);
+        counts.add(Maps::PORUS);
+        let mut w = PacketWriter::new();
+        w.write(GameListPacket {
+            games: Vec::new(),
+            unparsed: Vec::new(),
+            counts: Some(counts),
+        });
+        let data = w.finish();
+        let packet = (&data[..]).get_reader().read::<GameListPacket>().unwrap();
+        assert_eq!(
+            packet.counts,
+            Some(GameCounts {
+                skeld: 1,
+                mira_hq: 0,
+                porus: 2,
+            })
+        );
+        assert_eq!(packet.counts.unwrap().total(), 3);
+
+        // Older servers only send the listings
+        let mut w = PacketWriter::new();
+        w.start_message(0);
+        w.end_message();
+        let data = w.finish();
+        let packet = (&data[..]).get_reader().read::<GameListPacket>().unwrap();
+        assert!(packet.games.is_empty());
+        assert_eq!(packet.counts, None);
+    }
+
+    #[test]
+    fn test_host_game_round_trip() {
+        let game_options = GameOptions {
+            max_players: 8,
+            ..GameOptions::default()
+        };
+        let mut w = PacketWriter::new();
+        w.write(HostGamePacket { game_options });
+        let data = w.finish();
+        let packet = (&data[..]).get_reader().read::<HostGamePacket>().unwrap();
+        assert_eq!(packet.game_options.max_players, 8);
+    }
+
+    #[test]
+    fn test_game_altered_round_trip() {
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::AlterGameInfo as u8);
+        w.write(Packet::GameAltered {
+            game_id: GameId { id: 32 },
+            alteration: AlterGameTag::ChangePrivacy { is_public: true },
+        });
+        w.end_message();
+        // Unknown tags are kept rather than failing the packet
+        w.start_message(PacketType::AlterGameInfo as u8);
+        w.write(Packet::GameAltered {
+            game_id: GameId { id: 32 },
+            alteration: AlterGameTag::Unknown {
+                tag: 9,
+                data: vec![4, 2],
+            },
+        });
+        w.end_message();
+        let data = w.finish();
+        let packets = (&data[..]).get_reader().read_all::<Packet>().unwrap();
+        let alterations = packets
+            .into_iter()
+            .map(|packet| match packet {
+                Packet::GameAltered {
+                    game_id,
+                    alteration,
+                } => {
+                    assert_eq!(game_id.id, 32);
+                    alteration
+                }
+                packet => panic!("Unexpected packet {:?}", packet),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alterations,
+            [
+                AlterGameTag::ChangePrivacy { is_public: true },
+                AlterGameTag::Unknown {
+                    tag: 9,
+                    data: vec![4, 2]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meeting_hud() {
+        let states = vec![
+            VoteState::from_byte(0, 0),
+            VoteState::from_byte(1, 0x80),
+            VoteState::from_byte(2, 0x20),
+        ];
+        let mut w = PacketWriter::new();
+        w.write(GameInfo::CreateFromPrefab {
+            spawn_flags: 0,
+            prefab: Prefab::MeetingHud(MeetingHud::new(20, -2, states.clone())),
+        });
+        let data = w.finish();
+        let mut meeting_hud = match (&data[..]).get_reader().read::<GameInfo>().unwrap() {
+            GameInfo::CreateFromPrefab {
+                prefab: Prefab::MeetingHud(meeting_hud),
+                ..
+            } => meeting_hud,
+            info => panic!("Unexpected game info {:?}", info),
+        };
+        meeting_hud.set_player_ids(&[0, 3, 7]);
+        assert_eq!(meeting_hud.net_id(), 20);
+        assert!(meeting_hud.states[1].is_dead);
+        assert_eq!(meeting_hud.states[2].player_id, 7);
+        assert!(meeting_hud.states[2].did_report);
+
+        let skipped = VoteState {
+            did_vote: true,
+            ..VoteState::from_byte(4, 0)
+        };
+        assert_eq!(skipped.to_byte(), 0x4f);
+        let skipped = VoteState::from_byte(4, 0x4f);
+        assert!(skipped.did_vote && skipped.voted_for.is_none());
+
+        let mut host_hud = MeetingHud::new(20, -2, states);
+        host_hud.states[0].did_vote = true;
+        host_hud.states[0].voted_for = Some(7);
+        let info = host_hud.rpc_voting_complete(Some(7), false);
+        let result = match handle_rpc(&mut meeting_hud, info) {
+            RPCCallback::VotingComplete {
+                exiled_player_id,
+                tie,
+            } => (exiled_player_id, tie),
+            callback => panic!("Unexpected callback {:?}", callback),
+        };
+        assert_eq!(result, (Some(7), false));
+        assert_eq!(meeting_hud.states[0].voted_for, Some(7));
+        assert_eq!(meeting_hud.states[2].player_id, 7);
+    }
+
+    #[test]
+    fn test_set_tasks() {
+        let player = PlayerData {
+            name: "Otter".to_string(),
+            color: 0,
+            hat_id: 0,
+            skin_id: 0,
+            pet_id: 0,
+            disconnected: false,
+            is_imposter: false,
+            is_dead: false,
+            tasks: Vec::new(),
+            dirty: false,
+        };
+        let mut w = PacketWriter::new();
+        w.write_u32_encoded(1);
+        w.write_u8(4);
+        w.write(&player);
+        let data = w.finish();
+        let mut game_data = GameData::initialize(3, -2, &mut (&data[..]).get_reader()).unwrap();
+
+        let info = game_data.rpc_set_tasks(4, &[16, 2, 30]);
+        handle_rpc(&mut game_data, info);
+        let tasks = &game_data.players[&4].tasks;
+        assert_eq!(
+            tasks.iter().map(TaskInfo::id).collect::<Vec<_>>(),
+            [16, 2, 30]
+        );
+        assert!(tasks.iter().all(|task| !task.is_complete()));
+    }
+
+    #[test]
+    fn test_host_spawns() {
+        let mut game_data = GameData::new(1, -2);
+        game_data.players.insert(
+            0,
+            PlayerData {
+                name: "Otter".to_string(),
+                ..PlayerData::default()
+            },
+        );
+        let mut world = World::new(7, -2);
+        world.door_open[3] = false;
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameStarted as u8);
+        w.write(Packet::GameStarted {
+            game_id: GameId { id: 32 },
+        });
+        w.end_message();
+        for prefab in [
+            Prefab::GameData(game_data, VoteBanSystem::new(2, -2)),
+            Prefab::Player(
+                PlayerControl::new(3, 5, 0),
+                PlayerPhysics::new(4, 5),
+                PlayerTransform::new(5, 5, Vector2::new(1., -2.)),
+            ),
+            Prefab::World(world),
+        ] {
+            w.write(GameInfo::CreateFromPrefab {
+                spawn_flags: 0,
+                prefab,
+            });
+        }
+        let data = w.finish();
+        let data = &data[..];
+
+        let mut r = data.get_reader();
+        match r.read::<Packet>().unwrap() {
+            Packet::GameStarted { game_id } => assert_eq!(game_id.id, 32),
+            packet => panic!("Unexpected packet {:?}", packet),
+        }
+        let prefabs = r
+            .read_all::<GameInfo>()
+            .unwrap()
+            .into_iter()
+            .map(|info| match info {
+                GameInfo::CreateFromPrefab { prefab, .. } => prefab,
+                info => panic!("Unexpected game info {:?}", info),
+            })
+            .collect::<Vec<_>>();
+        match &prefabs[..] {
+            [Prefab::GameData(game_data, _), Prefab::Player(control, _, transform), Prefab::World(world)] =>
+            {
+                assert_eq!(game_data.players[&0].name, "Otter");
+                assert_eq!(control.owner_id(), 5);
+                assert_eq!(control.player_id, 0);
+                assert_eq!(transform.net_id(), 5);
+                assert!((transform.target_position.y() + 2.).abs() < 0.01);
+                assert_eq!(world.net_id(), 7);
+                assert!(world.door_open[0] && !world.door_open[3]);
+            }
+            prefabs => panic!("Unexpected prefabs {:?}", prefabs),
+        }
+    }
+
+    #[test]
+    fn test_game_data_order() {
+        let mut game_data = GameData::new(3, -2);
+        for player_id in [4, 1, 4, 2] {
+            game_data.add_player(player_id, PlayerData::default());
+        }
+        assert_eq!(game_data.player_ids(), [4, 1, 2]);
+
+        // Meetings list players in this order rather than by id
+        let data = game_data.spawn_data();
+        let read = GameData::initialize(3, -2, &mut (&data[..]).get_reader()).unwrap();
+        assert_eq!(read.player_ids(), [4, 1, 2]);
+    }
+
+    #[test]
+    fn test_set_infected() {
+        let mut control = PlayerControl::new(3, 5, 0);
+        let info = control.rpc_set_infected(&[2, 7]);
+        match handle_rpc(&mut control, info) {
+            RPCCallback::SetInfected { impostors } => assert_eq!(impostors, [2, 7]),
+            callback => panic!("Unexpected callback {:?}", callback),
+        }
+    }
+
+    #[test]
+    fn test_unknown_rpc() {
+        let mut control = PlayerControl::new(3, 5, 0);
+        let info = GameInfo::RPC {
+            net_id: 3,
+            call_id: 200,
+            data: Data::Bytes(vec![1, 2, 3]),
+        };
+        match handle_rpc(&mut control, info) {
+            RPCCallback::Unknown { call_id } => assert_eq!(call_id, 200),
+            callback => panic!("Unexpected callback {:?}", callback),
+        }
+    }
+
+    #[test]
+    fn test_set_game_options() {
+        let mut control = PlayerControl::new(3, 5, 0);
+        let options = GameOptions {
+            num_imposters: 2,
+            kill_cooldown: 22.5,
+            ..GameOptions::default()
+        };
+        let info = control.rpc_set_game_options(&options);
+        match handle_rpc(&mut control, info) {
+            RPCCallback::GameOptions(read) => {
+                assert_eq!(read.num_imposters, 2);
+                assert_eq!(read.kill_cooldown, 22.5);
+                assert_eq!(read.map_id, options.map_id);
+            }
+            callback => panic!("Unexpected callback {:?}", callback),
+        }
+    }
+
+    #[test]
+    fn test_world_doors() {
+        assert_eq!(World::doors_of(SystemType::Cafeteria), [0, 3, 8]);
+        assert_eq!(World::doors_of(SystemType::Reactor), [0; 0]);
+        assert_eq!(World::door_room(9), Some(SystemType::Electrical));
+        assert_eq!(World::door_room(13), None);
+
+        let mut host = World::new(4, -2);
+        let mut world =
+            World::initialize(4, -2, &mut (&host.spawn_data()[..]).get_reader()).unwrap();
+        for door in World::doors_of(SystemType::MedBay) {
+            host.door_open[door] = false;
+        }
+        match host.doors_update_info(1 << 10) {
+            GameInfo::UpdateData {
+                net_id: 4,
+                data: Data::Bytes(data),
+            } => world.update_data(&mut (&data[..]).get_reader()).unwrap(),
+            info => panic!("Unexpected game info {:?}", info),
+        }
+        assert_eq!(world.door_open, host.door_open);
+        assert!(!world.door_open[10]);
+        assert_eq!(world.door_open.iter().filter(|open| !**open).count(), 1);
+
+        match handle_rpc(&mut world, host.rpc_close_doors(SystemType::Storage)) {
+            RPCCallback::CloseDoors {
+                room: SystemType::Storage,
+            } => (),
+            callback => panic!("Unexpected callback {:?}", callback),
+        }
+    }
+}
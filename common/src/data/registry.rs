@@ -0,0 +1,235 @@
+//! Names for every id the protocol uses, so logs, the dissector and anything else printing packets
+//! agree on them
+
+use crate::data::GameVersion;
+
+/// The oldest version this crate knows the protocol of. Ids without a later `since` are in every
+/// version from here on
+pub const OLDEST_VERSION: GameVersion = GameVersion::new(2020, 6, 9, 0);
+
+/// The type of a net object
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ObjectKind {
+    PlayerControl,
+    PlayerPhysics,
+    PlayerTransform,
+    World,
+    Lobby,
+    GameData,
+    VoteBanSystem,
+    MeetingHud,
+}
+
+/// The id spaces. RPC call ids are only unique per object
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OpcodeKind {
+    /// `HazelType`
+    Hazel,
+    /// `PacketType`
+    Packet,
+    /// `GameInfoType`
+    GameInfo,
+    /// `PrefabType`
+    Prefab,
+    Rpc(ObjectKind),
+}
+
+/// A named id
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Opcode {
+    pub kind: OpcodeKind,
+    pub id: u8,
+    /// Matches the variant in the id's own enum, like `HazelType::KeepAlive`
+    pub name: &'static str,
+    /// How people are used to seeing it written, e.g. in Wireshark. Usually `name`
+    pub display: &'static str,
+    /// The first game version it's used in, `OLDEST_VERSION` if it's in every version we know of
+    pub since: GameVersion,
+}
+
+impl Opcode {
+    pub fn available_in(&self, version: GameVersion) -> bool {
+        version >= self.since
+    }
+}
+
+macro_rules! opcodes {
+    (@or $default:expr) => { $default };
+    (@or $default:expr, $value:expr) => { $value };
+    ($($kind:expr => {
+        $($id:expr => $name:ident $(as $display:literal)? $(since $since:expr)?,)*
+    })*) => {
+        &[$($(Opcode {
+            kind: $kind,
+            id: $id,
+            name: stringify!($name),
+            display: opcodes!(@or stringify!($name) $(, $display)?),
+            since: opcodes!(@or OLDEST_VERSION $(, $since)?),
+        },)*)*]
+    };
+}
+
+/// Every id we know of
+pub const OPCODES: &[Opcode] = opcodes! {
+    OpcodeKind::Hazel => {
+        0 => Unreliable,
+        1 => Reliable,
+        8 => Hello,
+        9 => Disconnect,
+        10 => Acknowledge,
+        11 => Fragment,
+        12 => KeepAlive as "Keep-Alive",
+    }
+    OpcodeKind::Packet => {
+        0x00 => HostingGame,
+        0x01 => GameJoinDisconnect,
+        0x02 => GameStarted,
+        0x04 => PlayerLeft,
+        0x05 => GameInfo,
+        0x06 => GameInfoTo,
+        0x07 => JoinedGame,
+        0x08 => EndGame,
+        0x0a => AlterGameInfo,
+        0x0b => KickPlayer,
+        0x0d => ChangeServer,
+        0x0e => ServerList,
+        0x10 => GameList,
+    }
+    OpcodeKind::GameInfo => {
+        1 => UpdateData,
+        2 => RPC,
+        4 => CreateFromPrefab,
+        5 => Destroy,
+        6 => ChangeScene,
+        7 => ClientReady,
+    }
+    OpcodeKind::Prefab => {
+        0 => World,
+        1 => MeetingHud,
+        2 => Lobby,
+        3 => GameData,
+        4 => Player,
+        5 => HeadQuarters,
+    }
+    OpcodeKind::Rpc(ObjectKind::PlayerControl) => {
+        0 => PlayAnimation,
+        1 => CompleteTask,
+        2 => SetGameOptions,
+        3 => SetInfected,
+        4 => Exile,
+        5 => CheckName,
+        6 => SetName,
+        7 => CheckColor,
+        8 => SetColor,
+        9 => SetHat,
+        10 => SetSkin,
+        11 => ReportBody,
+        12 => MurderPlayer,
+        13 => SendChat,
+        14 => MeetingCalled,
+        15 => SetScanner,
+        16 => AddChatNote,
+        17 => SetPet,
+        18 => SetStartCounter,
+    }
+    OpcodeKind::Rpc(ObjectKind::PlayerPhysics) => {
+        0x13 => EnterVent,
+        0x14 => ExitVent,
+    }
+    OpcodeKind::Rpc(ObjectKind::PlayerTransform) => {
+        0x15 => SnapTo,
+    }
+    OpcodeKind::Rpc(ObjectKind::World) => {
+        0 => CloseDoorsOfType,
+        1 => RepairSystem,
+    }
+    OpcodeKind::Rpc(ObjectKind::GameData) => {
+        0x1d => SetTasks,
+        0x1e => UpdatePlayerInfo,
+    }
+    OpcodeKind::Rpc(ObjectKind::MeetingHud) => {
+        22 => Close,
+        23 => VotingComplete,
+        24 => CastVote,
+        25 => ClearVote,
+        26 => AddVote,
+    }
+};
+
+/// Looks up an id
+pub fn opcode(kind: OpcodeKind, id: u8) -> Option<&'static Opcode> {
+    OPCODES
+        .iter()
+        .find(|opcode| opcode.kind == kind && opcode.id == id)
+}
+
+/// The name of an id, or `"Unknown"`
+pub fn opcode_name(kind: OpcodeKind, id: u8) -> &'static str {
+    opcode(kind, id).map_or("Unknown", |opcode| opcode.name)
+}
+
+/// Every id with `query` in its name, ignoring case
+pub fn search_opcodes(query: &str) -> impl Iterator<Item = &'static Opcode> {
+    let query = query.to_lowercase();
+    OPCODES
+        .iter()
+        .filter(move |opcode| opcode.name.to_lowercase().contains(&query))
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::FromPrimitive;
+
+    use super::*;
+    use crate::data::{GameInfoType, HazelType, PacketType};
+
+    #[test]
+    fn test_names_match_types() {
+        for opcode in OPCODES {
+            let name = match opcode.kind {
+                OpcodeKind::Hazel => format!("{:?}", HazelType::from_u8(opcode.id).unwrap()),
+                OpcodeKind::Packet => format!("{:?}", PacketType::from_u8(opcode.id).unwrap()),
+                OpcodeKind::GameInfo => {
+                    format!("{:?}", GameInfoType::from_u8(opcode.id).unwrap())
+                }
+                _ => continue,
+            };
+            assert_eq!(opcode.name, name);
+        }
+    }
+
+    #[test]
+    fn test_unique() {
+        for (i, a) in OPCODES.iter().enumerate() {
+            for b in &OPCODES[i + 1..] {
+                assert!(a.kind != b.kind || a.id != b.id, "{:?} and {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(
+            opcode_name(OpcodeKind::Rpc(ObjectKind::PlayerControl), 13),
+            "SendChat"
+        );
+        assert_eq!(
+            opcode_name(OpcodeKind::Rpc(ObjectKind::Lobby), 13),
+            "Unknown"
+        );
+        let votes: Vec<_> = search_opcodes("vote").map(|opcode| opcode.name).collect();
+        assert_eq!(votes, ["CastVote", "ClearVote", "AddVote"]);
+    }
+
+    #[test]
+    fn test_display_and_since() {
+        let keep_alive = opcode(OpcodeKind::Hazel, 12).unwrap();
+        assert_eq!(keep_alive.name, "KeepAlive");
+        assert_eq!(keep_alive.display, "Keep-Alive");
+        assert_eq!(opcode(OpcodeKind::Hazel, 8).unwrap().display, "Hello");
+        assert!(OPCODES
+            .iter()
+            .all(|opcode| opcode.available_in(OLDEST_VERSION)));
+        assert!(!keep_alive.available_in(GameVersion::new(2020, 6, 8, 0)));
+    }
+}
@@ -0,0 +1,137 @@
+/// How a task is handed out
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TaskLength {
+    /// Given to everyone, so impostors can't fake knowing it
+    Common,
+    Short,
+    Long,
+}
+
+/// A task that can be given to a player with `SetTasks`
+#[derive(Debug, Copy, Clone)]
+pub struct TaskDefinition {
+    /// Index into the map's list of tasks. What `SetTasks` sends
+    pub id: u8,
+    pub name: &'static str,
+    pub length: TaskLength,
+}
+
+const fn task(id: u8, name: &'static str, length: TaskLength) -> TaskDefinition {
+    TaskDefinition { id, name, length }
+}
+
+use TaskLength::*;
+
+pub const SKELD_TASKS: &[TaskDefinition] = &[
+    task(0, "Submit Scan", Long),
+    task(1, "Prime Shields", Short),
+    task(2, "Fuel Engines", Long),
+    task(3, "Chart Course", Short),
+    task(4, "Start Reactor", Long),
+    task(5, "Swipe Card", Common),
+    task(6, "Clear Asteroids", Long),
+    task(7, "Upload Data", Short),
+    task(8, "Inspect Sample", Long),
+    task(9, "Empty Chute", Short),
+    task(10, "Empty Garbage", Long),
+    task(11, "Align Engine Output", Long),
+    task(12, "Fix Wiring", Common),
+    task(13, "Calibrate Distributor", Short),
+    task(14, "Divert Power (Upper Engine)", Short),
+    task(15, "Unlock Manifolds", Short),
+    task(16, "Download Data (Cafeteria)", Short),
+    task(17, "Clean O2 Filter", Short),
+    task(18, "Stabilize Steering", Short),
+    task(19, "Divert Power (Lower Engine)", Short),
+    task(20, "Download Data (Weapons)", Short),
+    task(21, "Divert Power (Weapons)", Short),
+    task(22, "Download Data (Navigation)", Short),
+    task(23, "Divert Power (Shields)", Short),
+    task(24, "Divert Power (Navigation)", Short),
+    task(25, "Download Data (Communications)", Short),
+    task(26, "Divert Power (Communications)", Short),
+    task(27, "Divert Power (O2)", Short),
+];
+
+pub const MIRA_HQ_TASKS: &[TaskDefinition] = &[
+    task(0, "Enter ID Code", Common),
+    task(1, "Fix Wiring", Common),
+    task(2, "Start Reactor", Long),
+    task(3, "Submit Scan", Long),
+    task(4, "Clear Asteroids", Long),
+    task(5, "Divert Power", Long),
+    task(6, "Water Plants", Long),
+    task(7, "Fuel Engines", Long),
+    task(8, "Run Diagnostics", Long),
+    task(9, "Process Data", Short),
+    task(10, "Chart Course", Short),
+    task(11, "Clean O2 Filter", Short),
+    task(12, "Sort Samples", Short),
+    task(13, "Measure Weather", Short),
+    task(14, "Prime Shields", Short),
+    task(15, "Empty Garbage", Short),
+    task(16, "Buy Beverage", Short),
+    task(17, "Assemble Artifact", Short),
+    task(18, "Align Telescope", Short),
+    task(19, "Record Temperature", Short),
+];
+
+pub const POLUS_TASKS: &[TaskDefinition] = &[
+    task(0, "Swipe Card", Common),
+    task(1, "Insert Keys", Common),
+    task(2, "Scan Boarding Pass", Common),
+    task(3, "Fix Wiring", Common),
+    task(4, "Upload Data", Long),
+    task(5, "Start Reactor", Long),
+    task(6, "Fuel Engines", Long),
+    task(7, "Open Waterways", Long),
+    task(8, "Inspect Sample", Long),
+    task(9, "Replace Water Jug", Long),
+    task(10, "Fix Weather Node", Long),
+    task(11, "Reboot Wifi", Long),
+    task(12, "Monitor Tree", Short),
+    task(13, "Store Artifacts", Short),
+    task(14, "Fill Canisters", Short),
+    task(15, "Empty Garbage", Short),
+    task(16, "Chart Course", Short),
+    task(17, "Submit Scan", Long),
+    task(18, "Clear Asteroids", Short),
+    task(19, "Record Temperature (Laboratory)", Short),
+    task(20, "Repair Drill", Short),
+    task(21, "Align Telescope", Short),
+    task(22, "Record Temperature (Outside)", Short),
+    task(23, "Unlock Manifolds", Short),
+    task(24, "Download Data (Office)", Short),
+    task(25, "Download Data (Specimen Room)", Short),
+    task(26, "Download Data (Communications)", Short),
+    task(27, "Download Data (Weapons)", Short),
+];
+
+/// The tasks for a map, by `GameOptions::map_id`
+pub fn map_tasks(map_id: u8) -> Option<&'static [TaskDefinition]> {
+    match map_id {
+        0 => Some(SKELD_TASKS),
+        1 => Some(MIRA_HQ_TASKS),
+        2 => Some(POLUS_TASKS),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_tables() {
+        for map_id in 0..3 {
+            let tasks = map_tasks(map_id).unwrap();
+            for (index, task) in tasks.iter().enumerate() {
+                assert_eq!(task.id as usize, index);
+            }
+            for length in [Common, Short, Long].iter() {
+                assert!(tasks.iter().any(|task| task.length == *length));
+            }
+        }
+        assert!(map_tasks(3).is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{fmt, str::FromStr};
+
+/// A game version as sent in the hello, which servers check against what they support
+///
+/// Encoded from the build date as `year * 25000 + month * 1800 + day * 50 + revision`. The build
+/// can be a while before the release, e.g. 2020.9.7 shipped as v2020.9.22
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameVersion {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+    pub revision: u32,
+}
+
+impl GameVersion {
+    pub const fn new(year: u32, month: u32, day: u32, revision: u32) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            revision,
+        }
+    }
+
+    /// The integer sent over the wire
+    pub const fn to_u32(self) -> u32 {
+        self.year * 25000 + self.month * 1800 + self.day * 50 + self.revision
+    }
+
+    pub const fn from_u32(value: u32) -> Self {
+        let rest = value % 25000;
+        Self {
+            year: value / 25000,
+            month: rest / 1800,
+            day: rest % 1800 / 50,
+            revision: rest % 50,
+        }
+    }
+
+    /// The first build whose `GameOptions` have confirm ejects and visual tasks, version 3
+    pub const OPTIONS_V3: GameVersion = GameVersion::new(2020, 11, 17, 0);
+
+    /// The first build whose `GameOptions` have anonymous votes and task bar updates, version 4
+    pub const OPTIONS_V4: GameVersion = GameVersion::new(2020, 12, 9, 0);
+
+    /// The first build to say hello with the last nonce received, language and chat mode after
+    /// the username
+    pub const HELLO_CHAT_MODE: GameVersion = GameVersion::new(2021, 4, 25, 0);
+
+    /// The `GameOptions::game_settings_version` this build reads and writes. Builds older than
+    /// `OPTIONS_V3` all use 2, as far back as this crate knows
+    pub fn game_options_version(self) -> u8 {
+        if self >= Self::OPTIONS_V4 {
+            4
+        } else if self >= Self::OPTIONS_V3 {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Whether this build's hello has the fields added in `HELLO_CHAT_MODE`
+    pub fn hello_has_chat_mode(self) -> bool {
+        self >= Self::HELLO_CHAT_MODE
+    }
+
+    /// The first revision of the next day's build
+    pub fn next_day(self) -> Self {
+        match self {
+            GameVersion {
+                year,
+                month: 12,
+                day: 31,
+                ..
+            } => Self::new(year + 1, 1, 1, 0),
+            GameVersion {
+                year,
+                month,
+                day: 31,
+                ..
+            } => Self::new(year, month + 1, 1, 0),
+            GameVersion {
+                year, month, day, ..
+            } => Self::new(year, month, day + 1, 0),
+        }
+    }
+}
+
+impl From<u32> for GameVersion {
+    fn from(value: u32) -> Self {
+        Self::from_u32(value)
+    }
+}
+
+impl From<GameVersion> for u32 {
+    fn from(version: GameVersion) -> Self {
+        version.to_u32()
+    }
+}
+
+/// Like `2020.9.7`, with the revision on the end if there is one
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.year, self.month, self.day)?;
+        if self.revision != 0 {
+            write!(f, ".{}", self.revision)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads `year.month.day` or `year.month.day.revision`
+impl FromStr for GameVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts = value
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?;
+        let (year, month, day, revision) = match parts[..] {
+            [year, month, day] => (year, month, day, 0),
+            [year, month, day, revision] => (year, month, day, revision),
+            _ => return Err("Expected a version like 2020.9.7".to_string()),
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || revision >= 50 {
+            return Err(format!("{} isn't a valid version", value));
+        }
+        Ok(Self::new(year, month, day, revision))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_version() {
+        let version = GameVersion::new(2020, 9, 7, 0);
+        assert_eq!(version.to_u32(), 50_51_65_50);
+        assert_eq!(
+            GameVersion::from_u32(50_51_91_00),
+            GameVersion::new(2020, 10, 22, 0)
+        );
+        assert_eq!(version.to_string(), "2020.9.7");
+        assert_eq!("2020.6.9.2".parse(), Ok(GameVersion::new(2020, 6, 9, 2)));
+        assert!("2020.13.1".parse::<GameVersion>().is_err());
+        assert_eq!(
+            GameVersion::new(2020, 12, 31, 3).next_day(),
+            GameVersion::new(2021, 1, 1, 0)
+        );
+        assert!(GameVersion::new(2020, 9, 22, 0) > version);
+        assert_eq!(version.game_options_version(), 2);
+        assert_eq!(GameVersion::new(2020, 11, 17, 0).game_options_version(), 3);
+        assert_eq!(GameVersion::new(2021, 3, 5, 0).game_options_version(), 4);
+        assert!(!GameVersion::new(2021, 3, 5, 0).hello_has_chat_mode());
+        assert!(GameVersion::new(2021, 6, 30, 0).hello_has_chat_mode());
+    }
+}
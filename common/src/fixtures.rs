@@ -0,0 +1,218 @@
+//! Known-good datagrams with what they should decode to, for checking parsers and integrations
+//! against
+//!
+//! Everything happens in game `GAME_CODE`, where we're client `CLIENT_ID` and our player spawns
+//! with the net ids below
+
+/// The game everything happens in
+pub const GAME_CODE: &str = "AQNKQQ";
+/// Our client id
+pub const CLIENT_ID: i32 = 5;
+/// Our player id
+pub const PLAYER_ID: u8 = 1;
+pub const CONTROL_NET_ID: u32 = 10;
+pub const PHYSICS_NET_ID: u32 = 11;
+pub const TRANSFORM_NET_ID: u32 = 12;
+
+/// The version in `HELLO`. 2020.9.22
+pub const VERSION: u32 = 50_51_65_50;
+/// The username in `HELLO`
+pub const USERNAME: &str = "client";
+/// The message in `CHAT`
+pub const CHAT_MESSAGE: &str = "hi";
+
+/// Hello with ack id 1: a reserved byte, `VERSION` and `USERNAME`
+pub const HELLO: &[u8] = &[
+    0x08, 0x00, 0x01, // Hello, ack id
+    0x00, // Reserved
+    0x46, 0xd2, 0x02, 0x03, // Version
+    0x06, b'c', b'l', b'i', b'e', b'n', b't', // Username
+];
+
+/// Reliable with ack id 2 asking to join `GAME_CODE`, owning every map
+pub const JOIN_GAME: &[u8] = &[
+    0x01, 0x00, 0x02, // Reliable, ack id
+    0x05, 0x00, 0x01, // GameJoinDisconnect
+    0x19, 0xdc, 0x06, 0x80, // Game id
+    0x07, // Maps owned
+];
+
+/// Reliable with ack id 1 from the host spawning our player. `PlayerTransform` starts with
+/// sequence id 1 at (40, -40), barely moving
+pub const SPAWN_PLAYER: &[u8] = &[
+    0x01, 0x00, 0x01, // Reliable, ack id
+    0x23, 0x00, 0x05, // GameInfo
+    0x19, 0xdc, 0x06, 0x80, // Game id
+    0x1c, 0x00, 0x04, // CreateFromPrefab
+    0x04, // Player prefab
+    0x05, // Owner id
+    0x01, // Spawn flags
+    0x03, // Children
+    0x0a, 0x02, 0x00, 0x01, 0x01, 0x01, // PlayerControl: is new, player id
+    0x0b, 0x00, 0x00, 0x01, // PlayerPhysics
+    0x0c, 0x0a, 0x00, 0x01, // PlayerTransform
+    0x01, 0x00, // Sequence id
+    0xff, 0xff, 0x00, 0x00, // Position
+    0x00, 0x80, 0x00, 0x80, // Velocity
+];
+
+/// Reliable with ack id 2 where our player says `CHAT_MESSAGE`
+pub const CHAT: &[u8] = &[
+    0x01, 0x00, 0x02, // Reliable, ack id
+    0x0c, 0x00, 0x05, // GameInfo
+    0x19, 0xdc, 0x06, 0x80, // Game id
+    0x05, 0x00, 0x02, // RPC
+    0x0a, // PlayerControl
+    0x0d, // SendChat
+    0x02, b'h', b'i', // Message
+];
+
+/// Unreliable movement snapshot for our player with sequence id 2 at (-40, 40), barely moving
+pub const MOVEMENT: &[u8] = &[
+    0x00, // Unreliable
+    0x12, 0x00, 0x05, // GameInfo
+    0x19, 0xdc, 0x06, 0x80, // Game id
+    0x0b, 0x00, 0x01, // UpdateData
+    0x0c, // PlayerTransform
+    0x02, 0x00, // Sequence id
+    0x00, 0x00, 0xff, 0xff, // Position
+    0x00, 0x80, 0x00, 0x80, // Velocity
+];
+
+/// Every fixture by name, e.g. to seed a fuzzer
+pub const ALL: &[(&str, &[u8])] = &[
+    ("hello", HELLO),
+    ("join_game", JOIN_GAME),
+    ("spawn_player", SPAWN_PLAYER),
+    ("chat", CHAT),
+    ("movement", MOVEMENT),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{
+            GameId, GameInfo, GenericMessage, HazelPacket, HazelPacketOut, JoinGamePacket,
+            NetObject, Packet, PacketType, Prefab, Vector2,
+        },
+        reader::{GetReader, Serialize},
+    };
+
+    fn hazel(bytes: &[u8]) -> HazelPacket {
+        bytes.get_reader().read().unwrap()
+    }
+
+    fn game_info(data: &[u8]) -> Vec<GameInfo> {
+        let mut packets = data.get_reader().read_all::<Packet>().unwrap();
+        assert_eq!(packets.len(), 1);
+        match packets.remove(0) {
+            Packet::GameInfo { game_id, data } => {
+                assert_eq!(game_id, GameId::from_chars(GAME_CODE));
+                data
+            }
+            packet => panic!("Unexpected packet {:?}", packet),
+        }
+    }
+
+    fn near(a: Vector2, x: f32, y: f32) -> bool {
+        (a.x() - x).abs() < 0.01 && (a.y() - y).abs() < 0.01
+    }
+
+    #[test]
+    fn test_hello() {
+        let data = match hazel(HELLO) {
+            HazelPacket::Hello { ack_id: 1, data } => data,
+            packet => panic!("Unexpected packet {:?}", packet),
+        };
+        let data = &data[..];
+        let mut r = data.get_reader();
+        assert_eq!(r.read_u8().unwrap(), 0);
+        assert_eq!(r.read_u32().unwrap(), VERSION);
+        assert_eq!(r.read_string().unwrap(), USERNAME);
+        assert_eq!(r.remaining(), 0);
+    }
+
+    #[test]
+    fn test_join_game() {
+        let packet = HazelPacketOut::Reliable {
+            ack_id: 2,
+            data: Box::new(GenericMessage {
+                tag: PacketType::GameJoinDisconnect as u8,
+                data: Box::new(JoinGamePacket {
+                    game_id: GameId::from_chars(GAME_CODE),
+                    maps_owned: 7,
+                }),
+            }),
+        };
+        assert_eq!(packet.serialize_bytes(), JOIN_GAME);
+    }
+
+    #[test]
+    fn test_spawn_player() {
+        let data = match hazel(SPAWN_PLAYER) {
+            HazelPacket::Reliable { ack_id: 1, data } => data,
+            packet => panic!("Unexpected packet {:?}", packet),
+        };
+        match &game_info(&data)[..] {
+            [GameInfo::CreateFromPrefab {
+                spawn_flags: 1,
+                prefab: Prefab::Player(control, physics, transform),
+            }] => {
+                assert_eq!(control.net_id(), CONTROL_NET_ID);
+                assert_eq!(control.owner_id(), CLIENT_ID);
+                assert_eq!(control.player_id, PLAYER_ID);
+                assert_eq!(physics.net_id(), PHYSICS_NET_ID);
+                assert_eq!(transform.net_id(), TRANSFORM_NET_ID);
+                assert_eq!(transform.last_seq_id, 1);
+                assert!(near(transform.target_position, 40., -40.));
+                assert!(near(transform.velocity, 0., 0.));
+            }
+            infos => panic!("Unexpected game info {:?}", infos),
+        }
+    }
+
+    #[test]
+    fn test_chat() {
+        let data = match hazel(CHAT) {
+            HazelPacket::Reliable { ack_id: 2, data } => data,
+            packet => panic!("Unexpected packet {:?}", packet),
+        };
+        match &game_info(&data)[..] {
+            [GameInfo::RPC {
+                net_id: CONTROL_NET_ID,
+                call_id: 13,
+                data,
+            }] => {
+                let data = data.serialize_bytes();
+                assert_eq!(
+                    (&data[..]).get_reader().read_string().unwrap(),
+                    CHAT_MESSAGE
+                );
+            }
+            infos => panic!("Unexpected game info {:?}", infos),
+        }
+    }
+
+    #[test]
+    fn test_movement() {
+        let data = match hazel(MOVEMENT) {
+            HazelPacket::Unreliable { data } => data,
+            packet => panic!("Unexpected packet {:?}", packet),
+        };
+        match &game_info(&data)[..] {
+            [GameInfo::UpdateData {
+                net_id: TRANSFORM_NET_ID,
+                data,
+            }] => {
+                let data = data.serialize_bytes();
+                let data = &data[..];
+                let mut r = data.get_reader();
+                assert_eq!(r.read_u16().unwrap(), 2);
+                assert!(near(r.read().unwrap(), -40., 40.));
+                assert!(near(r.read().unwrap(), 0., 0.));
+            }
+            infos => panic!("Unexpected game info {:?}", infos),
+        }
+    }
+}
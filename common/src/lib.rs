@@ -4,6 +4,8 @@ extern crate bitflags;
 #[macro_use]
 extern crate num_derive;
 
+pub mod capture;
 pub mod data;
+pub mod fixtures;
 pub mod reader;
 pub mod utils;
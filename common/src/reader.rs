@@ -58,13 +58,47 @@ impl<T: PacketRead> PacketReader<T> {
         Ok(result)
     }
 
+    /// Reads messages of type `S` until no data left, skipping any that fail to parse and adding
+    /// them to `anomalies`. Only errors in the message framing itself fail the read
+    ///
+    /// `S` must be read as a single message, like `GameInfo`
+    pub fn read_all_lenient<S: Deserialize>(
+        &mut self,
+        anomalies: &mut Vec<ParseAnomaly>,
+    ) -> Result<Vec<S>> {
+        let mut result = Vec::new();
+        while self.data.remaining() != 0 {
+            let length = self.read_u16()?;
+            let tag = self.read_u8()?;
+            let data = self.read_slice(length as usize)?.to_vec();
+            let mut message = Vec::with_capacity(data.len() + 3);
+            message.extend_from_slice(&length.to_le_bytes());
+            message.push(tag);
+            message.extend_from_slice(&data);
+            match (&message[..]).get_reader().read::<S>() {
+                Ok(value) => result.push(value),
+                Err(error) => anomalies.push(ParseAnomaly {
+                    tag,
+                    data,
+                    error: error.to_string(),
+                }),
+            }
+        }
+        Ok(result)
+    }
+
     /// Reads a bool encoded as a single byte
     #[inline]
     pub fn read_bool(&mut self) -> Result<bool> {
         Ok(match self.read_u8()? {
             0 => false,
             1 => true,
-            value => panic!("Unexpected value for read_bool {}", value),
+            value => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unexpected value for read_bool {}", value),
+                ))
+            }
         })
     }
 
@@ -230,6 +264,15 @@ impl PacketRead for &[u8] {
     }
 }
 
+/// A message skipped by a lenient read because it couldn't be parsed
+#[derive(Debug, Clone)]
+pub struct ParseAnomaly {
+    pub tag: u8,
+    /// The message data, without the length and tag
+    pub data: Vec<u8>,
+    pub error: String,
+}
+
 pub trait PacketRead: Read {
     fn remaining(&mut self) -> usize;
 
@@ -1,10 +1,17 @@
 use std::{
+    convert::TryFrom,
     fs::File,
-    io::{self, BufReader},
+    io::{self, BufReader, BufWriter, ErrorKind},
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::Path,
 };
 
-use crate::reader::{IntoReader, PacketWriter};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::Address,
+    reader::{IntoReader, PacketWriter},
+};
 
 pub fn read_purchase_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
     // Read data
@@ -49,10 +56,497 @@ pub fn write_purchase_file<P: AsRef<Path>>(path: P, values: &[String]) -> io::Re
     std::fs::write(path, data_encoded)
 }
 
+/// A list of servers to pick from, as the game keeps in `regionInfo.json` or, before 2020.12.9,
+/// `regionInfo.dat`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RegionFile {
+    /// Index into `regions` of the one in use
+    #[serde(rename = "CurrentRegionIdx")]
+    pub current_region: i32,
+    pub regions: Vec<RegionInfo>,
+}
+
+impl RegionFile {
+    /// The region in use, if the index is valid
+    pub fn current(&self) -> Option<&RegionInfo> {
+        usize::try_from(self.current_region)
+            .ok()
+            .and_then(|index| self.regions.get(index))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "$type")]
+pub enum RegionInfo {
+    /// One server found by looking up `fqdn`, or at `default_ip` if that fails
+    #[serde(rename = "DnsRegionInfo, Assembly-CSharp", rename_all = "PascalCase")]
+    Dns {
+        name: String,
+        fqdn: String,
+        default_ip: String,
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        translate_name: Option<i32>,
+    },
+    /// A fixed list of servers. The only kind `regionInfo.dat` can hold
+    #[serde(
+        rename = "StaticRegionInfo, Assembly-CSharp",
+        rename_all = "PascalCase"
+    )]
+    Static {
+        name: String,
+        ping_server: String,
+        servers: Vec<ServerInfo>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        translate_name: Option<i32>,
+    },
+}
+
+impl RegionInfo {
+    pub fn name(&self) -> &str {
+        match self {
+            RegionInfo::Dns { name, .. } | RegionInfo::Static { name, .. } => name,
+        }
+    }
+
+    /// Where to connect, looking up DNS regions. Each can be used as a `ServerTarget::Custom` or
+    /// with `NetClient::connect_direct`
+    pub fn addresses(&self) -> io::Result<Vec<SocketAddr>> {
+        match self {
+            RegionInfo::Dns {
+                fqdn,
+                default_ip,
+                port,
+                ..
+            } => match (fqdn.as_str(), *port).to_socket_addrs() {
+                Ok(addresses) => Ok(addresses.collect()),
+                Err(_) => Ok((default_ip.as_str(), *port).to_socket_addrs()?.collect()),
+            },
+            RegionInfo::Static { servers, .. } => servers
+                .iter()
+                .map(|server| {
+                    (server.ip.as_str(), server.port)
+                        .to_socket_addrs()?
+                        .next()
+                        .ok_or_else(|| {
+                            io::Error::new(
+                                ErrorKind::NotFound,
+                                format!("No address for server {}", server.name),
+                            )
+                        })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServerInfo {
+    pub name: String,
+    /// An IPv4 address in `regionInfo.dat`, though the JSON lets it be a host name
+    pub ip: String,
+    pub port: u16,
+    #[serde(default)]
+    pub use_dtls: bool,
+    #[serde(default)]
+    pub players: i32,
+    #[serde(default)]
+    pub connection_failures: i32,
+}
+
+/// Reads a `regionInfo.json`, or a `regionInfo.dat` if the file doesn't end in `.json`
+pub fn read_region_file<P: AsRef<Path>>(path: P) -> io::Result<RegionFile> {
+    let path = path.as_ref();
+    if is_json(path) {
+        let file = File::open(path)?;
+        return Ok(serde_json::from_reader(BufReader::new(file))?);
+    }
+
+    let data = std::fs::read(path)?;
+    let mut r = data.into_reader();
+    let current_region = r.read_i32()?;
+    let region_count = r.read_i32()?;
+    let mut regions = Vec::new();
+    for _ in 0..region_count {
+        let name = r.read_string()?;
+        let ping_server = r.read_string()?;
+        let server_count = r.read_i32()?;
+        let mut servers = Vec::new();
+        for _ in 0..server_count {
+            let name = r.read_string()?;
+            let address = r.read::<Address>()?;
+            servers.push(ServerInfo {
+                name,
+                ip: Ipv4Addr::from(address.ip).to_string(),
+                port: address.port,
+                use_dtls: false,
+                players: 0,
+                connection_failures: r.read_i32()?,
+            });
+        }
+        regions.push(RegionInfo::Static {
+            name,
+            ping_server,
+            servers,
+            translate_name: None,
+        });
+    }
+    Ok(RegionFile {
+        current_region,
+        regions,
+    })
+}
+
+/// Writes a `regionInfo.json`, or a `regionInfo.dat` if the path doesn't end in `.json`. The
+/// `.dat` only holds static regions with IPv4 servers, anything else is an `InvalidInput` error
+pub fn write_region_file<P: AsRef<Path>>(path: P, regions: &RegionFile) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_json(path) {
+        let file = File::create(path)?;
+        return Ok(serde_json::to_writer_pretty(BufWriter::new(file), regions)?);
+    }
+
+    let mut w = PacketWriter::new();
+    w.write_i32(regions.current_region);
+    w.write_i32(regions.regions.len() as i32);
+    for region in &regions.regions {
+        let (name, ping_server, servers) = match region {
+            RegionInfo::Static {
+                name,
+                ping_server,
+                servers,
+                ..
+            } => (name, ping_server, servers),
+            RegionInfo::Dns { name, .. } => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("DNS region {} can't be written to a .dat", name),
+                ))
+            }
+        };
+        w.write_string(name);
+        w.write_string(ping_server);
+        w.write_i32(servers.len() as i32);
+        for server in servers {
+            let ip: Ipv4Addr = server.ip.parse().map_err(|_| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Server {} needs an IPv4 address for a .dat", server.name),
+                )
+            })?;
+            w.write_string(&server.name);
+            w.write(Address {
+                ip: ip.octets(),
+                port: server.port,
+            });
+            w.write_i32(server.connection_failures);
+        }
+    }
+    std::fs::write(path, w.finish())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Cleans up player supplied text such as names and chat messages
+///
+/// Griefers use control characters and rich text markup to break terminals and UIs
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    /// Remove control characters, zero-width characters and bidi overrides
+    pub strip_control: bool,
+
+    /// Remove rich text tags such as `<color=#ff0000>` and `<size=1000%>`. Only tags the game
+    /// renders are removed, and bare ones like `<b>` only when they're closed later, so `<3` and
+    /// `a<b>c` are left alone
+    pub strip_markup: bool,
+
+    /// Words to replace with asterisks, like the game's censor chat option. Matched as whole
+    /// words, ignoring case. The game censors where chat is shown rather than telling the server,
+    /// so this is never part of the hello
+    pub censor: Vec<String>,
+
+    /// Truncate to this many characters
+    pub max_len: Option<usize>,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self {
+            strip_control: true,
+            strip_markup: true,
+            censor: Vec::new(),
+            max_len: None,
+        }
+    }
+}
+
+impl Sanitizer {
+    pub fn sanitize(&self, value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        let mut chars = value.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if self.strip_control && is_invisible(c) {
+                continue;
+            }
+            if self.strip_markup && c == '<' {
+                let rest = &value[i + 1..];
+                if let Some(end) = markup_tag_end(rest) {
+                    for _ in rest[..=end].chars() {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            result.push(c);
+        }
+        if !self.censor.is_empty() {
+            result = self.censor_words(&result);
+        }
+        if let Some(max_len) = self.max_len {
+            if let Some((end, _)) = result.char_indices().nth(max_len) {
+                result.truncate(end);
+            }
+        }
+        result
+    }
+
+    /// Replaces every word in `censor` with as many asterisks as it has characters
+    fn censor_words(&self, value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find(char::is_alphanumeric) {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let lowercase = word.to_lowercase();
+            if self
+                .censor
+                .iter()
+                .any(|censored| censored.to_lowercase() == lowercase)
+            {
+                result.extend(word.chars().map(|_| '*'));
+            } else {
+                result.push_str(word);
+            }
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// The rich text tags the game renders
+const MARKUP_TAGS: &[&str] = &[
+    "align",
+    "allcaps",
+    "alpha",
+    "b",
+    "br",
+    "color",
+    "cspace",
+    "font",
+    "gradient",
+    "i",
+    "indent",
+    "line-height",
+    "line-indent",
+    "link",
+    "lowercase",
+    "margin",
+    "mark",
+    "material",
+    "mspace",
+    "nobr",
+    "noparse",
+    "page",
+    "pos",
+    "rotate",
+    "s",
+    "size",
+    "smallcaps",
+    "space",
+    "sprite",
+    "strikethrough",
+    "style",
+    "sub",
+    "sup",
+    "u",
+    "uppercase",
+    "voffset",
+    "width",
+];
+
+/// Tags that don't wrap anything
+const VOID_MARKUP_TAGS: &[&str] = &["br", "page"];
+
+/// Where the `>` is if `rest`, the text after a `<`, starts with a tag the game would render
+fn markup_tag_end(rest: &str) -> Option<usize> {
+    let end = rest.find('>')?;
+    let inner = &rest[..end];
+    let (closing, body) = match inner.strip_prefix('/') {
+        Some(body) => (true, body),
+        None => (false, inner),
+    };
+    let name = body
+        .split(['=', ' '])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if !MARKUP_TAGS.contains(&name.as_str()) {
+        return None;
+    }
+    let rendered = closing
+        || body.contains('=')
+        || VOID_MARKUP_TAGS.contains(&name.as_str())
+        || rest[end..]
+            .to_ascii_lowercase()
+            .contains(&format!("</{}>", name));
+    if rendered {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// Control characters plus the zero-width and bidi formatting characters
+fn is_invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(c, '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' | '\u{FEFF}')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sanitize() {
+        let sanitizer = Sanitizer::default();
+        assert_eq!(sanitizer.sanitize("normal name"), "normal name");
+        assert_eq!(sanitizer.sanitize("a\nb\u{0}c\u{202E}d"), "abcd");
+        assert_eq!(
+            sanitizer.sanitize("<color=#ff0000><size=1000%>big</size></color>"),
+            "big"
+        );
+        assert_eq!(sanitizer.sanitize("a <b"), "a <b");
+        assert_eq!(sanitizer.sanitize("<3 you"), "<3 you");
+        assert_eq!(sanitizer.sanitize("a<b>c"), "a<b>c");
+        assert_eq!(sanitizer.sanitize("<B>bold</b> <i>"), "bold <i>");
+        assert_eq!(sanitizer.sanitize("x<br>y<sprite=3>"), "xy");
+        assert_eq!(sanitizer.sanitize("<nope=1>"), "<nope=1>");
+
+        let sanitizer = Sanitizer {
+            strip_control: false,
+            strip_markup: false,
+            censor: Vec::new(),
+            max_len: Some(3),
+        };
+        assert_eq!(sanitizer.sanitize("<b>\u{e9}\u{e9}"), "<b>");
+
+        let sanitizer = Sanitizer {
+            censor: vec!["heck".to_string()],
+            ..Sanitizer::default()
+        };
+        assert_eq!(
+            sanitizer.sanitize("Heck, what the HECK"),
+            "****, what the ****"
+        );
+        assert_eq!(sanitizer.sanitize("<b>heck</b>"), "****");
+        assert_eq!(sanitizer.sanitize("checked heckle"), "checked heckle");
+    }
+
+    #[test]
+    fn test_region_file() {
+        let regions = RegionFile {
+            current_region: 1,
+            regions: vec![
+                RegionInfo::Static {
+                    name: "Local".to_string(),
+                    ping_server: "127.0.0.1".to_string(),
+                    servers: vec![ServerInfo {
+                        name: "Local-1".to_string(),
+                        ip: "127.0.0.1".to_string(),
+                        port: 22023,
+                        use_dtls: false,
+                        players: 0,
+                        connection_failures: 2,
+                    }],
+                    translate_name: None,
+                },
+                RegionInfo::Static {
+                    name: "Other".to_string(),
+                    ping_server: "10.0.0.1".to_string(),
+                    servers: Vec::new(),
+                    translate_name: None,
+                },
+            ],
+        };
+        let path = std::env::temp_dir().join("among-us-test-regionInfo.dat");
+        write_region_file(&path, &regions).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let read = read_region_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(data.len(), 4 + 4 + (6 + 10 + 4 + (8 + 6 + 4)) + (6 + 9 + 4));
+        assert_eq!(read, regions);
+        assert_eq!(read.current().unwrap().name(), "Other");
+        assert_eq!(
+            regions.regions[0].addresses().unwrap(),
+            vec![SocketAddr::from(([127, 0, 0, 1], 22023))]
+        );
+
+        let dns = RegionFile {
+            current_region: 0,
+            regions: vec![RegionInfo::Dns {
+                name: "North America".to_string(),
+                fqdn: "na.mm.among.us".to_string(),
+                default_ip: "50.116.1.42".to_string(),
+                port: 22023,
+                translate_name: Some(289),
+            }],
+        };
+        assert_eq!(
+            write_region_file(&path, &dns).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_region_file_json() {
+        let json = r#"{"CurrentRegionIdx":0,"Regions":[
+            {"$type":"DnsRegionInfo, Assembly-CSharp","Fqdn":"na.mm.among.us",
+                "DefaultIp":"50.116.1.42","Port":22023,"Name":"North America","TranslateName":289},
+            {"$type":"StaticRegionInfo, Assembly-CSharp","Name":"Custom","PingServer":"127.0.0.1",
+                "Servers":[{"Name":"Custom-Master-1","Ip":"127.0.0.1","Port":22023,"UseDtls":false,
+                "Players":0,"ConnectionFailures":0}],"TranslateName":1003}
+        ]}"#;
+        let regions: RegionFile = serde_json::from_str(json).unwrap();
+        assert_eq!(regions.regions.len(), 2);
+        assert_eq!(
+            regions.current(),
+            Some(&RegionInfo::Dns {
+                name: "North America".to_string(),
+                fqdn: "na.mm.among.us".to_string(),
+                default_ip: "50.116.1.42".to_string(),
+                port: 22023,
+                translate_name: Some(289),
+            })
+        );
+        assert_eq!(regions.regions[1].name(), "Custom");
+
+        let path = std::env::temp_dir().join("among-us-test-regionInfo.json");
+        write_region_file(&path, &regions).unwrap();
+        let read = read_region_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, regions);
+    }
+
     #[test]
     fn test_read_purchase_file() {
         let mut data = read_purchase_file("../dumps/secureNew").unwrap();
@@ -1,6 +1,6 @@
-use std::cell::UnsafeCell;
+use std::{cell::UnsafeCell, ffi::CString};
 
-use byte_strings::concat_bytes;
+use common::data::{GameVersion, OpcodeKind, OPCODES};
 use common::networking::DEFAULT_PORT;
 use epan_sys::{
     _header_field_info, _value_string, col_add_str, col_clear, col_set_str,
@@ -8,7 +8,8 @@ use epan_sys::{
     ftenum_FT_UINT16, ftenum_FT_UINT8, hf_ref_type_HF_REF_TYPE_NONE, hf_register_info,
     proto_item_add_subtree, proto_plugin, proto_register_field_array, proto_register_plugin,
     proto_register_protocol, proto_register_subtree_array, proto_tree_add_item,
-    tvb_captured_length, tvb_get_guint8, COL_INFO, COL_PROTOCOL, ENC_BIG_ENDIAN, ENC_NA,
+    tvb_captured_length, tvb_get_guint8, tvb_get_letohl, COL_INFO, COL_PROTOCOL, ENC_BIG_ENDIAN,
+    ENC_NA,
 };
 
 // Useful wireshark macros
@@ -47,22 +48,22 @@ static mut ETT_AMONGUS: UnsafeCell<i32> = UnsafeCell::new(-1);
 struct HfRegisterInfo(hf_register_info);
 unsafe impl Sync for HfRegisterInfo {}
 
-macro_rules! value_string {
-    ($name:expr, $val:expr) => {{
-        _value_string {
-            strptr: concat_bytes!($name, b"\0").as_ptr() as *const i8,
-            value: $val,
-        }
-    }};
+/// A wireshark value string of every id of a kind in `OPCODES`. Leaked as wireshark keeps it
+fn value_strings(kind: OpcodeKind) -> &'static [_value_string] {
+    let values = OPCODES
+        .iter()
+        .filter(|opcode| opcode.kind == kind)
+        .map(|opcode| _value_string {
+            strptr: CString::new(opcode.display).unwrap().into_raw(),
+            value: opcode.id as u32,
+        })
+        .chain(std::iter::once(_value_string {
+            strptr: std::ptr::null(),
+            value: 0,
+        }))
+        .collect::<Vec<_>>();
+    Box::leak(values.into_boxed_slice())
 }
-const HAZEL_HEADER_NAMES: &[_value_string] = &[
-    value_string!(b"Hello", 0x08),
-    value_string!(b"Disconnect", 0x09),
-    value_string!(b"Acknowledge", 0x0a),
-    value_string!(b"Keep-Alive", 0x0c),
-    value_string!(b"Unreliable", 0x00),
-    value_string!(b"Reliable", 0x01),
-];
 
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -75,7 +76,8 @@ pub unsafe extern "C" fn proto_register_among_us() {
                 abbrev: b"amongus.hazel\0".as_ptr() as *const i8,
                 type_: ftenum_FT_UINT8,
                 display: field_display_e_BASE_HEX as i32,
-                strings: HAZEL_HEADER_NAMES.as_ptr() as *const std::ffi::c_void,
+                // Set below
+                strings: std::ptr::null(),
                 bitmask: 0,
                 blurb: std::ptr::null(),
                 id: -1,
@@ -103,6 +105,7 @@ pub unsafe extern "C" fn proto_register_among_us() {
             },
         }),
     ];
+    INFO[0].0.hfinfo.strings = value_strings(OpcodeKind::Hazel).as_ptr() as *const std::ffi::c_void;
     static mut ETT: [*mut i32; 1] = [unsafe { ETT_AMONGUS.get() }];
     PROTO_AMONG_US = proto_register_protocol(
         b"Among Us Protocol\0".as_ptr() as *const i8,
@@ -179,6 +182,13 @@ pub unsafe extern "C" fn dissect_among_us(
         .as_ptr() as *const i8,
     );
 
+    // Hello, with the version after the ack id and a reserved byte
+    if header_type == 8 && tvb_captured_length(tvbuff) >= 8 {
+        let version = GameVersion::from_u32(tvb_get_letohl(tvbuff, 4));
+        let info = CString::new(format!("C -> S Hello {}", version)).unwrap();
+        col_add_str(packet_info.cinfo, COL_INFO as i32, info.as_ptr());
+    }
+
     // Return captured length
     tvb_captured_length(tvbuff) as i32
 }
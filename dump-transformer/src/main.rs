@@ -1,6 +1,6 @@
-use std::io::{BufWriter, Write};
+use std::{io::BufWriter, time::Duration};
 
-use common::reader::PacketWriter;
+use common::capture::{CaptureFormat, Direction};
 fn main() {
     // Check args
     let args: Vec<String> = std::env::args().collect();
@@ -14,47 +14,39 @@ fn main() {
 
     // Parse input file
     // Output from
-    // `tshark -r AmongUsDump2.pcapng -Y 'udp.port == 22023' -Tfields -e 'udp.srcport' -e 'data.data' > dump.txt`
+    // `tshark -r AmongUsDump2.pcapng -Y 'udp.port == 22023' -Tfields -e 'udp.srcport' -e 'frame.time_relative' -e 'data.data' > dump.txt`
+    // Dumps without the time column still work, with every packet at the start
     let mut packets = Vec::new();
     for line in input.lines() {
         if line.is_empty() {
             continue;
         }
-        let mut split = line.split_ascii_whitespace();
-        let port_str = split.next().unwrap();
-        let data_str = split.next().unwrap();
+        let split = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let (port_str, time_str, data_str) = match split[..] {
+            [port_str, data_str] => (port_str, "0", data_str),
+            [port_str, time_str, data_str] => (port_str, time_str, data_str),
+            _ => panic!("Expected a port, optional time and data on each line"),
+        };
         let port = u16::from_str_radix(port_str, 10).unwrap();
         let to_server = port != 22023;
+        let offset = Duration::from_secs_f64(time_str.parse().unwrap());
         let data = decode_hex(data_str);
-        packets.push((to_server, "", data));
+        packets.push((to_server, offset, data));
     }
 
     // Write to output file
     let out_file = std::fs::File::create(args.get(2).unwrap()).unwrap();
-    let mut file_w = BufWriter::new(out_file);
+    let mut capture = CaptureFormat::writer(BufWriter::new(out_file)).unwrap();
     println!("{}", packets.len());
-    for (to_server, _name, bytes) in packets.into_iter() {
-        let mut w = PacketWriter::new();
-
-        // Start header
-        w.write_bool(to_server);
-
-        // Get padded data
-        let bytes_len = bytes.len();
-        let mut pad_w = PacketWriter::new();
-        pad_w.write_bytes_raw(&bytes);
-        pad_w.write_bytes_raw(&vec![0; 16 - (bytes.len() % 16)]);
-        let padded_len = pad_w.len();
-
-        // Finish header
-        w.write_u32(bytes_len as u32);
-        w.write_u32(padded_len as u32);
-        w.write_bytes_raw(&[0; 7]);
-
-        // Write Data
-        w.write_bytes_raw(&pad_w.finish());
-        file_w.write_all(&w.finish()).unwrap();
+    for (to_server, offset, bytes) in packets.into_iter() {
+        let direction = if to_server {
+            Direction::ToServer
+        } else {
+            Direction::ToClient
+        };
+        capture.write_packet(direction, offset, &bytes).unwrap();
     }
+    capture.flush().unwrap();
 
     println!("Done");
 }
@@ -0,0 +1,200 @@
+use std::{
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Rotate once the log gets this big
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many rotated logs to keep as `<path>.1`, `<path>.2` etc.
+pub const DEFAULT_KEEP: usize = 5;
+
+/// Per-connection events in logfmt, one per line, e.g.
+///
+/// `time=1602720000 event=connect addr=1.2.3.4:5678 client_id=3 name="bob smith" version=50516550`
+pub struct AccessLog {
+    path: PathBuf,
+    file: File,
+    /// Size of the current file
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl AccessLog {
+    /// Opens `path` for appending, creating it if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_bytes,
+            keep,
+        })
+    }
+
+    pub fn record(&mut self, event: &str, fields: &[(&str, &dyn Display)]) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let mut line = format!("time={} event={}", time, event);
+        for (key, value) in fields {
+            line.push(' ');
+            line.push_str(key);
+            line.push('=');
+            line.push_str(&quote(&value.to_string()));
+        }
+        line.push('\n');
+
+        if self.written + line.len() as u64 > self.max_bytes && self.written > 0 {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `<path>.n` to `<path>.n+1`, dropping the oldest, and starts a fresh file
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+/// Quotes values that would otherwise break up the line
+fn quote(value: &str) -> String {
+    if value.is_empty()
+        || value.contains(|c: char| c.is_whitespace() || c.is_control() || c == '"' || c == '=')
+    {
+        format!("{:?}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for a test's logs
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("among-us-test-access-log-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_record() {
+        let path = log_dir("record").join("access.log");
+        let mut log = AccessLog::open(&path, DEFAULT_MAX_BYTES, DEFAULT_KEEP).unwrap();
+        log.record(
+            "connect",
+            &[("addr", &"1.2.3.4:5678"), ("name", &"bob smith")],
+        )
+        .unwrap();
+        log.record("disconnect", &[("reason", &"")]).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines = contents
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                r#"event=connect addr=1.2.3.4:5678 name="bob smith""#,
+                r#"event=disconnect reason="""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rotate_at_max_bytes() {
+        let path = log_dir("max-bytes").join("access.log");
+        // Sparse, so this doesn't actually write 10 MiB
+        let almost_full = DEFAULT_MAX_BYTES - 10;
+        File::create(&path).unwrap().set_len(almost_full).unwrap();
+
+        let mut log = AccessLog::open(&path, DEFAULT_MAX_BYTES, DEFAULT_KEEP).unwrap();
+        log.record("connect", &[]).unwrap();
+        assert_eq!(
+            fs::metadata(log.rotated_path(1)).unwrap().len(),
+            almost_full
+        );
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .ends_with(" event=connect\n"));
+    }
+
+    #[test]
+    fn test_rotate_keep() {
+        let path = log_dir("keep").join("access.log");
+        // Every line after the first rotates
+        let mut log = AccessLog::open(&path, 1, DEFAULT_KEEP).unwrap();
+        let count = DEFAULT_KEEP + 3;
+        for index in 0..count {
+            log.record("connect", &[("index", &index)]).unwrap();
+        }
+
+        let index_in = |path: &Path| {
+            let contents = fs::read_to_string(path).unwrap();
+            contents
+                .trim_end()
+                .rsplit("index=")
+                .next()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(index_in(&path), (count - 1).to_string());
+        for rotated in 1..=DEFAULT_KEEP {
+            assert_eq!(
+                index_in(&log.rotated_path(rotated)),
+                (count - 1 - rotated).to_string()
+            );
+        }
+        // The oldest are dropped
+        assert!(!log.rotated_path(DEFAULT_KEEP + 1).exists());
+    }
+
+    #[test]
+    fn test_rotate_keep_none() {
+        let path = log_dir("keep-none").join("access.log");
+        let mut log = AccessLog::open(&path, 1, 0).unwrap();
+        log.record("connect", &[]).unwrap();
+        log.record("disconnect", &[]).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.ends_with(" event=disconnect\n"));
+        assert!(!log.rotated_path(1).exists());
+    }
+}
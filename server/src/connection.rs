@@ -0,0 +1,222 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use common::{
+    data::{fragment, ChatMode, GameId, HazelPacketOut, Reassembler},
+    reader::{Data, Serialize},
+};
+
+/// How long to wait for an acknowledgement before sending a reliable packet again
+const RESEND_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Clients send keep alives every second or so, so anything this quiet has gone
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reliable packets bigger than this are split into fragments
+const MAX_PACKET_SIZE: usize = 65_507;
+
+/// How long to wait for the rest of a fragmented payload before giving up on it
+pub const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many of a client's latest reliable ack ids are remembered to spot resends. Clients resend
+/// for a few seconds at most, far fewer packets than this
+const RECEIVED_WINDOW: usize = 1024;
+
+/// How many times a reliable packet is sent again before the client is given up on, as the
+/// client does for the server
+const MAX_RESENDS: u32 = 10;
+
+/// Most reliable packets kept waiting for an acknowledgement, half the ack id space, as the client
+/// does for the server
+const MAX_UNCONFIRMED: usize = 1 << 15;
+
+/// A reliable packet waiting to be acknowledged
+#[derive(Debug)]
+struct Unconfirmed {
+    last_sent: Instant,
+    resends: u32,
+    packet: Vec<u8>,
+}
+
+/// The Hazel state of a single remote client
+#[derive(Debug)]
+pub struct Connection {
+    pub addr: SocketAddr,
+    pub client_id: i32,
+    /// The username sent in the hello. `None` until the hello arrives
+    pub name: Option<String>,
+    pub version: Option<u32>,
+    /// From the hello, for versions that send it
+    pub chat_mode: Option<ChatMode>,
+    /// The game this client has joined, if any
+    pub game_id: Option<GameId>,
+    pub connected_at: Instant,
+    /// Total size of every datagram received from the client
+    pub bytes_in: u64,
+    /// Total size of every datagram sent to the client, including resends
+    pub bytes_out: u64,
+    last_received: Instant,
+    ack_index: u16,
+    /// Reliable packets not acknowledged yet
+    unconfirmed: HashMap<u16, Unconfirmed>,
+    fragment_index: u16,
+    pub reassembler: Reassembler,
+    /// The latest reliable ack ids received, oldest first
+    received: VecDeque<u16>,
+}
+
+impl Connection {
+    pub fn new(addr: SocketAddr, client_id: i32) -> Self {
+        Self {
+            addr,
+            client_id,
+            name: None,
+            version: None,
+            chat_mode: None,
+            game_id: None,
+            connected_at: Instant::now(),
+            bytes_in: 0,
+            bytes_out: 0,
+            last_received: Instant::now(),
+            ack_index: 1,
+            unconfirmed: HashMap::new(),
+            fragment_index: 0,
+            reassembler: Reassembler::new(),
+            received: VecDeque::with_capacity(RECEIVED_WINDOW),
+        }
+    }
+
+    /// Call whenever anything arrives from the client
+    pub fn received(&mut self, bytes: usize) {
+        self.last_received = Instant::now();
+        self.bytes_in += bytes as u64;
+    }
+
+    /// Remembers a reliable ack id, returning `false` if it was already received
+    pub fn first_receipt(&mut self, ack_id: u16) -> bool {
+        if self.received.contains(&ack_id) {
+            return false;
+        }
+        if self.received.len() == RECEIVED_WINDOW {
+            self.received.pop_front();
+        }
+        self.received.push_back(ack_id);
+        true
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.last_received.elapsed() >= CONNECTION_TIMEOUT
+    }
+
+    /// Whether the client has stopped acknowledging, with a packet sent `MAX_RESENDS` times and
+    /// still due or more than `MAX_UNCONFIRMED` waiting
+    pub fn unresponsive(&self) -> bool {
+        self.unconfirmed.len() > MAX_UNCONFIRMED
+            || self.unconfirmed.values().any(|unconfirmed| {
+                unconfirmed.resends >= MAX_RESENDS
+                    && unconfirmed.last_sent.elapsed() >= RESEND_INTERVAL
+            })
+    }
+
+    /// Wraps `data` in a reliable packet, or fragments if it's too big for one, returning the
+    /// packets to send
+    ///
+    /// Packets are kept to be resent until acknowledged
+    pub fn reliable(&mut self, data: Vec<u8>) -> Vec<Vec<u8>> {
+        // Type and ack id
+        if data.len() + 3 <= MAX_PACKET_SIZE {
+            let ack_id = self.next_ack_id();
+            let packet = HazelPacketOut::Reliable {
+                ack_id,
+                data: Box::new(Data::Bytes(data)),
+            };
+            return vec![self.track(ack_id, packet)];
+        }
+
+        let fragment_id = self.fragment_index;
+        self.fragment_index = self.fragment_index.wrapping_add(1);
+        let pieces = fragment(&data, MAX_PACKET_SIZE);
+        let count = pieces.len() as u16;
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, piece)| {
+                let ack_id = self.next_ack_id();
+                let packet = HazelPacketOut::Fragment {
+                    ack_id,
+                    fragment_id,
+                    index: index as u16,
+                    count,
+                    data: piece.to_vec(),
+                };
+                self.track(ack_id, packet)
+            })
+            .collect()
+    }
+
+    fn next_ack_id(&mut self) -> u16 {
+        let ack_id = self.ack_index;
+        self.ack_index = self.ack_index.wrapping_add(1);
+        ack_id
+    }
+
+    /// Keeps a packet to resend until it's acknowledged, returning its bytes
+    fn track(&mut self, ack_id: u16, packet: HazelPacketOut) -> Vec<u8> {
+        let packet = packet.serialize_bytes();
+        let unconfirmed = Unconfirmed {
+            last_sent: Instant::now(),
+            resends: 0,
+            packet: packet.clone(),
+        };
+        self.unconfirmed.insert(ack_id, unconfirmed);
+        packet
+    }
+
+    pub fn acknowledged(&mut self, ack_id: u16) {
+        self.unconfirmed.remove(&ack_id);
+    }
+
+    /// Packets that have gone unacknowledged for too long. Each is counted as resent, up to
+    /// `MAX_RESENDS` times
+    pub fn resends(&mut self) -> Vec<Vec<u8>> {
+        self.unconfirmed
+            .values_mut()
+            .filter(|unconfirmed| {
+                unconfirmed.resends < MAX_RESENDS
+                    && unconfirmed.last_sent.elapsed() >= RESEND_INTERVAL
+            })
+            .map(|unconfirmed| {
+                unconfirmed.last_sent = Instant::now();
+                unconfirmed.resends += 1;
+                unconfirmed.packet.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_receipt_wraparound() {
+        let mut connection = Connection::new("127.0.0.1:22023".parse().unwrap(), 1);
+        let start = u16::MAX - 100;
+        let ack_ids = (0..RECEIVED_WINDOW as u16 + 50)
+            .map(|offset| start.wrapping_add(offset))
+            .collect::<Vec<_>>();
+        for &ack_id in &ack_ids {
+            assert!(connection.first_receipt(ack_id));
+        }
+
+        // Either side of the wrap are still remembered
+        assert!(!connection.first_receipt(u16::MAX));
+        assert!(!connection.first_receipt(0));
+        assert!(!connection.first_receipt(*ack_ids.last().unwrap()));
+        // The oldest have been forgotten, so count as new
+        assert!(connection.first_receipt(start));
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Instant;
+
+use common::data::{Address, GameId, GameListing, GameOptions, Maps};
+use rand::Rng;
+
+/// A lobby or game in progress hosted on this server
+#[derive(Debug)]
+pub struct Game {
+    pub id: GameId,
+    /// Client id of the host. The first player to join, then whoever's left after they leave
+    pub host_id: i32,
+    /// Client ids in join order
+    pub players: Vec<i32>,
+    pub options: GameOptions,
+    pub is_public: bool,
+    pub started: bool,
+    pub created: Instant,
+}
+
+impl Game {
+    pub fn new(id: GameId, options: GameOptions) -> Self {
+        Self {
+            id,
+            host_id: -1,
+            players: Vec::new(),
+            options,
+            is_public: false,
+            started: false,
+            created: Instant::now(),
+        }
+    }
+
+    /// A random V2 game code
+    pub fn random_id<R: Rng>(rng: &mut R) -> GameId {
+        let chars = (0..6)
+            .map(|_| rng.gen_range(b'A', b'Z' + 1) as char)
+            .collect::<String>();
+        GameId::from_chars(&chars)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= self.options.max_players as usize
+    }
+
+    /// Whether this game should show up for a `RequestGameListPacket` with the given filter
+    ///
+    /// Only public lobbies with space are listed, like the official servers
+    pub fn matches(&self, filter: &GameOptions) -> bool {
+        if !self.is_public || self.started || self.is_full() {
+            return false;
+        }
+        let map_bit = 1u8.checked_shl(self.options.map_id as u32).unwrap_or(0);
+        if filter.map_id & map_bit == 0 {
+            return false;
+        }
+        // No language bits means any language
+        if !filter.language.is_empty() && !filter.language.intersects(self.options.language) {
+            return false;
+        }
+        filter.num_imposters == 0 || filter.num_imposters == self.options.num_imposters
+    }
+
+    /// The map being played, counting unknown ids as the Skeld
+    pub fn map(&self) -> Maps {
+        Maps::from_map_id(self.options.map_id).unwrap_or(Maps::SKELD)
+    }
+
+    /// The listing sent to clients browsing games. `address` is where clients should connect
+    pub fn listing(&self, address: &Address, host_username: &str) -> GameListing {
+        GameListing {
+            address: address.clone(),
+            id: self.id,
+            host_username: host_username.to_string(),
+            player_count: self.players.len() as u8,
+            age: self.created.elapsed().as_secs() as u32,
+            map_id: self.map(),
+            num_imposters: self.options.num_imposters.max(0) as u8,
+            max_players: self.options.max_players,
+        }
+    }
+}
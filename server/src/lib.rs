@@ -0,0 +1,15 @@
+pub use crate::access_log::{AccessLog, DEFAULT_KEEP, DEFAULT_MAX_BYTES};
+pub use crate::nat::{external_ip, map_port, PortMapping, MAPPING_LIFETIME};
+pub use crate::server::{Server, ServerSettings, SUPPORTED_VERSIONS};
+pub use crate::storage::{
+    MemoryStorage, ServerStats, SledStorage, Storage, StoredBan, StoredLobby,
+};
+pub use crate::throttle::{Action, ThrottleSettings};
+
+mod access_log;
+mod connection;
+mod game;
+mod nat;
+mod server;
+mod storage;
+mod throttle;
@@ -0,0 +1,164 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    thread,
+};
+
+use common::data::Address;
+
+use log::warn;
+use server::{
+    AccessLog, Server, ServerSettings, SledStorage, Storage, ThrottleSettings, MAPPING_LIFETIME,
+    SUPPORTED_VERSIONS,
+};
+
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:22023";
+
+fn main() {
+    // Init logging
+    flexi_logger::Logger::with_env_or_str("info")
+        .start()
+        .unwrap();
+
+    let usage = "Usage: ./server [bind_address:port] [public_address:port] [--access-log <path>] [--storage <path>] [--map-port]";
+    let mut args = Vec::new();
+    let mut access_log_path = None;
+    let mut storage_path = None;
+    let mut map_port = false;
+    let mut all_args = std::env::args().skip(1);
+    while let Some(arg) = all_args.next() {
+        match arg.as_str() {
+            "--access-log" => match all_args.next() {
+                Some(path) => access_log_path = Some(path),
+                None => {
+                    println!("{}", usage);
+                    return;
+                }
+            },
+            "--storage" => match all_args.next() {
+                Some(path) => storage_path = Some(path),
+                None => {
+                    println!("{}", usage);
+                    return;
+                }
+            },
+            "--map-port" => map_port = true,
+            _ => args.push(arg),
+        }
+    }
+
+    let bind_address = match args
+        .first()
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_BIND_ADDRESS)
+        .parse::<SocketAddr>()
+    {
+        Ok(value) => value,
+        Err(_) => {
+            println!("{}", usage);
+            return;
+        }
+    };
+
+    // Forward the port on the router so people outside the network can join
+    let mapping = if map_port {
+        match server::map_port(bind_address.port()) {
+            Ok(mapping) => {
+                println!("Forwarded port {}", mapping.external_port);
+                Some(mapping)
+            }
+            Err(error) => {
+                println!("Couldn't forward port: {}", error);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(mapping) = mapping.clone() {
+        thread::Builder::new()
+            .name("port-mapping".to_string())
+            .spawn(move || loop {
+                thread::sleep(MAPPING_LIFETIME / 2);
+                if let Err(error) = mapping.renew() {
+                    warn!("Couldn't renew port mapping: {}", error);
+                }
+            })
+            .unwrap();
+    }
+
+    // Listings need an address clients can reach, which a wildcard bind isn't
+    let external_address = mapping
+        .as_ref()
+        .and_then(|mapping| mapping.external_address());
+    let public_address = match args.get(1).map(|arg| arg.parse::<SocketAddr>()) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => {
+            println!("{}", usage);
+            return;
+        }
+        None if external_address.is_some() => SocketAddr::V4(external_address.unwrap()),
+        None if bind_address.ip().is_unspecified() => {
+            SocketAddr::from(([127, 0, 0, 1], bind_address.port()))
+        }
+        None => bind_address,
+    };
+    let public_address = match public_address.ip() {
+        IpAddr::V4(ip) => Address {
+            ip: ip.octets(),
+            port: public_address.port(),
+        },
+        IpAddr::V6(_) => {
+            println!("Game listings only support IPv4 addresses");
+            return;
+        }
+    };
+
+    let access_log = match access_log_path {
+        Some(path) => {
+            match AccessLog::open(&path, server::DEFAULT_MAX_BYTES, server::DEFAULT_KEEP) {
+                Ok(access_log) => Some(access_log),
+                Err(error) => {
+                    println!("Couldn't open access log {}: {}", path, error);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Without a path nothing survives a restart
+    let storage = match storage_path {
+        Some(path) => match SledStorage::open(&path) {
+            Ok(storage) => Some(Box::new(storage) as Box<dyn Storage>),
+            Err(error) => {
+                println!("Couldn't open storage {}: {}", path, error);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let settings = ServerSettings {
+        bind_address,
+        public_address,
+        access_log,
+        throttle: ThrottleSettings::default(),
+        storage,
+        versions: SUPPORTED_VERSIONS,
+    };
+    let mut server = match Server::bind(settings) {
+        Ok(server) => server,
+        Err(error) => {
+            println!("Couldn't bind to {}: {}", bind_address, error);
+            return;
+        }
+    };
+    if let Err(error) = server.run() {
+        println!("Server stopped: {}", error);
+    }
+    if let Some(mapping) = mapping {
+        if let Err(error) = mapping.remove() {
+            println!("Couldn't remove port mapping: {}", error);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    fs,
+    io::{self, ErrorKind, Read, Write},
+    net::{Ipv4Addr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// How long NAT-PMP mappings last before they have to be renewed. UPnP ones last until removed
+pub const MAPPING_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How long to wait for the router to answer
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+const NAT_PMP_PORT: u16 = 5351;
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+/// The UPnP services that can forward ports, depending on how the router connects
+const WAN_SERVICES: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// A UDP port forwarded by the router, so people outside the network can reach the server. See
+/// `map_port`
+#[derive(Debug, Clone)]
+pub struct PortMapping {
+    /// The router's public address, if it said
+    pub external_ip: Option<Ipv4Addr>,
+    pub external_port: u16,
+    internal_port: u16,
+    method: Method,
+}
+
+#[derive(Debug, Clone)]
+enum Method {
+    NatPmp {
+        gateway: Ipv4Addr,
+    },
+    Upnp {
+        control_url: String,
+        service: &'static str,
+    },
+}
+
+impl PortMapping {
+    /// Where people outside the network should connect, if the router gave its address
+    pub fn external_address(&self) -> Option<SocketAddrV4> {
+        self.external_ip
+            .map(|ip| SocketAddrV4::new(ip, self.external_port))
+    }
+
+    /// Asks for the same mapping again. NAT-PMP mappings need this before `MAPPING_LIFETIME` runs
+    /// out
+    pub fn renew(&self) -> io::Result<()> {
+        match &self.method {
+            Method::NatPmp { gateway } => {
+                nat_pmp_map(
+                    *gateway,
+                    self.internal_port,
+                    self.external_port,
+                    MAPPING_LIFETIME,
+                )?;
+            }
+            Method::Upnp {
+                control_url,
+                service,
+            } => upnp_map(control_url, service, self.internal_port, self.external_port)?,
+        }
+        Ok(())
+    }
+
+    /// Takes the mapping off the router
+    pub fn remove(&self) -> io::Result<()> {
+        match &self.method {
+            Method::NatPmp { gateway } => {
+                nat_pmp_map(*gateway, self.internal_port, 0, Duration::from_secs(0))?;
+            }
+            Method::Upnp {
+                control_url,
+                service,
+            } => {
+                let args = format!(
+                    "<NewRemoteHost></NewRemoteHost>\
+                     <NewExternalPort>{}</NewExternalPort>\
+                     <NewProtocol>UDP</NewProtocol>",
+                    self.external_port
+                );
+                soap(control_url, service, "DeletePortMapping", &args)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Forwards UDP `port` on the router to this machine, trying NAT-PMP and then UPnP
+pub fn map_port(port: u16) -> io::Result<PortMapping> {
+    let nat_pmp_error = match default_gateway() {
+        Ok(gateway) => match nat_pmp_map(gateway, port, port, MAPPING_LIFETIME) {
+            Ok(external_port) => {
+                return Ok(PortMapping {
+                    external_ip: nat_pmp_external_ip(gateway).ok(),
+                    external_port,
+                    internal_port: port,
+                    method: Method::NatPmp { gateway },
+                });
+            }
+            Err(error) => error,
+        },
+        Err(error) => error,
+    };
+    let (control_url, service) = upnp_discover().map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("NAT-PMP: {}, UPnP: {}", nat_pmp_error, error),
+        )
+    })?;
+    upnp_map(&control_url, service, port, port)?;
+    Ok(PortMapping {
+        external_ip: upnp_external_ip(&control_url, service).ok(),
+        external_port: port,
+        internal_port: port,
+        method: Method::Upnp {
+            control_url,
+            service,
+        },
+    })
+}
+
+/// The router's public address, asking with NAT-PMP and then UPnP
+pub fn external_ip() -> io::Result<Ipv4Addr> {
+    if let Ok(ip) = default_gateway().and_then(nat_pmp_external_ip) {
+        return Ok(ip);
+    }
+    let (control_url, service) = upnp_discover()?;
+    upnp_external_ip(&control_url, service)
+}
+
+/// The default route's gateway from `/proc/net/route`, so only works on little-endian Linux
+fn default_gateway() -> io::Result<Ipv4Addr> {
+    let routes = fs::read_to_string("/proc/net/route")?;
+    for line in routes.lines().skip(1) {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if let [_, "00000000", gateway, ..] = fields[..] {
+            if let Ok(gateway) = u32::from_str_radix(gateway, 16) {
+                let [a, b, c, d] = gateway.to_le_bytes();
+                return Ok(Ipv4Addr::new(a, b, c, d));
+            }
+        }
+    }
+    Err(io::Error::new(ErrorKind::NotFound, "No default gateway"))
+}
+
+/// Sends a NAT-PMP request, resending with backoff until the gateway answers
+fn nat_pmp_request(gateway: Ipv4Addr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect((gateway, NAT_PMP_PORT))?;
+    let mut wait = Duration::from_millis(250);
+    let mut buffer = [0; 16];
+    for _ in 0..4 {
+        socket.send(request)?;
+        socket.set_read_timeout(Some(wait))?;
+        match socket.recv(&mut buffer) {
+            Ok(size) if size >= 8 && buffer[1] == request[1] + 128 => {
+                let result = u16::from_be_bytes([buffer[2], buffer[3]]);
+                if result != 0 {
+                    return Err(io::Error::other(format!("NAT-PMP result code {}", result)));
+                }
+                return Ok(buffer[..size].to_vec());
+            }
+            Ok(_) => (),
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+            Err(error) => return Err(error),
+        }
+        wait *= 2;
+    }
+    Err(io::Error::new(
+        ErrorKind::TimedOut,
+        "No answer from NAT-PMP",
+    ))
+}
+
+fn nat_pmp_external_ip(gateway: Ipv4Addr) -> io::Result<Ipv4Addr> {
+    let response = nat_pmp_request(gateway, &[0, 0])?;
+    match response[..] {
+        [_, _, _, _, _, _, _, _, a, b, c, d] => Ok(Ipv4Addr::new(a, b, c, d)),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Bad NAT-PMP response",
+        )),
+    }
+}
+
+/// Maps a UDP port, returning the external port the gateway picked. A lifetime of zero removes it
+fn nat_pmp_map(
+    gateway: Ipv4Addr,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: Duration,
+) -> io::Result<u16> {
+    let mut request = vec![0, 1, 0, 0];
+    request.extend_from_slice(&internal_port.to_be_bytes());
+    request.extend_from_slice(&external_port.to_be_bytes());
+    request.extend_from_slice(&(lifetime.as_secs() as u32).to_be_bytes());
+    let response = nat_pmp_request(gateway, &request)?;
+    match response[..] {
+        [_, _, _, _, _, _, _, _, _, _, a, b, _, _, _, _] => Ok(u16::from_be_bytes([a, b])),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Bad NAT-PMP response",
+        )),
+    }
+}
+
+/// Finds a router that can forward ports, returning the control URL and service to ask it with
+fn upnp_discover() -> io::Result<(String, &'static str)> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    let search = "M-SEARCH * HTTP/1.1\r\n\
+                  HOST: 239.255.255.250:1900\r\n\
+                  MAN: \"ssdp:discover\"\r\n\
+                  MX: 2\r\n\
+                  ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+    socket.send_to(search.as_bytes(), SSDP_ADDRESS)?;
+
+    let started = Instant::now();
+    let mut buffer = [0; 2048];
+    while started.elapsed() < TIMEOUT {
+        let size = match socket.recv(&mut buffer) {
+            Ok(size) => size,
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                break
+            }
+            Err(error) => return Err(error),
+        };
+        let response = String::from_utf8_lossy(&buffer[..size]);
+        let location = response.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.eq_ignore_ascii_case("location") {
+                Some(value.trim().to_string())
+            } else {
+                None
+            }
+        });
+        let location = match location {
+            Some(value) => value,
+            None => continue,
+        };
+        let description = http(&location, "GET", &[], "")?;
+        for &service in WAN_SERVICES {
+            let start = match description.find(&format!("<serviceType>{}</serviceType>", service)) {
+                Some(value) => value,
+                None => continue,
+            };
+            if let Some(control_path) = tag_value(&description[start..], "controlURL") {
+                let control_url = if control_path.starts_with("http://") {
+                    control_path.to_string()
+                } else {
+                    let (host, _) = split_url(&location)?;
+                    format!("http://{}/{}", host, control_path.trim_start_matches('/'))
+                };
+                return Ok((control_url, service));
+            }
+        }
+    }
+    Err(io::Error::new(ErrorKind::NotFound, "No UPnP router found"))
+}
+
+fn upnp_map(
+    control_url: &str,
+    service: &str,
+    internal_port: u16,
+    external_port: u16,
+) -> io::Result<()> {
+    // The router needs our address on its network, which is whatever we reach it from
+    let (host, _) = split_url(control_url)?;
+    let local_ip = TcpStream::connect(host)?.local_addr()?.ip();
+    let args = format!(
+        "<NewRemoteHost></NewRemoteHost>\
+         <NewExternalPort>{}</NewExternalPort>\
+         <NewProtocol>UDP</NewProtocol>\
+         <NewInternalPort>{}</NewInternalPort>\
+         <NewInternalClient>{}</NewInternalClient>\
+         <NewEnabled>1</NewEnabled>\
+         <NewPortMappingDescription>Among Us server</NewPortMappingDescription>\
+         <NewLeaseDuration>0</NewLeaseDuration>",
+        external_port, internal_port, local_ip
+    );
+    soap(control_url, service, "AddPortMapping", &args)?;
+    Ok(())
+}
+
+fn upnp_external_ip(control_url: &str, service: &str) -> io::Result<Ipv4Addr> {
+    let response = soap(control_url, service, "GetExternalIPAddress", "")?;
+    tag_value(&response, "NewExternalIPAddress")
+        .and_then(|ip| ip.trim().parse().ok())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "No external address in response"))
+}
+
+/// Calls a UPnP action, returning the response body
+fn soap(control_url: &str, service: &str, action: &str, args: &str) -> io::Result<String> {
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body>\
+         </s:Envelope>",
+        action = action,
+        service = service,
+        args = args
+    );
+    let soap_action = format!("\"{}#{}\"", service, action);
+    http(
+        control_url,
+        "POST",
+        &[
+            ("Content-Type", "text/xml; charset=\"utf-8\""),
+            ("SOAPAction", &soap_action),
+        ],
+        &body,
+    )
+}
+
+/// Makes a plain HTTP/1.0 request, returning the body if the status was 200
+fn http(url: &str, method: &str, headers: &[(&str, &str)], body: &str) -> io::Result<String> {
+    let (host, path) = split_url(url)?;
+    let addr = host
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Bad URL host"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut request = format!(
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        host,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(io::Error::other(format!(
+            "{} {} failed: {}",
+            method, url, status
+        )));
+    }
+    Ok(body.to_string())
+}
+
+/// Splits `http://host:port/path` into `host:port` and `/path`
+fn split_url(url: &str) -> io::Result<(&str, &str)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(ErrorKind::InvalidInput, format!("Not an http URL: {}", url))
+    })?;
+    Ok(match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    })
+}
+
+/// The text inside the first `<tag>`
+fn tag_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..end])
+}
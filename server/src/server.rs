@@ -0,0 +1,872 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+    ops::RangeInclusive,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use common::{
+    data::{
+        write_disconnect_reason, Address, ChatMode, DisconnectReason, GameCounts, GameId,
+        GameListPacket, GameOptions, GameVersion, HazelPacket, HazelPacketOut, HazelType,
+        HelloPacket, HostGamePacket, PacketType, RequestGameListPacket,
+    },
+    reader::{Data, IntoReader, PacketReader, PacketWriter, Serialize},
+};
+use log::{debug, info, warn};
+use num_traits::FromPrimitive;
+use rand::{rngs::ThreadRng, thread_rng};
+
+use crate::{
+    access_log::AccessLog,
+    connection::{Connection, FRAGMENT_TIMEOUT},
+    game::Game,
+    storage::{MemoryStorage, ServerStats, Storage, StoredBan, StoredLobby},
+    throttle::{Action, Throttle, ThrottleSettings},
+};
+
+const BUFFER_SIZE: usize = 65_507;
+
+/// The builds this server speaks the protocol of, from the oldest the client knows to the newest
+/// protocol change this crate knows about. See `ServerSettings::versions`
+pub const SUPPORTED_VERSIONS: RangeInclusive<GameVersion> =
+    GameVersion::new(2020, 6, 9, 0)..=GameVersion::new(2021, 4, 25, 49);
+
+/// How long to block waiting for a packet before resending and timing out connections
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often to forget old throttle state, save stats and remove abandoned games
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a game can exist without players before it's removed. Covers hosts that never join
+/// and lobbies restored from storage that nobody comes back to
+const EMPTY_GAME_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The most games sent in response to a single game list request
+const MAX_LISTINGS: usize = 10;
+
+// Disconnect reasons. See `common::data::DisconnectReason`
+const REASON_EXIT_GAME: i32 = 0;
+const REASON_GAME_FULL: i32 = 1;
+const REASON_GAME_STARTED: i32 = 2;
+const REASON_GAME_NOT_FOUND: i32 = 3;
+const REASON_BANNED: i32 = 6;
+const REASON_KICKED: i32 = 7;
+
+pub struct ServerSettings {
+    pub bind_address: SocketAddr,
+    /// The address put in game listings for clients to connect to
+    pub public_address: Address,
+    pub access_log: Option<AccessLog>,
+    pub throttle: ThrottleSettings,
+    /// Where lobbies, bans and stats are kept. Defaults to `MemoryStorage`
+    pub storage: Option<Box<dyn Storage>>,
+    /// Hellos from any other version are refused with `DisconnectReason::IncorrectVersion`.
+    /// Usually `SUPPORTED_VERSIONS`
+    pub versions: RangeInclusive<GameVersion>,
+}
+
+/// A minimal Among Us server. Relays game data between players, leaving the game logic to
+/// whichever client is host
+pub struct Server {
+    socket: UdpSocket,
+    settings: ServerSettings,
+    connections: HashMap<SocketAddr, Connection>,
+    /// Games by `GameId::id`
+    games: HashMap<i32, Game>,
+    next_client_id: i32,
+    rng: ThreadRng,
+    access_log: Option<AccessLog>,
+    throttle: Throttle,
+    last_pruned: Instant,
+    storage: Box<dyn Storage>,
+    stats: ServerStats,
+}
+
+impl Server {
+    pub fn bind(mut settings: ServerSettings) -> io::Result<Self> {
+        let socket = UdpSocket::bind(settings.bind_address)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        info!("Listening on {}", settings.bind_address);
+        let mut storage = settings
+            .storage
+            .take()
+            .unwrap_or_else(|| Box::new(MemoryStorage::default()));
+
+        let mut games = HashMap::new();
+        for lobby in storage.lobbies()? {
+            let mut game = Game::new(GameId { id: lobby.game_id }, lobby.options);
+            game.is_public = lobby.is_public;
+            games.insert(lobby.game_id, game);
+        }
+        let mut throttle = Throttle::new(settings.throttle.clone());
+        let mut bans = 0;
+        let now = unix_time();
+        for ban in storage.bans()? {
+            if ban.expires_at > now {
+                throttle.ban(ban.ip, Duration::from_secs(ban.expires_at - now));
+                bans += 1;
+            } else {
+                storage.remove_ban(ban.ip)?;
+            }
+        }
+        let stats = storage.stats()?;
+        info!("Restored {} games and {} bans", games.len(), bans);
+
+        Ok(Self {
+            socket,
+            connections: HashMap::new(),
+            games,
+            next_client_id: 1,
+            rng: thread_rng(),
+            access_log: settings.access_log.take(),
+            throttle,
+            last_pruned: Instant::now(),
+            storage,
+            stats,
+            settings,
+        })
+    }
+
+    /// Totals since the storage was created, including connections still open
+    pub fn stats(&self) -> ServerStats {
+        let mut stats = self.stats.clone();
+        for connection in self.connections.values() {
+            stats.bytes_in += connection.bytes_in;
+            stats.bytes_out += connection.bytes_out;
+        }
+        stats
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut buffer = vec![0; BUFFER_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, addr)) => {
+                    let data = buffer[..size].to_vec();
+                    self.handle_datagram(addr, data);
+                }
+                Err(error)
+                    if error.kind() == ErrorKind::WouldBlock
+                        || error.kind() == ErrorKind::TimedOut => {}
+                Err(error) => warn!("Error receiving: {}", error),
+            }
+            self.maintain();
+        }
+    }
+
+    /// Resends unacknowledged packets and drops quiet or unresponsive connections
+    fn maintain(&mut self) {
+        if self.last_pruned.elapsed() >= PRUNE_INTERVAL {
+            self.throttle.prune();
+            self.remove_abandoned_games();
+            let result = self.storage.save_stats(&self.stats());
+            self.check_storage(result);
+            self.last_pruned = Instant::now();
+        }
+
+        let mut timed_out = Vec::new();
+        let mut unresponsive = Vec::new();
+        for connection in self.connections.values_mut() {
+            connection
+                .reassembler
+                .prune(FRAGMENT_TIMEOUT, Instant::now());
+            if connection.timed_out() {
+                timed_out.push(connection.addr);
+                continue;
+            }
+            if connection.unresponsive() {
+                unresponsive.push(connection.addr);
+                continue;
+            }
+            for packet in connection.resends() {
+                connection.bytes_out += packet.len() as u64;
+                let _ = self.socket.send_to(&packet, connection.addr);
+            }
+        }
+        for addr in timed_out {
+            info!("{} timed out", addr);
+            self.remove_connection(addr, "timeout");
+        }
+        for addr in unresponsive {
+            info!("{} stopped acknowledging", addr);
+            self.remove_connection(addr, "unresponsive");
+        }
+    }
+
+    fn handle_datagram(&mut self, addr: SocketAddr, data: Vec<u8>) {
+        // Hellos get through to be told they're banned
+        let is_hello = data.first() == Some(&(HazelType::Hello as u8));
+        if !is_hello && self.throttle.is_banned(addr.ip()) {
+            return;
+        }
+        let size = data.len();
+        let packet = match data.into_reader().read::<HazelPacket>() {
+            Ok(packet) => packet,
+            Err(error) => {
+                debug!("Bad packet from {}: {}", addr, error);
+                return;
+            }
+        };
+
+        if let HazelPacket::Hello { ack_id, data } = packet {
+            if !self.connections.contains_key(&addr) && !self.throttle(addr, Action::Hello) {
+                self.refuse(addr, DisconnectReason::Banned);
+                return;
+            }
+            self.handle_hello(addr, data);
+            // Refused and bad hellos aren't acknowledged
+            match self.connections.get_mut(&addr) {
+                Some(connection) => connection.received(size),
+                None => return,
+            }
+            self.acknowledge(addr, ack_id);
+            return;
+        }
+        match self.connections.get_mut(&addr) {
+            Some(connection) => connection.received(size),
+            // Anything else needs a hello first
+            None => return,
+        }
+        match packet {
+            HazelPacket::Unreliable { data } => self.handle_messages(addr, data, false),
+            HazelPacket::Reliable { ack_id, data } => {
+                // Acked either way, so a throttled client isn't left resending it
+                self.acknowledge(addr, ack_id);
+                if !self.throttle(addr, Action::Reliable) {
+                    return;
+                }
+                if self.first_receipt(addr, ack_id) {
+                    self.handle_messages(addr, data, true);
+                }
+            }
+            HazelPacket::Fragment {
+                ack_id,
+                fragment_id,
+                index,
+                count,
+                data,
+            } => {
+                self.acknowledge(addr, ack_id);
+                if !self.throttle(addr, Action::Reliable) {
+                    return;
+                }
+                if !self.first_receipt(addr, ack_id) {
+                    return;
+                }
+                let payload = match self.connections.get_mut(&addr) {
+                    Some(connection) => {
+                        connection
+                            .reassembler
+                            .add(fragment_id, index, count, data, Instant::now())
+                    }
+                    None => return,
+                };
+                if let Some(payload) = payload {
+                    self.handle_messages(addr, payload, true);
+                }
+            }
+            HazelPacket::KeepAlive { ack_id } => self.acknowledge(addr, ack_id),
+            HazelPacket::Acknowledge { ack_id } => {
+                if let Some(connection) = self.connections.get_mut(&addr) {
+                    connection.acknowledged(ack_id);
+                }
+            }
+            HazelPacket::Disconnect { reason, .. } => {
+                info!("{} disconnected ({:?})", addr, reason);
+                self.remove_connection(addr, "disconnect");
+            }
+            HazelPacket::Hello { .. } => unreachable!(),
+        }
+    }
+
+    fn handle_hello(&mut self, addr: SocketAddr, data: Vec<u8>) {
+        if self.connections.contains_key(&addr) {
+            // Resent because our ack was lost
+            return;
+        }
+        let hello = match data.into_reader().read::<HelloPacket>() {
+            Ok(hello) => hello,
+            Err(error) => {
+                debug!("Bad hello from {}: {}", addr, error);
+                return;
+            }
+        };
+        if !self.settings.versions.contains(&hello.version) {
+            self.refuse(addr, DisconnectReason::IncorrectVersion);
+            return;
+        }
+
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+        info!(
+            "{} connected as {} ({}, {})",
+            addr, client_id, hello.username, hello.version
+        );
+        self.log_event(
+            "connect",
+            &[
+                ("addr", &addr),
+                ("client_id", &client_id),
+                ("name", &hello.username),
+                ("version", &hello.version.to_u32()),
+            ],
+        );
+        self.stats.connections += 1;
+        let mut connection = Connection::new(addr, client_id);
+        connection.version = Some(hello.version.to_u32());
+        connection.chat_mode = hello.chat_mode;
+        connection.name = Some(hello.username);
+        self.connections.insert(addr, connection);
+    }
+
+    /// Handles every message in a reliable or unreliable packet
+    fn handle_messages(&mut self, addr: SocketAddr, data: Vec<u8>, reliable: bool) {
+        let mut r = data.into_reader();
+        while r.remaining() > 0 {
+            let (tag, mut message) = match r.read_message() {
+                Ok(value) => value,
+                Err(error) => {
+                    debug!("Bad message from {}: {}", addr, error);
+                    return;
+                }
+            };
+            let length = message.remaining();
+            let body = message.read_slice(length).unwrap();
+            let raw = raw_message(tag, body);
+            let mut body = PacketReader::new(body);
+            if let Err(error) = self.handle_message(addr, tag, &mut body, &raw, reliable) {
+                debug!("Error handling message {} from {}: {}", tag, addr, error);
+            }
+        }
+    }
+
+    /// `raw` is the whole message including its header, for relaying. Relayed game data is sent
+    /// as `reliable` as it arrived
+    fn handle_message(
+        &mut self,
+        addr: SocketAddr,
+        tag: u8,
+        r: &mut PacketReader<&[u8]>,
+        raw: &[u8],
+        reliable: bool,
+    ) -> io::Result<()> {
+        // Could have been dropped by an earlier message in the same packet
+        let client_id = match self.connections.get(&addr) {
+            Some(connection) => connection.client_id,
+            None => return Ok(()),
+        };
+        match PacketType::from_u8(tag) {
+            Some(PacketType::HostingGame) => {
+                let request = r.read::<HostGamePacket>()?;
+                self.host_game(addr, request.game_options);
+            }
+            Some(PacketType::GameJoinDisconnect) => {
+                let game_id = r.read::<GameId>()?;
+                if self.throttle(addr, Action::Join) {
+                    self.join_game(addr, game_id);
+                }
+            }
+            Some(PacketType::GameStarted) => {
+                let game_id = r.read::<GameId>()?;
+                if let Some(game) = self.host_game_mut(client_id, game_id) {
+                    game.started = true;
+                    self.broadcast(game_id, None, raw, true);
+                }
+            }
+            Some(PacketType::EndGame) => {
+                let game_id = r.read::<GameId>()?;
+                if let Some(game) = self.host_game_mut(client_id, game_id) {
+                    game.started = false;
+                    self.broadcast(game_id, None, raw, true);
+                }
+            }
+            Some(PacketType::AlterGameInfo) => {
+                let game_id = r.read::<GameId>()?;
+                let is_public = match r.read_u8()? {
+                    1 => Some(r.read_bool()?),
+                    // Passed on for clients that know what it is
+                    _ => None,
+                };
+                if let Some(game) = self.host_game_mut(client_id, game_id) {
+                    if let Some(is_public) = is_public {
+                        game.is_public = is_public;
+                    }
+                    self.save_lobby(game_id);
+                    self.broadcast(game_id, None, raw, true);
+                }
+            }
+            Some(PacketType::GameInfo) => {
+                let game_id = r.read::<GameId>()?;
+                if self.in_game(client_id, game_id) {
+                    self.broadcast(game_id, Some(client_id), raw, reliable);
+                }
+            }
+            Some(PacketType::GameInfoTo) => {
+                let game_id = r.read::<GameId>()?;
+                let target_id = r.read_i32_encoded()?;
+                if self.in_game(client_id, game_id) && self.in_game(target_id, game_id) {
+                    self.send_to_client(target_id, raw.to_vec(), reliable);
+                }
+            }
+            Some(PacketType::PlayerLeft) | Some(PacketType::KickPlayer) => {
+                let game_id = r.read::<GameId>()?;
+                let target_id = r.read_i32_encoded()?;
+                let ban = tag == PacketType::KickPlayer as u8 && r.read_bool()?;
+                // Only the host can remove players, and only from their own game
+                if self.host_game_mut(client_id, game_id).is_some()
+                    && self.in_game(target_id, game_id)
+                {
+                    self.kick(game_id, target_id, ban);
+                } else {
+                    debug!(
+                        "Ignoring kick of {} from {} by {}",
+                        target_id, game_id, client_id
+                    );
+                }
+            }
+            Some(PacketType::GameList) => {
+                let request = r.read::<RequestGameListPacket>()?;
+                self.send_game_list(addr, &request.game_options);
+            }
+            _ => debug!("Unhandled message {} from {}", tag, addr),
+        }
+        Ok(())
+    }
+
+    fn host_game(&mut self, addr: SocketAddr, options: GameOptions) {
+        let client_id = self.connections[&addr].client_id;
+        let game_id = loop {
+            let game_id = Game::random_id(&mut self.rng);
+            if !self.games.contains_key(&game_id.id) {
+                break game_id;
+            }
+        };
+        info!("Created game {}", game_id);
+        self.log_event("host", &[("client_id", &client_id), ("game", &game_id)]);
+        self.games.insert(game_id.id, Game::new(game_id, options));
+        self.stats.games_hosted += 1;
+        self.save_lobby(game_id);
+
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::HostingGame as u8);
+        w.write(game_id);
+        w.end_message();
+        self.send(addr, w.finish());
+    }
+
+    fn join_game(&mut self, addr: SocketAddr, game_id: GameId) {
+        let client_id = self.connections[&addr].client_id;
+        let rejection = match self.games.get(&game_id.id) {
+            None => Some(REASON_GAME_NOT_FOUND),
+            Some(game) if game.players.contains(&client_id) => return,
+            Some(game) if game.started => Some(REASON_GAME_STARTED),
+            Some(game) if game.is_full() => Some(REASON_GAME_FULL),
+            Some(_) => None,
+        };
+        let result = rejection.map_or("ok", reason_name);
+        self.log_event(
+            "join",
+            &[
+                ("client_id", &client_id),
+                ("game", &game_id),
+                ("result", &result),
+            ],
+        );
+        if let Some(reason) = rejection {
+            return self.send_join_error(addr, reason);
+        }
+
+        // Joining another game leaves the one we're in
+        let previous = self.connections[&addr].game_id;
+        if let Some(previous) = previous.filter(|&previous| previous != game_id) {
+            self.leave_game(client_id, previous, REASON_EXIT_GAME);
+        }
+
+        let game = self.games.get_mut(&game_id.id).unwrap();
+        if game.players.is_empty() {
+            game.host_id = client_id;
+        }
+        let others = game.players.clone();
+        game.players.push(client_id);
+        let host_id = game.host_id;
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.game_id = Some(game_id);
+        }
+        info!("{} joined {}", client_id, game_id);
+
+        // Tell everyone else. Chat modes go after what the game sends, only to versions whose
+        // hello has one
+        let joiner_chat_mode = self.chat_mode(client_id);
+        for &other in &others {
+            let mut w = PacketWriter::new();
+            w.start_message(PacketType::GameJoinDisconnect as u8);
+            w.write(game_id);
+            w.write_i32(client_id);
+            w.write_i32(host_id);
+            if self.has_chat_modes(other) {
+                w.write_u8(joiner_chat_mode as u8);
+            }
+            w.end_message();
+            self.send_to_client(other, w.finish(), true);
+        }
+
+        // Tell the joiner
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::JoinedGame as u8);
+        w.write(game_id);
+        w.write_i32(client_id);
+        w.write_i32(host_id);
+        w.write_u32_encoded(others.len() as u32);
+        for &other in &others {
+            w.write_i32_encoded(other);
+        }
+        if self.has_chat_modes(client_id) {
+            for &other in &others {
+                w.write_u8(self.chat_mode(other) as u8);
+            }
+        }
+        w.end_message();
+        self.send(addr, w.finish());
+    }
+
+    /// Whether a client's version has chat modes, so expects them when players join
+    fn has_chat_modes(&self, client_id: i32) -> bool {
+        self.connections
+            .values()
+            .find(|connection| connection.client_id == client_id)
+            .and_then(|connection| connection.version)
+            .is_some_and(|version| GameVersion::from_u32(version).hello_has_chat_mode())
+    }
+
+    /// A client's chat mode from their hello, free chat for versions without one
+    fn chat_mode(&self, client_id: i32) -> ChatMode {
+        self.connections
+            .values()
+            .find(|connection| connection.client_id == client_id)
+            .and_then(|connection| connection.chat_mode)
+            .unwrap_or(ChatMode::FreeChat)
+    }
+
+    /// Tells a client they've been removed from or couldn't join a game
+    fn send_join_error(&mut self, addr: SocketAddr, reason: i32) {
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameJoinDisconnect as u8);
+        w.write_i32(reason);
+        w.end_message();
+        self.send(addr, w.finish());
+    }
+
+    fn send_game_list(&mut self, addr: SocketAddr, filter: &GameOptions) {
+        let address = &self.settings.public_address;
+        let connections = &self.connections;
+        let games = self
+            .games
+            .values()
+            .filter(|game| game.matches(filter))
+            .take(MAX_LISTINGS)
+            .map(|game| {
+                let host_name = connections
+                    .values()
+                    .find(|connection| connection.client_id == game.host_id)
+                    .and_then(|connection| connection.name.as_deref())
+                    .unwrap_or("");
+                game.listing(address, host_name)
+            })
+            .collect::<Vec<_>>();
+        let mut counts = GameCounts::default();
+        for game in self.games.values().filter(|game| game.is_public && !game.started) {
+            counts.add(game.map());
+        }
+        debug!("Sending {} games to {}", games.len(), addr);
+
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::GameList as u8);
+        w.write(GameListPacket {
+            games,
+            unparsed: Vec::new(),
+            counts: Some(counts),
+        });
+        w.end_message();
+        self.send(addr, w.finish());
+    }
+
+    /// Removes a player from a game, telling them why and letting everyone else know. Does
+    /// nothing if they aren't in it
+    fn kick(&mut self, game_id: GameId, target_id: i32, ban: bool) {
+        let addr = match self.client_addr(target_id) {
+            Some(addr) if self.in_game(target_id, game_id) => addr,
+            _ => return,
+        };
+        let reason = if ban { REASON_BANNED } else { REASON_KICKED };
+        self.log_event(
+            "kick",
+            &[
+                ("client_id", &target_id),
+                ("game", &game_id),
+                ("reason", &reason_name(reason)),
+            ],
+        );
+        self.leave_game(target_id, game_id, reason);
+        self.send_join_error(addr, reason);
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.game_id = None;
+        }
+    }
+
+    /// Takes a player out of a game, moving the host if needed and removing empty games
+    fn leave_game(&mut self, client_id: i32, game_id: GameId, reason: i32) {
+        let game = match self.games.get_mut(&game_id.id) {
+            Some(game) => game,
+            None => return,
+        };
+        game.players.retain(|&player| player != client_id);
+        if game.players.is_empty() {
+            info!("Removing empty game {}", game_id);
+            self.remove_game(game_id);
+            return;
+        }
+        if game.host_id == client_id {
+            game.host_id = game.players[0];
+        }
+
+        let mut w = PacketWriter::new();
+        w.start_message(PacketType::PlayerLeft as u8);
+        w.write(game_id);
+        w.write_i32(client_id);
+        w.write_i32(game.host_id);
+        w.write_u8(reason as u8);
+        w.end_message();
+        self.broadcast(game_id, None, &w.finish(), true);
+    }
+
+    /// Answers a hello that won't get a connection with a disconnect saying why, so the client
+    /// doesn't wait for an ack that never comes
+    fn refuse(&mut self, addr: SocketAddr, reason: DisconnectReason) {
+        debug!("Refusing {}: {:?}", addr, reason);
+        let mut w = PacketWriter::new();
+        write_disconnect_reason(&mut w, &reason);
+        let packet = HazelPacketOut::Disconnect { data: w.finish() }.serialize_bytes();
+        let _ = self.socket.send_to(&packet, addr);
+    }
+
+    /// `reason` is only for the access log
+    fn remove_connection(&mut self, addr: SocketAddr, reason: &str) {
+        let connection = match self.connections.remove(&addr) {
+            Some(connection) => connection,
+            None => return,
+        };
+        self.log_event(
+            "disconnect",
+            &[
+                ("addr", &addr),
+                ("client_id", &connection.client_id),
+                ("reason", &reason),
+                ("bytes_in", &connection.bytes_in),
+                ("bytes_out", &connection.bytes_out),
+                (
+                    "duration_secs",
+                    &connection.connected_at.elapsed().as_secs(),
+                ),
+            ],
+        );
+        self.stats.bytes_in += connection.bytes_in;
+        self.stats.bytes_out += connection.bytes_out;
+        if let Some(game_id) = connection.game_id {
+            self.leave_game(connection.client_id, game_id, REASON_EXIT_GAME);
+        }
+        let _ = self.socket.send_to(
+            &HazelPacketOut::Disconnect { data: Vec::new() }.serialize_bytes(),
+            addr,
+        );
+    }
+
+    fn remove_game(&mut self, game_id: GameId) {
+        self.games.remove(&game_id.id);
+        let result = self.storage.remove_lobby(game_id.id);
+        self.check_storage(result);
+    }
+
+    /// Removes games that have had nobody in them for too long
+    fn remove_abandoned_games(&mut self) {
+        let abandoned = self
+            .games
+            .values()
+            .filter(|game| game.players.is_empty() && game.created.elapsed() >= EMPTY_GAME_TIMEOUT)
+            .map(|game| game.id)
+            .collect::<Vec<_>>();
+        for game_id in abandoned {
+            info!("Removing abandoned game {}", game_id);
+            self.remove_game(game_id);
+        }
+    }
+
+    fn save_lobby(&mut self, game_id: GameId) {
+        let lobby = match self.games.get(&game_id.id) {
+            Some(game) => StoredLobby {
+                game_id: game_id.id,
+                options: game.options.clone(),
+                is_public: game.is_public,
+            },
+            None => return,
+        };
+        let result = self.storage.save_lobby(&lobby);
+        self.check_storage(result);
+    }
+
+    /// Storage failures are logged rather than taking the server down
+    fn check_storage(&self, result: io::Result<()>) {
+        if let Err(error) = result {
+            warn!("Couldn't update storage: {}", error);
+        }
+    }
+
+    /// The game if `client_id` is its host
+    fn host_game_mut(&mut self, client_id: i32, game_id: GameId) -> Option<&mut Game> {
+        self.games
+            .get_mut(&game_id.id)
+            .filter(|game| game.host_id == client_id)
+    }
+
+    fn in_game(&self, client_id: i32, game_id: GameId) -> bool {
+        self.games
+            .get(&game_id.id)
+            .is_some_and(|game| game.players.contains(&client_id))
+    }
+
+    fn client_addr(&self, client_id: i32) -> Option<SocketAddr> {
+        self.connections
+            .values()
+            .find(|connection| connection.client_id == client_id)
+            .map(|connection| connection.addr)
+    }
+
+    /// Sends a message to every player in a game, except `except` if given
+    fn broadcast(&mut self, game_id: GameId, except: Option<i32>, message: &[u8], reliable: bool) {
+        let players = match self.games.get(&game_id.id) {
+            Some(game) => game.players.clone(),
+            None => return,
+        };
+        for player in players.into_iter().filter(|&id| Some(id) != except) {
+            self.send_to_client(player, message.to_vec(), reliable);
+        }
+    }
+
+    fn send_to_client(&mut self, client_id: i32, message: Vec<u8>, reliable: bool) {
+        let addr = match self.client_addr(client_id) {
+            Some(addr) => addr,
+            None => return,
+        };
+        if reliable {
+            self.send(addr, message);
+        } else {
+            let packet = HazelPacketOut::Unreliable {
+                data: Box::new(Data::Bytes(message)),
+            };
+            self.send_raw(addr, &packet.serialize_bytes());
+        }
+    }
+
+    /// Sends messages reliably
+    fn send(&mut self, addr: SocketAddr, messages: Vec<u8>) {
+        let packets = match self.connections.get_mut(&addr) {
+            Some(connection) => connection.reliable(messages),
+            None => return,
+        };
+        for packet in packets {
+            self.send_raw(addr, &packet);
+        }
+    }
+
+    /// Sends a whole Hazel packet, counting it towards the connection's bytes sent
+    fn send_raw(&mut self, addr: SocketAddr, packet: &[u8]) {
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.bytes_out += packet.len() as u64;
+        }
+        if let Err(error) = self.socket.send_to(packet, addr) {
+            warn!("Error sending to {}: {}", addr, error);
+        }
+    }
+
+    /// Counts an action against the sender's IP. If it's over the limit, the IP is banned for
+    /// `ThrottleSettings::ban_duration`, every connection from it is disconnected and `false`
+    /// returned
+    fn throttle(&mut self, addr: SocketAddr, action: Action) -> bool {
+        let ip = addr.ip();
+        if self.throttle.allow(ip, action) {
+            return true;
+        }
+        warn!("Temporarily banning {} for too many {:?}", ip, action);
+        self.log_event("ban", &[("ip", &ip), ("action", &format!("{:?}", action))]);
+        let ban = StoredBan {
+            ip,
+            expires_at: unix_time() + self.throttle.ban_duration().as_secs(),
+        };
+        let result = self.storage.save_ban(&ban);
+        self.check_storage(result);
+        let addrs = self
+            .connections
+            .keys()
+            .filter(|other| other.ip() == ip)
+            .copied()
+            .collect::<Vec<_>>();
+        for addr in addrs {
+            self.remove_connection(addr, "throttled");
+        }
+        false
+    }
+
+    fn log_event(&mut self, event: &str, fields: &[(&str, &dyn Display)]) {
+        if let Some(access_log) = &mut self.access_log {
+            if let Err(error) = access_log.record(event, fields) {
+                warn!("Couldn't write to access log: {}", error);
+            }
+        }
+    }
+
+    /// Whether a reliable packet is being seen for the first time. Repeats still need acking, as
+    /// they're resent when our ack is lost, but mustn't be handled again
+    fn first_receipt(&mut self, addr: SocketAddr, ack_id: u16) -> bool {
+        match self.connections.get_mut(&addr) {
+            Some(connection) => connection.first_receipt(ack_id),
+            None => false,
+        }
+    }
+
+    fn acknowledge(&mut self, addr: SocketAddr, ack_id: u16) {
+        let packet = HazelPacketOut::Acknowledge { ack_id }.serialize_bytes();
+        self.send_raw(addr, &packet);
+    }
+}
+
+/// Seconds since the unix epoch, which unlike `Instant` means something after a restart
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Short name for a disconnect reason, for the access log
+fn reason_name(reason: i32) -> &'static str {
+    match reason {
+        REASON_EXIT_GAME => "exit_game",
+        REASON_GAME_FULL => "game_full",
+        REASON_GAME_STARTED => "game_started",
+        REASON_GAME_NOT_FOUND => "game_not_found",
+        REASON_BANNED => "banned",
+        REASON_KICKED => "kicked",
+        _ => "other",
+    }
+}
+
+/// Rebuilds a message with its length and tag header so it can be relayed untouched
+fn raw_message(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut w = PacketWriter::new();
+    w.start_message(tag);
+    w.write_bytes_raw(body);
+    w.end_message();
+    w.finish()
+}
@@ -0,0 +1,363 @@
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    io::{self, ErrorKind, Read},
+    net::IpAddr,
+    path::Path,
+};
+
+use common::{
+    data::GameOptions,
+    reader::{Deserialize, PacketRead, PacketReader, PacketWriter, Serialize},
+};
+
+/// A lobby as stored, enough to recreate it after a restart
+#[derive(Debug, Clone)]
+pub struct StoredLobby {
+    /// `GameId::id`
+    pub game_id: i32,
+    pub options: GameOptions,
+    pub is_public: bool,
+}
+
+/// A temporary ban from the throttle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBan {
+    pub ip: IpAddr,
+    /// Unix timestamp (seconds)
+    pub expires_at: u64,
+}
+
+/// Running totals since the storage was created
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    pub connections: u64,
+    pub games_hosted: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// Somewhere to keep server state between restarts
+pub trait Storage: Send {
+    /// Adds or replaces a lobby
+    fn save_lobby(&mut self, lobby: &StoredLobby) -> io::Result<()>;
+
+    fn remove_lobby(&mut self, game_id: i32) -> io::Result<()>;
+
+    fn lobbies(&self) -> io::Result<Vec<StoredLobby>>;
+
+    /// Adds or replaces the ban for an IP
+    fn save_ban(&mut self, ban: &StoredBan) -> io::Result<()>;
+
+    fn remove_ban(&mut self, ip: IpAddr) -> io::Result<()>;
+
+    fn bans(&self) -> io::Result<Vec<StoredBan>>;
+
+    fn save_stats(&mut self, stats: &ServerStats) -> io::Result<()>;
+
+    /// Defaults to all zeroes if never saved
+    fn stats(&self) -> io::Result<ServerStats>;
+}
+
+/// Keeps everything in memory, so nothing survives a restart. The default
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    lobbies: HashMap<i32, StoredLobby>,
+    bans: HashMap<IpAddr, StoredBan>,
+    stats: ServerStats,
+}
+
+impl Storage for MemoryStorage {
+    fn save_lobby(&mut self, lobby: &StoredLobby) -> io::Result<()> {
+        self.lobbies.insert(lobby.game_id, lobby.clone());
+        Ok(())
+    }
+
+    fn remove_lobby(&mut self, game_id: i32) -> io::Result<()> {
+        self.lobbies.remove(&game_id);
+        Ok(())
+    }
+
+    fn lobbies(&self) -> io::Result<Vec<StoredLobby>> {
+        Ok(self.lobbies.values().cloned().collect())
+    }
+
+    fn save_ban(&mut self, ban: &StoredBan) -> io::Result<()> {
+        self.bans.insert(ban.ip, ban.clone());
+        Ok(())
+    }
+
+    fn remove_ban(&mut self, ip: IpAddr) -> io::Result<()> {
+        self.bans.remove(&ip);
+        Ok(())
+    }
+
+    fn bans(&self) -> io::Result<Vec<StoredBan>> {
+        Ok(self.bans.values().cloned().collect())
+    }
+
+    fn save_stats(&mut self, stats: &ServerStats) -> io::Result<()> {
+        self.stats = stats.clone();
+        Ok(())
+    }
+
+    fn stats(&self) -> io::Result<ServerStats> {
+        Ok(self.stats.clone())
+    }
+}
+
+/// Keeps everything in a sled database on disk
+pub struct SledStorage {
+    lobbies: sled::Tree,
+    bans: sled::Tree,
+    db: sled::Db,
+}
+
+const STATS_KEY: &[u8] = b"stats";
+
+impl SledStorage {
+    /// Opens the database at `path`, creating it if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let db = sled::open(path).map_err(to_io)?;
+        Ok(Self {
+            lobbies: db.open_tree("lobbies").map_err(to_io)?,
+            bans: db.open_tree("bans").map_err(to_io)?,
+            db,
+        })
+    }
+}
+
+impl Storage for SledStorage {
+    fn save_lobby(&mut self, lobby: &StoredLobby) -> io::Result<()> {
+        self.lobbies
+            .insert(lobby.game_id.to_be_bytes(), lobby.serialize_bytes())
+            .map_err(to_io)?;
+        self.db.flush().map_err(to_io)?;
+        Ok(())
+    }
+
+    fn remove_lobby(&mut self, game_id: i32) -> io::Result<()> {
+        self.lobbies.remove(game_id.to_be_bytes()).map_err(to_io)?;
+        self.db.flush().map_err(to_io)?;
+        Ok(())
+    }
+
+    fn lobbies(&self) -> io::Result<Vec<StoredLobby>> {
+        read_tree(&self.lobbies)
+    }
+
+    fn save_ban(&mut self, ban: &StoredBan) -> io::Result<()> {
+        self.bans
+            .insert(ban.ip.to_string(), ban.serialize_bytes())
+            .map_err(to_io)?;
+        self.db.flush().map_err(to_io)?;
+        Ok(())
+    }
+
+    fn remove_ban(&mut self, ip: IpAddr) -> io::Result<()> {
+        self.bans.remove(ip.to_string()).map_err(to_io)?;
+        self.db.flush().map_err(to_io)?;
+        Ok(())
+    }
+
+    fn bans(&self) -> io::Result<Vec<StoredBan>> {
+        read_tree(&self.bans)
+    }
+
+    fn save_stats(&mut self, stats: &ServerStats) -> io::Result<()> {
+        self.db
+            .insert(STATS_KEY, stats.serialize_bytes())
+            .map_err(to_io)?;
+        self.db.flush().map_err(to_io)?;
+        Ok(())
+    }
+
+    fn stats(&self) -> io::Result<ServerStats> {
+        match self.db.get(STATS_KEY).map_err(to_io)? {
+            Some(value) => PacketReader::new(&value[..]).read(),
+            None => Ok(ServerStats::default()),
+        }
+    }
+}
+
+fn read_tree<T: Deserialize>(tree: &sled::Tree) -> io::Result<Vec<T>> {
+    tree.iter()
+        .map(|entry| {
+            let (_, value) = entry.map_err(to_io)?;
+            PacketReader::new(&value[..]).read()
+        })
+        .collect()
+}
+
+fn to_io(error: sled::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+fn write_u64(w: &mut PacketWriter, value: u64) {
+    w.write_bytes_raw(&value.to_le_bytes());
+}
+
+fn read_u64<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(r.read_slice(8)?.try_into().unwrap()))
+}
+
+impl Serialize for StoredLobby {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_i32(self.game_id);
+        w.write(&self.options);
+        w.write_bool(self.is_public);
+    }
+}
+
+impl Deserialize for StoredLobby {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        Ok(Self {
+            game_id: r.read_i32()?,
+            options: r.read()?,
+            is_public: r.read_bool()?,
+        })
+    }
+}
+
+impl Serialize for StoredBan {
+    fn serialize(&self, w: &mut PacketWriter) {
+        w.write_string(&self.ip.to_string());
+        write_u64(w, self.expires_at);
+    }
+}
+
+impl Deserialize for StoredBan {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        let ip = r
+            .read_string()?
+            .parse()
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Invalid ban IP"))?;
+        Ok(Self {
+            ip,
+            expires_at: read_u64(r)?,
+        })
+    }
+}
+
+impl Serialize for ServerStats {
+    fn serialize(&self, w: &mut PacketWriter) {
+        write_u64(w, self.connections);
+        write_u64(w, self.games_hosted);
+        write_u64(w, self.bytes_in);
+        write_u64(w, self.bytes_out);
+    }
+}
+
+impl Deserialize for ServerStats {
+    fn deserialize<T: PacketRead + Read>(r: &mut PacketReader<T>) -> io::Result<Self> {
+        Ok(Self {
+            connections: read_u64(r)?,
+            games_hosted: read_u64(r)?,
+            bytes_in: read_u64(r)?,
+            bytes_out: read_u64(r)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::data::OptionsPreset;
+    use std::{fs, net::Ipv4Addr};
+
+    fn lobby(game_id: i32, preset: OptionsPreset) -> StoredLobby {
+        StoredLobby {
+            game_id,
+            options: GameOptions::preset(preset),
+            is_public: game_id % 2 == 0,
+        }
+    }
+
+    /// Lobbies by game id, as bytes since `GameOptions` can't be compared
+    fn lobby_bytes(storage: &dyn Storage) -> Vec<Vec<u8>> {
+        let mut lobbies = storage.lobbies().unwrap();
+        lobbies.sort_by_key(|lobby| lobby.game_id);
+        lobbies.iter().map(Serialize::serialize_bytes).collect()
+    }
+
+    fn sorted_bans(storage: &dyn Storage) -> Vec<StoredBan> {
+        let mut bans = storage.bans().unwrap();
+        bans.sort_by_key(|ban| ban.ip);
+        bans
+    }
+
+    fn ban(last: u8, expires_at: u64) -> StoredBan {
+        StoredBan {
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, last)),
+            expires_at,
+        }
+    }
+
+    /// Saves, replaces and removes every kind of record
+    fn check_round_trip(storage: &mut dyn Storage) {
+        assert!(storage.lobbies().unwrap().is_empty());
+        assert!(storage.bans().unwrap().is_empty());
+        assert_eq!(storage.stats().unwrap(), ServerStats::default());
+
+        let first = lobby(-5, OptionsPreset::Classic);
+        let second = lobby(8, OptionsPreset::HideAndSeek);
+        storage.save_lobby(&first).unwrap();
+        storage.save_lobby(&second).unwrap();
+        let replaced = lobby(8, OptionsPreset::Competitive);
+        storage.save_lobby(&replaced).unwrap();
+        assert_eq!(
+            lobby_bytes(storage),
+            vec![first.serialize_bytes(), replaced.serialize_bytes()]
+        );
+        storage.remove_lobby(-5).unwrap();
+        assert_eq!(lobby_bytes(storage), vec![replaced.serialize_bytes()]);
+
+        storage.save_ban(&ban(1, 100)).unwrap();
+        storage.save_ban(&ban(2, 200)).unwrap();
+        storage.save_ban(&ban(1, 300)).unwrap();
+        assert_eq!(sorted_bans(storage), vec![ban(1, 300), ban(2, 200)]);
+        storage.remove_ban(ban(2, 0).ip).unwrap();
+        assert_eq!(sorted_bans(storage), vec![ban(1, 300)]);
+
+        let stats = ServerStats {
+            connections: 3,
+            games_hosted: 2,
+            bytes_in: u64::MAX,
+            bytes_out: 1 << 40,
+        };
+        storage.save_stats(&stats).unwrap();
+        assert_eq!(storage.stats().unwrap(), stats);
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_round_trip(&mut MemoryStorage::default());
+    }
+
+    #[test]
+    fn test_sled_storage() {
+        let path = std::env::temp_dir().join("among-us-test-storage");
+        let _ = fs::remove_dir_all(&path);
+        check_round_trip(&mut SledStorage::open(&path).unwrap());
+
+        // Everything's still there after reopening
+        let storage = SledStorage::open(&path).unwrap();
+        assert_eq!(
+            lobby_bytes(&storage),
+            vec![lobby(8, OptionsPreset::Competitive).serialize_bytes()]
+        );
+        assert_eq!(sorted_bans(&storage), vec![ban(1, 300)]);
+        assert_eq!(storage.stats().unwrap().bytes_in, u64::MAX);
+    }
+
+    #[test]
+    fn test_stored_ban_invalid_ip() {
+        let mut w = PacketWriter::new();
+        w.write_string("not an ip");
+        write_u64(&mut w, 0);
+        let error = PacketReader::new(&w.finish()[..])
+            .read::<StoredBan>()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+/// Something a client does that's limited per IP
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Hello,
+    Join,
+    Reliable,
+}
+
+/// How much each IP is allowed to do before being banned for a while
+#[derive(Debug, Clone)]
+pub struct ThrottleSettings {
+    /// New connections per `window`
+    pub hellos: u32,
+    /// Join attempts per `window`
+    pub joins: u32,
+    /// Reliable packets per `window`
+    pub reliable_packets: u32,
+    pub window: Duration,
+    pub ban_duration: Duration,
+}
+
+impl Default for ThrottleSettings {
+    fn default() -> Self {
+        Self {
+            hellos: 10,
+            joins: 20,
+            // Movement is sent unreliably so even busy games stay well under this
+            reliable_packets: 3000,
+            window: Duration::from_secs(60),
+            ban_duration: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl ThrottleSettings {
+    fn limit(&self, action: Action) -> u32 {
+        match action {
+            Action::Hello => self.hellos,
+            Action::Join => self.joins,
+            Action::Reliable => self.reliable_packets,
+        }
+    }
+}
+
+/// Per-IP rate limits using fixed windows, with temporary bans for going over
+pub struct Throttle {
+    settings: ThrottleSettings,
+    /// When each window started and how much has been done in it
+    windows: HashMap<(IpAddr, Action), (Instant, u32)>,
+    /// When each ban expires
+    bans: HashMap<IpAddr, Instant>,
+}
+
+impl Throttle {
+    pub fn new(settings: ThrottleSettings) -> Self {
+        Self {
+            settings,
+            windows: HashMap::new(),
+            bans: HashMap::new(),
+        }
+    }
+
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        self.bans
+            .get(&ip)
+            .is_some_and(|&expires| Instant::now() < expires)
+    }
+
+    /// Bans an IP for `duration`, e.g. to restore a ban from before a restart
+    pub fn ban(&mut self, ip: IpAddr, duration: Duration) {
+        self.bans.insert(ip, Instant::now() + duration);
+    }
+
+    pub fn ban_duration(&self) -> Duration {
+        self.settings.ban_duration
+    }
+
+    /// Counts an action, returning `false` and banning the IP if it's over the limit
+    pub fn allow(&mut self, ip: IpAddr, action: Action) -> bool {
+        if self.is_banned(ip) {
+            return false;
+        }
+        let now = Instant::now();
+        let window = self.settings.window;
+        let (started, count) = self.windows.entry((ip, action)).or_insert((now, 0));
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count > self.settings.limit(action) {
+            self.bans.insert(ip, now + self.settings.ban_duration);
+            return false;
+        }
+        true
+    }
+
+    /// Forgets expired windows and bans so memory doesn't grow forever
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let window = self.settings.window;
+        self.windows
+            .retain(|_, (started, _)| now.duration_since(*started) < window);
+        self.bans.retain(|_, expires| now < *expires);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::Ipv4Addr, thread};
+
+    fn settings(window: Duration, ban_duration: Duration) -> ThrottleSettings {
+        ThrottleSettings {
+            hellos: 2,
+            joins: 3,
+            reliable_packets: 4,
+            window,
+            ban_duration,
+        }
+    }
+
+    #[test]
+    fn test_allow() {
+        let mut throttle =
+            Throttle::new(settings(Duration::from_secs(60), Duration::from_secs(60)));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        assert!(throttle.allow(ip, Action::Hello));
+        assert!(throttle.allow(ip, Action::Hello));
+        // Each action has its own limit
+        assert!(throttle.allow(ip, Action::Join));
+        assert!(!throttle.allow(ip, Action::Hello));
+        assert!(throttle.is_banned(ip));
+        // Banned for everything, not just what went over
+        assert!(!throttle.allow(ip, Action::Join));
+        assert!(throttle.allow(other, Action::Hello));
+        assert!(!throttle.is_banned(other));
+    }
+
+    #[test]
+    fn test_allow_window() {
+        let window = Duration::from_millis(50);
+        let mut throttle = Throttle::new(settings(window, Duration::from_secs(60)));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        assert!(throttle.allow(ip, Action::Hello));
+        assert!(throttle.allow(ip, Action::Hello));
+        thread::sleep(window);
+        // A new window starts the count again
+        assert!(throttle.allow(ip, Action::Hello));
+        assert!(throttle.allow(ip, Action::Hello));
+        assert!(!throttle.is_banned(ip));
+    }
+
+    #[test]
+    fn test_ban() {
+        let mut throttle =
+            Throttle::new(settings(Duration::from_secs(60), Duration::from_secs(60)));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        throttle.ban(ip, Duration::from_millis(50));
+        assert!(throttle.is_banned(ip));
+        assert!(!throttle.allow(ip, Action::Reliable));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!throttle.is_banned(ip));
+        assert!(throttle.allow(ip, Action::Reliable));
+    }
+
+    #[test]
+    fn test_prune() {
+        let window = Duration::from_millis(50);
+        let mut throttle = Throttle::new(settings(window, Duration::from_secs(60)));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let banned = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        throttle.allow(ip, Action::Join);
+        throttle.ban(banned, Duration::from_secs(60));
+        throttle.ban(ip, Duration::from_millis(50));
+        throttle.prune();
+        assert_eq!(throttle.windows.len(), 1);
+        assert_eq!(throttle.bans.len(), 2);
+
+        thread::sleep(window);
+        throttle.prune();
+        assert!(throttle.windows.is_empty());
+        // Only the ban that's still going is kept
+        assert_eq!(throttle.bans.keys().collect::<Vec<_>>(), vec![&banned]);
+    }
+}